@@ -0,0 +1,62 @@
+//! Contains `run_blocking` helper for calling blocking Filen API from async code.
+use crate::SettingsBundle;
+
+/// Runs given closure with blocking Filen API calls on Tokio's blocking thread pool, so async runtime threads
+/// are not stalled while waiting for Filen servers. Closure receives its own clone of the given settings.
+///
+/// Useful when embedding blocking API into async app, or when some helper has no `_async` counterpart.
+///
+/// # Panics
+///
+/// Resumes panic if the closure panicked, or panics if called outside of Tokio runtime.
+pub async fn run_blocking<T, F>(settings: &SettingsBundle, action: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce(&SettingsBundle) -> T + Send + 'static,
+{
+    let settings = settings.clone();
+    match tokio::task::spawn_blocking(move || action(&settings)).await {
+        Ok(result) => result,
+        Err(join_error) => std::panic::resume_unwind(join_error.into_panic()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::init_server,
+        v1::{user_dirs_request, UserDirsResponsePayload},
+    };
+    use httpmock::Method::POST;
+    use pretty_assertions::assert_eq;
+    use secstr::SecUtf8;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn run_blocking_should_perform_blocking_request_off_runtime() {
+        let (server, filen_settings) = init_server();
+        let settings = SettingsBundle {
+            filen: filen_settings,
+            ..SettingsBundle::default()
+        };
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/user/dirs");
+            then.status(200)
+                .json_body(json!({"status": true, "message": "Folders fetched.", "data": []}));
+        });
+        let api_key = SecUtf8::from("bYZmrwdVEbHJSqeA1RfnPtKiBcXzUpRdKGRkjw9m1o1eqSGP1s6DM11CDnklpFq6");
+
+        let result = run_blocking(&settings, move |settings| user_dirs_request(&api_key, &settings.filen)).await;
+
+        mock.assert_hits(1);
+        assert_eq!(
+            result.unwrap(),
+            UserDirsResponsePayload {
+                status: true,
+                message: Some("Folders fetched.".to_owned()),
+                data: Some(Vec::new())
+            }
+        );
+    }
+}
@@ -11,13 +11,14 @@ use hmac::digest::{FixedOutput, KeyInit};
 use hmac::{Hmac, Mac};
 use md5::Md5;
 use pbkdf2::pbkdf2;
-use rand::{thread_rng, Rng};
+use rand::{thread_rng, Rng, RngCore};
 use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
 use rsa::PublicKey;
 use secstr::{SecUtf8, SecVec};
-use snafu::{ensure, Backtrace, ResultExt, Snafu};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use snafu::{ensure, Backtrace, OptionExt, ResultExt, Snafu};
 
-use crate::utils;
+use crate::utils::{self, Base64Padding};
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -31,7 +32,12 @@ pub const OPENSSL_SALT_LENGTH: usize = 8;
 pub const AES_CBC_IV_LENGTH: usize = 16;
 pub const AES_CBC_KEY_LENGTH: usize = 32;
 pub const AES_GCM_IV_LENGTH: usize = 12;
+pub const AES_GCM_TAG_LENGTH: usize = 16;
 pub const FILEN_VERSION_LENGTH: usize = 3;
+/// Amount of random bytes in a public link salt.
+pub const LINK_SALT_BYTES: usize = 32;
+/// Length of a public link salt string, which is `LINK_SALT_BYTES` random bytes as a lowercase hex string.
+pub const LINK_SALT_LENGTH: usize = LINK_SALT_BYTES * 2;
 
 #[derive(Snafu, Debug)]
 pub enum Error {
@@ -67,6 +73,14 @@ pub enum Error {
     ))]
     EncryptedMetadataIsNotUtf8 { source: std::string::FromUtf8Error },
 
+    #[snafu(display("Decrypted note content is not a JSON object with 'content' string: {}", source))]
+    NoteContentIsNotJson { source: serde_json::Error },
+
+    #[snafu(display(
+        "User has no RSA private key; key pair should be initialized with `user_key_pair_update_request` first"
+    ))]
+    NoPrivateKey { backtrace: Backtrace },
+
     #[snafu(display(
         "Cannot encrypt data with given public key, assuming RSA-OAEP with SHA512 hash and PKCS8 format: {}",
         source
@@ -101,6 +115,20 @@ pub fn hash_fn<S: Into<String>>(value: S) -> String {
     sha1(&sha512(&value.into()).to_hex_string()).to_hex_string()
 }
 
+/// Calculates `nameHashed` value for the given file or folder name, exactly like the official Filen client does:
+/// name is lowercased and then hashed with `hash_fn`. No other normalization is applied.
+///
+/// Lowercasing uses full Unicode case mapping, same as JavaScript's `String.prototype.toLowerCase`,
+/// so accented, Cyrillic or Greek names hash identically to the official client. Like the official client,
+/// name is not converted to NFC, so precomposed and decomposed forms of the same name get different hashes.
+///
+/// Filen uses this hash to detect duplicate names in the same folder, so every API call sending `nameHashed`
+/// should use this function.
+#[must_use]
+pub fn hash_name(name: &str) -> String {
+    hash_fn(name.to_lowercase())
+}
+
 /// Calculates login key from the specified user password using chain of hashes. Deprecated since August 2021.
 #[must_use]
 pub fn hash_password<S: Into<String>>(password: S) -> String {
@@ -130,6 +158,28 @@ pub fn derive_key_from_password_256(password: &[u8], salt: &[u8], iterations: u3
     pbkdf2_hash
 }
 
+/// Filen metadata versions which can be used to encrypt metadata.
+#[derive(Clone, Copy, Debug, Default, Deserialize_repr, Eq, Hash, PartialEq, Serialize_repr)]
+#[repr(u32)]
+pub enum MetadataVersion {
+    /// AES-256-CBC with OpenSSL-compatible key derivation. Deprecated since August 2021,
+    /// but still understood by every Filen deployment.
+    #[default]
+    V1 = 1,
+
+    /// AES-256-GCM with a version mark prefix.
+    V2 = 2,
+}
+
+impl From<MetadataVersion> for u32 {
+    fn from(metadata_version: MetadataVersion) -> Self {
+        match metadata_version {
+            MetadataVersion::V1 => 1,
+            MetadataVersion::V2 => 2,
+        }
+    }
+}
+
 /// Encrypts given data to Filen metadata using given key.
 /// Depending on metadata version, different encryption algos will be used.
 pub fn encrypt_metadata(data: &[u8], key: &[u8], metadata_version: u32) -> Result<Vec<u8>> {
@@ -139,7 +189,11 @@ pub fn encrypt_metadata(data: &[u8], key: &[u8], metadata_version: u32) -> Resul
 
     match metadata_version {
         // 1 is Deprecated since August 2021
-        1 => encrypt_aes_openssl(data, key, None).map(|encrypted| base64::encode(encrypted).as_bytes().to_vec()),
+        1 => encrypt_aes_openssl(data, key, None).map(|encrypted| {
+            utils::base64_encode(encrypted, Base64Padding::Padded)
+                .as_bytes()
+                .to_vec()
+        }),
         2 => {
             let mut version_mark = format!("{:0>3}", metadata_version).into_bytes();
             version_mark.extend(encrypt_aes_gcm_base64(data, key)?);
@@ -152,6 +206,38 @@ pub fn encrypt_metadata(data: &[u8], key: &[u8], metadata_version: u32) -> Resul
     }
 }
 
+/// Calculates length of Filen metadata produced by `encrypt_metadata` for plain data of the given length.
+/// Length does not depend on data contents or key, so it can be calculated without encrypting anything.
+#[must_use]
+pub const fn encrypted_metadata_len(plaintext_len: usize, metadata_version: MetadataVersion) -> usize {
+    if plaintext_len == 0 {
+        return 0;
+    }
+
+    match metadata_version {
+        MetadataVersion::V1 => {
+            let padded_len = (plaintext_len / AES_CBC_IV_LENGTH + 1) * AES_CBC_IV_LENGTH;
+            base64_encoded_len(OPENSSL_SALT_PREFIX.len() + OPENSSL_SALT_LENGTH + padded_len)
+        }
+        MetadataVersion::V2 => {
+            FILEN_VERSION_LENGTH + AES_GCM_IV_LENGTH + base64_encoded_len(plaintext_len + AES_GCM_TAG_LENGTH)
+        }
+    }
+}
+
+/// Calculates by how many bytes Filen metadata produced by `encrypt_metadata` is larger than
+/// plain data of the given length, e.g. 130 bytes of plain data become 211 bytes of V2 metadata,
+/// so the overhead is 81 bytes.
+#[must_use]
+pub const fn metadata_overhead(plaintext_len: usize, metadata_version: MetadataVersion) -> usize {
+    encrypted_metadata_len(plaintext_len, metadata_version) - plaintext_len
+}
+
+/// Length of padded base64 string for data of the given length.
+const fn base64_encoded_len(data_len: usize) -> usize {
+    data_len.div_ceil(3) * 4
+}
+
 /// Decrypts Filen metadata prefiously encrypted with `encrypt_metadata`/`encrypt_metadata_str` and one of the
 /// given keys. Tries to decrypt using given keys until one of them succeeds.
 pub fn decrypt_metadata_any_key(data: &[u8], keys: &[&[u8]]) -> Result<Vec<u8>> {
@@ -184,25 +270,41 @@ pub fn decrypt_metadata_any_key(data: &[u8], keys: &[&[u8]]) -> Result<Vec<u8>>
     }
 }
 
-/// Decrypts Filen metadata prefiously encrypted with `encrypt_metadata`/`encrypt_metadata_str` and given key.
-pub fn decrypt_metadata(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
-    fn read_metadata_version(data: &[u8]) -> Result<i32> {
-        let possible_salted_mark = data.get(..OPENSSL_SALT_PREFIX.len()).unwrap_or_default();
-        let possible_version_mark = data.get(..FILEN_VERSION_LENGTH).unwrap_or_default();
-        if possible_salted_mark == OPENSSL_SALT_PREFIX_BASE64 {
-            Ok(1)
-        } else if possible_salted_mark == OPENSSL_SALT_PREFIX {
-            Ok(-1) // Means data is base_64 decoded already, so we won't have to decode later.
-        } else {
-            let possible_version_string = String::from_utf8_lossy(possible_version_mark);
-            possible_version_string
-                .parse::<i32>()
-                .context(CannotParseFilenMetadataVersionSnafu {
-                    erroneous_part: possible_version_string.to_string(),
-                })
+/// Detects which metadata version was used to encrypt given Filen metadata, without decrypting it.
+/// Useful to find metadata which still uses deprecated `MetadataVersion::V1`.
+pub fn detect_metadata_version(data: &[u8]) -> Result<MetadataVersion> {
+    match read_metadata_version(data)? {
+        -1 | 1 => Ok(MetadataVersion::V1),
+        2 => Ok(MetadataVersion::V2),
+        version => UnsupportedFilenMetadataVersionSnafu {
+            metadata_version: version,
         }
+        .fail(),
+    }
+}
+
+fn read_metadata_version(data: &[u8]) -> Result<i32> {
+    let possible_salted_mark = data.get(..OPENSSL_SALT_PREFIX.len()).unwrap_or_default();
+    let possible_version_mark = data.get(..FILEN_VERSION_LENGTH).unwrap_or_default();
+    if possible_salted_mark == OPENSSL_SALT_PREFIX_BASE64 {
+        Ok(1)
+    } else if possible_salted_mark == OPENSSL_SALT_PREFIX {
+        Ok(-1) // Means data is base_64 decoded already, so we won't have to decode later.
+    } else {
+        let possible_version_string = String::from_utf8_lossy(possible_version_mark);
+        possible_version_string
+            .parse::<i32>()
+            .context(CannotParseFilenMetadataVersionSnafu {
+                erroneous_part: possible_version_string.to_string(),
+            })
     }
+}
 
+/// Decrypts Filen metadata prefiously encrypted with `encrypt_metadata`/`encrypt_metadata_str` and given key.
+///
+/// Key is used as is, so to decrypt with a master key pass its UTF-8 bytes, or use
+/// `decrypt_metadata_with_master_key` instead.
+pub fn decrypt_metadata(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
     if data.is_empty() {
         return Ok(vec![0_u8; 0]);
     }
@@ -210,7 +312,7 @@ pub fn decrypt_metadata(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
     let metadata_version = read_metadata_version(data)?;
     match metadata_version {
         -1 => decrypt_aes_openssl(data, key), // Deprecated since August 2021
-        1 => base64::decode(data)
+        1 => utils::base64_decode(data)
             .context(CannotDecodeBase64Snafu {})
             .and_then(|decoded| decrypt_aes_openssl(&decoded, key)), // Deprecated since August 2021
         2 => decrypt_aes_gcm_base64(data.get(FILEN_VERSION_LENGTH..).unwrap_or_default(), key),
@@ -221,6 +323,18 @@ pub fn decrypt_metadata(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
     }
 }
 
+/// Returns given data as is if it is already a plain JSON value, like some legacy metadata fields are stored;
+/// otherwise decrypts it with `decrypt_metadata`.
+///
+/// Encrypted metadata of any supported version is never a valid JSON, so it cannot be mistaken for plain data.
+pub fn decrypt_metadata_or_plain(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
+    if serde_json::from_slice::<serde::de::IgnoredAny>(data).is_ok() {
+        return Ok(data.to_vec());
+    }
+
+    decrypt_metadata(data, key)
+}
+
 /// Encrypts given data to Filen metadata using given key.
 /// Depending on metadata version, different encryption algos will be used.
 /// Convenience overload of the `encrypt_metadata` for string params.
@@ -229,11 +343,29 @@ pub fn encrypt_metadata_str(data: &str, key: &SecUtf8, metadata_version: u32) ->
         .and_then(|bytes| String::from_utf8(bytes).context(EncryptedMetadataIsNotUtf8Snafu {}))
 }
 
+/// Decrypts Filen metadata prefiously encrypted with `encrypt_metadata`/`encrypt_metadata_str` and given key,
+/// expecting decrypted bytes to be a valid UTF-8 string.
+///
+/// Unlike lossy conversion of `decrypt_metadata` results, returns `Error::DecryptedMetadataIsNotUtf8`
+/// for invalid UTF-8, which usually means metadata was decrypted with a wrong key.
+pub fn decrypt_metadata_to_string(data: &[u8], key: &[u8]) -> Result<String> {
+    decrypt_metadata(data, key).and_then(|bytes| String::from_utf8(bytes).context(DecryptedMetadataIsNotUtf8Snafu {}))
+}
+
 /// Decrypts Filen metadata prefiously encrypted with `encrypt_metadata`/`encrypt_metadata_str`.
-/// Convenience overload of the `decrypt_metadata` for string params.
+/// Convenience overload of the `decrypt_metadata_to_string` for string params.
 pub fn decrypt_metadata_str(data: &str, key: &SecUtf8) -> Result<String> {
-    decrypt_metadata(data.as_bytes(), key.unsecure().as_bytes())
-        .and_then(|bytes| String::from_utf8(bytes).context(DecryptedMetadataIsNotUtf8Snafu {}))
+    decrypt_metadata_to_string(data.as_bytes(), key.unsecure().as_bytes())
+}
+
+/// Decrypts Filen metadata with the given master key, as returned by `decrypt_master_keys_metadata`
+/// or derived from user password.
+///
+/// Master key is already a `hash_fn`-like hex string, and Filen encrypts metadata with the UTF-8 bytes
+/// of that string, so it must not be hashed or hex-decoded again. This function takes care of it,
+/// trimming surrounding whitespace like `master_key_hex` does.
+pub fn decrypt_metadata_with_master_key(data: &[u8], master_key: &SecUtf8) -> Result<Vec<u8>> {
+    decrypt_metadata(data, master_key_hex(master_key).as_bytes())
 }
 
 /// Decrypts Filen metadata prefiously encrypted with `encrypt_metadata`/`encrypt_metadata_str` and one of the
@@ -243,11 +375,74 @@ pub fn decrypt_metadata_str_any_key(data: &str, keys: &[SecUtf8]) -> Result<Stri
         return Ok(String::new());
     }
 
-    let keys = keys.iter().map(|key| key.unsecure().as_bytes()).collect::<Vec<&[u8]>>();
-    decrypt_metadata_any_key(data.as_bytes(), &keys)
+    decrypt_metadata_with_keys(data.as_bytes(), keys)
         .and_then(|bytes| String::from_utf8(bytes).context(DecryptedMetadataIsNotUtf8Snafu {}))
 }
 
+/// Decrypts Filen metadata using one of the given master keys. Keys are tried in the given order
+/// and the first successful decryption is returned.
+///
+/// Useful after master key rotation: metadata encrypted with one of the older master keys
+/// cannot be decrypted with the last master key, but can with the whole list of user's master keys.
+pub fn decrypt_metadata_with_keys(data: &[u8], keys: &[SecUtf8]) -> Result<Vec<u8>> {
+    let keys = keys.iter().map(|key| key.unsecure().as_bytes()).collect::<Vec<&[u8]>>();
+    decrypt_metadata_any_key(data, &keys)
+}
+
+/// Note content as Filen stores it before encryption.
+#[derive(serde::Deserialize, serde::Serialize)]
+struct NoteContent<'content> {
+    #[serde(borrow)]
+    content: std::borrow::Cow<'content, str>,
+}
+
+/// Encrypts given note text with the note's own key, as Filen Notes do.
+///
+/// Every note has a separate key, shared with each note participant encrypted by their public key,
+/// so note content is not encrypted with user's master key. Text is wrapped into `{"content": ...}` JSON
+/// and then encrypted like any other metadata of the given version.
+pub fn encrypt_note_content(plain_text: &str, note_key: &SecUtf8, metadata_version: u32) -> Result<String> {
+    let note_content = NoteContent {
+        content: plain_text.into(),
+    };
+    let note_content_json = serde_json::to_string(&note_content).context(NoteContentIsNotJsonSnafu {})?;
+    encrypt_metadata_str(&note_content_json, note_key, metadata_version)
+}
+
+/// Decrypts note content previously encrypted with `encrypt_note_content` and the note's own key.
+///
+/// Empty content is returned as empty text, same as empty metadata.
+pub fn decrypt_note_content(encrypted_content: &str, note_key: &SecUtf8) -> Result<String> {
+    if encrypted_content.is_empty() {
+        return Ok(String::new());
+    }
+
+    let note_content_json = decrypt_metadata_str(encrypted_content, note_key)?;
+    serde_json::from_str::<NoteContent>(&note_content_json)
+        .map(|note_content| note_content.content.into_owned())
+        .context(NoteContentIsNotJsonSnafu {})
+}
+
+/// Re-encrypts given Filen metadata blobs from `old_key` to `new_key`, without any network calls.
+/// Useful for migrating exported metadata to a new master key.
+///
+/// Every blob is processed independently, so results are returned in the same order as given blobs,
+/// and a blob which cannot be decrypted with `old_key` does not prevent others from being migrated.
+pub fn migrate_metadata_blobs(
+    blobs: &[String],
+    old_key: &SecUtf8,
+    new_key: &SecUtf8,
+    new_metadata_version: u32,
+) -> Vec<Result<String>> {
+    blobs
+        .iter()
+        .map(|blob| {
+            decrypt_metadata_str(blob, old_key)
+                .and_then(|metadata| encrypt_metadata_str(&metadata, new_key, new_metadata_version))
+        })
+        .collect()
+}
+
 /// Encrypts file chunk for uploading to Filen. Resulting encoded chunk bytes are treated as unicode scalars,
 /// hence the resulting type. File key can be fetched from file metadata.
 /// Note that `encrypt_file_chunk` and `decrypt_file_chunk` are not symmetric.
@@ -273,12 +468,15 @@ pub fn encrypt_file_chunk(chunk_data: &[u8], file_key: &[u8; AES_CBC_KEY_LENGTH]
 /// Note that `encrypt_file_chunk` and `decrypt_file_chunk` are not symmetric.
 /// You are supposed to call `decrypt_file_chunk` on file chunks received from Filen, not on strings produced by
 /// `encrypt_file_chunk`.
+///
+/// Empty chunk is decrypted to empty bytes, same as `encrypt_file_chunk` encrypts empty bytes to empty chunk.
 pub fn decrypt_file_chunk(
     filen_encrypted_chunk_data: &[u8],
     file_key: &[u8; AES_CBC_KEY_LENGTH],
     version: u32,
 ) -> Result<Vec<u8>> {
     match version {
+        1 | 2 if filen_encrypted_chunk_data.is_empty() => Ok(Vec::new()),
         1 => {
             if filen_encrypted_chunk_data.len() < OPENSSL_SALT_PREFIX.len() {
                 BadArgumentSnafu {
@@ -290,7 +488,10 @@ pub fn decrypt_file_chunk(
                     .get(0..OPENSSL_SALT_PREFIX.len())
                     .unwrap_or_default();
                 if possible_prefix == OPENSSL_SALT_PREFIX {
-                    decrypt_aes_openssl(base64::encode(filen_encrypted_chunk_data).as_bytes(), file_key)
+                    decrypt_aes_openssl(
+                        utils::base64_encode(filen_encrypted_chunk_data, Base64Padding::Padded).as_bytes(),
+                        file_key,
+                    )
                 } else if possible_prefix == OPENSSL_SALT_PREFIX_BASE64 {
                     decrypt_aes_openssl(
                         utils::bytes_to_binary_string(filen_encrypted_chunk_data).as_bytes(),
@@ -307,6 +508,16 @@ pub fn decrypt_file_chunk(
     }
 }
 
+/// Checks AES-GCM authentication tag of a file chunk downloaded from Filen, without returning its decrypted bytes.
+/// Only file version 2 chunks are AES-GCM encrypted, so only they can be verified. File key can be fetched
+/// from file metadata.
+///
+/// Chunk is still deciphered during verification, but deciphered bytes are discarded right away, so memory
+/// usage does not grow with the amount of verified chunks.
+pub fn verify_file_chunk(filen_encrypted_chunk_data: &[u8], file_key: &SecUtf8) -> Result<()> {
+    decrypt_aes_gcm(filen_encrypted_chunk_data, file_key.unsecure().as_bytes()).map(|_| ())
+}
+
 /// Helper which encrypts master keys stored in a metadata into a list of key strings, using specified master key.
 pub fn encrypt_master_keys_metadata(
     master_keys: &[SecUtf8],
@@ -324,6 +535,8 @@ pub fn encrypt_master_keys_metadata(
 
 /// Helper which decrypts master keys stored in a metadata into a list of key strings,
 /// using the specified user's last master key.
+///
+/// Master keys are hex strings joined by '|'; blank entries are skipped, but at least one key must be present.
 pub fn decrypt_master_keys_metadata(master_keys_metadata: &str, last_master_key: &SecUtf8) -> Result<Vec<SecUtf8>> {
     ensure!(
         !master_keys_metadata.is_empty(),
@@ -332,24 +545,146 @@ pub fn decrypt_master_keys_metadata(master_keys_metadata: &str, last_master_key:
         }
     );
 
-    decrypt_metadata_str(master_keys_metadata, last_master_key).map(|keys| keys.split('|').map(SecUtf8::from).collect())
+    let keys = decrypt_metadata_str(master_keys_metadata, last_master_key)?;
+    let master_keys = keys
+        .split('|')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(SecUtf8::from)
+        .collect::<Vec<SecUtf8>>();
+    ensure!(
+        !master_keys.is_empty(),
+        BadArgumentSnafu {
+            message: "decrypted master keys metadata contains no keys",
+        }
+    );
+    Ok(master_keys)
+}
+
+/// Returns hex representation of the given master key, for passing it to external tools.
+///
+/// Master keys returned by `decrypt_master_keys_metadata` or derived from user password are already hex strings
+/// in the canonical form Filen expects, so no decoding is done here: the UTF-8 bytes of the returned string
+/// are exactly the key bytes `decrypt_metadata` and `encrypt_metadata` expect.
+#[must_use]
+pub fn master_key_hex(master_key: &SecUtf8) -> String {
+    master_key.unsecure().trim().to_owned()
+}
+
+/// Adds conveniences to a list of user's master keys, like the one returned by `decrypt_master_keys_metadata`.
+pub trait MasterKeysExt {
+    /// Returns the last master key, which is the one Filen expects to be used for encrypting new metadata.
+    /// Returns None if there are no master keys.
+    fn last_master_key(&self) -> Option<SecUtf8>;
+}
+
+impl MasterKeysExt for [SecUtf8] {
+    fn last_master_key(&self) -> Option<SecUtf8> {
+        self.last().cloned()
+    }
+}
+
+/// User's master keys together with AES-GCM keys derived from them, so keys are not derived again for every
+/// encrypted or decrypted metadata. This is what a client is supposed to hold after login.
+///
+/// Last master key is the one used for encrypting new metadata, while all keys are tried when decrypting,
+/// since older metadata could have been encrypted with one of the previous master keys.
+#[derive(Clone, Debug)]
+pub struct MasterKeys {
+    keys: Vec<SecUtf8>,
+    derived_keys: Vec<SecVec<u8>>,
+}
+
+impl MasterKeys {
+    /// Creates master keys from the given list, where the last key is user's last master key.
+    /// Fails if the list is empty.
+    pub fn new(keys: Vec<SecUtf8>) -> Result<Self> {
+        ensure!(
+            !keys.is_empty(),
+            BadArgumentSnafu {
+                message: "master keys cannot be empty",
+            }
+        );
+
+        let derived_keys = keys
+            .iter()
+            .map(|key| SecVec::from(derive_aes_gcm_key(key.unsecure().as_bytes()).to_vec()))
+            .collect();
+        Ok(Self { keys, derived_keys })
+    }
+
+    /// Decrypts master keys stored in a metadata using the specified user's last master key.
+    /// See `decrypt_master_keys_metadata` for details.
+    pub fn from_metadata(master_keys_metadata: &str, last_master_key: &SecUtf8) -> Result<Self> {
+        decrypt_master_keys_metadata(master_keys_metadata, last_master_key).and_then(Self::new)
+    }
+
+    /// Returns all master keys, with user's last master key at the end.
+    #[must_use]
+    pub fn keys(&self) -> &[SecUtf8] {
+        &self.keys
+    }
+
+    /// Returns user's last master key, which is the one Filen expects to be used for encrypting new metadata.
+    #[must_use]
+    pub fn last_key(&self) -> &SecUtf8 {
+        self.keys.last().expect("Master keys cannot be empty")
+    }
+
+    /// Encrypts given string to Filen metadata using user's last master key.
+    pub fn encrypt_metadata(&self, data: &str, metadata_version: u32) -> Result<String> {
+        if data.is_empty() || metadata_version != 2 {
+            return encrypt_metadata_str(data, self.last_key(), metadata_version);
+        }
+
+        let derived_key = self.derived_keys.last().expect("Master keys cannot be empty");
+        let (mut encrypted, encrypted_bytes) =
+            encrypt_aes_gcm_with_derived_key(data.as_bytes(), derived_key.unsecure(), &mut thread_rng())?;
+        encrypted.push_str(&utils::base64_encode(encrypted_bytes, Base64Padding::Padded));
+        Ok(format!("{:0>3}{}", metadata_version, encrypted))
+    }
+
+    /// Decrypts Filen metadata into a string, trying master keys from the first to the last one
+    /// until one of them succeeds.
+    pub fn decrypt_metadata(&self, data: &str) -> Result<String> {
+        if data.is_empty() {
+            return Ok(String::new());
+        }
+
+        let data = data.as_bytes();
+        let decrypted = if read_metadata_version(data)? == 2 {
+            let (iv, encrypted_base64) =
+                extract_aes_gcm_iv_and_message(data.get(FILEN_VERSION_LENGTH..).unwrap_or_default())?;
+            let encrypted = utils::base64_decode(encrypted_base64).context(CannotDecodeBase64Snafu {})?;
+            self.derived_keys
+                .iter()
+                .find_map(|derived_key| decrypt_aes_gcm_with_derived_key(derived_key.unsecure(), iv, &encrypted).ok())
+                .context(BadArgumentSnafu {
+                    message: "all given keys failed to decrypt metadata",
+                })?
+        } else {
+            decrypt_metadata_with_keys(data, &self.keys)?
+        };
+        String::from_utf8(decrypted).context(DecryptedMetadataIsNotUtf8Snafu {})
+    }
+}
+
+impl AsRef<[SecUtf8]> for MasterKeys {
+    fn as_ref(&self) -> &[SecUtf8] {
+        &self.keys
+    }
 }
 
 /// Helper which decrypts user's RSA private key stored in a metadata into key bytes,
 /// using one of the specified master keys.
 pub fn decrypt_private_key_metadata(private_key_metadata: &str, master_keys: &[SecUtf8]) -> Result<SecVec<u8>> {
     fn decode_base64_to_secvec(string: &str) -> Result<SecVec<u8>> {
-        base64::decode(string)
+        utils::base64_decode(string)
             .context(CannotDecodeBase64Snafu {})
             .map(SecVec::from)
     }
 
-    ensure!(
-        !private_key_metadata.is_empty(),
-        BadArgumentSnafu {
-            message: "cannot decrypt private key metadata, it is empty",
-        }
-    );
+    ensure!(!private_key_metadata.is_empty(), NoPrivateKeySnafu {});
 
     decrypt_metadata_str_any_key(private_key_metadata, master_keys).and_then(|str| decode_base64_to_secvec(&str))
 }
@@ -364,7 +699,10 @@ pub fn encrypt_rsa(data: &[u8], public_key: &[u8]) -> Result<Vec<u8>> {
 }
 
 /// Decrypts data prefiously encrypted with `encrypt_rsa` using PKCS#8 private key in ASN.1 DER-encoded format.
+///
+/// Fails with `Error::NoPrivateKey` if private key is empty, which is the case for users who never set up key pair.
 pub fn decrypt_rsa(data: &[u8], private_key: &[u8]) -> Result<Vec<u8>> {
+    ensure!(!private_key.is_empty(), NoPrivateKeySnafu {});
     let padding = rsa::PaddingScheme::new_oaep::<sha2::Sha512>();
     let private_key =
         rsa::RsaPrivateKey::from_pkcs8_der(private_key).context(RsaCannotDeserializePrivateKeySnafu {})?;
@@ -375,24 +713,62 @@ pub fn decrypt_rsa(data: &[u8], private_key: &[u8]) -> Result<Vec<u8>> {
 
 /// Creates Filen's public link password from the given plain text password,
 /// returns both link's password and salt used for its creation.
+///
+/// Salt is `LINK_SALT_BYTES` random bytes encoded as a lowercase hex string of `LINK_SALT_LENGTH` characters,
+/// which is the format accepted by Filen link edit endpoints.
 #[must_use]
 pub fn encrypt_to_link_password_and_salt(plain_text_password: &SecUtf8) -> (String, String) {
-    let salt = utils::random_alphanumeric_string(32);
-    let password_hashed = utils::bytes_to_hex_string(&derive_key_from_password_512(
-        plain_text_password.unsecure().as_bytes(),
+    encrypt_to_link_password_and_salt_with_rng(plain_text_password, &mut thread_rng())
+}
+
+/// Same as `encrypt_to_link_password_and_salt`, but random salt is taken from the given random number generator.
+#[must_use]
+pub fn encrypt_to_link_password_and_salt_with_rng(
+    plain_text_password: &SecUtf8,
+    rng: &mut impl RngCore,
+) -> (String, String) {
+    let mut salt_bytes = [0_u8; LINK_SALT_BYTES];
+    rng.fill_bytes(&mut salt_bytes);
+    let salt = utils::bytes_to_hex_string(&salt_bytes);
+    let password_hashed = link_password_key(plain_text_password, &salt);
+    (password_hashed, salt)
+}
+
+/// Decrypts Filen metadata encrypted with a key derived from the public link password,
+/// which is used for password-protected links instead of the master key.
+/// `salt` is the link's salt, as returned by Filen along with the link info.
+pub fn decrypt_link_metadata(metadata: &str, link_password: &SecUtf8, salt: &str) -> Result<Vec<u8>> {
+    let key = link_password_key(link_password, salt);
+    decrypt_metadata(metadata.as_bytes(), key.as_bytes())
+}
+
+/// Derives hex-encoded key from the link password and salt, same as Filen does for password-protected links.
+fn link_password_key(link_password: &SecUtf8, salt: &str) -> String {
+    utils::bytes_to_hex_string(&derive_key_from_password_512(
+        link_password.unsecure().as_bytes(),
         salt.as_bytes(),
         200_000,
-    ));
-    (password_hashed, salt)
+    ))
 }
 
 /// Calculates OpenSSL-compatible AES 256 CBC (Pkcs7 padding) hash with 'Salted__' prefix,
 /// then 8 bytes of salt, rest is ciphered.
 pub fn encrypt_aes_openssl(data: &[u8], key: &[u8], maybe_salt: Option<&[u8]>) -> Result<Vec<u8>> {
+    encrypt_aes_openssl_with_rng(data, key, maybe_salt, &mut thread_rng())
+}
+
+/// Same as `encrypt_aes_openssl`, but random salt is taken from the given random number generator.
+/// Useful to get deterministic output in tests or to control the source of entropy.
+pub fn encrypt_aes_openssl_with_rng(
+    data: &[u8],
+    key: &[u8],
+    maybe_salt: Option<&[u8]>,
+    rng: &mut impl RngCore,
+) -> Result<Vec<u8>> {
     let mut salt = [0_u8; OPENSSL_SALT_LENGTH];
     match maybe_salt {
         Some(user_salt) if user_salt.len() == OPENSSL_SALT_LENGTH => salt.copy_from_slice(user_salt),
-        _ => rand::thread_rng().fill(&mut salt),
+        _ => rng.fill(&mut salt),
     };
 
     let (key, iv) = generate_aes_key_and_iv(AES_CBC_KEY_LENGTH, AES_CBC_IV_LENGTH, 1, Some(&salt), key);
@@ -445,7 +821,7 @@ fn decrypt_aes_cbc_with_key_and_iv(
 /// and encrypted message in base64-encoded part starting at `AES_GCM_IV_LENGTH` string index.
 pub fn encrypt_aes_gcm_base64(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
     let (mut iv, encrypted) = encrypt_aes_gcm(data, key)?;
-    iv.push_str(&base64::encode(encrypted));
+    iv.push_str(&utils::base64_encode(encrypted, Base64Padding::Padded));
     Ok(iv.into_bytes())
 }
 
@@ -461,9 +837,27 @@ pub fn encrypt_aes_gcm_bstr(data: &[u8], key: &[u8]) -> Result<String> {
 /// Calculates AES-GCM hash. Returns IV in the first item,
 /// and raw encrypted message in the second item.
 pub fn encrypt_aes_gcm(data: &[u8], key: &[u8]) -> Result<(String, Vec<u8>)> {
-    let derived_key = derive_key_from_password_256(key, key, 1);
-    let iv = utils::random_alphanumeric_string(AES_GCM_IV_LENGTH);
-    let cipher = Aes256Gcm::new(Key::from_slice(&derived_key));
+    encrypt_aes_gcm_with_rng(data, key, &mut thread_rng())
+}
+
+/// Same as `encrypt_aes_gcm`, but IV is taken from the given random number generator.
+/// Useful to get deterministic output in tests or to control the source of entropy.
+pub fn encrypt_aes_gcm_with_rng(data: &[u8], key: &[u8], rng: &mut impl RngCore) -> Result<(String, Vec<u8>)> {
+    encrypt_aes_gcm_with_derived_key(data, &derive_aes_gcm_key(key), rng)
+}
+
+/// Derives the actual AES-GCM key Filen uses from the given key.
+fn derive_aes_gcm_key(key: &[u8]) -> [u8; 32] {
+    derive_key_from_password_256(key, key, 1)
+}
+
+fn encrypt_aes_gcm_with_derived_key(
+    data: &[u8],
+    derived_key: &[u8],
+    rng: &mut impl RngCore,
+) -> Result<(String, Vec<u8>)> {
+    let iv = utils::random_alphanumeric_string_with_rng(AES_GCM_IV_LENGTH, rng);
+    let cipher = Aes256Gcm::new(Key::from_slice(derived_key));
     let nonce = Nonce::from_slice(iv.as_bytes());
     let encrypted = cipher.encrypt(nonce, data).context(AesGcmCannotCipherDataSnafu {
         data_length: data.len(),
@@ -474,7 +868,7 @@ pub fn encrypt_aes_gcm(data: &[u8], key: &[u8]) -> Result<(String, Vec<u8>)> {
 /// Decrypts data prefiously encrypted with `encrypt_aes_gcm_base64`.
 pub fn decrypt_aes_gcm_base64(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
     let (iv, encrypted_base64) = extract_aes_gcm_iv_and_message(data)?;
-    base64::decode(encrypted_base64)
+    utils::base64_decode(encrypted_base64)
         .context(CannotDecodeBase64Snafu {})
         .and_then(|encrypted| decrypt_aes_gcm_from_iv_and_bytes(key, iv, &encrypted))
 }
@@ -486,8 +880,11 @@ pub fn decrypt_aes_gcm(data: &[u8], key: &[u8]) -> Result<Vec<u8>> {
 }
 
 fn decrypt_aes_gcm_from_iv_and_bytes(key: &[u8], iv: &[u8], encrypted: &[u8]) -> Result<Vec<u8>> {
-    let derived_key = derive_key_from_password_256(key, key, 1);
-    let cipher = Aes256Gcm::new(Key::from_slice(&derived_key));
+    decrypt_aes_gcm_with_derived_key(&derive_aes_gcm_key(key), iv, encrypted)
+}
+
+fn decrypt_aes_gcm_with_derived_key(derived_key: &[u8], iv: &[u8], encrypted: &[u8]) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new(Key::from_slice(derived_key));
     let nonce = Nonce::from_slice(iv);
     cipher
         .decrypt(nonce, encrypted)
@@ -565,11 +962,222 @@ fn generate_aes_key_and_iv(
     (Vec::from(key), Vec::from(iv))
 }
 
+/// Exposes crypto primitives which are normally private, so downstream crates can test their own code
+/// against the exact crypto Filen uses. Available only with `test-internals` feature.
+///
+/// **Not for production use.** These functions are implementation details: their signatures can change
+/// in any release, and using them directly makes it easy to produce data official Filen clients cannot read.
+#[cfg(feature = "test-internals")]
+pub mod internals {
+    use super::Result;
+
+    pub use super::{
+        decrypt_aes_gcm, decrypt_aes_openssl, derive_key_from_password_256, derive_key_from_password_512,
+        encrypt_aes_gcm, encrypt_aes_openssl, hash_fn, hash_password,
+    };
+
+    /// Encrypts data with AES256 in CBC mode with Pkcs7 padding, using the given key and IV.
+    pub fn encrypt_aes_cbc(
+        data: &[u8],
+        key: &[u8; super::AES_CBC_KEY_LENGTH],
+        iv: &[u8; super::AES_CBC_IV_LENGTH],
+    ) -> Result<Vec<u8>> {
+        super::encrypt_aes_cbc_with_key_and_iv(data, key, iv)
+    }
+
+    /// Decrypts data previously encrypted with `encrypt_aes_cbc`.
+    pub fn decrypt_aes_cbc(
+        data: &[u8],
+        key: &[u8; super::AES_CBC_KEY_LENGTH],
+        iv: &[u8; super::AES_CBC_IV_LENGTH],
+    ) -> Result<Vec<u8>> {
+        super::decrypt_aes_cbc_with_key_and_iv(data, key, iv)
+    }
+
+    /// Decrypts AES-GCM encrypted bytes with the given IV, instead of reading IV from the data prefix.
+    pub fn decrypt_aes_gcm_with_iv(data: &[u8], key: &[u8], iv: &[u8]) -> Result<Vec<u8>> {
+        super::decrypt_aes_gcm_from_iv_and_bytes(key, iv, data)
+    }
+
+    /// Derives OpenSSL-compatible AES key and IV from password and salt with MD5-based EVP KDF.
+    #[must_use]
+    pub fn evp_bytes_to_key(
+        password: &[u8],
+        salt: Option<&[u8]>,
+        key_length: usize,
+        iv_length: usize,
+        iterations: usize,
+    ) -> (Vec<u8>, Vec<u8>) {
+        super::generate_aes_key_and_iv(key_length, iv_length, iterations, salt, password)
+    }
+
+    /// Reads metadata version mark without decrypting metadata.
+    /// Returns -1 for base64-decoded version 1 metadata.
+    pub fn read_metadata_version(data: &[u8]) -> Result<i32> {
+        super::read_metadata_version(data)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn internals_should_match_public_crypto() {
+            let key = b"test";
+            let encrypted = encrypt_aes_openssl(b"This is Jimmy.", key, Some(b"12345678")).unwrap();
+            let (aes_key, aes_iv) = evp_bytes_to_key(key, Some(b"12345678"), 32, 16, 1);
+            let aes_key: [u8; 32] = aes_key.try_into().unwrap();
+            let aes_iv: [u8; 16] = aes_iv.try_into().unwrap();
+
+            let decrypted = decrypt_aes_cbc(&encrypted[16..], &aes_key, &aes_iv).unwrap();
+
+            assert_eq!(decrypted, b"This is Jimmy.");
+            assert_eq!(
+                encrypt_aes_cbc(b"This is Jimmy.", &aes_key, &aes_iv).unwrap(),
+                &encrypted[16..]
+            );
+            assert_eq!(read_metadata_version(b"002abc").unwrap(), 2);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::test_utils::read_project_file;
     use pretty_assertions::{assert_eq, assert_ne};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn detect_metadata_version_should_detect_v1_and_v2() {
+        let key = b"b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae";
+        let metadata_v1 = encrypt_metadata(b"This is Jimmy.", key, 1).unwrap();
+        let metadata_v2 = encrypt_metadata(b"This is Jimmy.", key, 2).unwrap();
+        let metadata_v1_decoded = base64::decode(&metadata_v1).unwrap();
+
+        assert_eq!(detect_metadata_version(&metadata_v1).unwrap(), MetadataVersion::V1);
+        assert_eq!(
+            detect_metadata_version(&metadata_v1_decoded).unwrap(),
+            MetadataVersion::V1
+        );
+        assert_eq!(detect_metadata_version(&metadata_v2).unwrap(), MetadataVersion::V2);
+    }
+
+    #[test]
+    fn detect_metadata_version_should_fail_for_unknown_version() {
+        assert!(matches!(
+            detect_metadata_version(b"003whatever"),
+            Err(Error::UnsupportedFilenMetadataVersion { .. })
+        ));
+        assert!(matches!(
+            detect_metadata_version(b"garbage"),
+            Err(Error::CannotParseFilenMetadataVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn decrypt_master_keys_metadata_should_return_last_master_key() {
+        let first_key = SecUtf8::from("ed8d39b6c2d00ece398199a3e83988f1c4942b24");
+        let last_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let metadata = encrypt_master_keys_metadata(&[first_key.clone(), last_key.clone()], &last_key, 1).unwrap();
+
+        let master_keys = decrypt_master_keys_metadata(&metadata, &last_key).unwrap();
+
+        assert_eq!(master_keys, vec![first_key, last_key.clone()]);
+        assert_eq!(master_keys.last_master_key(), Some(last_key));
+    }
+
+    #[test]
+    fn decrypt_master_keys_metadata_should_skip_blank_keys() {
+        let key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let metadata = encrypt_metadata_str(&format!("|{}|", key.unsecure()), &key, 1).unwrap();
+        let blank_metadata = encrypt_metadata_str("|", &key, 1).unwrap();
+
+        let master_keys = decrypt_master_keys_metadata(&metadata, &key).unwrap();
+        let blank_result = decrypt_master_keys_metadata(&blank_metadata, &key);
+
+        assert_eq!(master_keys, vec![key]);
+        assert!(matches!(blank_result, Err(Error::BadArgument { .. })));
+    }
+
+    #[test]
+    fn decrypt_rsa_should_fail_with_no_private_key_for_empty_key() {
+        let result = decrypt_rsa(b"encrypted share metadata", &[]);
+
+        assert!(matches!(result, Err(Error::NoPrivateKey { .. })));
+        assert!(matches!(
+            decrypt_private_key_metadata("", &[SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae")]),
+            Err(Error::NoPrivateKey { .. })
+        ));
+    }
+
+    #[test]
+    fn master_keys_should_encrypt_with_last_key_and_decrypt_with_any_key() {
+        let first_key = SecUtf8::from("ed8d39b6c2d00ece398199a3e83988f1c4942b24");
+        let last_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let master_keys = MasterKeys::new(vec![first_key.clone(), last_key.clone()]).unwrap();
+        let old_metadata = encrypt_metadata_str("old", &first_key, 2).unwrap();
+        let old_metadata_v1 = encrypt_metadata_str("old v1", &first_key, 1).unwrap();
+
+        let new_metadata = master_keys.encrypt_metadata("new", 2).unwrap();
+
+        assert_eq!(master_keys.last_key(), &last_key);
+        assert_eq!(&new_metadata[..FILEN_VERSION_LENGTH], "002");
+        assert_eq!(decrypt_metadata_str(&new_metadata, &last_key).unwrap(), "new");
+        assert_eq!(master_keys.decrypt_metadata(&new_metadata).unwrap(), "new");
+        assert_eq!(master_keys.decrypt_metadata(&old_metadata).unwrap(), "old");
+        assert_eq!(master_keys.decrypt_metadata(&old_metadata_v1).unwrap(), "old v1");
+    }
+
+    #[test]
+    fn master_keys_should_fail_to_decrypt_metadata_encrypted_with_unknown_key() {
+        let master_keys = MasterKeys::new(vec![SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae")]).unwrap();
+        let metadata =
+            encrypt_metadata_str("secret", &SecUtf8::from("ed8d39b6c2d00ece398199a3e83988f1c4942b24"), 2).unwrap();
+
+        let result = master_keys.decrypt_metadata(&metadata);
+
+        assert!(matches!(result, Err(Error::BadArgument { .. })));
+        assert!(matches!(MasterKeys::new(Vec::new()), Err(Error::BadArgument { .. })));
+    }
+
+    #[test]
+    fn master_keys_should_be_created_from_master_keys_metadata() {
+        let first_key = SecUtf8::from("ed8d39b6c2d00ece398199a3e83988f1c4942b24");
+        let last_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let metadata = encrypt_master_keys_metadata(&[first_key.clone(), last_key.clone()], &last_key, 2).unwrap();
+
+        let master_keys = MasterKeys::from_metadata(&metadata, &last_key).unwrap();
+
+        assert_eq!(master_keys.keys(), &[first_key, last_key]);
+    }
+
+    #[test]
+    fn hash_name_should_match_filen_name_hashes() {
+        // Hashes were sent by the official Filen web client for these names.
+        assert_eq!(hash_name("test_folder"), "19d24c63b1170a0b1b40520a636a25235735f39f");
+        assert_eq!(hash_name("Test_Folder"), "19d24c63b1170a0b1b40520a636a25235735f39f");
+        assert_eq!(hash_name("test.txt"), "809a953250a3917a9993645d1ba146348a198fc2");
+    }
+
+    #[test]
+    fn hash_name_should_lowercase_non_ascii_names() {
+        assert_eq!(hash_name("Café"), "625b03007cb51805e416cd31caf6f8eb0c3b7f07");
+        assert_eq!(hash_name("CAFÉ"), "625b03007cb51805e416cd31caf6f8eb0c3b7f07");
+        assert_eq!(hash_name("Ünïcödé Földer"), "c7199c97e7ec8e09b91f86b9bf468c1ea745d9c0");
+        assert_eq!(hash_name("📁 Фото 😀"), "dfce158cbe6cf912e29b4ec30d2f80a52860d548");
+        // Final sigma is lowercased to 'ς', like JavaScript does.
+        assert_eq!(hash_name("ΟΔΟΣ"), "e3112dcd2e8198c7073421d887cb6ed24150a135");
+    }
+
+    #[test]
+    fn hash_name_should_not_normalize_decomposed_names() {
+        let decomposed_name = "cafe\u{301}";
+
+        assert_eq!(hash_name(decomposed_name), "8fabfa7a0d2eaa9d75df44becb0b5177edaddf00");
+        assert_ne!(hash_name(decomposed_name), hash_name("café"));
+    }
 
     #[test]
     fn encrypt_metadata_v1_should_use_simple_aes_with_base64() {
@@ -597,6 +1205,49 @@ mod tests {
         assert_eq!(String::from_utf8_lossy(&decrypted_metadata), expected_metadata);
     }
 
+    #[test]
+    fn decrypt_metadata_with_master_key_should_use_plain_master_key_string() {
+        let m_key = SecUtf8::from(" a8e949c7907eb67e02ee2f07a44c17a0638746e3\n");
+        let metadata_base64 = "U2FsdGVkX1//gOpv81xPNI3PuT1CryNCVXpcfmISGNR+1g2OPT8SBP2/My7G6o5lSvVtkn2smbYrAo1\
+        Mgaq9RIJlCEjcYpMsr+A9RSpkX7zLyXtMPV6q+PRbQj1WkP8ymuh0lmmnFRa+oRy0EvJnw97m3aLTHN4DD5XmJ36tecA2cwSrFskYn9E8+0\
+        y+Wj/LcXh1l5n4Q1l5j8TSjS5mIQ==";
+        let expected_metadata = "{\"name\":\"perform.js\",\"size\":156,\"mime\":\"application/javascript\",\
+        \"key\":\"tqNrczqVdTCgFzB1b1gyiQBIYmwDBwa9\",\"lastModified\":499162500}";
+
+        let decrypted_metadata = decrypt_metadata_with_master_key(metadata_base64.as_bytes(), &m_key).unwrap();
+
+        assert_eq!(hash_fn("test"), master_key_hex(&m_key));
+        assert_eq!(String::from_utf8_lossy(&decrypted_metadata), expected_metadata);
+    }
+
+    #[test]
+    fn decrypt_metadata_to_string_should_not_return_garbage_for_wrong_key() {
+        let wrong_key = hash_fn("wrong key");
+        let metadata_base64 = "U2FsdGVkX1//gOpv81xPNI3PuT1CryNCVXpcfmISGNR+1g2OPT8SBP2/My7G6o5lSvVtkn2smbYrAo1\
+        Mgaq9RIJlCEjcYpMsr+A9RSpkX7zLyXtMPV6q+PRbQj1WkP8ymuh0lmmnFRa+oRy0EvJnw97m3aLTHN4DD5XmJ36tecA2cwSrFskYn9E8+0\
+        y+Wj/LcXh1l5n4Q1l5j8TSjS5mIQ==";
+
+        let decrypted_metadata_result = decrypt_metadata_to_string(metadata_base64.as_bytes(), wrong_key.as_bytes());
+
+        assert!(matches!(
+            decrypted_metadata_result,
+            Err(Error::DecryptedMetadataIsNotUtf8 { .. } | Error::AesCbcCannotDecipherData { .. })
+        ));
+    }
+
+    #[test]
+    fn decrypt_metadata_to_string_should_reject_invalid_utf8() {
+        let m_key = hash_fn("test");
+        let encrypted_metadata = encrypt_metadata(&[0xff, 0xfe, 0xfd], m_key.as_bytes(), 2).unwrap();
+
+        let decrypted_metadata_result = decrypt_metadata_to_string(&encrypted_metadata, m_key.as_bytes());
+
+        assert!(matches!(
+            decrypted_metadata_result,
+            Err(Error::DecryptedMetadataIsNotUtf8 { .. })
+        ));
+    }
+
     #[test]
     fn encrypt_metadata_v2_should_use_aes_gcm_with_version_mark() {
         let m_key = hash_fn("test");
@@ -609,6 +1260,27 @@ mod tests {
         assert_eq!(&encrypted_metadata[..3], b"002");
     }
 
+    #[test]
+    fn encrypted_metadata_len_should_match_lengths_of_encrypted_metadata() {
+        let m_key = hash_fn("test");
+        let metadata = "{\"name\":\"perform.js\",\"size\":156,\"mime\":\"application/javascript\",\
+        \"key\":\"tqNrczqVdTCgFzB1b1gyiQBIYmwDBwa9\",\"lastModified\":499162500}";
+
+        assert_eq!(metadata.len(), 130);
+        assert_eq!(encrypted_metadata_len(metadata.len(), MetadataVersion::V1), 216);
+        assert_eq!(encrypted_metadata_len(metadata.len(), MetadataVersion::V2), 211);
+        assert_eq!(metadata_overhead(metadata.len(), MetadataVersion::V1), 86);
+        assert_eq!(metadata_overhead(metadata.len(), MetadataVersion::V2), 81);
+        assert_eq!(metadata_overhead(0, MetadataVersion::V2), 0);
+        for len in [1, 2, 3, 15, 16, 17, 31, 32, 33, 100, 1000] {
+            let data = vec![b'a'; len];
+            for version in [MetadataVersion::V1, MetadataVersion::V2] {
+                let encrypted_metadata = encrypt_metadata(&data, m_key.as_bytes(), version.into()).unwrap();
+                assert_eq!(encrypted_metadata_len(len, version), encrypted_metadata.len());
+            }
+        }
+    }
+
     #[test]
     fn decrypt_metadata_v2_should_use_aes_gcm_with_version_mark() {
         let m_key = hash_fn("test");
@@ -657,6 +1329,102 @@ mod tests {
         assert_eq!(decrypted_metadata, expected_metadata);
     }
 
+    #[test]
+    fn migrate_metadata_blobs_should_reencrypt_valid_blobs_and_report_corrupt_ones() {
+        let old_key = SecUtf8::from(hash_fn("test"));
+        let new_key = SecUtf8::from(hash_fn("new test"));
+        let metadata = "{\"name\":\"perform.js\",\"size\":156,\"mime\":\"application/javascript\",\
+        \"key\":\"tqNrczqVdTCgFzB1b1gyiQBIYmwDBwa9\",\"lastModified\":499162500}";
+        let blobs = vec![
+            encrypt_metadata_str(metadata, &old_key, 1).unwrap(),
+            "002this is not valid metadata".to_owned(),
+            encrypt_metadata_str(metadata, &old_key, 2).unwrap(),
+            encrypt_metadata_str(metadata, &new_key, 2).unwrap(),
+        ];
+
+        let results = migrate_metadata_blobs(&blobs, &old_key, &new_key, 2);
+
+        assert_eq!(results.len(), 4);
+        assert!(results[1].is_err());
+        assert!(results[3].is_err());
+        for migrated in [&results[0], &results[2]] {
+            let migrated = migrated.as_ref().unwrap();
+            assert_eq!(&migrated[..3], "002");
+            assert_eq!(decrypt_metadata_str(migrated, &new_key).unwrap(), metadata);
+        }
+    }
+
+    #[test]
+    fn note_content_should_round_trip_with_note_key() {
+        let note_key = SecUtf8::from("Zb3VJ1Y6q0oQ2rPw8GfT5mKxL9cNhDsA");
+        let text = "Groceries:\n- milk\n- \"fancy\" bread";
+
+        for metadata_version in [1, 2] {
+            let encrypted = encrypt_note_content(text, &note_key, metadata_version).unwrap();
+            let decrypted = decrypt_note_content(&encrypted, &note_key).unwrap();
+
+            assert_eq!(decrypted, text);
+            assert_eq!(
+                decrypt_metadata_str(&encrypted, &note_key).unwrap(),
+                serde_json::json!({ "content": text }).to_string()
+            );
+        }
+        assert_eq!(decrypt_note_content("", &note_key).unwrap(), "");
+    }
+
+    #[test]
+    fn decrypt_note_content_should_reject_metadata_which_is_not_note_content() {
+        let note_key = SecUtf8::from("Zb3VJ1Y6q0oQ2rPw8GfT5mKxL9cNhDsA");
+        let metadata = encrypt_metadata_str("just some name", &note_key, 2).unwrap();
+
+        assert!(matches!(
+            decrypt_note_content(&metadata, &note_key),
+            Err(Error::NoteContentIsNotJson { .. })
+        ));
+    }
+
+    #[test]
+    fn decrypt_metadata_or_plain_should_return_plain_json_as_is() {
+        let key = b"b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae";
+        let plain_json = br#"{"name":"legacy.txt","size":1}"#;
+
+        let result = decrypt_metadata_or_plain(plain_json, key).unwrap();
+
+        assert_eq!(result, plain_json);
+    }
+
+    #[test]
+    fn decrypt_metadata_or_plain_should_decrypt_encrypted_metadata() {
+        let key = b"b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae";
+        let plain_json = br#"{"name":"new.txt","size":1}"#;
+
+        for metadata_version in [1, 2] {
+            let metadata = encrypt_metadata(plain_json, key, metadata_version).unwrap();
+
+            let result = decrypt_metadata_or_plain(&metadata, key).unwrap();
+
+            assert_eq!(result, plain_json);
+        }
+        assert!(matches!(
+            decrypt_metadata_or_plain(b"{not json", key),
+            Err(Error::CannotParseFilenMetadataVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn decrypt_metadata_should_accept_metadata_with_stripped_base64_padding() {
+        let key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let data = "This is Jimmy";
+
+        for metadata_version in [1, 2] {
+            let metadata = encrypt_metadata_str(data, &key, metadata_version).unwrap();
+            assert!(metadata.ends_with('='));
+            let unpadded = metadata.trim_end_matches('=');
+
+            assert_eq!(decrypt_metadata_str(unpadded, &key).unwrap(), data);
+        }
+    }
+
     #[test]
     fn encrypt_aes_gcm_should_should_work_and_have_same_algorithm() {
         let key = b"test";
@@ -670,6 +1438,142 @@ mod tests {
         assert_eq!(String::from_utf8_lossy(&decrypted_data), expected_data);
     }
 
+    #[test]
+    fn decrypt_metadata_with_keys_should_use_first_key_which_decrypts() {
+        let old_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let new_key = SecUtf8::from("ed8d39b6c2d00ece398199a3e83988f1c4942b24");
+        let metadata = encrypt_metadata(b"This is Jimmy.", old_key.unsecure().as_bytes(), 2).unwrap();
+
+        let decrypted = decrypt_metadata_with_keys(&metadata, &[new_key.clone(), old_key]).unwrap();
+        let not_decrypted = decrypt_metadata_with_keys(&metadata, &[new_key]);
+
+        assert_eq!(decrypted, b"This is Jimmy.");
+        assert!(not_decrypted.is_err());
+    }
+
+    #[test]
+    fn encrypt_to_link_password_and_salt_should_produce_lowercase_hex_salt() {
+        let link_password = SecUtf8::from("test");
+
+        let (password_hashed, salt) = encrypt_to_link_password_and_salt(&link_password);
+        let (_, other_salt) = encrypt_to_link_password_and_salt(&link_password);
+
+        assert_eq!(salt.len(), LINK_SALT_LENGTH);
+        assert!(salt.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)));
+        assert_eq!(utils::hex_string_to_bytes(&salt).unwrap().len(), LINK_SALT_BYTES);
+        assert_ne!(salt, other_salt);
+        assert_eq!(password_hashed, link_password_key(&link_password, &salt));
+    }
+
+    #[test]
+    fn encrypt_to_link_password_and_salt_with_rng_should_hex_encode_generated_bytes() {
+        let mut rng = rand::rngs::mock::StepRng::new(0, 0);
+
+        let (_, salt) = encrypt_to_link_password_and_salt_with_rng(&SecUtf8::from("test"), &mut rng);
+
+        assert_eq!(salt, "0".repeat(LINK_SALT_LENGTH));
+    }
+
+    #[test]
+    fn decrypt_link_metadata_should_decrypt_metadata_encrypted_with_link_password_key() {
+        let link_password = SecUtf8::from("test");
+        let salt = "4JtoNm7sbakhPyAfJlpHwakrGB3CGzUq";
+        let link_key = "259156460fab5ece5ad2bcf00d247311cd1c851c7b6b9f107246fb545488f152\
+                        5e8695525ad2d023220c6a2d8c659b0cb9b516faa8685be1542b295c02530e27";
+        let metadata = String::from_utf8(encrypt_metadata(b"This is Jimmy.", link_key.as_bytes(), 2).unwrap()).unwrap();
+
+        let decrypted = decrypt_link_metadata(&metadata, &link_password, salt).unwrap();
+        let wrong_password_result = decrypt_link_metadata(&metadata, &SecUtf8::from("wrong"), salt);
+
+        assert_eq!(decrypted, b"This is Jimmy.");
+        assert!(wrong_password_result.is_err());
+    }
+
+    #[test]
+    fn master_key_hex_should_be_usable_as_metadata_key() {
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let master_keys_metadata = encrypt_master_keys_metadata(std::slice::from_ref(&m_key), &m_key, 2).unwrap();
+        let decrypted_m_key = decrypt_master_keys_metadata(&master_keys_metadata, &m_key).unwrap()[0].clone();
+        let metadata = encrypt_metadata_str("This is Jimmy.", &m_key, 2).unwrap();
+
+        let hex = master_key_hex(&decrypted_m_key);
+
+        assert_eq!(hex, "b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        assert_eq!(
+            decrypt_metadata(metadata.as_bytes(), hex.as_bytes()).unwrap(),
+            b"This is Jimmy."
+        );
+    }
+
+    #[test]
+    fn encrypt_aes_should_round_trip_empty_data() {
+        let key = b"test";
+        let (iv, encrypted_gcm) = encrypt_aes_gcm(b"", key).unwrap();
+        let mut encrypted_gcm_with_iv = iv.into_bytes();
+        encrypted_gcm_with_iv.extend_from_slice(&encrypted_gcm);
+        let encrypted_gcm_base64 = encrypt_aes_gcm_base64(b"", key).unwrap();
+        let encrypted_openssl = encrypt_aes_openssl(b"", key, None).unwrap();
+
+        assert_eq!(decrypt_aes_gcm(&encrypted_gcm_with_iv, key).unwrap(), b"");
+        assert_eq!(decrypt_aes_gcm_base64(&encrypted_gcm_base64, key).unwrap(), b"");
+        assert_eq!(decrypt_aes_openssl(&encrypted_openssl, key).unwrap(), b"");
+    }
+
+    #[test]
+    fn encrypt_metadata_should_round_trip_empty_data() {
+        let key = b"b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae";
+
+        for metadata_version in [1, 2] {
+            let encrypted = encrypt_metadata(b"", key, metadata_version).unwrap();
+            assert_eq!(decrypt_metadata(&encrypted, key).unwrap(), b"");
+        }
+    }
+
+    #[test]
+    fn decrypt_file_chunk_should_round_trip_empty_chunk() {
+        let file_key = b"sh1YRHfx22Ij40tQBbt6BgpBlqkzch8Y";
+
+        for version in [1, 2] {
+            let encrypted = encrypt_file_chunk(b"", file_key, version).unwrap();
+            assert_eq!(
+                decrypt_file_chunk(encrypted.as_bytes(), file_key, version).unwrap(),
+                b""
+            );
+        }
+        assert!(decrypt_file_chunk(b"", file_key, 3).is_err());
+    }
+
+    #[test]
+    fn encrypt_aes_openssl_with_rng_should_be_deterministic_for_seeded_rng() {
+        let key = b"test";
+        let encrypted = encrypt_aes_openssl_with_rng(b"This is Jimmy.", key, None, &mut StdRng::seed_from_u64(42));
+        let encrypted_again =
+            encrypt_aes_openssl_with_rng(b"This is Jimmy.", key, None, &mut StdRng::seed_from_u64(42));
+        let encrypted_other_seed =
+            encrypt_aes_openssl_with_rng(b"This is Jimmy.", key, None, &mut StdRng::seed_from_u64(43));
+
+        assert_eq!(encrypted.as_ref().unwrap(), encrypted_again.as_ref().unwrap());
+        assert_ne!(encrypted.as_ref().unwrap(), encrypted_other_seed.as_ref().unwrap());
+        assert_eq!(
+            decrypt_aes_openssl(&encrypted.unwrap(), key).unwrap(),
+            b"This is Jimmy."
+        );
+    }
+
+    #[test]
+    fn encrypt_aes_gcm_with_rng_should_be_deterministic_for_seeded_rng() {
+        let key = b"test";
+        let (iv, encrypted) = encrypt_aes_gcm_with_rng(b"This is Jimmy.", key, &mut StdRng::seed_from_u64(42)).unwrap();
+        let (iv_again, encrypted_again) =
+            encrypt_aes_gcm_with_rng(b"This is Jimmy.", key, &mut StdRng::seed_from_u64(42)).unwrap();
+        let mut combined = iv.clone().into_bytes();
+        combined.extend_from_slice(&encrypted);
+
+        assert_eq!(iv, iv_again);
+        assert_eq!(encrypted, encrypted_again);
+        assert_eq!(decrypt_aes_gcm(&combined, key).unwrap(), b"This is Jimmy.");
+    }
+
     #[test]
     fn encrypt_aes_openssl_should_return_valid_aes_hash_without_explicit_salt() {
         let key = b"test";
@@ -777,6 +1681,27 @@ mod tests {
         assert_eq!(actual_hash, expected_hash);
     }
 
+    #[test]
+    fn verify_file_chunk_should_accept_valid_chunk() {
+        let file_key = SecUtf8::from("sh1YRHfx22Ij40tQBbt6BgpBlqkzch8Y");
+        let encrypted_chunk = encrypt_file_chunk(b"This is Jimmy.", b"sh1YRHfx22Ij40tQBbt6BgpBlqkzch8Y", 2).unwrap();
+        let encrypted_chunk_bytes = encrypted_chunk.chars().map(|c| c as u8).collect::<Vec<u8>>();
+
+        assert!(verify_file_chunk(&encrypted_chunk_bytes, &file_key).is_ok());
+    }
+
+    #[test]
+    fn verify_file_chunk_should_reject_tampered_chunk() {
+        let file_key = SecUtf8::from("sh1YRHfx22Ij40tQBbt6BgpBlqkzch8Y");
+        let encrypted_chunk = encrypt_file_chunk(b"This is Jimmy.", b"sh1YRHfx22Ij40tQBbt6BgpBlqkzch8Y", 2).unwrap();
+        let mut encrypted_chunk_bytes = encrypted_chunk.chars().map(|c| c as u8).collect::<Vec<u8>>();
+        encrypted_chunk_bytes[AES_GCM_IV_LENGTH] ^= 1;
+
+        let verify_result = verify_file_chunk(&encrypted_chunk_bytes, &file_key);
+
+        assert!(matches!(verify_result, Err(Error::AesGcmCannotDecipherData { .. })));
+    }
+
     #[test]
     fn decrypt_file_data_should_decrypt_raw_aes_cbc() {
         let file_key: &[u8; 32] = b"sh1YRHfx22Ij40tQBbt6BgpBlqkzch8Y";
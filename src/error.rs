@@ -0,0 +1,153 @@
+//! Contains crate-level `Error`, which every module error converts into, and a matching `Result` alias.
+//!
+//! Module functions keep returning their own errors, so callers can still match on specific failures,
+//! while application code composing calls from several modules can use `?` with a single error type.
+use crate::{crypto, queries, v1, CertificatePinError};
+use snafu::Snafu;
+
+/// Result with crate-level `Error` by default.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Any error returned by this crate. Variants are named after modules and wrap their errors as is.
+#[derive(Snafu, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    #[snafu(context(false), display("{}", source))]
+    CertificatePin { source: CertificatePinError },
+
+    #[snafu(context(false), display("{}", source))]
+    Crypto { source: crypto::Error },
+
+    #[snafu(context(false), display("{}", source))]
+    Queries { source: queries::Error },
+
+    #[snafu(context(false), display("{}", source))]
+    Filen { source: v1::Error },
+
+    #[snafu(context(false), display("{}", source))]
+    Auth { source: v1::AuthError },
+
+    #[snafu(context(false), display("{}", source))]
+    Bulk { source: v1::BulkError },
+
+    #[snafu(context(false), display("{}", source))]
+    Client { source: v1::ClientError },
+
+    #[snafu(context(false), display("{}", source))]
+    DirLinks { source: v1::DirLinksError },
+
+    #[snafu(context(false), display("{}", source))]
+    DirPath { source: v1::DirPathError },
+
+    #[snafu(context(false), display("{}", source))]
+    Dirs { source: v1::DirsError },
+
+    #[snafu(context(false), display("{}", source))]
+    DownloadDir { source: v1::DownloadDirError },
+
+    #[snafu(context(false), display("{}", source))]
+    DownloadFile { source: v1::DownloadFileError },
+
+    #[snafu(context(false), display("{}", source))]
+    Events { source: v1::EventsError },
+
+    #[snafu(context(false), display("{}", source))]
+    FileLinks { source: v1::FileLinksError },
+
+    #[snafu(context(false), display("{}", source))]
+    Files { source: v1::FilesError },
+
+    #[snafu(context(false), display("{}", source))]
+    Fs { source: v1::FsError },
+
+    #[snafu(context(false), display("{}", source))]
+    Links {
+        #[snafu(source(from(v1::LinksError, Box::new)))]
+        source: Box<v1::LinksError>,
+    },
+
+    #[snafu(context(false), display("{}", source))]
+    Share {
+        #[snafu(source(from(v1::ShareError, Box::new)))]
+        source: Box<v1::ShareError>,
+    },
+
+    #[snafu(context(false), display("{}", source))]
+    SyncChanges { source: v1::SyncChangesError },
+
+    #[snafu(context(false), display("{}", source))]
+    SyncDir { source: v1::SyncDirError },
+
+    #[snafu(context(false), display("{}", source))]
+    UploadFile { source: v1::UploadFileError },
+
+    #[snafu(context(false), display("{}", source))]
+    Usage { source: v1::UsageError },
+
+    #[snafu(context(false), display("{}", source))]
+    User { source: v1::UserError },
+
+    #[snafu(context(false), display("{}", source))]
+    UserKeys { source: v1::UserKeysError },
+
+    #[snafu(context(false), display("{}", source))]
+    Versions { source: v1::VersionsError },
+
+    #[snafu(context(false), display("{}", source))]
+    Walk { source: v1::WalkError },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{deserialize_from_file, init_server, setup_json_mock},
+        utils,
+        v1::{user_dirs_request, FilenResponse, UserDirsResponsePayload},
+        FilenSettings,
+    };
+    use once_cell::sync::Lazy;
+    use pretty_assertions::assert_eq;
+    use secstr::SecUtf8;
+    use serde_json::json;
+
+    static API_KEY: Lazy<SecUtf8> =
+        Lazy::new(|| SecUtf8::from("bYZmrwdVEbHJSqeA1RfnPtKiBcXzUpRdKGRkjw9m1o1eqSGP1s6DM11CDnklpFq6"));
+    static M_KEY: Lazy<SecUtf8> = Lazy::new(|| SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae"));
+
+    /// Composes calls returning `dirs::Error`, `v1::Error` and `crypto::Error` with `?`.
+    fn first_folder_name(master_key: &SecUtf8, filen_settings: &FilenSettings) -> Result<String> {
+        let response = user_dirs_request(&API_KEY, filen_settings)?;
+        let folders = response.data_ref_or_err()?;
+        let name_metadata = folders.first().map_or("", |folder| folder.name_metadata.as_str());
+        Ok(crypto::decrypt_metadata_str(name_metadata, master_key)?)
+    }
+
+    #[test]
+    fn question_mark_should_convert_errors_of_different_modules() {
+        let (server, filen_settings) = init_server();
+        let mut response: UserDirsResponsePayload =
+            deserialize_from_file("tests/resources/responses/user_dirs_default.json");
+        response.data.as_mut().unwrap()[0].name_metadata = crypto::encrypt_metadata_str("Default", &M_KEY, 2).unwrap();
+        let mock = setup_json_mock("/v1/user/dirs", &utils::api_key_json(&API_KEY), &response, &server);
+
+        let name = first_folder_name(&M_KEY, &filen_settings);
+        let crypto_result = first_folder_name(&SecUtf8::from("wrong key"), &filen_settings);
+
+        mock.assert_hits(2);
+        assert_eq!(name.unwrap(), "Default");
+        assert!(matches!(crypto_result, Err(Error::Crypto { .. })));
+    }
+
+    #[test]
+    fn question_mark_should_convert_refused_response_error() {
+        let (server, filen_settings) = init_server();
+        let response = json!({"status": false, "message": "Invalid API key."});
+        let mock = setup_json_mock("/v1/user/dirs", &utils::api_key_json(&API_KEY), &response, &server);
+
+        let result = first_folder_name(&M_KEY, &filen_settings);
+
+        mock.assert_hits(1);
+        assert!(matches!(result, Err(Error::Filen { .. })));
+    }
+}
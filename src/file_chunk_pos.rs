@@ -1,5 +1,10 @@
 #![doc(hidden)]
 
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+use crate::utils;
+
 /// Represents single file chunk bounds, defined by chunk starting byte and chunk length.
 /// Also stores this chunk's index in its file.
 #[derive(Copy, Clone, Debug)]
@@ -69,10 +74,293 @@ impl Iterator for FileChunkPositions {
     }
 }
 
+/// Defines how file bytes are split into chunks.
+///
+/// Note that Filen itself expects files to be uploaded in fixed-size 1 MiB chunks, so content-defined chunking
+/// is meant for client-side change detection, e.g. to find which parts of a locally edited file differ
+/// from its previous version.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ChunkingStrategy {
+    /// Every chunk except the last one has the given size, in bytes.
+    Fixed { chunk_size: u32 },
+
+    /// Chunk boundaries are chosen by a rolling hash over file content (FastCDC), so inserting or removing bytes
+    /// only changes chunks near the edit, while chunks before and after it keep their content.
+    ///
+    /// Every chunk except the last one is at least `min_size` and at most `max_size` bytes long,
+    /// with chunk size averaging around `avg_size`. Sizes must satisfy `0 < min_size <= avg_size <= max_size`.
+    ContentDefined {
+        min_size: u32,
+        avg_size: u32,
+        max_size: u32,
+    },
+}
+
+impl ChunkingStrategy {
+    /// Calculates chunk positions for the given file bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if chunk sizes are 0 or content-defined chunk sizes are not ordered as `min <= avg <= max`.
+    #[must_use]
+    pub fn chunk_positions(&self, data: &[u8]) -> Vec<FileChunkPosition> {
+        match *self {
+            Self::Fixed { chunk_size } => FileChunkPositions::new(chunk_size, data.len() as u64).collect(),
+            Self::ContentDefined {
+                min_size,
+                avg_size,
+                max_size,
+            } => ContentDefinedChunkPositions::new(min_size, avg_size, max_size, data).collect(),
+        }
+    }
+
+    /// Calculates hex-encoded SHA-256 hashes of the chunks of the given file bytes. Keep them for the current file
+    /// version, so `ChunkingStrategy::changed_chunks` can later tell which chunks of the next version are new.
+    ///
+    /// # Panics
+    ///
+    /// Panics on invalid chunk sizes, same as `ChunkingStrategy::chunk_positions`.
+    #[must_use]
+    pub fn chunk_hashes(&self, data: &[u8]) -> Vec<String> {
+        self.chunk_positions(data)
+            .iter()
+            .map(|position| chunk_hash(data, position))
+            .collect()
+    }
+
+    /// Returns positions of the chunks of the given file bytes whose hashes are not among `previous_chunk_hashes`,
+    /// as calculated by `ChunkingStrategy::chunk_hashes` for the previous file version with the same strategy.
+    ///
+    /// # Panics
+    ///
+    /// Panics on invalid chunk sizes, same as `ChunkingStrategy::chunk_positions`.
+    #[must_use]
+    pub fn changed_chunks(&self, previous_chunk_hashes: &[String], data: &[u8]) -> Vec<FileChunkPosition> {
+        let previous_chunk_hashes = previous_chunk_hashes.iter().map(String::as_str).collect::<HashSet<_>>();
+        self.chunk_positions(data)
+            .into_iter()
+            .filter(|position| !previous_chunk_hashes.contains(chunk_hash(data, position).as_str()))
+            .collect()
+    }
+}
+
+fn chunk_hash(data: &[u8], position: &FileChunkPosition) -> String {
+    let start = position.start_position as usize;
+    let chunk = &data[start..start + position.chunk_size as usize];
+    utils::bytes_to_hex_string(&Sha256::digest(chunk))
+}
+
+/// Random values the gear rolling hash adds for every byte; generated with splitmix64 from a fixed seed,
+/// so chunk boundaries are stable between runs and crate versions.
+const GEAR: [u64; 256] = {
+    let mut table = [0_u64; 256];
+    let mut state = 0x9E37_79B9_7F4A_7C15_u64;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+/// Used as an iterator calculating content-defined chunk positions in the given bytes with FastCDC algorithm.
+#[derive(Clone, Debug)]
+pub struct ContentDefinedChunkPositions<'data> {
+    current_index: u32,
+    current_offset: usize,
+    min_size: usize,
+    avg_size: usize,
+    max_size: usize,
+    /// Harder to match mask used before chunk reaches average size, makes small chunks less likely.
+    small_chunk_mask: u64,
+    /// Easier to match mask used after chunk reaches average size, makes large chunks less likely.
+    large_chunk_mask: u64,
+    data: &'data [u8],
+}
+
+impl<'data> ContentDefinedChunkPositions<'data> {
+    pub fn new(min_size: u32, avg_size: u32, max_size: u32, data: &'data [u8]) -> Self {
+        if min_size == 0 {
+            panic!("Chunk size cannot be 0");
+        }
+        if min_size > avg_size || avg_size > max_size {
+            panic!("Chunk sizes should be ordered as min <= avg <= max");
+        }
+
+        let avg_bits = 31 - avg_size.leading_zeros();
+        Self {
+            current_index: 0,
+            current_offset: 0,
+            min_size: min_size as usize,
+            avg_size: avg_size as usize,
+            max_size: max_size as usize,
+            small_chunk_mask: high_bits_mask(avg_bits + 1),
+            large_chunk_mask: high_bits_mask(avg_bits.saturating_sub(1)),
+            data,
+        }
+    }
+
+    /// Returns length of the chunk starting at the beginning of the given bytes.
+    fn cut_point(&self, data: &[u8]) -> usize {
+        if data.len() <= self.min_size {
+            return data.len();
+        }
+
+        let max_len = data.len().min(self.max_size);
+        let avg_len = max_len.min(self.avg_size);
+        let mut hash = 0_u64;
+        for (i, byte) in data.iter().enumerate().take(max_len).skip(self.min_size) {
+            hash = (hash << 1).wrapping_add(GEAR[*byte as usize]);
+            let mask = if i < avg_len {
+                self.small_chunk_mask
+            } else {
+                self.large_chunk_mask
+            };
+            if hash & mask == 0 {
+                return i + 1;
+            }
+        }
+        max_len
+    }
+}
+
+impl Iterator for ContentDefinedChunkPositions<'_> {
+    type Item = FileChunkPosition;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        (self.current_offset < self.data.len()).then(|| {
+            let chunk_size = self.cut_point(&self.data[self.current_offset..]);
+            let result = FileChunkPosition {
+                index: self.current_index,
+                start_position: self.current_offset as u64,
+                chunk_size: chunk_size as u32,
+            };
+
+            self.current_index += 1;
+            self.current_offset += chunk_size;
+            result
+        })
+    }
+}
+
+/// Creates mask with the given number of highest bits set. Gear hash is shifted left on every byte,
+/// so its highest bits depend on the most bytes.
+const fn high_bits_mask(bits: u32) -> u64 {
+    if bits == 0 {
+        0
+    } else {
+        u64::MAX << (64 - bits)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Generates deterministic pseudo-random bytes, so content-defined chunk boundaries are reproducible.
+    fn pseudo_random_bytes(len: usize) -> Vec<u8> {
+        let mut state = 42_u32;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    fn chunks_of<'data>(data: &'data [u8], positions: &[FileChunkPosition]) -> Vec<&'data [u8]> {
+        positions
+            .iter()
+            .map(|pos| &data[pos.start_position as usize..(pos.start_position + pos.chunk_size as u64) as usize])
+            .collect()
+    }
+
+    const CONTENT_DEFINED: ChunkingStrategy = ChunkingStrategy::ContentDefined {
+        min_size: 2048,
+        avg_size: 8192,
+        max_size: 32768,
+    };
+
+    #[test]
+    fn content_defined_chunk_boundaries_should_be_stable_across_insertion() {
+        let original = pseudo_random_bytes(256 * 1024);
+        let mut edited = original.clone();
+        edited.insert(10_000, 0xFF);
+
+        let original_positions = CONTENT_DEFINED.chunk_positions(&original);
+        let edited_positions = CONTENT_DEFINED.chunk_positions(&edited);
+
+        let changed_chunks = CONTENT_DEFINED
+            .changed_chunks(&CONTENT_DEFINED.chunk_hashes(&original), &edited)
+            .len();
+        assert!(original_positions.len() > 10);
+        assert!(changed_chunks <= 2, "{} chunks changed", changed_chunks);
+        // Boundaries after the edited chunk are shifted by exactly one inserted byte.
+        let last_original = original_positions.last().unwrap();
+        let last_edited = edited_positions.last().unwrap();
+        assert_eq!(last_edited.start_position, last_original.start_position + 1);
+        assert_eq!(last_edited.chunk_size, last_original.chunk_size);
+    }
+
+    #[test]
+    fn changed_chunks_should_be_empty_for_same_content_and_cover_edit() {
+        let original = pseudo_random_bytes(64 * 1024);
+        let mut edited = original.clone();
+        edited[40_000] ^= 0xFF;
+        let original_hashes = CONTENT_DEFINED.chunk_hashes(&original);
+
+        let changed_chunks = CONTENT_DEFINED.changed_chunks(&original_hashes, &edited);
+
+        assert!(CONTENT_DEFINED.changed_chunks(&original_hashes, &original).is_empty());
+        assert_eq!(changed_chunks.len(), 1);
+        let changed_chunk = changed_chunks[0];
+        assert!(
+            changed_chunk.start_position <= 40_000
+                && 40_000 < changed_chunk.start_position + changed_chunk.chunk_size as u64
+        );
+    }
+
+    #[test]
+    fn fixed_chunk_boundaries_should_all_change_after_insertion() {
+        let strategy = ChunkingStrategy::Fixed { chunk_size: 8192 };
+        let original = pseudo_random_bytes(256 * 1024);
+        let mut edited = original.clone();
+        edited.insert(10_000, 0xFF);
+
+        let original_chunks = chunks_of(&original, &strategy.chunk_positions(&original));
+        let edited_chunks = chunks_of(&edited, &strategy.chunk_positions(&edited));
+
+        let unchanged_chunks = edited_chunks
+            .iter()
+            .filter(|chunk| original_chunks.contains(chunk))
+            .count();
+        assert_eq!(unchanged_chunks, 1);
+    }
+
+    #[test]
+    fn content_defined_chunks_should_cover_data_within_size_bounds() {
+        let data = pseudo_random_bytes(300 * 1024 + 17);
+
+        let positions = CONTENT_DEFINED.chunk_positions(&data);
+
+        let mut expected_start = 0_u64;
+        for (index, pos) in positions.iter().enumerate() {
+            assert_eq!(pos.index, index as u32);
+            assert_eq!(pos.start_position, expected_start);
+            assert!(pos.chunk_size <= 32768);
+            if index + 1 < positions.len() {
+                assert!(pos.chunk_size >= 2048);
+            }
+            expected_start += pos.chunk_size as u64;
+        }
+        assert_eq!(expected_start, data.len() as u64);
+        assert_eq!(CONTENT_DEFINED.chunk_positions(&[]).len(), 0);
+    }
+
     #[test]
     fn even_offsets_should_work() {
         let data = (0..4).collect::<Vec<u8>>();
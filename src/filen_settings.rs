@@ -1,11 +1,17 @@
 //! Contains `FilenSettings` used to provide Filen-specific information to API calls.
 use std::time::Duration;
+#[cfg(feature = "async")]
+use std::{fmt, hash, sync::Arc};
 
 use once_cell::sync::Lazy;
+#[cfg(feature = "async")]
+use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
 use url::Url;
 
+use crate::{crypto::MetadataVersion, server_clock::ServerClock, CertificatePin, ClockOffset, SharedMetricsSink};
+
 pub static DEFAULT_FILEN_SETTINGS: Lazy<FilenSettings> = Lazy::new(FilenSettings::default);
 
 #[allow(clippy::unwrap_used)]
@@ -80,6 +86,138 @@ pub struct FilenSettings {
 
     /// File chunk upload timeout.
     pub upload_chunk_timeout: Duration,
+
+    /// Maximum size of API and upload response bodies in bytes. Responses with larger bodies will not be read
+    /// fully and will fail instead. Downloaded file chunks are not affected. If not set, size is not limited.
+    #[serde(rename = "maxResponseBytes")]
+    pub max_response_bytes: Option<usize>,
+
+    /// Maximum amount of file chunks being uploaded or downloaded asynchronously at the same time.
+    /// Limit is shared by all transfers using these settings or their clones, so clone settings
+    /// instead of creating new ones for every transfer. If not set, amount of chunks in flight is not limited.
+    ///
+    /// Limit is fixed when the first chunk is transferred, changing it afterwards has no effect.
+    #[serde(rename = "maxConcurrentChunks")]
+    pub max_concurrent_chunks: Option<usize>,
+
+    /// Maximum amount of bytes an asynchronous file download keeps in memory, counting chunks being downloaded
+    /// and chunks waiting to be written in order. Both encrypted and decrypted chunk bytes are accounted
+    /// by their real size.
+    /// If not set, whole file is downloaded and decrypted before being written.
+    #[serde(rename = "maxBufferedDownloadBytes")]
+    pub max_buffered_download_bytes: Option<usize>,
+
+    /// HTTP protocol version used for queries to Filen servers.
+    /// Some proxies break HTTP/2, so forcing HTTP/1.1 can help, while HTTP/2 benefits many parallel chunk transfers.
+    #[serde(default, rename = "httpVersion")]
+    pub http_version: HttpVersion,
+
+    /// Metadata version used by helpers to encrypt new metadata.
+    /// Clients talking to older Filen deployments may need to stick with `MetadataVersion::V1`.
+    #[serde(default, rename = "metadataVersion")]
+    pub metadata_version: MetadataVersion,
+
+    /// If set, queries remember offset of Filen servers clock from the `Date` header of their responses,
+    /// so `FilenSettings::server_now` can correct skewed local clock. Offset is kept by these settings
+    /// and shared by their clones.
+    #[serde(default, rename = "trackServerClock")]
+    pub track_server_clock: bool,
+
+    /// SHA-256 hashes of server leaf certificates which are trusted for queries to Filen servers.
+    /// If not empty, servers presenting any other certificate are rejected, and certificate authorities
+    /// are not consulted at all. With `async` feature, pinning requires `rustls` feature.
+    #[serde(default, rename = "pinnedCertificates")]
+    pub pinned_certificates: Vec<CertificatePin>,
+
+    /// Optional sink receiving response time of every query made with these settings.
+    /// If not set, queries are not measured.
+    #[serde(skip)]
+    pub metrics_sink: SharedMetricsSink,
+
+    #[serde(skip)]
+    pub(crate) server_clock: ServerClock,
+
+    #[cfg(feature = "async")]
+    #[serde(skip)]
+    pub(crate) chunk_semaphore: ChunkSemaphore,
+}
+
+impl FilenSettings {
+    /// Returns clock offset calculated from the `Date` header of the last Filen API response made with these
+    /// settings or their clones, or None if `FilenSettings::track_server_clock` is not set or no API calls
+    /// were made yet.
+    #[must_use]
+    pub fn server_clock_offset(&self) -> Option<ClockOffset> {
+        self.server_clock.offset()
+    }
+
+    /// Returns current time as seen by Filen servers, which can differ from the local time if the local clock is off.
+    /// Falls back to the local time if server clock offset is unknown, see `FilenSettings::server_clock_offset`.
+    ///
+    /// Use this instead of `SystemTime::now()` for any time-based values checked by Filen.
+    #[must_use]
+    pub fn server_now(&self) -> std::time::SystemTime {
+        self.server_clock.now()
+    }
+
+    /// Waits until one more file chunk can be transferred without exceeding `max_concurrent_chunks`.
+    /// Returned permit should be held until chunk transfer is finished.
+    #[cfg(feature = "async")]
+    pub(crate) async fn acquire_chunk_permit_async(&self) -> Option<tokio::sync::SemaphorePermit<'_>> {
+        let semaphore = self.chunk_semaphore.0.get_or_init(|| {
+            self.max_concurrent_chunks
+                .map(|max| tokio::sync::Semaphore::new(max.max(1)))
+        });
+        match semaphore {
+            // Semaphore is never closed, so acquiring can only fail if something is really off.
+            Some(semaphore) => semaphore.acquire().await.ok(),
+            None => None,
+        }
+    }
+}
+
+/// HTTP protocol version selection for queries to Filen servers.
+///
+/// Without `async` feature queries are sent by ureq, which only speaks HTTP/1.1, so this setting has no effect.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum HttpVersion {
+    /// Use HTTP/2 if it was negotiated with the server during TLS handshake, HTTP/1.1 otherwise.
+    #[default]
+    Auto,
+
+    /// Always use HTTP/1.1.
+    Http1Only,
+
+    /// Use HTTP/2 with prior knowledge, assuming server supports it without any negotiation.
+    Http2PriorKnowledge,
+}
+
+/// Semaphore limiting file chunks in flight, shared by `FilenSettings` clones.
+/// It is an implementation detail, so it is ignored by comparisons and hashing.
+#[cfg(feature = "async")]
+#[derive(Clone, Default)]
+pub(crate) struct ChunkSemaphore(Arc<OnceCell<Option<tokio::sync::Semaphore>>>);
+
+#[cfg(feature = "async")]
+impl fmt::Debug for ChunkSemaphore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ChunkSemaphore")
+    }
+}
+
+#[cfg(feature = "async")]
+impl PartialEq for ChunkSemaphore {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "async")]
+impl Eq for ChunkSemaphore {}
+
+#[cfg(feature = "async")]
+impl hash::Hash for ChunkSemaphore {
+    fn hash<H: hash::Hasher>(&self, _state: &mut H) {}
 }
 
 impl Default for FilenSettings {
@@ -91,6 +229,17 @@ impl Default for FilenSettings {
             download_chunk_timeout: Duration::from_secs(DOWNLOAD_TIMEOUT_SECS),
             request_timeout: Duration::from_secs(REQUEST_TIMEOUT_SECS),
             upload_chunk_timeout: Duration::from_secs(UPLOAD_TIMEOUT_SECS),
+            max_response_bytes: None,
+            max_concurrent_chunks: None,
+            max_buffered_download_bytes: None,
+            http_version: HttpVersion::default(),
+            pinned_certificates: Vec::new(),
+            metadata_version: MetadataVersion::default(),
+            track_server_clock: false,
+            metrics_sink: SharedMetricsSink::default(),
+            server_clock: ServerClock::default(),
+            #[cfg(feature = "async")]
+            chunk_semaphore: ChunkSemaphore::default(),
         }
     }
 }
@@ -1,20 +1,42 @@
 #![crate_type = "staticlib"]
 #![forbid(unsafe_code)]
 
+#[cfg(all(feature = "async", not(any(feature = "native-tls", feature = "rustls"))))]
+compile_error!("'async' feature requires a TLS backend: enable either 'native-tls' or 'rustls' feature");
+
+#[cfg(all(feature = "native-tls", feature = "rustls"))]
+compile_error!("'native-tls' and 'rustls' features are mutually exclusive: disable default features to use 'rustls'");
+
+#[cfg(feature = "async")]
+pub use blocking::run_blocking;
+pub use error::{Error, Result};
+pub use file_chunk_pos::{ChunkingStrategy, FileChunkPosition};
 use once_cell::sync::Lazy;
+pub use tls_pinning::{CertificatePin, Error as CertificatePinError};
 #[cfg(not(feature = "async"))]
 pub use ureq;
-pub use {filen_settings::*, retry_settings::*};
+pub use utils::{
+    base64_decode, base64_encode, base64_to_hex, hash_local_file, hex_to_base64, is_filen_uuid, parse_filen_uuid,
+    Base64Padding, FileHashAlgorithm,
+};
+pub use {filen_settings::*, limited_exponential::LimitedExponential, metrics::*, retry_settings::*, server_clock::*};
 #[cfg(feature = "async")]
 pub use {fure, reqwest};
 pub use {retry, secstr, uuid};
 
+#[cfg(feature = "async")]
+mod blocking;
 pub mod crypto;
+mod error;
 mod file_chunk_pos;
 mod filen_settings;
 mod limited_exponential;
+mod metrics;
+mod otel;
 pub mod queries;
 mod retry_settings;
+mod server_clock;
+mod tls_pinning;
 mod utils;
 pub mod v1;
 
@@ -25,7 +47,7 @@ mod test_utils;
 /// to retry 5 times with 1, 2, 4, 8 and 15 seconds pause between retries.
 pub static STANDARD_SETTINGS_BUNDLE: Lazy<SettingsBundle> = Lazy::new(|| SettingsBundle {
     filen: DEFAULT_FILEN_SETTINGS.clone(),
-    retry: *STANDARD_RETRIES,
+    retry: STANDARD_RETRIES.clone(),
 });
 
 /// Groups together several settings which can be used for API queries, when just `FilenSettings` does not cut it.
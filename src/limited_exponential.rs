@@ -1,9 +1,10 @@
-#![doc(hidden)]
-
 use crate::retry_settings::RetrySettings;
 use std::time::Duration;
 
 /// Each retry increases the delay since the last exponentially, but a maximum delay is limited.
+///
+/// This is the backoff schedule used by `RetrySettings`, exposed for custom retry loops. Iterator is infinite,
+/// so use `Iterator::take` to limit the amount of retries.
 #[derive(Clone, Debug, PartialEq)]
 pub struct LimitedExponential {
     current: u64,
@@ -12,14 +13,23 @@ pub struct LimitedExponential {
 }
 
 impl LimitedExponential {
-    /// Creates a new `LimitedExponential` from the corresponding `RetrySettings` params.
-    pub const fn from_retry_settings(settings: &RetrySettings) -> Self {
+    /// Creates a new `LimitedExponential` starting with `base` delay, which is multiplied by `factor` on each step,
+    /// but never exceeds `max` delay. If `factor` is 0, `max` will be used as a delay after the first step.
+    #[must_use]
+    pub fn new(base: Duration, factor: u32, max: Duration) -> Self {
+        let max = max.as_millis() as u64;
         Self {
-            current: settings.initial_delay().as_millis() as u64,
-            factor: settings.exp_factor() as f64,
-            max: settings.max_delay().as_millis() as u64,
+            current: (base.as_millis() as u64).min(max),
+            factor: f64::from(factor),
+            max,
         }
     }
+
+    /// Creates a new `LimitedExponential` from the corresponding `RetrySettings` params.
+    #[must_use]
+    pub fn from_retry_settings(settings: &RetrySettings) -> Self {
+        Self::new(*settings.initial_delay(), settings.exp_factor(), *settings.max_delay())
+    }
 }
 
 impl Iterator for LimitedExponential {
@@ -29,7 +39,7 @@ impl Iterator for LimitedExponential {
         let duration = Duration::from_millis(self.current);
 
         let next = (self.current as f64) * self.factor;
-        self.current = if next > (self.max as f64) {
+        self.current = if next > (self.max as f64) || self.factor == 0.0 {
             self.max
         } else {
             next as u64
@@ -38,3 +48,38 @@ impl Iterator for LimitedExponential {
         Some(duration)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn limited_exponential_should_grow_until_max() {
+        let delays: Vec<u64> = LimitedExponential::new(Duration::from_secs(1), 2, Duration::from_secs(15))
+            .take(6)
+            .map(|delay| delay.as_secs())
+            .collect();
+
+        assert_eq!(delays, vec![1, 2, 4, 8, 15, 15]);
+    }
+
+    #[test]
+    fn limited_exponential_should_cap_base_at_max() {
+        let delays: Vec<Duration> = LimitedExponential::new(Duration::from_secs(20), 2, Duration::from_secs(15))
+            .take(2)
+            .collect();
+
+        assert_eq!(delays, vec![Duration::from_secs(15), Duration::from_secs(15)]);
+    }
+
+    #[test]
+    fn limited_exponential_with_zero_factor_should_use_max_after_first_delay() {
+        let delays: Vec<u64> = LimitedExponential::new(Duration::from_secs(1), 0, Duration::from_secs(15))
+            .take(3)
+            .map(|delay| delay.as_secs())
+            .collect();
+
+        assert_eq!(delays, vec![1, 15, 15]);
+    }
+}
@@ -0,0 +1,136 @@
+//! Contains `MetricsSink` used to collect Filen API response times and rate limits.
+use std::{
+    fmt, hash,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Receives response time of every Filen API, download and upload query, which can be used
+/// to calculate latency percentiles per endpoint.
+pub trait MetricsSink: Send + Sync {
+    /// Records single query.
+    ///
+    /// * `path` - Queried endpoint, like `/v1/dir/content`, without server URL and query string.
+    /// * `duration` - Time it took to get and read a response.
+    /// * `success` - True if response was received and read successfully, even if Filen response status is false.
+    fn record(&self, path: &str, duration: Duration, success: bool);
+
+    /// Records rate limit reported by Filen in response headers, so caller can slow down before queries
+    /// start failing with `TooManyRequests`. Called only for responses with at least one of rate limit headers,
+    /// including error responses. Does nothing by default.
+    ///
+    /// * `path` - Queried endpoint, like `/v1/dir/content`, without server URL and query string.
+    /// * `rate_limit` - Parsed rate limit headers.
+    fn record_rate_limit(&self, _path: &str, _rate_limit: RateLimit) {}
+}
+
+/// Rate limit reported by Filen in `X-RateLimit-Remaining` and `X-RateLimit-Reset` response headers.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct RateLimit {
+    /// Number of queries left before Filen starts to throttle; None if header is missing or is not a number.
+    pub remaining: Option<u64>,
+
+    /// Value of `X-RateLimit-Reset` header as is, without assuming whether it is a number of seconds
+    /// until the limit resets or a Unix timestamp; None if header is missing or is not a number.
+    pub reset: Option<u64>,
+}
+
+impl RateLimit {
+    pub(crate) const REMAINING_HEADER: &'static str = "x-ratelimit-remaining";
+    pub(crate) const RESET_HEADER: &'static str = "x-ratelimit-reset";
+
+    /// Parses rate limit using the given header getter. Returns None if there are no parsable rate limit headers.
+    pub(crate) fn from_headers<'headers, F: Fn(&str) -> Option<&'headers str>>(header: F) -> Option<Self> {
+        let parse = |name: &str| header(name).and_then(|value| value.trim().parse::<u64>().ok());
+        let rate_limit = Self {
+            remaining: parse(Self::REMAINING_HEADER),
+            reset: parse(Self::RESET_HEADER),
+        };
+        (rate_limit != Self::default()).then_some(rate_limit)
+    }
+}
+
+/// Optional `MetricsSink` shared by `FilenSettings` clones. Default instance has no sink, and in that case
+/// queries are not measured at all.
+///
+/// It is not a part of the settings data, so it is ignored by comparisons and hashing.
+#[derive(Clone, Default)]
+pub struct SharedMetricsSink(Option<Arc<dyn MetricsSink>>);
+
+impl SharedMetricsSink {
+    /// Wraps the given sink, so it can be put into `FilenSettings`.
+    #[must_use]
+    pub fn new(sink: Arc<dyn MetricsSink>) -> Self {
+        Self(Some(sink))
+    }
+
+    /// Runs the given query and records its response time, if there is a sink.
+    pub(crate) fn measure<T, E, F: FnOnce() -> Result<T, E>>(&self, api_endpoint: &str, query: F) -> Result<T, E> {
+        match &self.0 {
+            Some(sink) => {
+                let started = Instant::now();
+                let result = query();
+                sink.record(endpoint_path(api_endpoint), started.elapsed(), result.is_ok());
+                result
+            }
+            None => query(),
+        }
+    }
+
+    /// Passes rate limit parsed from the response headers to the sink, if there are both.
+    pub(crate) fn record_rate_limit(&self, api_endpoint: &str, rate_limit: Option<RateLimit>) {
+        if let (Some(sink), Some(rate_limit)) = (&self.0, rate_limit) {
+            sink.record_rate_limit(endpoint_path(api_endpoint), rate_limit);
+        }
+    }
+
+    /// Awaits the given query and records its response time, if there is a sink.
+    #[cfg(feature = "async")]
+    pub(crate) async fn measure_async<T, E, F>(&self, api_endpoint: &str, query: F) -> Result<T, E>
+    where
+        F: std::future::Future<Output = Result<T, E>>,
+    {
+        match &self.0 {
+            Some(sink) => {
+                let started = Instant::now();
+                let result = query.await;
+                sink.record(endpoint_path(api_endpoint), started.elapsed(), result.is_ok());
+                result
+            }
+            None => query.await,
+        }
+    }
+}
+
+impl From<Arc<dyn MetricsSink>> for SharedMetricsSink {
+    fn from(sink: Arc<dyn MetricsSink>) -> Self {
+        Self::new(sink)
+    }
+}
+
+impl fmt::Debug for SharedMetricsSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.0.is_some() {
+            "SharedMetricsSink(Some)"
+        } else {
+            "SharedMetricsSink(None)"
+        })
+    }
+}
+
+impl PartialEq for SharedMetricsSink {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for SharedMetricsSink {}
+
+impl hash::Hash for SharedMetricsSink {
+    fn hash<H: hash::Hasher>(&self, _state: &mut H) {}
+}
+
+/// Strips query string from the endpoint, so upload endpoints do not leak API keys into metrics.
+pub(crate) fn endpoint_path(api_endpoint: &str) -> &str {
+    api_endpoint.split('?').next().unwrap_or(api_endpoint)
+}
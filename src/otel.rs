@@ -0,0 +1,136 @@
+//! Contains `QuerySpan` which wraps Filen API queries into OpenTelemetry spans when `otel` feature is enabled.
+//! Without the feature all span operations are no-ops.
+#[cfg(feature = "otel")]
+use crate::metrics::endpoint_path;
+#[cfg(feature = "otel")]
+use opentelemetry::{
+    global::{self, BoxedSpan},
+    trace::{Span, SpanKind, Status, Tracer},
+    KeyValue,
+};
+use url::Url;
+
+/// Name of the OpenTelemetry tracer used for Filen query spans.
+#[cfg(feature = "otel")]
+const TRACER_NAME: &str = "rust_filen";
+
+/// OpenTelemetry client span covering a single Filen API query, including failover to other servers.
+/// Span is ended when dropped.
+pub(crate) struct QuerySpan {
+    #[cfg(feature = "otel")]
+    span: BoxedSpan,
+}
+
+#[cfg(feature = "otel")]
+impl QuerySpan {
+    /// Starts span for POST to the given relative API endpoint using global tracer provider.
+    pub(crate) fn start(api_endpoint: &str) -> Self {
+        let tracer = global::tracer(TRACER_NAME);
+        let span = tracer
+            .span_builder(format!("POST {}", endpoint_path(api_endpoint)))
+            .with_kind(SpanKind::Client)
+            .with_attributes(vec![KeyValue::new("http.method", "POST")])
+            .start(&tracer);
+        Self { span }
+    }
+
+    /// Records URL the query was finally sent to and HTTP status code of the response, if any.
+    pub(crate) fn record_response(&mut self, url: &Url, status_code: Option<u16>) {
+        self.span
+            .set_attribute(KeyValue::new("http.url", endpoint_path(url.as_str()).to_owned()));
+        if let Some(status_code) = status_code {
+            self.span
+                .set_attribute(KeyValue::new("http.status_code", i64::from(status_code)));
+        }
+    }
+
+    /// Marks span as failed if given query result is an error.
+    pub(crate) fn record_result<T, E: std::fmt::Display>(&mut self, result: &Result<T, E>) {
+        if let Err(err) = result {
+            self.span.set_status(Status::error(err.to_string()));
+        }
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+impl QuerySpan {
+    pub(crate) const fn start(_api_endpoint: &str) -> Self {
+        Self {}
+    }
+
+    #[allow(clippy::unused_self)]
+    pub(crate) fn record_response(&mut self, _url: &Url, _status_code: Option<u16>) {}
+
+    #[allow(clippy::unused_self)]
+    pub(crate) fn record_result<T, E: std::fmt::Display>(&mut self, _result: &Result<T, E>) {}
+}
+
+#[cfg(all(test, feature = "otel"))]
+mod tests {
+    use crate::{queries, test_utils::init_server, v1::PlainResponsePayload};
+    use httpmock::Method::POST;
+    use opentelemetry::{trace::TraceResult, Context, KeyValue, Value};
+    use opentelemetry_sdk::{
+        export::trace::SpanData,
+        trace::{Span, SpanProcessor, TracerProvider},
+    };
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Default)]
+    struct InMemorySpanProcessor {
+        spans: Arc<Mutex<Vec<SpanData>>>,
+    }
+
+    impl SpanProcessor for InMemorySpanProcessor {
+        fn on_start(&self, _span: &mut Span, _cx: &Context) {}
+
+        fn on_end(&self, span: SpanData) {
+            self.spans.lock().unwrap().push(span);
+        }
+
+        fn force_flush(&self) -> TraceResult<()> {
+            Ok(())
+        }
+
+        fn shutdown(&self) -> TraceResult<()> {
+            Ok(())
+        }
+    }
+
+    fn attribute<'span>(span: &'span SpanData, key: &str) -> Option<&'span Value> {
+        span.attributes
+            .iter()
+            .find(|attribute: &&KeyValue| attribute.key.as_str() == key)
+            .map(|attribute| &attribute.value)
+    }
+
+    #[test]
+    fn query_filen_api_should_record_span_with_http_attributes() {
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        let processor = InMemorySpanProcessor { spans: spans.clone() };
+        opentelemetry::global::set_tracer_provider(TracerProvider::builder().with_span_processor(processor).build());
+        let (server, filen_settings) = init_server();
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/otel/test");
+            then.status(200).json_body(json!({"status": true}));
+        });
+
+        let result = queries::query_filen_api::<_, PlainResponsePayload>("/v1/otel/test", &json!({}), &filen_settings);
+
+        mock.assert_hits(1);
+        assert!(result.is_ok());
+        let recorded_spans = spans.lock().unwrap();
+        let span = recorded_spans
+            .iter()
+            .find(|span| span.name == "POST /v1/otel/test")
+            .expect("Span for the query should be recorded");
+        assert_eq!(attribute(span, "http.method"), Some(&Value::from("POST")));
+        assert_eq!(
+            attribute(span, "http.url"),
+            Some(&Value::from(format!("{}/v1/otel/test", server.base_url())))
+        );
+        assert_eq!(attribute(span, "http.status_code"), Some(&Value::I64(200)));
+    }
+}
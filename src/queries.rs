@@ -1,36 +1,178 @@
 //! This module contains helper methods to perform arbitrary web queries to Filen servers.
 //! You can use it to add some missing API query or re-implement some of them to your liking.
+#[cfg(any(not(feature = "async"), feature = "rustls"))]
 use once_cell::sync::Lazy;
+#[cfg(feature = "async")]
+use once_cell::sync::OnceCell;
 use rand::{thread_rng, Rng};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use snafu::{ResultExt, Snafu};
-#[cfg(not(feature = "async"))]
+use snafu::{ensure, ResultExt, Snafu};
 use std::io::Read;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+#[cfg(any(not(feature = "async"), feature = "rustls"))]
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+};
 use url::Url;
 
-use crate::filen_settings::FilenSettings;
+#[cfg(any(not(feature = "async"), feature = "rustls"))]
+use crate::tls_pinning;
+use crate::{
+    filen_settings::{FilenSettings, HttpVersion},
+    otel::QuerySpan,
+    CertificatePin, RateLimit,
+};
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
-#[allow(clippy::unwrap_used)]
+/// Clients for every `HttpVersion`, indexed by its discriminant and built on first use.
 #[cfg(feature = "async")]
-static ASYNC_CLIENT: Lazy<reqwest::Client> =
-    Lazy::new(|| reqwest::Client::builder().user_agent(CRATE_USER_AGENT).build().unwrap());
+static ASYNC_CLIENTS: [OnceCell<reqwest::Client>; 3] = [OnceCell::new(), OnceCell::new(), OnceCell::new()];
 
-#[allow(clippy::unwrap_used)]
+/// Clients for every `HttpVersion`, indexed by its discriminant and built on first use.
 #[cfg(feature = "async")]
-static BLOCKING_CLIENT: Lazy<reqwest::blocking::Client> = Lazy::new(|| {
-    reqwest::blocking::Client::builder()
-        .user_agent(CRATE_USER_AGENT)
-        .build()
-        .unwrap()
-});
+static BLOCKING_CLIENTS: [OnceCell<reqwest::blocking::Client>; 3] = [OnceCell::new(), OnceCell::new(), OnceCell::new()];
+
+/// Clients trusting only pinned certificates, built on first use for every set of pins.
+#[cfg(all(feature = "async", feature = "rustls"))]
+static PINNED_ASYNC_CLIENTS: Lazy<PinnedClients<reqwest::Client>> = Lazy::new(PinnedClients::default);
+
+/// Clients trusting only pinned certificates, built on first use for every set of pins.
+#[cfg(all(feature = "async", feature = "rustls"))]
+static PINNED_BLOCKING_CLIENTS: Lazy<PinnedClients<reqwest::blocking::Client>> = Lazy::new(PinnedClients::default);
+
+/// ureq only speaks HTTP/1.1, so there is a single agent regardless of `HttpVersion`.
 #[cfg(not(feature = "async"))]
 static AGENT: Lazy<ureq::Agent> = Lazy::new(|| ureq::AgentBuilder::new().user_agent(CRATE_USER_AGENT).build());
 
+/// Agents trusting only pinned certificates, built on first use for every set of pins.
+#[cfg(not(feature = "async"))]
+static PINNED_AGENTS: Lazy<PinnedClients<ureq::Agent>> = Lazy::new(PinnedClients::default);
+
+/// HTTP clients keyed by HTTP version and certificate pins they were built with.
+#[cfg(any(not(feature = "async"), feature = "rustls"))]
+type PinnedClients<C> = Mutex<HashMap<(HttpVersion, Vec<CertificatePin>), C>>;
+
+/// Parts of `FilenSettings` which determine how HTTP client for a query is built.
+#[derive(Clone, Copy, Debug)]
+struct ClientOptions<'settings> {
+    http_version: HttpVersion,
+    // Without rustls, pinning is refused before any client is built.
+    #[cfg_attr(all(feature = "async", not(feature = "rustls")), allow(dead_code))]
+    pinned_certificates: &'settings [CertificatePin],
+}
+
+impl<'settings> ClientOptions<'settings> {
+    /// Takes client options from the given settings, failing if certificate pinning cannot be honored.
+    fn from_settings(filen_settings: &'settings FilenSettings) -> Result<Self> {
+        #[cfg(all(feature = "async", not(feature = "rustls")))]
+        ensure!(
+            filen_settings.pinned_certificates.is_empty(),
+            CertificatePinningRequiresRustlsSnafu {}
+        );
+        Ok(Self {
+            http_version: filen_settings.http_version,
+            pinned_certificates: &filen_settings.pinned_certificates,
+        })
+    }
+}
+
+/// Returns cached client built for the given options, building it first if needed.
+#[cfg(any(not(feature = "async"), feature = "rustls"))]
+fn pinned_client<C: Clone, F: FnOnce() -> C>(
+    pinned_clients: &PinnedClients<C>,
+    client_options: ClientOptions<'_>,
+    build_client: F,
+) -> C {
+    // Client map stays consistent even if some thread panicked while holding the lock.
+    let mut pinned_clients = pinned_clients.lock().unwrap_or_else(PoisonError::into_inner);
+    pinned_clients
+        .entry((client_options.http_version, client_options.pinned_certificates.to_vec()))
+        .or_insert_with(build_client)
+        .clone()
+}
+
+#[cfg(not(feature = "async"))]
+fn agent(client_options: ClientOptions<'_>) -> ureq::Agent {
+    if client_options.pinned_certificates.is_empty() {
+        return AGENT.clone();
+    }
+    pinned_client(&PINNED_AGENTS, client_options, || {
+        ureq::AgentBuilder::new()
+            .user_agent(CRATE_USER_AGENT)
+            .tls_config(tls_pinning::ureq_tls_config(client_options.pinned_certificates))
+            .build()
+    })
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(feature = "async")]
+fn async_client(client_options: ClientOptions<'_>) -> reqwest::Client {
+    let build_client = || async_client_builder(client_options).build().unwrap();
+    #[cfg(feature = "rustls")]
+    if !client_options.pinned_certificates.is_empty() {
+        return pinned_client(&PINNED_ASYNC_CLIENTS, client_options, build_client);
+    }
+    ASYNC_CLIENTS[client_options.http_version as usize]
+        .get_or_init(build_client)
+        .clone()
+}
+
+#[allow(clippy::unwrap_used)]
+#[cfg(feature = "async")]
+fn blocking_client(client_options: ClientOptions<'_>) -> reqwest::blocking::Client {
+    let build_client = || blocking_client_builder(client_options).build().unwrap();
+    #[cfg(feature = "rustls")]
+    if !client_options.pinned_certificates.is_empty() {
+        return pinned_client(&PINNED_BLOCKING_CLIENTS, client_options, build_client);
+    }
+    BLOCKING_CLIENTS[client_options.http_version as usize]
+        .get_or_init(build_client)
+        .clone()
+}
+
+#[cfg(feature = "async")]
+fn async_client_builder(client_options: ClientOptions<'_>) -> reqwest::ClientBuilder {
+    let builder = reqwest::Client::builder().user_agent(CRATE_USER_AGENT);
+    #[cfg(feature = "rustls")]
+    let builder = if client_options.pinned_certificates.is_empty() {
+        builder.use_rustls_tls()
+    } else {
+        builder.use_preconfigured_tls(tls_pinning::reqwest_tls_config(
+            client_options.pinned_certificates,
+            client_options.http_version,
+        ))
+    };
+    match client_options.http_version {
+        HttpVersion::Auto => builder,
+        HttpVersion::Http1Only => builder.http1_only(),
+        HttpVersion::Http2PriorKnowledge => builder.http2_prior_knowledge(),
+    }
+}
+
+#[cfg(feature = "async")]
+fn blocking_client_builder(client_options: ClientOptions<'_>) -> reqwest::blocking::ClientBuilder {
+    let builder = reqwest::blocking::Client::builder().user_agent(CRATE_USER_AGENT);
+    #[cfg(feature = "rustls")]
+    let builder = if client_options.pinned_certificates.is_empty() {
+        builder.use_rustls_tls()
+    } else {
+        builder.use_preconfigured_tls(tls_pinning::reqwest_tls_config(
+            client_options.pinned_certificates,
+            client_options.http_version,
+        ))
+    };
+    match client_options.http_version {
+        HttpVersion::Auto => builder,
+        HttpVersion::Http1Only => builder.http1_only(),
+        HttpVersion::Http2PriorKnowledge => builder.http2_prior_knowledge(),
+    }
+}
+
 const CRATE_USER_AGENT: &str = "Rust-Filen API (+https://github.com/EnoughTea/rust-filen)";
+const FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
 
 #[derive(Snafu, Debug)]
 pub enum Error {
@@ -46,87 +188,355 @@ pub enum Error {
         source: url::ParseError,
     },
 
+    #[snafu(display("Cannot deserialize response body JSON: {}", source))]
+    CannotDeserializeResponseBodyJson { source: serde_json::Error },
+
+    #[snafu(display("Cannot serialize request payload to form-urlencoded body: {}", source))]
+    CannotSerializePayloadToForm { source: serde_urlencoded::ser::Error },
+
+    #[snafu(display("Cannot read response body: {}", source))]
+    CannotReadResponseBody { source: std::io::Error },
+
     #[cfg(feature = "async")]
     #[snafu(display("Cannot deserialize response body JSON: {}", source))]
     ReqwestCannotDeserializeResponseBodyJson { source: reqwest::Error },
 
+    #[cfg(feature = "async")]
+    #[snafu(display("Cannot read response body: {}", source))]
+    ReqwestCannotReadResponseBody { source: reqwest::Error },
+
+    #[snafu(display("Response body is larger than allowed {} bytes", max_response_bytes))]
+    ResponseTooLarge { max_response_bytes: usize },
+
+    #[snafu(display("Response body was truncated: got {} of {} bytes", got, expected))]
+    TruncatedResponseBody { got: usize, expected: usize },
+
+    #[snafu(display("Server '{}' responded with server error status {}", server, status))]
+    ServerIsUnhealthy { server: String, status: u16 },
+
+    #[snafu(display("Server '{}' responded with unexpected status {}", url, status))]
+    UnexpectedStatus { url: String, status: u16 },
+
+    /// Filen throttles requests; `source` holds a delay from `Retry-After` header, if server sent it.
+    #[snafu(display("{}: server responded with 429 Too Many Requests, {}", message, source))]
+    TooManyRequests { message: String, source: Throttled },
+
     #[cfg(not(feature = "async"))]
     #[snafu(display("Cannot deserialize response body JSON: {}", source))]
     UreqCannotDeserializeResponseBodyJson { source: std::io::Error },
 
+    #[cfg(all(feature = "async", not(feature = "rustls")))]
+    #[snafu(display("Certificate pinning with 'async' feature requires 'rustls' feature to be enabled"))]
+    CertificatePinningRequiresRustls {},
+
     #[cfg(feature = "async")]
     #[snafu(display("{}: {}", message, source))]
     ReqwestWebRequestFailed { message: String, source: reqwest::Error },
 
     #[cfg(not(feature = "async"))]
     #[snafu(display("{}: {}", message, source))]
-    UreqWebRequestFailed { message: String, source: ureq::Error },
+    UreqWebRequestFailed {
+        message: String,
+        #[snafu(source(from(ureq::Error, Box::new)))]
+        source: Box<ureq::Error>,
+    },
+}
+
+/// Delay from `Retry-After` header of 429 Too Many Requests response, if Filen sent it.
+///
+/// This is a source of `Error::TooManyRequests`, so it can be found in the sources of any error wrapping it,
+/// which is how `RetrySettings` finds server-directed delay.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Throttled {
+    pub retry_after: Option<Duration>,
+}
+
+impl std::fmt::Display for Throttled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "retry after {:?}", self.retry_after)
+    }
+}
+
+impl std::error::Error for Throttled {}
+
+/// Determines how request payload is encoded in the request body.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum PayloadEncoding {
+    /// Payload is sent as JSON with `application/json` content type. Used by almost all Filen endpoints.
+    #[default]
+    Json,
+    /// Payload is sent with `application/x-www-form-urlencoded` content type, for legacy endpoints.
+    /// Payload should serialize into a flat map, since nested values cannot be form-encoded.
+    Form,
+}
+
+/// Request body prepared according to the chosen `PayloadEncoding`.
+enum EncodedPayload<'payload, T: ?Sized> {
+    Json(&'payload T),
+    Form(String),
+}
+
+impl<'payload, T: Serialize + ?Sized> EncodedPayload<'payload, T> {
+    fn new(payload: &'payload T, encoding: PayloadEncoding) -> Result<Self> {
+        match encoding {
+            PayloadEncoding::Json => Ok(Self::Json(payload)),
+            PayloadEncoding::Form => serde_urlencoded::to_string(payload)
+                .map(Self::Form)
+                .context(CannotSerializePayloadToFormSnafu {}),
+        }
+    }
 }
 
 /// Sends POST with given payload to one of Filen API servers.
 /// `api_endpoint` parameter should be relative, eg `/v1/some/api`, as one of the Filen servers will be chosen randomly.
+///
+/// If connection to the chosen server cannot be established, other configured API servers are tried in turn.
+/// HTTP errors returned by a reachable server do not cause failover.
 pub fn query_filen_api<T: Serialize + ?Sized, U: DeserializeOwned>(
     api_endpoint: &str,
     payload: &T,
     filen_settings: &FilenSettings,
 ) -> Result<U> {
-    let filen_endpoint = produce_filen_endpoint(api_endpoint, &filen_settings.api_servers)?;
-    let filen_response = post_json(
-        filen_endpoint.as_str(),
-        payload,
-        filen_settings.request_timeout.as_secs(),
-    );
-    deserialize_response(filen_response, || {
-        format!("Failed to query Filen API: {}", filen_endpoint)
+    query_filen_api_with_encoding(api_endpoint, payload, PayloadEncoding::Json, filen_settings)
+}
+
+/// Sends POST with given payload encoded as specified to one of Filen API servers.
+/// Use it instead of `query_filen_api` for endpoints which do not accept JSON.
+pub fn query_filen_api_with_encoding<T: Serialize + ?Sized, U: DeserializeOwned>(
+    api_endpoint: &str,
+    payload: &T,
+    encoding: PayloadEncoding,
+    filen_settings: &FilenSettings,
+) -> Result<U> {
+    let encoded_payload = EncodedPayload::new(payload, encoding)?;
+    let (first_endpoint, fallback_endpoints) = produce_failover_endpoints(api_endpoint, &filen_settings.api_servers)?;
+    let client_options = ClientOptions::from_settings(filen_settings)?;
+    filen_settings.metrics_sink.measure(api_endpoint, || {
+        let mut span = QuerySpan::start(api_endpoint);
+        let (filen_response, filen_endpoint) = post_with_failover(
+            &encoded_payload,
+            first_endpoint,
+            fallback_endpoints,
+            filen_settings.request_timeout.as_secs(),
+            client_options,
+        );
+        span.record_response(&filen_endpoint, response_status_code(&filen_response));
+        filen_settings
+            .metrics_sink
+            .record_rate_limit(api_endpoint, response_rate_limit(&filen_response));
+        track_server_clock(filen_settings, response_date(&filen_response));
+        let result = deserialize_response(filen_response, filen_settings, || {
+            format!("Failed to query Filen API: {}", filen_endpoint)
+        });
+        span.record_result(&result);
+        result
+    })
+}
+
+/// Sends POST with given payload to one of Filen API servers and passes response body reader to the given closure,
+/// so huge responses can be deserialized incrementally instead of being buffered in memory whole.
+///
+/// If `max_response_bytes` is set in the given settings, body reader stops after that many bytes,
+/// so deserialization of a larger response fails.
+pub fn query_filen_api_with_reader<T, V, F>(
+    api_endpoint: &str,
+    payload: &T,
+    filen_settings: &FilenSettings,
+    read_body: F,
+) -> Result<V>
+where
+    T: Serialize + ?Sized,
+    F: FnOnce(&mut dyn Read) -> serde_json::Result<V>,
+{
+    let (first_endpoint, fallback_endpoints) = produce_failover_endpoints(api_endpoint, &filen_settings.api_servers)?;
+    let client_options = ClientOptions::from_settings(filen_settings)?;
+    filen_settings.metrics_sink.measure(api_endpoint, || {
+        let mut span = QuerySpan::start(api_endpoint);
+        let (filen_response, filen_endpoint) = post_with_failover(
+            &EncodedPayload::Json(payload),
+            first_endpoint,
+            fallback_endpoints,
+            filen_settings.request_timeout.as_secs(),
+            client_options,
+        );
+        span.record_response(&filen_endpoint, response_status_code(&filen_response));
+        filen_settings
+            .metrics_sink
+            .record_rate_limit(api_endpoint, response_rate_limit(&filen_response));
+        track_server_clock(filen_settings, response_date(&filen_response));
+        let result = checked_response(filen_response, filen_settings, || {
+            format!("Failed to query Filen API: {}", filen_endpoint)
+        })
+        .and_then(|response| {
+            let max_body_bytes = filen_settings.max_response_bytes.map_or(u64::MAX, |max| max as u64);
+            let mut body_reader = response_body_reader(response).take(max_body_bytes);
+            read_body(&mut body_reader).context(CannotDeserializeResponseBodyJsonSnafu {})
+        });
+        span.record_result(&result);
+        result
     })
 }
 
 /// Asynchronously sends POST with given payload to one of Filen API servers.
 /// `api_endpoint` parameter should be relative, eg `/v1/some/api`, as one of the Filen servers will be chosen randomly.
+///
+/// If connection to the chosen server cannot be established, other configured API servers are tried in turn.
+/// HTTP errors returned by a reachable server do not cause failover.
 #[cfg(feature = "async")]
 pub async fn query_filen_api_async<T: Serialize + ?Sized + Sync, U: DeserializeOwned>(
     api_endpoint: &str,
     payload: &T,
     filen_settings: &FilenSettings,
 ) -> Result<U> {
-    let filen_endpoint = produce_filen_endpoint(api_endpoint, &filen_settings.api_servers)?;
-    let filen_response = post_json_async(
-        filen_endpoint.as_str(),
-        payload,
-        filen_settings.request_timeout.as_secs(),
-    )
-    .await;
-    deserialize_response_async(filen_response, || {
-        format!("Failed to query Filen API (async): {}", filen_endpoint)
-    })
-    .await
+    query_filen_api_with_encoding_async(api_endpoint, payload, PayloadEncoding::Json, filen_settings).await
+}
+
+/// Asynchronously sends POST with given payload encoded as specified to one of Filen API servers.
+/// Use it instead of `query_filen_api_async` for endpoints which do not accept JSON.
+#[cfg(feature = "async")]
+pub async fn query_filen_api_with_encoding_async<T: Serialize + ?Sized + Sync, U: DeserializeOwned>(
+    api_endpoint: &str,
+    payload: &T,
+    encoding: PayloadEncoding,
+    filen_settings: &FilenSettings,
+) -> Result<U> {
+    let encoded_payload = EncodedPayload::new(payload, encoding)?;
+    let (first_endpoint, fallback_endpoints) = produce_failover_endpoints(api_endpoint, &filen_settings.api_servers)?;
+    let client_options = ClientOptions::from_settings(filen_settings)?;
+    let query = async {
+        let mut span = QuerySpan::start(api_endpoint);
+        let mut fallback_endpoints = fallback_endpoints.into_iter();
+        let mut filen_endpoint = first_endpoint;
+        let filen_response = loop {
+            let timeout_secs = filen_settings.request_timeout.as_secs();
+            let filen_response = match &encoded_payload {
+                EncodedPayload::Json(payload) => {
+                    post_json_async(filen_endpoint.as_str(), payload, timeout_secs, client_options).await
+                }
+                EncodedPayload::Form(body) => {
+                    post_form_async(filen_endpoint.as_str(), body, timeout_secs, client_options).await
+                }
+            };
+            match (&filen_response, fallback_endpoints.next()) {
+                (Err(err), Some(next_endpoint)) if is_connection_failure(err) => filen_endpoint = next_endpoint,
+                _ => break filen_response,
+            }
+        };
+        span.record_response(
+            &filen_endpoint,
+            filen_response.as_ref().ok().map(|response| response.status().as_u16()),
+        );
+        filen_settings.metrics_sink.record_rate_limit(
+            api_endpoint,
+            filen_response
+                .as_ref()
+                .ok()
+                .and_then(|response| headers_rate_limit(response.headers())),
+        );
+        track_server_clock(
+            filen_settings,
+            filen_response
+                .as_ref()
+                .ok()
+                .and_then(|response| headers_date(response.headers())),
+        );
+        let result = deserialize_response_async(filen_response, filen_settings, || {
+            format!("Failed to query Filen API (async): {}", filen_endpoint)
+        })
+        .await;
+        span.record_result(&result);
+        result
+    };
+    filen_settings.metrics_sink.measure_async(api_endpoint, query).await
 }
 
 pub fn download_from_filen(api_endpoint: &str, filen_settings: &FilenSettings) -> Result<Vec<u8>> {
     let filen_endpoint = produce_filen_endpoint(api_endpoint, &filen_settings.download_servers)?;
-    let response = get_bytes(filen_endpoint.as_str(), filen_settings.download_chunk_timeout.as_secs());
-    #[cfg(feature = "async")]
-    {
-        response.context(ReqwestWebRequestFailedSnafu {
-            message: format!("Failed to download file chunk from '{}'", filen_endpoint),
-        })
-    }
-    #[cfg(not(feature = "async"))]
-    {
-        response.context(UreqWebRequestFailedSnafu {
-            message: format!("Failed to download file chunk from '{}'", filen_endpoint),
-        })
-    }
+    let client_options = ClientOptions::from_settings(filen_settings)?;
+    filen_settings.metrics_sink.measure(api_endpoint, || {
+        get_bytes(
+            filen_endpoint.as_str(),
+            filen_settings.download_chunk_timeout.as_secs(),
+            client_options,
+        )
+    })
 }
 
 #[cfg(feature = "async")]
 pub async fn download_from_filen_async(api_endpoint: &str, filen_settings: &FilenSettings) -> Result<Vec<u8>> {
     let filen_endpoint = produce_filen_endpoint(api_endpoint, &filen_settings.download_servers)?;
-    get_bytes_async(filen_endpoint.as_str(), filen_settings.download_chunk_timeout.as_secs())
+    let client_options = ClientOptions::from_settings(filen_settings)?;
+    let query = get_bytes_async(
+        filen_endpoint.as_str(),
+        filen_settings.download_chunk_timeout.as_secs(),
+        client_options,
+    );
+    filen_settings.metrics_sink.measure_async(api_endpoint, query).await
+}
+
+/// Sends HEAD to one of Filen download servers to check whether given file chunk is stored there.
+/// Returns false if server responded with 404 Not Found, and fails on any other non-success status.
+pub fn file_chunk_exists_on_filen(api_endpoint: &str, filen_settings: &FilenSettings) -> Result<bool> {
+    let filen_endpoint = produce_filen_endpoint(api_endpoint, &filen_settings.download_servers)?;
+    let client_options = ClientOptions::from_settings(filen_settings)?;
+    filen_settings.metrics_sink.measure(api_endpoint, || {
+        let response = head(
+            filen_endpoint.as_str(),
+            filen_settings.request_timeout.as_secs(),
+            client_options,
+        );
+        #[cfg(feature = "async")]
+        let status = response
+            .context(ReqwestWebRequestFailedSnafu {
+                message: format!("Failed to check file chunk at '{}'", filen_endpoint),
+            })?
+            .status()
+            .as_u16();
+        #[cfg(not(feature = "async"))]
+        let status = match response {
+            Ok(response) => response.status(),
+            Err(ureq::Error::Status(status, _)) => status,
+            Err(err) => Err(err).context(UreqWebRequestFailedSnafu {
+                message: format!("Failed to check file chunk at '{}'", filen_endpoint),
+            })?,
+        };
+        chunk_status_to_existence(&filen_endpoint, status)
+    })
+}
+
+/// Asynchronously sends HEAD to one of Filen download servers to check whether given file chunk is stored there.
+/// Returns false if server responded with 404 Not Found, and fails on any other non-success status.
+#[cfg(feature = "async")]
+pub async fn file_chunk_exists_on_filen_async(api_endpoint: &str, filen_settings: &FilenSettings) -> Result<bool> {
+    let filen_endpoint = produce_filen_endpoint(api_endpoint, &filen_settings.download_servers)?;
+    let client_options = ClientOptions::from_settings(filen_settings)?;
+    let query = async {
+        let status = head_async(
+            filen_endpoint.as_str(),
+            filen_settings.request_timeout.as_secs(),
+            client_options,
+        )
         .await
         .context(ReqwestWebRequestFailedSnafu {
-            message: format!("Failed to download file chunk (async) from '{}'", filen_endpoint),
-        })
+            message: format!("Failed to check file chunk (async) at '{}'", filen_endpoint),
+        })?
+        .status()
+        .as_u16();
+        chunk_status_to_existence(&filen_endpoint, status)
+    };
+    filen_settings.metrics_sink.measure_async(api_endpoint, query).await
+}
+
+fn chunk_status_to_existence(filen_endpoint: &Url, status: u16) -> Result<bool> {
+    match status {
+        200..=299 => Ok(true),
+        404 => Ok(false),
+        status => UnexpectedStatusSnafu {
+            url: filen_endpoint.as_str(),
+            status,
+        }
+        .fail(),
+    }
 }
 
 /// Sends POST with given data blob to one of Filen upload servers.
@@ -136,9 +546,21 @@ pub fn upload_to_filen<U: DeserializeOwned>(
     filen_settings: &FilenSettings,
 ) -> Result<U> {
     let filen_endpoint = produce_filen_endpoint(api_endpoint, &filen_settings.upload_servers)?;
-    let upload_result = post_blob(filen_endpoint.as_str(), blob, filen_settings.request_timeout.as_secs());
-    deserialize_response(upload_result, || {
-        format!("Failed to upload file chunk to '{}'", filen_endpoint)
+    let client_options = ClientOptions::from_settings(filen_settings)?;
+    filen_settings.metrics_sink.measure(api_endpoint, || {
+        let upload_result = post_blob(
+            filen_endpoint.as_str(),
+            blob,
+            filen_settings.request_timeout.as_secs(),
+            client_options,
+        );
+        filen_settings
+            .metrics_sink
+            .record_rate_limit(api_endpoint, response_rate_limit(&upload_result));
+        track_server_clock(filen_settings, response_date(&upload_result));
+        deserialize_response(upload_result, filen_settings, || {
+            format!("Failed to upload file chunk to '{}'", filen_endpoint)
+        })
     })
 }
 
@@ -150,14 +572,142 @@ pub async fn upload_to_filen_async<U: DeserializeOwned>(
     filen_settings: &FilenSettings,
 ) -> Result<U> {
     let filen_endpoint = produce_filen_endpoint(api_endpoint, &filen_settings.upload_servers)?;
-    let upload_result = post_blob_async(filen_endpoint.as_str(), blob, filen_settings.request_timeout.as_secs()).await;
-    deserialize_response_async(upload_result, || {
-        format!("Failed to upload file chunk (async) to '{}'", filen_endpoint)
-    })
+    let client_options = ClientOptions::from_settings(filen_settings)?;
+    let query = async {
+        let upload_result = post_blob_async(
+            filen_endpoint.as_str(),
+            blob,
+            filen_settings.request_timeout.as_secs(),
+            client_options,
+        )
+        .await;
+        filen_settings.metrics_sink.record_rate_limit(
+            api_endpoint,
+            upload_result
+                .as_ref()
+                .ok()
+                .and_then(|response| headers_rate_limit(response.headers())),
+        );
+        track_server_clock(
+            filen_settings,
+            upload_result
+                .as_ref()
+                .ok()
+                .and_then(|response| headers_date(response.headers())),
+        );
+        deserialize_response_async(upload_result, filen_settings, || {
+            format!("Failed to upload file chunk (async) to '{}'", filen_endpoint)
+        })
+        .await
+    };
+    filen_settings.metrics_sink.measure_async(api_endpoint, query).await
+}
+
+/// Sends HEAD to the given server and measures time it took to get a response.
+/// Any response which is not a server error means server is reachable and healthy.
+///
+/// Can be used to pick the fastest of Filen download servers before a large download.
+pub fn ping_server(server: &Url, filen_settings: &FilenSettings) -> Result<Duration> {
+    let client_options = ClientOptions::from_settings(filen_settings)?;
+    let started = Instant::now();
+    let response = head(
+        server.as_str(),
+        filen_settings.request_timeout.as_secs(),
+        client_options,
+    );
+    #[cfg(feature = "async")]
+    let status = response
+        .context(ReqwestWebRequestFailedSnafu {
+            message: format!("Failed to ping server '{}'", server),
+        })?
+        .status()
+        .as_u16();
+    #[cfg(not(feature = "async"))]
+    let status = match response {
+        Ok(response) => response.status(),
+        Err(ureq::Error::Status(status, _)) => status,
+        Err(err) => Err(err).context(UreqWebRequestFailedSnafu {
+            message: format!("Failed to ping server '{}'", server),
+        })?,
+    };
+    let elapsed = started.elapsed();
+    ensure!(
+        status < 500,
+        ServerIsUnhealthySnafu {
+            server: server.as_str(),
+            status
+        }
+    );
+    Ok(elapsed)
+}
+
+/// Asynchronously sends HEAD to the given server and measures time it took to get a response.
+/// Any response which is not a server error means server is reachable and healthy.
+///
+/// Can be used to pick the fastest of Filen download servers before a large download.
+#[cfg(feature = "async")]
+pub async fn ping_server_async(server: &Url, filen_settings: &FilenSettings) -> Result<Duration> {
+    let client_options = ClientOptions::from_settings(filen_settings)?;
+    let started = Instant::now();
+    let status = head_async(
+        server.as_str(),
+        filen_settings.request_timeout.as_secs(),
+        client_options,
+    )
     .await
+    .context(ReqwestWebRequestFailedSnafu {
+        message: format!("Failed to ping server (async) '{}'", server),
+    })?
+    .status()
+    .as_u16();
+    let elapsed = started.elapsed();
+    ensure!(
+        status < 500,
+        ServerIsUnhealthySnafu {
+            server: server.as_str(),
+            status
+        }
+    );
+    Ok(elapsed)
 }
 
-/// Randomly chooses one of the URLs in the given slice.
+/// Pings given servers one by one and returns the one with the shortest round-trip,
+/// or None if none of the servers are healthy.
+pub fn fastest_server<'servers>(servers: &'servers [Url], filen_settings: &FilenSettings) -> Option<&'servers Url> {
+    servers
+        .iter()
+        .filter_map(|server| {
+            ping_server(server, filen_settings)
+                .ok()
+                .map(|elapsed| (server, elapsed))
+        })
+        .min_by_key(|(_, elapsed)| *elapsed)
+        .map(|(server, _)| server)
+}
+
+/// Pings given servers concurrently and returns the one with the shortest round-trip,
+/// or None if none of the servers are healthy.
+#[cfg(feature = "async")]
+pub async fn fastest_server_async<'servers>(
+    servers: &'servers [Url],
+    filen_settings: &FilenSettings,
+) -> Option<&'servers Url> {
+    let pings = servers.iter().map(|server| async move {
+        ping_server_async(server, filen_settings)
+            .await
+            .ok()
+            .map(|elapsed| (server, elapsed))
+    });
+    futures::future::join_all(pings)
+        .await
+        .into_iter()
+        .flatten()
+        .min_by_key(|(_, elapsed)| *elapsed)
+        .map(|(server, _)| server)
+}
+
+/// Randomly chooses one of the URLs in the given slice without a shared request counter, so selection
+/// stays even and cannot overflow no matter how many queries a long-running process makes.
 fn choose_filen_server(servers: &[Url]) -> &Url {
     let chosen_server_index = thread_rng().gen_range(0..servers.len());
     &servers[chosen_server_index]
@@ -165,14 +715,21 @@ fn choose_filen_server(servers: &[Url]) -> &Url {
 
 /// Sends GET with the given timeout to the specified URL.
 #[cfg(not(feature = "async"))]
-fn get(url: &str, timeout_secs: u64) -> Result<ureq::Response, ureq::Error> {
-    AGENT.get(url).timeout(Duration::from_secs(timeout_secs)).call()
+fn get(url: &str, timeout_secs: u64, client_options: ClientOptions<'_>) -> Result<ureq::Response, ureq::Error> {
+    agent(client_options)
+        .get(url)
+        .timeout(Duration::from_secs(timeout_secs))
+        .call()
 }
 
 #[cfg(feature = "async")]
 /// Sends GET with the given timeout to the specified URL.
-fn get(url: &str, timeout_secs: u64) -> Result<reqwest::blocking::Response, reqwest::Error> {
-    BLOCKING_CLIENT
+fn get(
+    url: &str,
+    timeout_secs: u64,
+    client_options: ClientOptions<'_>,
+) -> Result<reqwest::blocking::Response, reqwest::Error> {
+    blocking_client(client_options)
         .get(url)
         .timeout(Duration::from_secs(timeout_secs))
         .send()
@@ -180,45 +737,141 @@ fn get(url: &str, timeout_secs: u64) -> Result<reqwest::blocking::Response, reqw
 
 /// Asynchronously sends GET with the given timeout to the specified URL.
 #[cfg(feature = "async")]
-async fn get_async(url: &str, timeout_secs: u64) -> Result<reqwest::Response, reqwest::Error> {
-    ASYNC_CLIENT
+async fn get_async(
+    url: &str,
+    timeout_secs: u64,
+    client_options: ClientOptions<'_>,
+) -> Result<reqwest::Response, reqwest::Error> {
+    async_client(client_options)
         .get(url)
         .timeout(Duration::from_secs(timeout_secs))
         .send()
         .await
 }
 
-/// Sends GET with the given timeout to the specified URL.
+/// Sends HEAD with the given timeout to the specified URL.
 #[cfg(not(feature = "async"))]
-fn get_bytes(filen_endpoint: &str, timeout_secs: u64) -> Result<Vec<u8>, ureq::Error> {
-    let response = get(filen_endpoint, timeout_secs)?;
-    let content_length = response
-        .header("Content-Length")
-        .and_then(|s| s.parse::<usize>().ok())
-        .unwrap_or(1024 * 1024);
+fn head(url: &str, timeout_secs: u64, client_options: ClientOptions<'_>) -> Result<ureq::Response, ureq::Error> {
+    agent(client_options)
+        .head(url)
+        .timeout(Duration::from_secs(timeout_secs))
+        .call()
+}
+
+/// Sends HEAD with the given timeout to the specified URL.
+#[cfg(feature = "async")]
+fn head(
+    url: &str,
+    timeout_secs: u64,
+    client_options: ClientOptions<'_>,
+) -> Result<reqwest::blocking::Response, reqwest::Error> {
+    blocking_client(client_options)
+        .head(url)
+        .timeout(Duration::from_secs(timeout_secs))
+        .send()
+}
+
+/// Asynchronously sends HEAD with the given timeout to the specified URL.
+#[cfg(feature = "async")]
+async fn head_async(
+    url: &str,
+    timeout_secs: u64,
+    client_options: ClientOptions<'_>,
+) -> Result<reqwest::Response, reqwest::Error> {
+    async_client(client_options)
+        .head(url)
+        .timeout(Duration::from_secs(timeout_secs))
+        .send()
+        .await
+}
 
-    let mut bytes: Vec<u8> = Vec::with_capacity(content_length);
-    response.into_reader().read_to_end(&mut bytes)?;
+/// Sends GET with the given timeout to the specified URL and reads response body.
+/// Fails if response body turns out to be shorter than its `Content-Length`.
+#[cfg(not(feature = "async"))]
+fn get_bytes(filen_endpoint: &str, timeout_secs: u64, client_options: ClientOptions<'_>) -> Result<Vec<u8>> {
+    let response = get(filen_endpoint, timeout_secs, client_options).context(UreqWebRequestFailedSnafu {
+        message: format!("Failed to download file chunk from '{}'", filen_endpoint),
+    })?;
+    let content_length = response.header("Content-Length").and_then(|s| s.parse::<usize>().ok());
+
+    let mut bytes: Vec<u8> = Vec::with_capacity(content_length.unwrap_or(1024 * 1024));
+    let read_result = response.into_reader().read_to_end(&mut bytes);
+    ensure_body_complete(bytes.len(), content_length)?;
+    read_result.context(CannotReadResponseBodySnafu {})?;
     Ok(bytes)
 }
 
-/// Sends GET with the given timeout to the specified URL.
+/// Sends GET with the given timeout to the specified URL and reads response body.
+/// Fails if response body turns out to be shorter than its `Content-Length`.
 #[cfg(feature = "async")]
-fn get_bytes(filen_endpoint: &str, timeout_secs: u64) -> Result<Vec<u8>, reqwest::Error> {
-    let response = get(filen_endpoint, timeout_secs)?;
-    response.bytes().map(|bytes| bytes.to_vec())
+fn get_bytes(filen_endpoint: &str, timeout_secs: u64, client_options: ClientOptions<'_>) -> Result<Vec<u8>> {
+    let mut response = get(filen_endpoint, timeout_secs, client_options).context(ReqwestWebRequestFailedSnafu {
+        message: format!("Failed to download file chunk from '{}'", filen_endpoint),
+    })?;
+    let content_length = response
+        .content_length()
+        .and_then(|length| usize::try_from(length).ok());
+
+    let mut bytes: Vec<u8> = Vec::with_capacity(content_length.unwrap_or(1024 * 1024));
+    let read_result = response.read_to_end(&mut bytes);
+    ensure_body_complete(bytes.len(), content_length)?;
+    read_result.context(CannotReadResponseBodySnafu {})?;
+    Ok(bytes)
 }
 
+/// Asynchronously sends GET with the given timeout to the specified URL and reads response body.
+/// Fails if response body turns out to be shorter than its `Content-Length`.
 #[cfg(feature = "async")]
-async fn get_bytes_async(filen_endpoint: &str, timeout_secs: u64) -> Result<Vec<u8>, reqwest::Error> {
-    let response = get_async(filen_endpoint, timeout_secs).await?;
-    response.bytes().await.map(|bytes| bytes.to_vec())
+async fn get_bytes_async(
+    filen_endpoint: &str,
+    timeout_secs: u64,
+    client_options: ClientOptions<'_>,
+) -> Result<Vec<u8>> {
+    let mut response =
+        get_async(filen_endpoint, timeout_secs, client_options)
+            .await
+            .context(ReqwestWebRequestFailedSnafu {
+                message: format!("Failed to download file chunk (async) from '{}'", filen_endpoint),
+            })?;
+    let content_length = response
+        .content_length()
+        .and_then(|length| usize::try_from(length).ok());
+
+    let mut bytes: Vec<u8> = Vec::with_capacity(content_length.unwrap_or(1024 * 1024));
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => bytes.extend_from_slice(&chunk),
+            Ok(None) => break,
+            Err(err) => {
+                ensure_body_complete(bytes.len(), content_length)?;
+                return Err(err).context(ReqwestCannotReadResponseBodySnafu {});
+            }
+        }
+    }
+    ensure_body_complete(bytes.len(), content_length)?;
+    Ok(bytes)
+}
+
+/// Fails with `Error::TruncatedResponseBody` if less bytes were received than expected.
+fn ensure_body_complete(got: usize, expected: Option<usize>) -> Result<()> {
+    match expected {
+        Some(expected) => {
+            ensure!(got >= expected, TruncatedResponseBodySnafu { got, expected });
+            Ok(())
+        }
+        None => Ok(()),
+    }
 }
 
 /// Sends POST with given blob and timeout to the specified URL.
 #[cfg(not(feature = "async"))]
-fn post_blob(url: &str, blob: &[u8], timeout_secs: u64) -> Result<ureq::Response, ureq::Error> {
-    AGENT
+fn post_blob(
+    url: &str,
+    blob: &[u8],
+    timeout_secs: u64,
+    client_options: ClientOptions<'_>,
+) -> Result<ureq::Response, ureq::Error> {
+    agent(client_options)
         .post(url)
         .timeout(Duration::from_secs(timeout_secs))
         .send_bytes(blob)
@@ -226,8 +879,13 @@ fn post_blob(url: &str, blob: &[u8], timeout_secs: u64) -> Result<ureq::Response
 
 /// Sends POST with given blob and timeout to the specified URL.
 #[cfg(feature = "async")]
-fn post_blob(url: &str, blob: &[u8], timeout_secs: u64) -> Result<reqwest::blocking::Response, reqwest::Error> {
-    BLOCKING_CLIENT
+fn post_blob(
+    url: &str,
+    blob: &[u8],
+    timeout_secs: u64,
+    client_options: ClientOptions<'_>,
+) -> Result<reqwest::blocking::Response, reqwest::Error> {
+    blocking_client(client_options)
         .post(url)
         .body(blob.to_owned())
         .timeout(Duration::from_secs(timeout_secs))
@@ -236,8 +894,13 @@ fn post_blob(url: &str, blob: &[u8], timeout_secs: u64) -> Result<reqwest::block
 
 /// Asynchronously sends POST with given blob and timeout to the specified URL.
 #[cfg(feature = "async")]
-async fn post_blob_async(url: &str, blob: &[u8], timeout_secs: u64) -> Result<reqwest::Response, reqwest::Error> {
-    ASYNC_CLIENT
+async fn post_blob_async(
+    url: &str,
+    blob: &[u8],
+    timeout_secs: u64,
+    client_options: ClientOptions<'_>,
+) -> Result<reqwest::Response, reqwest::Error> {
+    async_client(client_options)
         .post(url)
         .body(blob.to_owned())
         .timeout(Duration::from_secs(timeout_secs))
@@ -245,16 +908,165 @@ async fn post_blob_async(url: &str, blob: &[u8], timeout_secs: u64) -> Result<re
         .await
 }
 
+#[cfg(not(feature = "async"))]
+type BlockingResponse = ureq::Response;
+#[cfg(not(feature = "async"))]
+type BlockingRequestError = ureq::Error;
+#[cfg(feature = "async")]
+type BlockingResponse = reqwest::blocking::Response;
+#[cfg(feature = "async")]
+type BlockingRequestError = reqwest::Error;
+
+/// Returns HTTP status code of the response, including error responses.
+#[cfg(not(feature = "async"))]
+fn response_status_code(request_result: &Result<ureq::Response, ureq::Error>) -> Option<u16> {
+    match request_result {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => Some(response.status()),
+        Err(ureq::Error::Transport(_)) => None,
+    }
+}
+
+/// Returns HTTP status code of the response, including error responses.
+#[cfg(feature = "async")]
+fn response_status_code(request_result: &Result<reqwest::blocking::Response, reqwest::Error>) -> Option<u16> {
+    request_result.as_ref().ok().map(|response| response.status().as_u16())
+}
+
+/// Returns rate limit from the response headers, including error responses.
+#[cfg(not(feature = "async"))]
+fn response_rate_limit(request_result: &Result<ureq::Response, ureq::Error>) -> Option<RateLimit> {
+    match request_result {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => RateLimit::from_headers(|name| response.header(name)),
+        Err(ureq::Error::Transport(_)) => None,
+    }
+}
+
+/// Returns rate limit from the response headers, including error responses.
+#[cfg(feature = "async")]
+fn response_rate_limit(request_result: &Result<reqwest::blocking::Response, reqwest::Error>) -> Option<RateLimit> {
+    request_result
+        .as_ref()
+        .ok()
+        .and_then(|response| headers_rate_limit(response.headers()))
+}
+
+#[cfg(feature = "async")]
+fn headers_rate_limit(headers: &reqwest::header::HeaderMap) -> Option<RateLimit> {
+    RateLimit::from_headers(|name| headers.get(name).and_then(|value| value.to_str().ok()))
+}
+
+/// Returns `Date` header of the response, including error responses.
+#[cfg(not(feature = "async"))]
+fn response_date(request_result: &Result<ureq::Response, ureq::Error>) -> Option<&str> {
+    match request_result {
+        Ok(response) | Err(ureq::Error::Status(_, response)) => response.header("Date"),
+        Err(ureq::Error::Transport(_)) => None,
+    }
+}
+
+/// Returns `Date` header of the response, including error responses.
+#[cfg(feature = "async")]
+fn response_date(request_result: &Result<reqwest::blocking::Response, reqwest::Error>) -> Option<&str> {
+    request_result
+        .as_ref()
+        .ok()
+        .and_then(|response| headers_date(response.headers()))
+}
+
+#[cfg(feature = "async")]
+fn headers_date(headers: &reqwest::header::HeaderMap) -> Option<&str> {
+    headers.get(reqwest::header::DATE).and_then(|value| value.to_str().ok())
+}
+
+/// Remembers server clock offset from the given `Date` header, if `FilenSettings::track_server_clock` is set.
+fn track_server_clock(filen_settings: &FilenSettings, date_header: Option<&str>) {
+    if filen_settings.track_server_clock {
+        filen_settings.server_clock.record_date_header(date_header);
+    }
+}
+
+/// Sends POST with given payload to the first endpoint, trying fallback endpoints in turn
+/// while connection cannot be established. Returns the last result along with the endpoint it came from.
+fn post_with_failover<T: Serialize + ?Sized>(
+    encoded_payload: &EncodedPayload<'_, T>,
+    first_endpoint: Url,
+    fallback_endpoints: Vec<Url>,
+    timeout_secs: u64,
+    client_options: ClientOptions<'_>,
+) -> (Result<BlockingResponse, BlockingRequestError>, Url) {
+    let mut fallback_endpoints = fallback_endpoints.into_iter();
+    let mut filen_endpoint = first_endpoint;
+    loop {
+        let filen_response = match encoded_payload {
+            EncodedPayload::Json(payload) => post_json(filen_endpoint.as_str(), payload, timeout_secs, client_options),
+            EncodedPayload::Form(body) => post_form(filen_endpoint.as_str(), body, timeout_secs, client_options),
+        };
+        match (&filen_response, fallback_endpoints.next()) {
+            (Err(err), Some(next_endpoint)) if is_connection_failure(err) => filen_endpoint = next_endpoint,
+            _ => return (filen_response, filen_endpoint),
+        }
+    }
+}
+
+/// Sends POST with given form-urlencoded body and timeout to the specified URL.
+#[cfg(not(feature = "async"))]
+fn post_form(
+    url: &str,
+    body: &str,
+    timeout_secs: u64,
+    client_options: ClientOptions<'_>,
+) -> Result<ureq::Response, ureq::Error> {
+    agent(client_options)
+        .post(url)
+        .set("Content-Type", FORM_CONTENT_TYPE)
+        .timeout(Duration::from_secs(timeout_secs))
+        .send_string(body)
+}
+
+/// Sends POST with given form-urlencoded body and timeout to the specified URL.
+#[cfg(feature = "async")]
+fn post_form(
+    url: &str,
+    body: &str,
+    timeout_secs: u64,
+    client_options: ClientOptions<'_>,
+) -> Result<reqwest::blocking::Response, reqwest::Error> {
+    blocking_client(client_options)
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, FORM_CONTENT_TYPE)
+        .body(body.to_owned())
+        .timeout(Duration::from_secs(timeout_secs))
+        .send()
+}
+
+/// Asynchronously sends POST with given form-urlencoded body and timeout to the specified URL.
+#[cfg(feature = "async")]
+async fn post_form_async(
+    url: &str,
+    body: &str,
+    timeout_secs: u64,
+    client_options: ClientOptions<'_>,
+) -> Result<reqwest::Response, reqwest::Error> {
+    async_client(client_options)
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, FORM_CONTENT_TYPE)
+        .body(body.to_owned())
+        .timeout(Duration::from_secs(timeout_secs))
+        .send()
+        .await
+}
+
 /// Sends POST with given payload and timeout to the specified URL.
 #[cfg(not(feature = "async"))]
-pub(crate) fn post_json<T: Serialize + ?Sized>(
+fn post_json<T: Serialize + ?Sized>(
     url: &str,
     payload: &T,
     timeout_secs: u64,
+    client_options: ClientOptions<'_>,
 ) -> Result<ureq::Response, ureq::Error> {
     use serde_json::json;
 
-    AGENT
+    agent(client_options)
         .post(url)
         .timeout(Duration::from_secs(timeout_secs))
         .send_json(json!(payload))
@@ -266,8 +1078,9 @@ fn post_json<T: Serialize + ?Sized>(
     url: &str,
     payload: &T,
     timeout_secs: u64,
+    client_options: ClientOptions<'_>,
 ) -> Result<reqwest::blocking::Response, reqwest::Error> {
-    BLOCKING_CLIENT
+    blocking_client(client_options)
         .post(url)
         .json(payload)
         .timeout(Duration::from_secs(timeout_secs))
@@ -280,8 +1093,9 @@ async fn post_json_async<T: Serialize + ?Sized + Sync>(
     url: &str,
     payload: &T,
     timeout_secs: u64,
+    client_options: ClientOptions<'_>,
 ) -> Result<reqwest::Response, reqwest::Error> {
-    ASYNC_CLIENT
+    async_client(client_options)
         .post(url)
         .json(payload)
         .timeout(Duration::from_secs(timeout_secs))
@@ -290,59 +1104,920 @@ async fn post_json_async<T: Serialize + ?Sized + Sync>(
 }
 
 /// Randomly chooses one of the URLs in servers slice and joins it with the given API endpoint path.
+/// Also returns the rest of the servers joined with the given API endpoint path, in order after the chosen one,
+/// to be tried if the chosen server is unreachable.
+fn produce_failover_endpoints(api_endpoint: &str, servers: &[Url]) -> Result<(Url, Vec<Url>)> {
+    let first_server_index = thread_rng().gen_range(0..servers.len());
+    let first_endpoint = join_filen_endpoint(api_endpoint, &servers[first_server_index])?;
+    let fallback_endpoints = servers
+        .iter()
+        .cycle()
+        .skip(first_server_index + 1)
+        .take(servers.len() - 1)
+        .map(|server| join_filen_endpoint(api_endpoint, server))
+        .collect::<Result<Vec<Url>>>()?;
+    Ok((first_endpoint, fallback_endpoints))
+}
+
+/// Checks if request failed because server could not be reached at all, so trying another server makes sense.
+#[cfg(not(feature = "async"))]
+fn is_connection_failure(err: &ureq::Error) -> bool {
+    matches!(err, ureq::Error::Transport(transport)
+        if matches!(transport.kind(), ureq::ErrorKind::ConnectionFailed | ureq::ErrorKind::Dns))
+}
+
+/// Checks if request failed because server could not be reached at all, so trying another server makes sense.
+#[cfg(feature = "async")]
+fn is_connection_failure(err: &reqwest::Error) -> bool {
+    err.is_connect()
+}
+
+/// Randomly chooses one of the URLs in servers slice and joins it with the given API endpoint path.
+/// Endpoint path is always treated as relative to the server URL, so server URL path prefix,
+/// like `https://host/filen/` of a self-hosted gateway, is preserved.
 fn produce_filen_endpoint(api_endpoint: &str, servers: &[Url]) -> Result<Url> {
-    let chosen_server = choose_filen_server(servers);
-    chosen_server.join(api_endpoint).context(CannotJoinApiEndpointSnafu {
-        api_endpoint,
-        chosen_server: chosen_server.to_string(),
-    })
+    join_filen_endpoint(api_endpoint, choose_filen_server(servers))
+}
+
+/// Joins the given server URL with the given API endpoint path.
+fn join_filen_endpoint(api_endpoint: &str, server: &Url) -> Result<Url> {
+    let chosen_server = normalize_server_url(server);
+    chosen_server
+        .join(api_endpoint.trim_start_matches('/'))
+        .context(CannotJoinApiEndpointSnafu {
+            api_endpoint,
+            chosen_server: chosen_server.to_string(),
+        })
+}
+
+/// Makes sure server URL path ends with a single slash, so joining it with an endpoint path
+/// never produces empty path segments like `//v1/dir` or drops the last server path segment.
+fn normalize_server_url(server: &Url) -> Url {
+    let mut normalized_server = server.clone();
+    let trimmed_path = format!("{}/", server.path().trim_end_matches('/'));
+    normalized_server.set_path(&trimmed_path);
+    normalized_server
 }
 
 #[cfg(not(feature = "async"))]
-fn deserialize_response<U, F>(request_result: Result<ureq::Response, ureq::Error>, error_message: F) -> Result<U>
+fn deserialize_response<U, F>(
+    request_result: Result<ureq::Response, ureq::Error>,
+    filen_settings: &FilenSettings,
+    error_message: F,
+) -> Result<U>
 where
     U: DeserializeOwned,
     F: FnOnce() -> String,
 {
-    let response = request_result.context(UreqWebRequestFailedSnafu {
-        message: error_message(),
-    })?;
-    response
-        .into_json::<U>()
-        .context(UreqCannotDeserializeResponseBodyJsonSnafu {})
+    let response = checked_response(request_result, filen_settings, error_message)?;
+    match filen_settings.max_response_bytes {
+        Some(max_response_bytes) => {
+            let body = read_to_end_limited(response.into_reader(), max_response_bytes)?;
+            serde_json::from_slice(&body).context(CannotDeserializeResponseBodyJsonSnafu {})
+        }
+        None => response
+            .into_json::<U>()
+            .context(UreqCannotDeserializeResponseBodyJsonSnafu {}),
+    }
 }
 
 #[cfg(feature = "async")]
 fn deserialize_response<U, F>(
     request_result: Result<reqwest::blocking::Response, reqwest::Error>,
+    filen_settings: &FilenSettings,
     error_message: F,
 ) -> Result<U>
 where
     U: DeserializeOwned,
     F: Send + FnOnce() -> String,
 {
-    let response = request_result.context(ReqwestWebRequestFailedSnafu {
-        message: error_message(),
-    })?;
-    response
-        .json::<U>()
-        .context(ReqwestCannotDeserializeResponseBodyJsonSnafu {})
+    let response = checked_response(request_result, filen_settings, error_message)?;
+    match filen_settings.max_response_bytes {
+        Some(max_response_bytes) => {
+            let body = read_to_end_limited(response, max_response_bytes)?;
+            serde_json::from_slice(&body).context(CannotDeserializeResponseBodyJsonSnafu {})
+        }
+        None => response
+            .json::<U>()
+            .context(ReqwestCannotDeserializeResponseBodyJsonSnafu {}),
+    }
+}
+
+/// Unwraps successful response, failing on request errors and throttling.
+#[cfg(not(feature = "async"))]
+fn checked_response<F: FnOnce() -> String>(
+    request_result: Result<ureq::Response, ureq::Error>,
+    filen_settings: &FilenSettings,
+    error_message: F,
+) -> Result<ureq::Response> {
+    let response = match request_result {
+        Err(ureq::Error::Status(429, response)) => {
+            return Err(Throttled {
+                retry_after: response
+                    .header("Retry-After")
+                    .and_then(|retry_after| parse_retry_after(retry_after, response.header("Date"), filen_settings)),
+            })
+            .context(TooManyRequestsSnafu {
+                message: error_message(),
+            })
+        }
+        other => other.context(UreqWebRequestFailedSnafu {
+            message: error_message(),
+        })?,
+    };
+    Ok(response)
+}
+
+/// Unwraps successful response, failing on request errors and throttling.
+#[cfg(feature = "async")]
+fn checked_response<F: FnOnce() -> String>(
+    request_result: Result<reqwest::blocking::Response, reqwest::Error>,
+    filen_settings: &FilenSettings,
+    error_message: F,
+) -> Result<reqwest::blocking::Response> {
+    let message = error_message();
+    let response = request_result.context(ReqwestWebRequestFailedSnafu { message: &message })?;
+    ensure_not_throttled(response.status(), response.headers(), filen_settings, &message)?;
+    Ok(response)
+}
+
+#[cfg(not(feature = "async"))]
+fn response_body_reader(response: ureq::Response) -> Box<dyn Read + Send> {
+    response.into_reader()
+}
+
+#[cfg(feature = "async")]
+fn response_body_reader(response: reqwest::blocking::Response) -> Box<dyn Read + Send> {
+    Box::new(response)
 }
 
 #[cfg(feature = "async")]
 async fn deserialize_response_async<U, F>(
     request_result: Result<reqwest::Response, reqwest::Error>,
+    filen_settings: &FilenSettings,
     error_message: F,
 ) -> Result<U>
 where
     U: DeserializeOwned,
     F: Send + FnOnce() -> String,
 {
-    let response = request_result.context(ReqwestWebRequestFailedSnafu {
-        message: error_message(),
-    })?;
-    response
-        .json::<U>()
-        .await
-        .context(ReqwestCannotDeserializeResponseBodyJsonSnafu {})
+    let message = error_message();
+    let mut response = request_result.context(ReqwestWebRequestFailedSnafu { message: &message })?;
+    ensure_not_throttled(response.status(), response.headers(), filen_settings, &message)?;
+    match filen_settings.max_response_bytes {
+        Some(max_response_bytes) => {
+            let mut body: Vec<u8> = Vec::new();
+            while let Some(chunk) = response.chunk().await.context(ReqwestCannotReadResponseBodySnafu {})? {
+                ensure!(
+                    body.len() + chunk.len() <= max_response_bytes,
+                    ResponseTooLargeSnafu { max_response_bytes }
+                );
+                body.extend_from_slice(&chunk);
+            }
+            serde_json::from_slice(&body).context(CannotDeserializeResponseBodyJsonSnafu {})
+        }
+        None => response
+            .json::<U>()
+            .await
+            .context(ReqwestCannotDeserializeResponseBodyJsonSnafu {}),
+    }
+}
+
+/// Fails with `Error::TooManyRequests` if Filen responded with 429 status.
+#[cfg(feature = "async")]
+fn ensure_not_throttled(
+    status: reqwest::StatusCode,
+    headers: &reqwest::header::HeaderMap,
+    filen_settings: &FilenSettings,
+    message: &str,
+) -> Result<()> {
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = headers
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|retry_after| parse_retry_after(retry_after, headers_date(headers), filen_settings));
+        return Err(Throttled { retry_after }).context(TooManyRequestsSnafu { message });
+    }
+    Ok(())
+}
+
+/// Parses `Retry-After` header value, which is either a delay in seconds or an HTTP date.
+/// HTTP date is counted from the response `Date` header, if it is given, so local clock skew does not matter.
+/// Otherwise it is counted from `FilenSettings::server_now`.
+fn parse_retry_after(retry_after: &str, date_header: Option<&str>, filen_settings: &FilenSettings) -> Option<Duration> {
+    let retry_after = retry_after.trim();
+    retry_after.parse::<u64>().map(Duration::from_secs).ok().or_else(|| {
+        httpdate::parse_http_date(retry_after).ok().map(|retry_date| {
+            let response_date = date_header
+                .and_then(|date| httpdate::parse_http_date(date).ok())
+                .unwrap_or_else(|| filen_settings.server_now());
+            retry_date.duration_since(response_date).unwrap_or_default()
+        })
+    })
+}
+
+/// Reads the given response body, failing if it turns out to be larger than `max_response_bytes`.
+fn read_to_end_limited<R: Read>(reader: R, max_response_bytes: usize) -> Result<Vec<u8>> {
+    let mut body: Vec<u8> = Vec::new();
+    reader
+        .take(max_response_bytes as u64 + 1)
+        .read_to_end(&mut body)
+        .context(CannotReadResponseBodySnafu {})?;
+    ensure!(
+        body.len() <= max_response_bytes,
+        ResponseTooLargeSnafu { max_response_bytes }
+    );
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{init_server, read_project_file, start_tls_server};
+    use crate::v1::PlainResponsePayload;
+    use crate::RetrySettings;
+    use httpmock::Method::{GET, HEAD, POST};
+    use serde_json::json;
+    use std::collections::HashSet;
+    #[cfg(feature = "async")]
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::SystemTime;
+
+    fn setup_large_response_mock(server: &httpmock::MockServer) -> httpmock::Mock<'_> {
+        server.mock(|when, then| {
+            when.method(POST).path("/v1/test");
+            then.status(200).json_body(json!({
+                "status": true,
+                "message": "x".repeat(1024),
+            }));
+        })
+    }
+
+    #[test]
+    fn query_filen_api_should_fail_for_response_over_max_size() {
+        let (server, mut filen_settings) = init_server();
+        filen_settings.max_response_bytes = Some(512);
+        let mock = setup_large_response_mock(&server);
+
+        let result = query_filen_api::<_, PlainResponsePayload>("/v1/test", &json!({}), &filen_settings);
+
+        mock.assert_hits(1);
+        assert!(matches!(
+            result,
+            Err(Error::ResponseTooLarge {
+                max_response_bytes: 512
+            })
+        ));
+    }
+
+    #[test]
+    fn query_filen_api_should_read_response_under_max_size() {
+        let (server, mut filen_settings) = init_server();
+        filen_settings.max_response_bytes = Some(2048);
+        let mock = setup_large_response_mock(&server);
+
+        let result = query_filen_api::<_, PlainResponsePayload>("/v1/test", &json!({}), &filen_settings);
+
+        mock.assert_hits(1);
+        assert!(result.unwrap().status);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn query_filen_api_async_should_fail_for_response_over_max_size() {
+        let (server, mut filen_settings) = init_server();
+        filen_settings.max_response_bytes = Some(512);
+        let mock = setup_large_response_mock(&server);
+
+        let result = query_filen_api_async::<_, PlainResponsePayload>("/v1/test", &json!({}), &filen_settings).await;
+
+        mock.assert_hits(1);
+        assert!(matches!(
+            result,
+            Err(Error::ResponseTooLarge {
+                max_response_bytes: 512
+            })
+        ));
+    }
+
+    #[test]
+    fn query_filen_api_should_ignore_trailing_slashes_in_server_urls() {
+        let (server, mut filen_settings) = init_server();
+        filen_settings.api_servers = vec![Url::parse(&format!("{}//", server.base_url())).unwrap()];
+        filen_settings.download_servers = vec![Url::parse(&format!("{}//", server.base_url())).unwrap()];
+        let api_mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/test");
+            then.status(200).json_body(json!({"status": true}));
+        });
+        let download_mock = server.mock(|when, then| {
+            when.method(GET).path("/de-1/filen-1/chunk/0");
+            then.status(200).body("chunk");
+        });
+
+        let api_result = query_filen_api::<_, PlainResponsePayload>("/v1/test", &json!({}), &filen_settings);
+        let download_result = download_from_filen("de-1/filen-1/chunk/0", &filen_settings);
+
+        api_mock.assert_hits(1);
+        download_mock.assert_hits(1);
+        assert!(api_result.unwrap().status);
+        assert_eq!(download_result.unwrap(), b"chunk");
+    }
+
+    #[derive(Default)]
+    struct InMemoryMetricsSink {
+        records: std::sync::Mutex<Vec<(String, Duration, bool)>>,
+        rate_limits: std::sync::Mutex<Vec<(String, RateLimit)>>,
+    }
+
+    impl crate::MetricsSink for InMemoryMetricsSink {
+        fn record(&self, path: &str, duration: Duration, success: bool) {
+            self.records.lock().unwrap().push((path.to_owned(), duration, success));
+        }
+
+        fn record_rate_limit(&self, path: &str, rate_limit: RateLimit) {
+            self.rate_limits.lock().unwrap().push((path.to_owned(), rate_limit));
+        }
+    }
+
+    #[test]
+    fn query_filen_api_should_pass_rate_limit_headers_to_sink() {
+        let (server, mut filen_settings) = init_server();
+        let sink = std::sync::Arc::new(InMemoryMetricsSink::default());
+        filen_settings.metrics_sink = crate::SharedMetricsSink::new(sink.clone());
+        let limited_mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/limited");
+            then.status(200)
+                .header("X-RateLimit-Remaining", "7")
+                .header("X-RateLimit-Reset", "30")
+                .json_body(json!({"status": true}));
+        });
+        let unlimited_mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/unlimited");
+            then.status(200).json_body(json!({"status": true}));
+        });
+
+        let limited_result = query_filen_api::<_, PlainResponsePayload>("/v1/limited", &json!({}), &filen_settings);
+        let unlimited_result = query_filen_api::<_, PlainResponsePayload>("/v1/unlimited", &json!({}), &filen_settings);
+
+        limited_mock.assert_hits(1);
+        unlimited_mock.assert_hits(1);
+        assert!(limited_result.unwrap().status);
+        assert!(unlimited_result.unwrap().status);
+        let rate_limits = sink.rate_limits.lock().unwrap();
+        assert_eq!(
+            *rate_limits,
+            vec![(
+                "/v1/limited".to_owned(),
+                RateLimit {
+                    remaining: Some(7),
+                    reset: Some(30)
+                }
+            )]
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn query_filen_api_async_should_pass_rate_limit_headers_of_throttled_response_to_sink() {
+        let (server, mut filen_settings) = init_server();
+        let sink = std::sync::Arc::new(InMemoryMetricsSink::default());
+        filen_settings.metrics_sink = crate::SharedMetricsSink::new(sink.clone());
+        let throttled_mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/throttled");
+            then.status(429).header("X-RateLimit-Remaining", "0");
+        });
+
+        let result =
+            query_filen_api_async::<_, PlainResponsePayload>("/v1/throttled", &json!({}), &filen_settings).await;
+
+        throttled_mock.assert_hits(1);
+        assert!(matches!(result, Err(Error::TooManyRequests { .. })));
+        let rate_limits = sink.rate_limits.lock().unwrap();
+        assert_eq!(
+            *rate_limits,
+            vec![(
+                "/v1/throttled".to_owned(),
+                RateLimit {
+                    remaining: Some(0),
+                    reset: None
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn query_filen_api_should_record_metrics_into_sink() {
+        let (server, mut filen_settings) = init_server();
+        let sink = std::sync::Arc::new(InMemoryMetricsSink::default());
+        filen_settings.metrics_sink = crate::SharedMetricsSink::new(sink.clone());
+        let api_mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/test");
+            then.status(200)
+                .delay(Duration::from_millis(50))
+                .json_body(json!({"status": true}));
+        });
+
+        let result = query_filen_api::<_, PlainResponsePayload>("/v1/test", &json!({}), &filen_settings);
+
+        api_mock.assert_hits(1);
+        assert!(result.unwrap().status);
+        let records = sink.records.lock().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].0, "/v1/test");
+        assert!(records[0].1 >= Duration::from_millis(50));
+        assert!(records[0].2);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn upload_to_filen_async_should_record_metrics_without_query_string() {
+        let (server, mut filen_settings) = init_server();
+        let sink = std::sync::Arc::new(InMemoryMetricsSink::default());
+        filen_settings.metrics_sink = crate::SharedMetricsSink::new(sink.clone());
+        let upload_mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/upload");
+            then.status(500);
+        });
+
+        let result =
+            upload_to_filen_async::<PlainResponsePayload>("/v1/upload?apiKey=secret", b"chunk", &filen_settings).await;
+
+        upload_mock.assert_hits(1);
+        assert!(result.is_err());
+        let records = sink.records.lock().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].0, "/v1/upload");
+        assert!(!records[0].2);
+    }
+
+    /// Returns URL of a local port nobody listens to, so connections to it are refused.
+    fn refusing_server_url() -> Url {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+        Url::parse(&format!("http://{}/", address)).unwrap()
+    }
+
+    #[test]
+    fn query_filen_api_should_fail_over_to_next_server_when_connection_is_refused() {
+        let (server, mut filen_settings) = init_server();
+        filen_settings.api_servers = vec![refusing_server_url(), Url::parse(&server.base_url()).unwrap()];
+        let api_mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/test");
+            then.status(200).json_body(json!({"status": true}));
+        });
+
+        // Server is chosen randomly, so query enough times to make sure refusing server is chosen first at least once.
+        for _ in 0..10 {
+            let result = query_filen_api::<_, PlainResponsePayload>("/v1/test", &json!({}), &filen_settings);
+            assert!(result.unwrap().status);
+        }
+
+        api_mock.assert_hits(10);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn query_filen_api_async_should_fail_over_to_next_server_when_connection_is_refused() {
+        let (server, mut filen_settings) = init_server();
+        filen_settings.api_servers = vec![Url::parse(&server.base_url()).unwrap(), refusing_server_url()];
+        let api_mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/test");
+            then.status(200).json_body(json!({"status": true}));
+        });
+        let payload = json!({});
+
+        for _ in 0..10 {
+            let result = query_filen_api_async::<_, PlainResponsePayload>("/v1/test", &payload, &filen_settings).await;
+            assert!(result.unwrap().status);
+        }
+
+        api_mock.assert_hits(10);
+    }
+
+    #[test]
+    fn query_filen_api_should_not_fail_over_on_http_error() {
+        let (server, mut filen_settings) = init_server();
+        let (other_server, _) = init_server();
+        filen_settings.api_servers = vec![
+            Url::parse(&server.base_url()).unwrap(),
+            Url::parse(&other_server.base_url()).unwrap(),
+        ];
+        let api_mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/test");
+            then.status(500);
+        });
+        let other_api_mock = other_server.mock(|when, then| {
+            when.method(POST).path("/v1/test");
+            then.status(500);
+        });
+
+        let result = query_filen_api::<_, PlainResponsePayload>("/v1/test", &json!({}), &filen_settings);
+
+        assert!(result.is_err());
+        assert_eq!(api_mock.hits() + other_api_mock.hits(), 1);
+    }
+
+    #[derive(Serialize)]
+    struct LegacyPayload<'legacy> {
+        #[serde(rename = "apiKey")]
+        api_key: &'legacy str,
+        name: &'legacy str,
+    }
+
+    fn setup_form_mock(server: &httpmock::MockServer) -> httpmock::Mock<'_> {
+        server.mock(|when, then| {
+            when.method(POST)
+                .path("/v1/legacy")
+                .header("content-type", FORM_CONTENT_TYPE)
+                .body("apiKey=some+key&name=a%26b%3Dc");
+            then.status(200).json_body(json!({"status": true}));
+        })
+    }
+
+    #[test]
+    fn query_filen_api_with_encoding_should_send_form_urlencoded_body() {
+        let (server, filen_settings) = init_server();
+        let form_mock = setup_form_mock(&server);
+        let payload = LegacyPayload {
+            api_key: "some key",
+            name: "a&b=c",
+        };
+
+        let result = query_filen_api_with_encoding::<_, PlainResponsePayload>(
+            "/v1/legacy",
+            &payload,
+            PayloadEncoding::Form,
+            &filen_settings,
+        );
+
+        form_mock.assert_hits(1);
+        assert!(result.unwrap().status);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn query_filen_api_with_encoding_async_should_send_form_urlencoded_body() {
+        let (server, filen_settings) = init_server();
+        let form_mock = setup_form_mock(&server);
+        let payload = LegacyPayload {
+            api_key: "some key",
+            name: "a&b=c",
+        };
+
+        let result = query_filen_api_with_encoding_async::<_, PlainResponsePayload>(
+            "/v1/legacy",
+            &payload,
+            PayloadEncoding::Form,
+            &filen_settings,
+        )
+        .await;
+
+        form_mock.assert_hits(1);
+        assert!(result.unwrap().status);
+    }
+
+    #[test]
+    fn query_filen_api_with_encoding_should_fail_for_nested_form_payload() {
+        let (_server, filen_settings) = init_server();
+
+        let result = query_filen_api_with_encoding::<_, PlainResponsePayload>(
+            "/v1/legacy",
+            &json!({"nested": {"key": "value"}}),
+            PayloadEncoding::Form,
+            &filen_settings,
+        );
+
+        assert!(matches!(result, Err(Error::CannotSerializePayloadToForm { .. })));
+    }
+
+    #[test]
+    fn query_filen_api_should_preserve_server_url_path_prefix() {
+        let (server, mut filen_settings) = init_server();
+        filen_settings.api_servers = vec![Url::parse(&format!("{}/filen", server.base_url())).unwrap()];
+        filen_settings.download_servers = vec![Url::parse(&format!("{}/filen/", server.base_url())).unwrap()];
+        let api_mock = server.mock(|when, then| {
+            when.method(POST).path("/filen/v1/test");
+            then.status(200).json_body(json!({"status": true}));
+        });
+        let download_mock = server.mock(|when, then| {
+            when.method(GET).path("/filen/de-1/filen-1/chunk/0");
+            then.status(200).body("chunk");
+        });
+
+        let api_result = query_filen_api::<_, PlainResponsePayload>("/v1/test", &json!({}), &filen_settings);
+        let download_result = download_from_filen("de-1/filen-1/chunk/0", &filen_settings);
+
+        api_mock.assert_hits(1);
+        download_mock.assert_hits(1);
+        assert!(api_result.unwrap().status);
+        assert_eq!(download_result.unwrap(), b"chunk");
+    }
+
+    #[test]
+    fn normalize_server_url_should_leave_single_trailing_slash() {
+        let urls = [
+            "https://api.filen.io",
+            "https://api.filen.io/",
+            "https://api.filen.io///",
+        ]
+        .iter()
+        .map(|url| normalize_server_url(&Url::parse(url).unwrap()).to_string())
+        .collect::<Vec<String>>();
+
+        assert!(urls.iter().all(|url| url == "https://api.filen.io/"));
+    }
+
+    fn setup_throttling_mocks(server: &httpmock::MockServer) -> (httpmock::Mock<'_>, httpmock::Mock<'_>) {
+        let throttled_mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/throttled");
+            then.status(429).header("Retry-After", "2");
+        });
+        let test_mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/test");
+            then.status(200).json_body(json!({"status": true}));
+        });
+        (throttled_mock, test_mock)
+    }
+
+    #[test]
+    fn choose_filen_server_should_only_pick_given_servers() {
+        let servers: Vec<Url> = ["https://a.filen.io", "https://b.filen.io", "https://c.filen.io"]
+            .iter()
+            .map(|server| Url::parse(server).unwrap())
+            .collect();
+
+        let chosen: HashSet<&Url> = (0..1000).map(|_| choose_filen_server(&servers)).collect();
+
+        assert_eq!(chosen, servers.iter().collect());
+    }
+
+    #[test]
+    fn query_filen_api_should_track_server_clock_only_when_asked() {
+        let (server, mut filen_settings) = init_server();
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/dated");
+            then.status(200)
+                .header("Date", "Sun, 06 Nov 1994 08:49:37 GMT")
+                .json_body(json!({"status": true}));
+        });
+
+        query_filen_api::<_, PlainResponsePayload>("/v1/dated", &json!({}), &filen_settings).unwrap();
+        assert_eq!(filen_settings.server_clock_offset(), None);
+
+        filen_settings.track_server_clock = true;
+        let cloned_settings = filen_settings.clone();
+        query_filen_api::<_, PlainResponsePayload>("/v1/dated", &json!({}), &filen_settings).unwrap();
+
+        mock.assert_hits(2);
+        assert!(filen_settings.server_clock_offset().unwrap().as_millis() < 0);
+        assert_eq!(
+            cloned_settings.server_clock_offset(),
+            filen_settings.server_clock_offset()
+        );
+        assert_eq!(FilenSettings::default().server_clock_offset(), None);
+    }
+
+    #[test]
+    fn query_filen_api_should_fail_with_retry_after_for_too_many_requests() {
+        let (server, filen_settings) = init_server();
+        let (throttled_mock, _) = setup_throttling_mocks(&server);
+
+        let result = query_filen_api::<_, PlainResponsePayload>("/v1/throttled", &json!({}), &filen_settings);
+
+        throttled_mock.assert_hits(1);
+        assert!(matches!(
+            result,
+            Err(Error::TooManyRequests {
+                source: Throttled {
+                    retry_after: Some(retry_after)
+                },
+                ..
+            }) if retry_after == Duration::from_secs(2)
+        ));
+    }
+
+    #[test]
+    fn retry_should_wait_for_retry_after_delay_when_throttled() {
+        let (server, filen_settings) = init_server();
+        let (throttled_mock, test_mock) = setup_throttling_mocks(&server);
+        let retry_settings = RetrySettings::new(1, Duration::from_millis(10), 1, Duration::from_secs(2));
+        let mut attempts = 0;
+
+        let started = Instant::now();
+        let result = retry_settings.call(|| {
+            attempts += 1;
+            let api_endpoint = if attempts == 1 { "/v1/throttled" } else { "/v1/test" };
+            query_filen_api::<_, PlainResponsePayload>(api_endpoint, &json!({}), &filen_settings)
+        });
+
+        throttled_mock.assert_hits(1);
+        test_mock.assert_hits(1);
+        assert!(result.unwrap().status);
+        assert!(started.elapsed() >= Duration::from_secs(2));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn retry_async_should_wait_for_retry_after_delay_when_throttled() {
+        let (server, filen_settings) = init_server();
+        let (throttled_mock, test_mock) = setup_throttling_mocks(&server);
+        let retry_settings = RetrySettings::new(1, Duration::from_millis(10), 1, Duration::from_secs(2));
+        let attempts = AtomicUsize::new(0);
+        let payload = json!({});
+
+        let started = Instant::now();
+        let result = retry_settings
+            .call_async(|| {
+                let api_endpoint = if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                    "/v1/throttled"
+                } else {
+                    "/v1/test"
+                };
+                query_filen_api_async::<_, PlainResponsePayload>(api_endpoint, &payload, &filen_settings)
+            })
+            .await;
+
+        throttled_mock.assert_hits(1);
+        test_mock.assert_hits(1);
+        assert!(result.unwrap().status);
+        assert!(started.elapsed() >= Duration::from_secs(2));
+    }
+
+    #[test]
+    fn parse_retry_after_should_accept_seconds_and_http_date() {
+        let filen_settings = FilenSettings::default();
+        assert_eq!(
+            parse_retry_after(" 120 ", None, &filen_settings),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", None, &filen_settings),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            parse_retry_after(
+                "Sun, 06 Nov 1994 08:49:37 GMT",
+                Some("Sun, 06 Nov 1994 08:48:37 GMT"),
+                &filen_settings
+            ),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(parse_retry_after("later", None, &filen_settings), None);
+    }
+
+    #[test]
+    fn parse_retry_after_should_count_http_date_from_server_clock_without_date_header() {
+        let filen_settings = FilenSettings::default();
+        let server_date = SystemTime::now() - Duration::from_secs(3600);
+        filen_settings
+            .server_clock
+            .record_date_header(Some(&httpdate::fmt_http_date(server_date)));
+        let retry_date = httpdate::fmt_http_date(server_date + Duration::from_secs(60));
+
+        let retry_after = parse_retry_after(&retry_date, None, &filen_settings).unwrap();
+
+        assert!(retry_after > Duration::from_secs(55) && retry_after <= Duration::from_secs(61));
+    }
+
+    #[test]
+    fn ping_server_should_measure_round_trip_to_healthy_server() {
+        let (server, filen_settings) = init_server();
+        let mock = server.mock(|when, then| {
+            when.method(HEAD).path("/");
+            then.status(200);
+        });
+
+        let elapsed = ping_server(&filen_settings.download_servers[0], &filen_settings);
+
+        mock.assert_hits(1);
+        assert!(elapsed.unwrap() < filen_settings.request_timeout);
+        assert_eq!(
+            fastest_server(&filen_settings.download_servers, &filen_settings),
+            Some(&filen_settings.download_servers[0])
+        );
+    }
+
+    #[test]
+    fn ping_server_should_fail_for_server_error() {
+        let (server, filen_settings) = init_server();
+        let mock = server.mock(|when, then| {
+            when.method(HEAD).path("/");
+            then.status(503);
+        });
+
+        let result = ping_server(&filen_settings.download_servers[0], &filen_settings);
+
+        mock.assert_hits(1);
+        assert!(matches!(result, Err(Error::ServerIsUnhealthy { status: 503, .. })));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn ping_server_async_should_measure_round_trip_to_healthy_server() {
+        let (server, filen_settings) = init_server();
+        let mock = server.mock(|when, then| {
+            when.method(HEAD).path("/");
+            then.status(200);
+        });
+
+        let elapsed = ping_server_async(&filen_settings.download_servers[0], &filen_settings).await;
+
+        mock.assert_hits(1);
+        assert!(elapsed.unwrap() < filen_settings.request_timeout);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn client_builders_should_receive_selected_http_version() {
+        let options = |http_version| ClientOptions {
+            http_version,
+            pinned_certificates: &[],
+        };
+        let debug_async = |version| format!("{:?}", async_client_builder(options(version)));
+        let debug_blocking = |version| format!("{:?}", blocking_client_builder(options(version)));
+
+        assert!(debug_async(HttpVersion::Http1Only).contains("http1_only: true"));
+        assert!(debug_blocking(HttpVersion::Http1Only).contains("http1_only: true"));
+        assert!(debug_async(HttpVersion::Http2PriorKnowledge).contains("http2_prior_knowledge: true"));
+        assert!(debug_blocking(HttpVersion::Http2PriorKnowledge).contains("http2_prior_knowledge: true"));
+        assert!(!debug_async(HttpVersion::Auto).contains("http1_only"));
+        assert!(!debug_async(HttpVersion::Auto).contains("http2_prior_knowledge"));
+    }
+
+    #[test]
+    fn query_filen_api_should_work_over_forced_http1() {
+        let (server, mut filen_settings) = init_server();
+        filen_settings.http_version = HttpVersion::Http1Only;
+        let mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/test");
+            then.status(200).json_body(json!({"status": true}));
+        });
+
+        let result = query_filen_api::<_, PlainResponsePayload>("/v1/test", &json!({}), &filen_settings);
+
+        mock.assert_hits(1);
+        assert!(result.unwrap().status);
+    }
+
+    fn pinned_tls_settings(pinned_certificate: CertificatePin) -> FilenSettings {
+        FilenSettings {
+            api_servers: vec![start_tls_server()],
+            request_timeout: Duration::from_secs(10),
+            pinned_certificates: vec![pinned_certificate],
+            ..FilenSettings::default()
+        }
+    }
+
+    fn localhost_certificate_pin() -> CertificatePin {
+        CertificatePin::from_certificate_der(&read_project_file("tests/resources/tls/localhost_cert.der"))
+    }
+
+    #[cfg(any(not(feature = "async"), feature = "rustls"))]
+    #[test]
+    fn query_filen_api_should_accept_server_with_pinned_certificate() {
+        let filen_settings = pinned_tls_settings(localhost_certificate_pin());
+
+        let result = query_filen_api::<_, PlainResponsePayload>("/v1/test", &json!({}), &filen_settings);
+
+        assert!(result.unwrap().status);
+    }
+
+    #[cfg(any(not(feature = "async"), feature = "rustls"))]
+    #[test]
+    fn query_filen_api_should_reject_server_with_mismatched_pinned_certificate() {
+        let filen_settings = pinned_tls_settings(CertificatePin::new([7; 32]));
+
+        let result = query_filen_api::<_, PlainResponsePayload>("/v1/test", &json!({}), &filen_settings);
+
+        #[cfg(not(feature = "async"))]
+        assert!(matches!(result, Err(Error::UreqWebRequestFailed { .. })));
+        #[cfg(feature = "async")]
+        assert!(matches!(result, Err(Error::ReqwestWebRequestFailed { .. })));
+    }
+
+    #[cfg(all(feature = "async", feature = "rustls"))]
+    #[tokio::test]
+    async fn query_filen_api_async_should_accept_only_server_with_pinned_certificate() {
+        let pinned_settings = pinned_tls_settings(localhost_certificate_pin());
+        let mismatched_settings = pinned_tls_settings(CertificatePin::new([7; 32]));
+
+        let pinned_result =
+            query_filen_api_async::<_, PlainResponsePayload>("/v1/test", &json!({}), &pinned_settings).await;
+        let mismatched_result =
+            query_filen_api_async::<_, PlainResponsePayload>("/v1/test", &json!({}), &mismatched_settings).await;
+
+        assert!(pinned_result.unwrap().status);
+        assert!(matches!(mismatched_result, Err(Error::ReqwestWebRequestFailed { .. })));
+    }
+
+    #[cfg(all(feature = "async", not(feature = "rustls")))]
+    #[tokio::test]
+    async fn query_filen_api_async_should_refuse_certificate_pinning_without_rustls() {
+        let filen_settings = pinned_tls_settings(localhost_certificate_pin());
+
+        let result = query_filen_api_async::<_, PlainResponsePayload>("/v1/test", &json!({}), &filen_settings).await;
+
+        assert!(matches!(result, Err(Error::CertificatePinningRequiresRustls {})));
+    }
 }
@@ -1,7 +1,14 @@
-use std::time::Duration;
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    sync::Arc,
+    time::Duration,
+};
 
-use crate::limited_exponential::LimitedExponential;
+use crate::{limited_exponential::LimitedExponential, queries};
 use once_cell::sync::Lazy;
+#[cfg(feature = "async")]
+use std::{future::Future, pin::Pin};
 
 const RETRY_EXP_FACTOR: u32 = 2;
 const RETRY_INITIAL_DELAY_MILLIS: u64 = 1000;
@@ -16,11 +23,23 @@ pub static STANDARD_RETRIES: Lazy<RetrySettings> = Lazy::new(|| RetrySettings {
     ..RetrySettings::default()
 });
 
+/// User-defined predicate deciding if operation failed with the given error should be retried.
+///
+/// Operation error itself is not required to be `'static`, so it cannot be downcast, but its sources can.
+pub type RetryPredicate = Arc<dyn Fn(&dyn std::error::Error) -> bool + Send + Sync>;
+
 /// Parameters for exponential backoff retry strategy with random jitter. Default instance performs no retries.
 ///
 /// Turn any API query into retriable if needed: call `RetrySettings::call` for sync operations and
 /// `RetrySettings::call_async` for futures.
-#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+///
+/// When Filen throttles requests with 429 Too Many Requests and specifies `Retry-After` header,
+/// server-directed delay is used instead of the exponential backoff one, but it never exceeds
+/// `RetrySettings::max_delay`.
+///
+/// By default, every failed operation is retried, unless sync operation explicitly returns
+/// `retry::OperationResult::Err`. Set `RetrySettings::with_retry_predicate` to decide it yourself.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct RetrySettings {
     /// Initial delay for exponential backoff.
     initial_delay: Duration,
@@ -33,6 +52,9 @@ pub struct RetrySettings {
 
     /// Amount of retries to perform when something fails. If set to 0, no retries will be made.
     max_tries: usize,
+
+    /// Predicate which overrides default classification of errors into retriable and permanent ones.
+    retry_predicate: Option<CustomRetryPredicate>,
 }
 
 impl RetrySettings {
@@ -43,9 +65,19 @@ impl RetrySettings {
             exp_factor,
             max_delay,
             max_tries,
+            retry_predicate: None,
         }
     }
 
+    /// Sets predicate to be consulted for every failed operation: operation is retried if and only if
+    /// predicate returns true, regardless of default error classification. Retries count is still limited
+    /// by `RetrySettings::max_tries`.
+    #[must_use]
+    pub fn with_retry_predicate(mut self, retry_predicate: RetryPredicate) -> Self {
+        self.retry_predicate = Some(CustomRetryPredicate(retry_predicate));
+        self
+    }
+
     pub(crate) fn get_exp_backoff_iterator(&self) -> impl Iterator<Item = Duration> {
         LimitedExponential::from_retry_settings(self)
             .map(retry::delay::jitter)
@@ -60,30 +92,38 @@ impl RetrySettings {
         CF::Output: Send,
         OpErr: std::error::Error + Send,
     {
-        let exp_backoff = self.get_exp_backoff_iterator();
-        let policy = fure::policies::attempts(fure::policies::backoff(exp_backoff), self.max_tries);
+        let backoff = RetryAfterBackoff {
+            backoff: self.get_exp_backoff_iterator(),
+            max_delay: self.max_delay,
+            retry_predicate: self.retry_predicate().cloned(),
+        };
+        let policy = fure::policies::attempts(backoff, self.max_tries);
         fure::retry(operation, policy).await
     }
 
     /// Retry the given operation synchronously until it succeeds, or until retry count run out.
-    ///
-    /// # Panics
-    ///
-    /// Will panic on `retry::Error::Internal` emitting by `operation`.
-    pub fn call<O, R, OR, OpErr>(&self, operation: O) -> Result<R, OpErr>
+    pub fn call<O, R, OR, OpErr>(&self, mut operation: O) -> Result<R, OpErr>
     where
         O: Send + FnMut() -> OR,
         OR: Into<retry::OperationResult<R, OpErr>>,
         OpErr: std::error::Error + Send,
     {
-        let policy = self.get_exp_backoff_iterator();
-        let retry_result = retry::retry(policy, operation);
-        retry_result.map_err(|retry_err| match retry_err {
-            retry::Error::Operation { error, .. } => error,
-            retry::Error::Internal(description) => {
-                panic!("Sync retry internal logic failure marker: {}", description)
+        let mut backoff = self.get_exp_backoff_iterator();
+        loop {
+            let (error, retry_by_default) = match operation().into() {
+                retry::OperationResult::Ok(value) => return Ok(value),
+                retry::OperationResult::Err(error) => (error, false),
+                retry::OperationResult::Retry(error) => (error, true),
+            };
+            match self.retry_predicate() {
+                Some(retry_predicate) if !retry_predicate(&error) => return Err(error),
+                None if !retry_by_default => return Err(error),
+                _ => match backoff.next() {
+                    Some(backoff_delay) => std::thread::sleep(retry_delay(&error, backoff_delay, self.max_delay)),
+                    None => return Err(error),
+                },
             }
-        })
+        }
     }
 
     /// Get a reference to the initial delay.
@@ -109,6 +149,12 @@ impl RetrySettings {
     pub const fn max_tries(&self) -> usize {
         self.max_tries
     }
+
+    /// Get a reference to the predicate which decides if failed operation should be retried, if it was set.
+    #[must_use]
+    pub fn retry_predicate(&self) -> Option<&RetryPredicate> {
+        self.retry_predicate.as_ref().map(|predicate| &predicate.0)
+    }
 }
 
 impl Default for RetrySettings {
@@ -119,6 +165,226 @@ impl Default for RetrySettings {
             exp_factor: RETRY_EXP_FACTOR,
             max_delay: Duration::from_millis(RETRY_MAX_DELAY_MILLIS),
             max_tries: 0,
+            retry_predicate: None,
         }
     }
 }
+
+/// Wrapper for `RetryPredicate`, which compares and hashes predicates by their identity.
+#[derive(Clone)]
+struct CustomRetryPredicate(RetryPredicate);
+
+impl fmt::Debug for CustomRetryPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CustomRetryPredicate")
+    }
+}
+
+impl PartialEq for CustomRetryPredicate {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for CustomRetryPredicate {}
+
+impl Hash for CustomRetryPredicate {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.0).cast::<()>().hash(state);
+    }
+}
+
+/// Exponential backoff policy for `fure`, which prefers server-directed delay when Filen throttles requests.
+#[cfg(feature = "async")]
+struct RetryAfterBackoff<I> {
+    backoff: I,
+    max_delay: Duration,
+    retry_predicate: Option<RetryPredicate>,
+}
+
+#[cfg(feature = "async")]
+impl<I, T, E> fure::Policy<T, E> for RetryAfterBackoff<I>
+where
+    I: Iterator<Item = Duration> + Send + 'static,
+    E: std::error::Error,
+{
+    type ForceRetryFuture = std::future::Pending<()>;
+    type RetryFuture = Pin<Box<dyn Future<Output = Self> + Send>>;
+
+    fn force_retry_after(&self) -> Self::ForceRetryFuture {
+        std::future::pending()
+    }
+
+    fn retry(mut self, result: Option<Result<&T, &E>>) -> Option<Self::RetryFuture> {
+        if let (Some(Err(error)), Some(retry_predicate)) = (result, &self.retry_predicate) {
+            if !retry_predicate(error) {
+                return None;
+            }
+        }
+        let backoff_delay = self.backoff.next()?;
+        let delay = match result {
+            Some(Err(error)) => retry_delay(error, backoff_delay, self.max_delay),
+            _ => backoff_delay,
+        };
+        Some(Box::pin(async move {
+            fure::sleep::sleep(delay).await;
+            self
+        }))
+    }
+}
+
+/// Prefers server-directed delay from `Retry-After` found in the sources of the given error to the backoff one,
+/// but never waits longer than `max_delay`.
+fn retry_delay<E: std::error::Error + ?Sized>(error: &E, backoff_delay: Duration, max_delay: Duration) -> Duration {
+    std::iter::successors(error.source(), |error| error.source())
+        .find_map(|error| error.downcast_ref::<queries::Throttled>())
+        .and_then(|throttled| throttled.retry_after)
+        .map_or(backoff_delay, |retry_after| retry_after.min(max_delay))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::io;
+    #[cfg(feature = "async")]
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    fn is_permission_denied(error: &dyn std::error::Error) -> bool {
+        error.to_string() == io::ErrorKind::PermissionDenied.to_string()
+    }
+
+    fn fast_retries(max_tries: usize) -> RetrySettings {
+        RetrySettings::new(max_tries, Duration::from_millis(1), 1, Duration::from_millis(1))
+    }
+
+    fn throttled_for(retry_after: Duration) -> queries::Error {
+        queries::Error::TooManyRequests {
+            message: "Throttled".to_owned(),
+            source: queries::Throttled {
+                retry_after: Some(retry_after),
+            },
+        }
+    }
+
+    #[test]
+    fn call_should_not_wait_longer_than_max_delay_for_huge_retry_after() {
+        let mut attempts = 0;
+
+        let started = Instant::now();
+        let result = fast_retries(1).call(|| {
+            attempts += 1;
+            if attempts == 1 {
+                Err(throttled_for(Duration::MAX))
+            } else {
+                Ok(attempts)
+            }
+        });
+
+        assert_eq!(result.unwrap(), 2);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn call_async_should_not_wait_longer_than_max_delay_for_huge_retry_after() {
+        let attempts = AtomicUsize::new(0);
+
+        let started = Instant::now();
+        let result = fast_retries(1)
+            .call_async(|| {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                async move {
+                    if attempt == 1 {
+                        Err(throttled_for(Duration::MAX))
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            })
+            .await;
+
+        assert_eq!(result.unwrap(), 2);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn call_should_not_retry_permanent_error_by_default() {
+        let mut attempts = 0;
+
+        let result: Result<(), io::Error> = fast_retries(3).call(|| {
+            attempts += 1;
+            retry::OperationResult::Err(io::Error::from(io::ErrorKind::PermissionDenied))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn call_should_retry_permanent_error_when_predicate_says_so() {
+        let retry_settings = fast_retries(3).with_retry_predicate(Arc::new(is_permission_denied));
+        let mut attempts = 0;
+
+        let result = retry_settings.call(|| {
+            attempts += 1;
+            if attempts < 3 {
+                retry::OperationResult::Err(io::Error::from(io::ErrorKind::PermissionDenied))
+            } else {
+                retry::OperationResult::Ok(attempts)
+            }
+        });
+
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn call_should_not_retry_error_rejected_by_predicate() {
+        let retry_settings = fast_retries(3).with_retry_predicate(Arc::new(is_permission_denied));
+        let mut attempts = 0;
+
+        let result: Result<(), io::Error> = retry_settings.call(|| {
+            attempts += 1;
+            Err(io::Error::from(io::ErrorKind::NotFound))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn call_async_should_consult_predicate() {
+        let retry_settings = fast_retries(3).with_retry_predicate(Arc::new(is_permission_denied));
+        let attempts = AtomicUsize::new(0);
+
+        let result: Result<(), io::Error> = retry_settings
+            .call_async(|| {
+                let kind = if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                    io::ErrorKind::PermissionDenied
+                } else {
+                    io::ErrorKind::NotFound
+                };
+                async move { Err(io::Error::from(kind)) }
+            })
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn retry_settings_with_same_predicate_should_be_equal() {
+        let retry_predicate: RetryPredicate = Arc::new(is_permission_denied);
+
+        assert_eq!(
+            fast_retries(3).with_retry_predicate(retry_predicate.clone()),
+            fast_retries(3).with_retry_predicate(retry_predicate)
+        );
+        assert_ne!(
+            fast_retries(3).with_retry_predicate(Arc::new(is_permission_denied)),
+            fast_retries(3)
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Contains `ClockOffset` used to correct local time when it differs from Filen servers time.
+use std::{
+    fmt, hash,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Marks that no server date was seen yet.
+const UNKNOWN_OFFSET: i64 = i64::MIN;
+
+/// Difference between Filen servers clock and the local clock.
+/// Positive offset means server clock is ahead of the local one.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ClockOffset {
+    millis: i64,
+}
+
+impl ClockOffset {
+    /// Calculates clock offset from the server date and a local time at which that date was received.
+    #[must_use]
+    pub fn from_server_date(server_date: SystemTime, local_now: SystemTime) -> Self {
+        let millis = match server_date.duration_since(local_now) {
+            Ok(ahead) => i64::try_from(ahead.as_millis()).unwrap_or(i64::MAX),
+            Err(behind) => i64::try_from(behind.duration().as_millis()).map_or(i64::MIN + 1, |millis| -millis),
+        };
+        Self { millis }
+    }
+
+    /// Parses HTTP `Date` header value, like "Sun, 06 Nov 1994 08:49:37 GMT", and calculates clock offset from it.
+    /// Returns None if header value cannot be parsed.
+    #[must_use]
+    pub fn from_date_header(date_header: &str, local_now: SystemTime) -> Option<Self> {
+        httpdate::parse_http_date(date_header)
+            .ok()
+            .map(|server_date| Self::from_server_date(server_date, local_now))
+    }
+
+    /// Offset in milliseconds. Positive offset means server clock is ahead of the local one.
+    #[must_use]
+    pub const fn as_millis(self) -> i64 {
+        self.millis
+    }
+
+    /// Converts given local time to the server time.
+    #[must_use]
+    pub fn apply(self, local_time: SystemTime) -> SystemTime {
+        let offset = Duration::from_millis(self.millis.unsigned_abs());
+        if self.millis >= 0 {
+            local_time.checked_add(offset).unwrap_or(local_time)
+        } else {
+            local_time.checked_sub(offset).unwrap_or(UNIX_EPOCH)
+        }
+    }
+}
+
+/// Offset of Filen servers clock from the local clock, as seen in the last API response made with
+/// `FilenSettings` owning it, and shared by their clones.
+/// It is an implementation detail, so it is ignored by comparisons and hashing.
+#[derive(Clone)]
+pub(crate) struct ServerClock(Arc<AtomicI64>);
+
+impl ServerClock {
+    /// Returns clock offset calculated from the last remembered `Date` header, or None if there was none.
+    pub(crate) fn offset(&self) -> Option<ClockOffset> {
+        match self.0.load(Ordering::Relaxed) {
+            UNKNOWN_OFFSET => None,
+            millis => Some(ClockOffset { millis }),
+        }
+    }
+
+    /// Returns current time as seen by Filen servers, or the local time if server clock offset is unknown.
+    pub(crate) fn now(&self) -> SystemTime {
+        let now = SystemTime::now();
+        self.offset().map_or(now, |offset| offset.apply(now))
+    }
+
+    /// Remembers clock offset from the given HTTP `Date` header value of a Filen response.
+    pub(crate) fn record_date_header(&self, date_header: Option<&str>) {
+        if let Some(offset) = date_header.and_then(|date| ClockOffset::from_date_header(date, SystemTime::now())) {
+            self.0.store(offset.millis, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Default for ServerClock {
+    fn default() -> Self {
+        Self(Arc::new(AtomicI64::new(UNKNOWN_OFFSET)))
+    }
+}
+
+impl fmt::Debug for ServerClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ServerClock")
+    }
+}
+
+impl PartialEq for ServerClock {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for ServerClock {}
+
+impl hash::Hash for ServerClock {
+    fn hash<H: hash::Hasher>(&self, _state: &mut H) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn clock_offset_should_convert_skewed_local_time_to_server_time() {
+        let server_date = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        let skewed_local_now = server_date - Duration::from_secs(3600);
+
+        let offset = ClockOffset::from_date_header("Sun, 06 Nov 1994 08:49:37 GMT", skewed_local_now).unwrap();
+
+        assert_eq!(offset.as_millis(), 3_600_000);
+        assert_eq!(offset.apply(skewed_local_now), server_date);
+        assert_eq!(
+            offset.apply(skewed_local_now + Duration::from_secs(5)),
+            server_date + Duration::from_secs(5)
+        );
+    }
+
+    #[test]
+    fn clock_offset_should_be_negative_for_local_clock_ahead_of_server() {
+        let server_date = UNIX_EPOCH + Duration::from_secs(784_111_777);
+        let skewed_local_now = server_date + Duration::from_millis(1500);
+
+        let offset = ClockOffset::from_server_date(server_date, skewed_local_now);
+
+        assert_eq!(offset.as_millis(), -1500);
+        assert_eq!(offset.apply(skewed_local_now), server_date);
+    }
+
+    #[test]
+    fn server_clock_should_apply_recorded_offset_and_be_shared_by_clones() {
+        let server_clock = ServerClock::default();
+        let cloned_clock = server_clock.clone();
+        assert_eq!(server_clock.offset(), None);
+
+        let server_date = SystemTime::now() - Duration::from_secs(3600);
+        server_clock.record_date_header(Some(&httpdate::fmt_http_date(server_date)));
+
+        let offset_millis = cloned_clock.offset().unwrap().as_millis();
+        assert!((-3_602_000..=-3_599_000).contains(&offset_millis));
+        let server_now = cloned_clock.now();
+        assert!(server_now < SystemTime::now() - Duration::from_secs(3598));
+        assert_eq!(ServerClock::default().offset(), None);
+    }
+
+    #[test]
+    fn clock_offset_should_not_parse_invalid_date_header() {
+        assert_eq!(ClockOffset::from_date_header("yesterday", SystemTime::now()), None);
+    }
+}
@@ -12,9 +12,18 @@ use serde_json::json;
 use snafu::{ResultExt, Snafu};
 use std::convert::TryFrom;
 use std::fmt;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
 use std::path::Path;
+use std::sync::Arc;
+use std::thread;
 use std::time::Duration;
 use std::{env, fs};
+use ureq_rustls::{
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer},
+    ServerConfig, ServerConnection, StreamOwned,
+};
 use url::Url;
 
 type Result<T, E = Error> = std::result::Result<T, E>;
@@ -45,6 +54,7 @@ pub fn init_server() -> (MockServer, FilenSettings) {
         request_timeout: Duration::from_secs(10),
         upload_chunk_timeout: Duration::from_secs(10),
         download_chunk_timeout: Duration::from_secs(10),
+        ..FilenSettings::default()
     };
     (server, filen_settings)
 }
@@ -145,3 +155,70 @@ where
     assert_eq!(response.unwrap(), expected_response);
     server
 }
+
+/// Starts HTTPS server with self-signed certificate for `localhost` and `127.0.0.1`, which responds
+/// to every request with `{"status":true}` JSON. Returns URL of the started server.
+#[allow(clippy::unwrap_used)]
+pub fn start_tls_server() -> Url {
+    let certificate = CertificateDer::from(read_project_file("tests/resources/tls/localhost_cert.der"));
+    let private_key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(read_project_file(
+        "tests/resources/tls/localhost_key.der",
+    )));
+    let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(vec![certificate], private_key)
+        .unwrap();
+    let config = Arc::new(config);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let server_url = Url::parse(&format!("https://127.0.0.1:{}/", listener.local_addr().unwrap().port())).unwrap();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let config = config.clone();
+            thread::spawn(move || respond_over_tls(config, stream));
+        }
+    });
+    server_url
+}
+
+/// Reads single HTTP request from the given TCP stream and responds to it with JSON.
+/// Failed TLS handshakes, like ones rejected by client certificate pinning, are silently ignored.
+fn respond_over_tls(config: Arc<ServerConfig>, stream: TcpStream) {
+    let connection = match ServerConnection::new(config) {
+        Ok(connection) => connection,
+        Err(_) => return,
+    };
+    let mut tls_stream = StreamOwned::new(connection, stream);
+    let mut request = Vec::new();
+    let mut buffer = [0_u8; 4096];
+    let mut request_length = None;
+    while request_length.filter(|length| request.len() >= *length).is_none() {
+        match tls_stream.read(&mut buffer) {
+            Ok(0) | Err(_) => return,
+            Ok(read) => request.extend_from_slice(&buffer[..read]),
+        }
+        request_length = request_length.or_else(|| http_request_length(&request));
+    }
+    let body = r#"{"status":true}"#;
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    let _ = tls_stream.write_all(response.as_bytes());
+    tls_stream.conn.send_close_notify();
+    let _ = tls_stream.flush();
+}
+
+/// Returns length of the whole HTTP request, if its headers were received already.
+fn http_request_length(request: &[u8]) -> Option<usize> {
+    let headers_length = request.windows(4).position(|window| window == b"\r\n\r\n")? + 4;
+    let headers = String::from_utf8_lossy(&request[..headers_length]).to_lowercase();
+    let content_length = headers
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length:"))
+        .and_then(|length| length.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+    Some(headers_length + content_length)
+}
@@ -0,0 +1,272 @@
+//! Contains `CertificatePin` used to accept only specific TLS certificates from Filen servers.
+//!
+//! When `FilenSettings::pinned_certificates` is not empty, server certificates are not validated against
+//! system or bundled certificate authorities. Instead, SHA-256 hash of the server leaf certificate must match
+//! one of the pins, so even self-signed certificates can be trusted this way. TLS handshake signatures
+//! are still verified as usual.
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+use sha2::{Digest, Sha256};
+use snafu::{Backtrace, Snafu};
+use std::{fmt, str::FromStr};
+
+const PIN_LENGTH: usize = 32;
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display(
+        "Certificate pin '{}' is not a hex-encoded SHA-256 hash, expected {} bytes",
+        pin,
+        PIN_LENGTH
+    ))]
+    CannotParseCertificatePin { pin: String, backtrace: Backtrace },
+}
+
+/// SHA-256 hash of DER-encoded server leaf certificate.
+///
+/// It is displayed and parsed as lowercase hex. Parsing also accepts uppercase hex delimited by colons,
+/// as printed by `openssl x509 -noout -fingerprint -sha256`.
+#[derive(Clone, Copy, Debug, DeserializeFromStr, Eq, Hash, Ord, PartialEq, PartialOrd, SerializeDisplay)]
+pub struct CertificatePin([u8; PIN_LENGTH]);
+
+impl CertificatePin {
+    /// Creates pin from already computed SHA-256 hash of DER-encoded certificate.
+    #[must_use]
+    pub const fn new(sha256_hash: [u8; PIN_LENGTH]) -> Self {
+        Self(sha256_hash)
+    }
+
+    /// Creates pin by hashing the given DER-encoded certificate.
+    #[must_use]
+    pub fn from_certificate_der(certificate_der: &[u8]) -> Self {
+        Self(Sha256::digest(certificate_der).into())
+    }
+
+    /// Returns pinned SHA-256 hash.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; PIN_LENGTH] {
+        &self.0
+    }
+
+    /// Checks if the given DER-encoded certificate is the pinned one.
+    #[must_use]
+    pub fn matches_certificate_der(&self, certificate_der: &[u8]) -> bool {
+        *self == Self::from_certificate_der(certificate_der)
+    }
+}
+
+impl fmt::Display for CertificatePin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+impl FromStr for CertificatePin {
+    type Err = Error;
+
+    fn from_str(pin: &str) -> Result<Self, Self::Err> {
+        let hex_digits = pin.chars().filter(|c| *c != ':').collect::<String>();
+        let bytes = (hex_digits.len() == PIN_LENGTH * 2 && hex_digits.is_ascii())
+            .then(|| {
+                (0..hex_digits.len())
+                    .step_by(2)
+                    .map(|index| u8::from_str_radix(&hex_digits[index..index + 2], 16).ok())
+                    .collect::<Option<Vec<u8>>>()
+            })
+            .flatten()
+            .and_then(|bytes| <[u8; PIN_LENGTH]>::try_from(bytes).ok());
+        bytes
+            .map(Self)
+            .ok_or_else(|| CannotParseCertificatePinSnafu { pin }.build())
+    }
+}
+
+/// Checks if leaf certificate matches any of the given pins.
+#[cfg(any(not(feature = "async"), feature = "rustls"))]
+fn is_pinned(pins: &[CertificatePin], certificate_der: &[u8]) -> bool {
+    let certificate_pin = CertificatePin::from_certificate_der(certificate_der);
+    pins.contains(&certificate_pin)
+}
+
+/// TLS configuration for ureq which only accepts servers with pinned leaf certificates.
+#[cfg(not(feature = "async"))]
+pub(crate) fn ureq_tls_config(pins: &[CertificatePin]) -> std::sync::Arc<ureq_rustls::ClientConfig> {
+    use std::sync::Arc;
+    use ureq_rustls::{crypto::ring, ClientConfig};
+
+    let provider = Arc::new(ring::default_provider());
+    let verifier = ureq_verifier::PinnedCertificateVerifier {
+        pins: pins.to_vec(),
+        provider: provider.clone(),
+    };
+    #[allow(clippy::expect_used)]
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .expect("ring provider should support default TLS versions")
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    Arc::new(config)
+}
+
+#[cfg(not(feature = "async"))]
+mod ureq_verifier {
+    use super::{is_pinned, CertificatePin};
+    use std::sync::Arc;
+    use ureq_rustls::{
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider},
+        pki_types::{CertificateDer, ServerName, UnixTime},
+        CertificateError, DigitallySignedStruct, Error, SignatureScheme,
+    };
+
+    #[derive(Debug)]
+    pub(super) struct PinnedCertificateVerifier {
+        pub(super) pins: Vec<CertificatePin>,
+        pub(super) provider: Arc<CryptoProvider>,
+    }
+
+    impl ServerCertVerifier for PinnedCertificateVerifier {
+        fn verify_server_cert(
+            &self,
+            end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _server_name: &ServerName<'_>,
+            _ocsp_response: &[u8],
+            _now: UnixTime,
+        ) -> Result<ServerCertVerified, Error> {
+            if is_pinned(&self.pins, end_entity) {
+                Ok(ServerCertVerified::assertion())
+            } else {
+                Err(Error::InvalidCertificate(
+                    CertificateError::ApplicationVerificationFailure,
+                ))
+            }
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, Error> {
+            verify_tls12_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            message: &[u8],
+            cert: &CertificateDer<'_>,
+            dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, Error> {
+            verify_tls13_signature(message, cert, dss, &self.provider.signature_verification_algorithms)
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            self.provider.signature_verification_algorithms.supported_schemes()
+        }
+    }
+}
+
+/// TLS configuration for reqwest which only accepts servers with pinned leaf certificates.
+/// ALPN protocols are set according to the given HTTP version, since reqwest leaves preconfigured TLS as is.
+#[cfg(all(feature = "async", feature = "rustls"))]
+pub(crate) fn reqwest_tls_config(
+    pins: &[CertificatePin],
+    http_version: crate::HttpVersion,
+) -> reqwest_rustls::ClientConfig {
+    use crate::HttpVersion;
+    use std::sync::Arc;
+
+    let verifier = reqwest_verifier::PinnedCertificateVerifier { pins: pins.to_vec() };
+    let mut config = reqwest_rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    config.alpn_protocols = match http_version {
+        HttpVersion::Auto => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+        HttpVersion::Http1Only => vec![b"http/1.1".to_vec()],
+        HttpVersion::Http2PriorKnowledge => vec![b"h2".to_vec()],
+    };
+    config
+}
+
+#[cfg(all(feature = "async", feature = "rustls"))]
+mod reqwest_verifier {
+    use super::{is_pinned, CertificatePin};
+    use reqwest_rustls::{
+        client::{ServerCertVerified, ServerCertVerifier},
+        Certificate, CertificateError, Error, ServerName,
+    };
+    use std::time::SystemTime;
+
+    pub(super) struct PinnedCertificateVerifier {
+        pub(super) pins: Vec<CertificatePin>,
+    }
+
+    impl ServerCertVerifier for PinnedCertificateVerifier {
+        fn verify_server_cert(
+            &self,
+            end_entity: &Certificate,
+            _intermediates: &[Certificate],
+            _server_name: &ServerName,
+            _scts: &mut dyn Iterator<Item = &[u8]>,
+            _ocsp_response: &[u8],
+            _now: SystemTime,
+        ) -> Result<ServerCertVerified, Error> {
+            if is_pinned(&self.pins, &end_entity.0) {
+                Ok(ServerCertVerified::assertion())
+            } else {
+                Err(Error::InvalidCertificate(
+                    CertificateError::ApplicationVerificationFailure,
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const LOCALHOST_CERT_PIN: &str = "491b7f5bd4243ee767f10102bf58629168dae671d32f650621a7f640aa5cbc84";
+
+    #[test]
+    fn certificate_pin_should_be_sha256_of_certificate_der() {
+        let certificate_der = std::fs::read("tests/resources/tls/localhost_cert.der").unwrap();
+
+        let pin = CertificatePin::from_certificate_der(&certificate_der);
+
+        assert_eq!(pin.to_string(), LOCALHOST_CERT_PIN);
+        assert!(pin.matches_certificate_der(&certificate_der));
+        assert!(!pin.matches_certificate_der(b"not a certificate"));
+    }
+
+    #[test]
+    fn certificate_pin_should_be_parsed_from_openssl_fingerprint() {
+        let fingerprint = "49:1B:7F:5B:D4:24:3E:E7:67:F1:01:02:BF:58:62:91:\
+            68:DA:E6:71:D3:2F:65:06:21:A7:F6:40:AA:5C:BC:84";
+
+        let pin = fingerprint.parse::<CertificatePin>().unwrap();
+
+        assert_eq!(pin, LOCALHOST_CERT_PIN.parse::<CertificatePin>().unwrap());
+    }
+
+    #[test]
+    fn certificate_pin_should_not_be_parsed_from_bad_hex() {
+        assert!("491b7f".parse::<CertificatePin>().is_err());
+        assert!(LOCALHOST_CERT_PIN.replace('4', "z").parse::<CertificatePin>().is_err());
+        assert!(format!("{}ab", LOCALHOST_CERT_PIN).parse::<CertificatePin>().is_err());
+        assert!(LOCALHOST_CERT_PIN.replace("49", "é").parse::<CertificatePin>().is_err());
+    }
+
+    #[test]
+    fn certificate_pin_should_be_serialized_as_hex_string() {
+        let pin = LOCALHOST_CERT_PIN.parse::<CertificatePin>().unwrap();
+
+        let json = serde_json::to_string(&pin).unwrap();
+
+        assert_eq!(json, format!("\"{}\"", LOCALHOST_CERT_PIN));
+        assert_eq!(serde_json::from_str::<CertificatePin>(&json).unwrap(), pin);
+    }
+}
@@ -4,18 +4,63 @@
 
 use crate::v1::FileChunkLocation;
 use rand::distributions::Alphanumeric;
-use rand::{thread_rng, Rng};
+use rand::{thread_rng, Rng, RngCore};
 use secstr::SecUtf8;
 use serde_json::{json, Value};
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
 use uuid::Uuid;
 
+/// Size of chunks used to read local files for hashing.
+const HASH_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Hash algorithms which can be used to hash local files.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum FileHashAlgorithm {
+    Md5,
+    Sha256,
+    /// Filen uses SHA-512 of the file contents to detect files which were already uploaded.
+    Sha512,
+}
+
+/// Padding of base64 strings produced by `base64_encode`.
+///
+/// Filen web and desktop clients encode metadata, keys and encrypted chunks with standard padded base64,
+/// so every use site in this crate passes `Padded`; `Unpadded` is for callers talking to services which strip it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Base64Padding {
+    /// Standard alphabet, output padded with '=' to a multiple of 4 chars.
+    Padded,
+    /// Standard alphabet, without trailing '='.
+    Unpadded,
+}
+
+/// Encodes given bytes as base64 with the standard alphabet and the specified padding.
+pub fn base64_encode<T: AsRef<[u8]>>(data: T, padding: Base64Padding) -> String {
+    let config = match padding {
+        Base64Padding::Padded => base64::STANDARD,
+        Base64Padding::Unpadded => base64::STANDARD_NO_PAD,
+    };
+    base64::encode_config(data, config)
+}
+
+/// Decodes base64 string with the standard alphabet. Both padded and unpadded strings are accepted,
+/// so data encoded with any `Base64Padding` can be decoded.
+pub fn base64_decode<T: AsRef<[u8]>>(encoded: T) -> Result<Vec<u8>, base64::DecodeError> {
+    base64::decode_config(encoded, base64::STANDARD)
+}
+
 /// Generate random alphanumeric string of the specified length.
 pub fn random_alphanumeric_string(size: usize) -> String {
-    thread_rng()
-        .sample_iter(&Alphanumeric)
-        .take(size)
-        .map(char::from)
-        .collect()
+    random_alphanumeric_string_with_rng(size, &mut thread_rng())
+}
+
+/// Generate alphanumeric string of the specified length using the given random number generator.
+pub fn random_alphanumeric_string_with_rng(size: usize, rng: &mut impl RngCore) -> String {
+    rng.sample_iter(&Alphanumeric).take(size).map(char::from).collect()
 }
 
 /// Converts the specified bytes into corresponding hex-encoded string.
@@ -23,6 +68,52 @@ pub fn bytes_to_hex_string(data: &[u8]) -> String {
     data.iter().map(|byte| format!("{:02x}", byte)).collect()
 }
 
+/// Converts the specified hex-encoded string into corresponding bytes.
+/// Returns None if string has odd length or contains non-hex characters.
+#[allow(clippy::manual_is_multiple_of)]
+pub fn hex_string_to_bytes(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| {
+            hex.get(index..index + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+        })
+        .collect()
+}
+
+/// Re-encodes the specified hex-encoded string as base64 string.
+/// Returns None if given string is not a valid hex string.
+pub fn hex_to_base64(hex: &str) -> Option<String> {
+    hex_string_to_bytes(hex).map(|bytes| base64_encode(bytes, Base64Padding::Padded))
+}
+
+/// Re-encodes the specified base64 string as lowercase hex-encoded string.
+/// Returns None if given string is not a valid base64 string.
+pub fn base64_to_hex(base64_string: &str) -> Option<String> {
+    base64_decode(base64_string)
+        .ok()
+        .map(|bytes| bytes_to_hex_string(&bytes))
+}
+
+/// Returns true if given UUID is a random V4 UUID, the only kind Filen accepts for item identifiers.
+#[must_use]
+pub fn is_filen_uuid(uuid: &Uuid) -> bool {
+    uuid.get_version() == Some(uuid::Version::Random)
+}
+
+/// Parses UUID in a form Filen expects: hyphenated, lowercased UUID V4.
+/// Returns None for any other UUID form or version, so such values can be rejected before they reach Filen
+/// and cause an opaque server error.
+#[must_use]
+pub fn parse_filen_uuid(value: &str) -> Option<Uuid> {
+    Uuid::try_parse(value)
+        .ok()
+        .filter(|uuid| is_filen_uuid(uuid) && uuid.hyphenated().encode_lower(&mut Uuid::encode_buffer()) == value)
+}
+
 /// Treats given bytes as unicode scalar values and builds a string out of them.
 #[allow(clippy::as_conversions)]
 pub fn bytes_to_binary_string(bytes: &[u8]) -> String {
@@ -33,6 +124,69 @@ pub fn bytes_to_binary_string(bytes: &[u8]) -> String {
     buffer
 }
 
+/// Reads file at the given path in chunks of the specified size and passes each chunk to the given function,
+/// so the whole file is never loaded into memory. Every chunk except the last one will have exactly
+/// `chunk_size` bytes, even if the underlying reads return less. If file grows or shrinks while being read,
+/// reading still stops at the actual end of file.
+///
+/// This is what `hash_local_file` uses to hash local files. Uploads do not go through this function:
+/// `encrypt_and_upload_from_reader` already streams file contents chunk by chunk from any reader,
+/// so to upload a local file, open it into a reader and pass it there.
+///
+/// Returns total amount of bytes read.
+pub fn read_file_chunked<P: AsRef<Path>, F: FnMut(&[u8])>(path: P, chunk_size: usize, mut f: F) -> io::Result<u64> {
+    if chunk_size == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "chunk size should be > 0"));
+    }
+
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0_u8; chunk_size];
+    let mut total_read: u64 = 0;
+    loop {
+        let mut filled = 0;
+        while filled < chunk_size {
+            match file.read(&mut buffer[filled..]) {
+                Ok(0) => break,
+                Ok(read) => filled += read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+
+        if filled == 0 {
+            return Ok(total_read);
+        }
+
+        f(&buffer[..filled]);
+        total_read += filled as u64;
+        if filled < chunk_size {
+            return Ok(total_read);
+        }
+    }
+}
+
+/// Hashes file at the given path chunk by chunk, so the whole file is never loaded into memory.
+///
+/// Returns lowercase hex-encoded hash, which can be compared with file hashes known to Filen
+/// to avoid uploading files which already exist.
+pub fn hash_local_file<P: AsRef<Path>>(path: P, algorithm: FileHashAlgorithm) -> io::Result<String> {
+    match algorithm {
+        FileHashAlgorithm::Md5 => {
+            let mut hasher = <md5::Md5 as md5::Digest>::new();
+            read_file_chunked(path, HASH_CHUNK_SIZE, |chunk| md5::Digest::update(&mut hasher, chunk))?;
+            Ok(bytes_to_hex_string(&md5::Digest::finalize(hasher)))
+        }
+        FileHashAlgorithm::Sha256 => hash_local_file_with::<sha2::Sha256, P>(path),
+        FileHashAlgorithm::Sha512 => hash_local_file_with::<sha2::Sha512, P>(path),
+    }
+}
+
+fn hash_local_file_with<D: sha2::Digest, P: AsRef<Path>>(path: P) -> io::Result<String> {
+    let mut hasher = D::new();
+    read_file_chunked(path, HASH_CHUNK_SIZE, |chunk| hasher.update(chunk))?;
+    Ok(bytes_to_hex_string(&hasher.finalize()))
+}
+
 /// TODO: Remove when `Result::flatten` comes into stable compiler.
 pub fn flatten_result<V, E, F>(result: Result<Result<V, F>, E>) -> Result<V, E>
 where
@@ -112,6 +266,30 @@ pub(crate) use display_from_json_with_lifetime;
 mod tests {
     use super::*;
 
+    #[test]
+    fn parse_filen_uuid_should_accept_lowercase_hyphenated_v4_uuid() {
+        let uuid = parse_filen_uuid("b5ec90d4-f3b5-4b5e-8f8d-62a6d7b0a8e9");
+
+        assert_eq!(uuid, Uuid::parse_str("b5ec90d4-f3b5-4b5e-8f8d-62a6d7b0a8e9").ok());
+    }
+
+    #[test]
+    fn parse_filen_uuid_should_reject_non_filen_uuids() {
+        assert_eq!(parse_filen_uuid("B5EC90D4-F3B5-4B5E-8F8D-62A6D7B0A8E9"), None);
+        assert_eq!(parse_filen_uuid("b5ec90d4f3b54b5e8f8d62a6d7b0a8e9"), None);
+        assert_eq!(parse_filen_uuid("{b5ec90d4-f3b5-4b5e-8f8d-62a6d7b0a8e9}"), None);
+        // Version 1 UUID:
+        assert_eq!(parse_filen_uuid("c232ab00-9414-11ec-b3c8-9f6bdeced846"), None);
+        assert_eq!(parse_filen_uuid("00000000-0000-0000-0000-000000000000"), None);
+        assert_eq!(parse_filen_uuid("not a uuid"), None);
+    }
+
+    #[test]
+    fn is_filen_uuid_should_be_true_only_for_v4_uuid() {
+        assert!(is_filen_uuid(&Uuid::new_v4()));
+        assert!(!is_filen_uuid(&Uuid::nil()));
+    }
+
     #[test]
     fn byte_vec_to_hex_should_return_hex_codes_for_non_empty_bytes() {
         let expected_hash_hex = "f82a1812080acab7ed5751e7193984565c8b159be00bb6c66eac70ff0c8ad8dd";
@@ -125,6 +303,48 @@ mod tests {
         assert_eq!(expected_hash_hex, hash_hex);
     }
 
+    #[test]
+    fn hex_and_base64_conversions_should_round_trip() {
+        let hex = "f82a1812080acab7ed5751e7193984565c8b159be00bb6c66eac70ff0c8ad8dd";
+        let base64_string = "+CoYEggKyrftV1HnGTmEVlyLFZvgC7bGbqxw/wyK2N0=";
+
+        assert_eq!(hex_to_base64(hex).unwrap(), base64_string);
+        assert_eq!(base64_to_hex(base64_string).unwrap(), hex);
+        assert_eq!(base64_to_hex(&hex_to_base64(hex).unwrap()).unwrap(), hex);
+        assert_eq!(hex_to_base64(&hex.to_uppercase()).unwrap(), base64_string);
+        assert_eq!(hex_to_base64("").unwrap(), "");
+    }
+
+    #[test]
+    fn base64_encode_should_round_trip_padded_and_unpadded() {
+        for data in [&b""[..], b"f", b"fo", b"foo", b"foob"] {
+            let padded = base64_encode(data, Base64Padding::Padded);
+            let unpadded = base64_encode(data, Base64Padding::Unpadded);
+
+            assert_eq!(padded.len() % 4, 0);
+            assert_eq!(unpadded, padded.trim_end_matches('='));
+            assert_eq!(base64_decode(&padded).unwrap(), data);
+            assert_eq!(base64_decode(&unpadded).unwrap(), data);
+        }
+        assert_eq!(base64_encode(b"fo", Base64Padding::Padded), "Zm8=");
+        assert_eq!(base64_encode(b"fo", Base64Padding::Unpadded), "Zm8");
+    }
+
+    #[test]
+    fn base64_decode_should_reject_bad_padding_and_alphabet() {
+        assert!(base64_decode("Zm8==").is_err());
+        assert!(base64_decode("Zm8=Zm8=").is_err());
+        assert!(base64_decode("-_8").is_err());
+    }
+
+    #[test]
+    fn hex_and_base64_conversions_should_reject_invalid_input() {
+        assert_eq!(hex_to_base64("abc"), None);
+        assert_eq!(hex_to_base64("zz"), None);
+        assert_eq!(hex_to_base64("abé"), None);
+        assert_eq!(base64_to_hex("not base64!"), None);
+    }
+
     #[test]
     fn filen_file_address_to_api_endpoint_should_join_parts_correctly() {
         let expected = "de-1/filen-1/b5ec90d2-957c-4481-b211-08a68accd1b2/0";
@@ -136,4 +356,46 @@ mod tests {
         );
         assert_eq!(file_url, expected);
     }
+
+    #[test]
+    fn read_file_chunked_should_read_file_larger_than_chunk_size() {
+        let file_path = std::env::temp_dir().join(format!("rust_filen_{}.bin", random_alphanumeric_string(8)));
+        let file_contents: Vec<u8> = (0..2500_u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&file_path, &file_contents).unwrap();
+        let mut chunk_sizes = Vec::new();
+        let mut read_contents = Vec::new();
+
+        let total_read = read_file_chunked(&file_path, 1024, |chunk| {
+            chunk_sizes.push(chunk.len());
+            read_contents.extend_from_slice(chunk);
+        });
+        std::fs::remove_file(&file_path).unwrap();
+
+        assert_eq!(total_read.unwrap(), 2500);
+        assert_eq!(chunk_sizes, vec![1024, 1024, 452]);
+        assert_eq!(read_contents, file_contents);
+    }
+
+    #[test]
+    fn hash_local_file_should_hash_file_larger_than_hash_chunk_size() {
+        let file_path = std::env::temp_dir().join(format!("rust_filen_{}.bin", random_alphanumeric_string(8)));
+        let file_contents: Vec<u8> = (0..3 * 1024 * 1024 + 17_u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&file_path, &file_contents).unwrap();
+
+        let md5_hash = hash_local_file(&file_path, FileHashAlgorithm::Md5);
+        let sha256_hash = hash_local_file(&file_path, FileHashAlgorithm::Sha256);
+        let sha512_hash = hash_local_file(&file_path, FileHashAlgorithm::Sha512);
+        std::fs::remove_file(&file_path).unwrap();
+
+        assert_eq!(md5_hash.unwrap(), "7114d559ffda7029526064baa992a30d");
+        assert_eq!(
+            sha256_hash.unwrap(),
+            "fe2aaf82bfa2ffec207a0c6fa7ce7d4af268d67e2672fdaec675f3f9b65d0854"
+        );
+        assert_eq!(
+            sha512_hash.unwrap(),
+            "ffc05de5fba9d792394f2047713dde4c7af417fea57de040d9f0dd50780bb5e0\
+             292e76b93b4ea4e77cd3ca7f385bb285fe34830c0c0110e712dfaabfe97d9d87"
+        );
+    }
 }
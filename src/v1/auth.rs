@@ -1,13 +1,21 @@
 use crate::{
     crypto, queries, utils,
-    v1::{response_payload, HasMasterKeys, HasPrivateKey},
+    v1::{response_payload, FilenMessageCode, FilenResponse, HasMasterKeys, HasPrivateKey},
     FilenSettings,
 };
 use easy_hasher::easy_hasher::sha512;
-use secstr::SecUtf8;
+use secstr::{SecUtf8, SecVec};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
-use snafu::{Backtrace, ResultExt, Snafu};
+use snafu::{ensure, Backtrace, OptionExt, ResultExt, Snafu};
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::Path,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use uuid::Uuid;
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -22,6 +30,12 @@ pub enum Error {
     #[snafu(display("Caller provided invalid argument: {}", message))]
     BadArgument { message: String, backtrace: Backtrace },
 
+    #[snafu(display("Cannot parse Filen config export '{}': {}", path, source))]
+    CannotParseConfigExport { path: String, source: serde_json::Error },
+
+    #[snafu(display("Cannot read Filen config export '{}': {}", path, source))]
+    CannotReadConfigExport { path: String, source: std::io::Error },
+
     #[snafu(display("Failed to decrypt master keys metadata: {}", source))]
     DecryptMasterKeysFailed { source: crypto::Error },
 
@@ -31,6 +45,9 @@ pub enum Error {
     #[snafu(display("{} query failed (version {}): {}", LOGIN_PATH, auth_version, source))]
     LoginQueryFailed { auth_version: u32, source: queries::Error },
 
+    #[snafu(display("Filen requires two-factor authentication code to login: {}", message))]
+    TwoFactorRequired { message: String, backtrace: Backtrace },
+
     #[snafu(display("Unsupported Filen auth version {}", version))]
     UnsupportedAuthVersion { version: i64, backtrace: Backtrace },
 }
@@ -64,18 +81,60 @@ impl FilenPasswordWithMasterKey {
         Self::from_derived_key(&pbkdf2_hash)
     }
 
+    /// Derives only login hash from user's password, skipping master key derivation where possible.
+    /// Useful to verify credentials when master key is not needed. Expects plain text password and
+    /// auth version with salt from /auth/info API call; salt is only used by auth version 2.
+    ///
+    /// Fails with `Error::BadArgument` if auth version is 2, but salt is not given.
+    pub fn sent_password_only(password: &SecUtf8, salt: Option<&SecUtf8>, auth_version: u32) -> Result<SecUtf8> {
+        match auth_version {
+            1 => Ok(SecUtf8::from(crypto::hash_password(password.unsecure()))),
+            2 => {
+                let salt = salt.context(BadArgumentSnafu {
+                    message: "auth version 2 requires salt",
+                })?;
+                let pbkdf2_hash = crypto::derive_key_from_password_512(
+                    password.unsecure().as_bytes(),
+                    salt.unsecure().as_bytes(),
+                    200_000,
+                );
+                let (_, password_part) = pbkdf2_hash.split_at(pbkdf2_hash.len() / 2);
+                Ok(sent_password_from_derived_part(password_part))
+            }
+            _ => UnsupportedAuthVersionSnafu {
+                version: i64::from(auth_version),
+            }
+            .fail(),
+        }
+    }
+
+    /// Returns the key to decrypt metadata with, like master keys metadata from the login response.
+    ///
+    /// This is `m_key` as is: a lowercase hex string of the first half of the derived key for auth version 2,
+    /// or `crypto::hash_fn` of the password for auth version 1. Metadata is encrypted with the UTF-8 bytes of
+    /// this hex string, so it should be passed to `crypto::decrypt_metadata_str` and similar functions directly,
+    /// without decoding hex or hashing it again.
+    #[must_use]
+    pub fn metadata_key(&self) -> SecUtf8 {
+        self.m_key.clone()
+    }
+
     pub(crate) fn from_derived_key(derived_key: &[u8; 64]) -> Self {
         let (m_key, password_part) = derived_key.split_at(derived_key.len() / 2);
         let m_key_hex = utils::bytes_to_hex_string(m_key);
-        let sent_password = sha512(&utils::bytes_to_hex_string(password_part)).to_vec();
-        let sent_password_hex = utils::bytes_to_hex_string(&sent_password);
         Self {
             m_key: SecUtf8::from(m_key_hex),
-            sent_password: SecUtf8::from(sent_password_hex),
+            sent_password: sent_password_from_derived_part(password_part),
         }
     }
 }
 
+/// Hashes the second half of a derived key into a login hash.
+fn sent_password_from_derived_part(password_part: &[u8]) -> SecUtf8 {
+    let sent_password = sha512(&utils::bytes_to_hex_string(password_part)).to_vec();
+    SecUtf8::from(utils::bytes_to_hex_string(&sent_password))
+}
+
 /// Used for requests to `AUTH_INFO_PATH` endpoint.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub struct AuthInfoRequestPayload<'auth_info> {
@@ -101,8 +160,12 @@ pub struct AuthInfoResponseData {
     #[serde(rename = "authVersion")]
     pub auth_version: u32,
 
-    /// 256 alphanumeric characters or empty.
-    pub salt: Option<String>,
+    /// 256 alphanumeric characters or empty. Salt is used for password derivation, so it is kept as a secret.
+    pub salt: Option<SecUtf8>,
+
+    /// Response fields unknown to this crate, preserved as is.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 utils::display_from_json!(AuthInfoResponseData);
 
@@ -112,10 +175,11 @@ impl AuthInfoResponseData {
         match self.auth_version {
             1 => Ok(FilenPasswordWithMasterKey::from_user_password(user_password)),
             2 => {
-                let filen_salt = SecUtf8::from(self.salt.clone().unwrap_or_default());
+                let empty_salt = SecUtf8::from("");
+                let filen_salt = self.salt.as_ref().unwrap_or(&empty_salt);
                 Ok(FilenPasswordWithMasterKey::from_user_password_and_auth_info_salt(
                     user_password,
-                    &filen_salt,
+                    filen_salt,
                 ))
             }
             _ => UnsupportedAuthVersionSnafu {
@@ -138,7 +202,8 @@ pub struct LoginRequestPayload<'login> {
     pub email: &'login SecUtf8,
 
     /// Filen-processed password. Note that this is not a registered user password, but its hash.
-    /// Use one of `FilenPasswordWithMasterKey`::from... methods to calculate it.
+    /// Use one of `FilenPasswordWithMasterKey`::from... methods to calculate it, or `LoginRequestPayload::build`
+    /// to get the whole payload right.
     pub password: &'login SecUtf8,
 
     /// Registered user 2FA key, if present. XXXXXX means no 2FA key.
@@ -151,6 +216,32 @@ pub struct LoginRequestPayload<'login> {
 }
 utils::display_from_json_with_lifetime!('login, LoginRequestPayload);
 
+impl<'login> LoginRequestPayload<'login> {
+    /// Puts Filen password derived with `AuthInfoResponseData::filen_password_with_master_key` and auth version
+    /// from the same auth/info call together into a login payload, so they cannot mismatch.
+    ///
+    /// Fails with `Error::UnsupportedAuthVersion` if Filen password cannot be derived for the given auth version.
+    pub fn build(
+        email: &'login SecUtf8,
+        password_with_master_key: &'login FilenPasswordWithMasterKey,
+        two_factor_key: &'login SecUtf8,
+        auth_info: &AuthInfoResponseData,
+    ) -> Result<Self> {
+        ensure!(
+            matches!(auth_info.auth_version, 1 | 2),
+            UnsupportedAuthVersionSnafu {
+                version: auth_info.auth_version
+            }
+        );
+        Ok(Self {
+            email,
+            password: &password_with_master_key.sent_password,
+            two_factor_key,
+            auth_version: auth_info.auth_version,
+        })
+    }
+}
+
 /// Response data for [LOGIN_PATH] endpoint.
 #[skip_serializing_none]
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -174,9 +265,55 @@ pub struct LoginResponseData {
     /// Empty when no keys were set (currently before the first login).
     #[serde(rename = "privateKey")]
     pub private_key_metadata: Option<String>,
+
+    /// Response fields unknown to this crate, preserved as is.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 utils::display_from_json!(LoginResponseData);
 
+impl LoginResponseData {
+    /// Decrypts master keys and private key from this login response, using master key derived from user password.
+    /// Fails with `Error::BadArgument` if master keys are not set yet, see `is_first_login`.
+    ///
+    /// Accounts created before key pair setup have master keys, but no private key; for them session is
+    /// decrypted with `DecryptedSession::private_key` set to None.
+    pub fn decrypt_all(&self, password_with_master_key: &FilenPasswordWithMasterKey) -> Result<DecryptedSession> {
+        let master_keys_metadata = self.master_keys_metadata.as_deref().unwrap_or_default();
+        let private_key_metadata = self.private_key_metadata.as_deref().unwrap_or_default();
+        if master_keys_metadata.is_empty() {
+            BadArgumentSnafu {
+                message: "login response has no master keys set",
+            }
+            .fail()
+        } else {
+            let master_keys =
+                crypto::decrypt_master_keys_metadata(master_keys_metadata, &password_with_master_key.m_key)
+                    .context(DecryptMasterKeysFailedSnafu {})?;
+            let private_key = if private_key_metadata.is_empty() {
+                None
+            } else {
+                let private_key = crypto::decrypt_private_key_metadata(private_key_metadata, &master_keys)
+                    .context(DecryptPrivateKeyFailedSnafu {})?;
+                Some(private_key)
+            };
+            Ok(DecryptedSession {
+                api_key: self.api_key.clone(),
+                master_keys,
+                private_key,
+            })
+        }
+    }
+
+    /// Returns true if user has no master keys and private key set yet, which currently happens before
+    /// the first login. In this case client is expected to generate and upload those keys.
+    #[must_use]
+    pub fn is_first_login(&self) -> bool {
+        let is_blank = |metadata: &Option<String>| metadata.as_deref().is_none_or(str::is_empty);
+        is_blank(&self.master_keys_metadata) && is_blank(&self.private_key_metadata)
+    }
+}
+
 impl HasMasterKeys for LoginResponseData {
     fn master_keys_metadata_ref(&self) -> Option<&str> {
         self.master_keys_metadata.as_deref()
@@ -189,6 +326,74 @@ impl HasPrivateKey for LoginResponseData {
     }
 }
 
+/// Everything needed to make further API calls after a successful login, decrypted from `LoginResponseData`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DecryptedSession {
+    /// Filen API key.
+    pub api_key: SecUtf8,
+
+    /// User's master keys, with the last one being the current.
+    pub master_keys: Vec<SecUtf8>,
+
+    /// User's RSA private key bytes; None for accounts which never set up key pair.
+    pub private_key: Option<SecVec<u8>>,
+}
+
+/// User credentials from a ".filen" config exported by Filen CLI.
+///
+/// Contains everything needed to make further API calls without logging in again, so users migrating from the CLI
+/// can reuse their existing session. Fields of the config unknown to this crate are ignored.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct FilenCredentials {
+    /// User's email.
+    pub email: String,
+
+    /// Filen API key.
+    #[serde(rename = "apiKey")]
+    pub api_key: SecUtf8,
+
+    /// User's master keys, with the last one being the current.
+    #[serde(rename = "masterKeys")]
+    pub master_keys: Vec<SecUtf8>,
+
+    /// Auth version used by the user's account, if it was exported.
+    #[serde(rename = "authVersion", default, skip_serializing_if = "Option::is_none")]
+    pub auth_version: Option<u32>,
+
+    /// ID of the user's Filen "Default" folder, if it was exported.
+    #[serde(rename = "baseFolderUUID", default, skip_serializing_if = "Option::is_none")]
+    pub base_folder_uuid: Option<Uuid>,
+}
+utils::display_from_json!(FilenCredentials);
+
+impl FilenCredentials {
+    /// Reads credentials from a ".filen" JSON config exported by Filen CLI.
+    ///
+    /// Fails with `Error::BadArgument` if config has no API key or no master keys.
+    pub fn from_config_export<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path_string = path.as_ref().display().to_string();
+        let contents = fs::read(path.as_ref()).context(CannotReadConfigExportSnafu {
+            path: path_string.clone(),
+        })?;
+        let credentials: Self =
+            serde_json::from_slice(&contents).context(CannotParseConfigExportSnafu { path: path_string })?;
+        if credentials.api_key.unsecure().is_empty() || credentials.master_keys.is_empty() {
+            BadArgumentSnafu {
+                message: "config export has no API key or master keys",
+            }
+            .fail()
+        } else {
+            Ok(credentials)
+        }
+    }
+
+    /// Returns the current master key, which should be used to encrypt new metadata.
+    #[must_use]
+    pub fn last_master_key(&self) -> Option<&SecUtf8> {
+        self.master_keys.last()
+    }
+}
+
 response_payload!(
     /// Response for [LOGIN_PATH] endpoint.
     LoginResponsePayload<LoginResponseData>
@@ -213,14 +418,128 @@ pub async fn auth_info_request_async(
         .context(AuthInfoQueryFailedSnafu {})
 }
 
+/// Short-lived in-memory cache of successful `AUTH_INFO_PATH` responses, keyed by user email.
+///
+/// Useful when logging in many users or the same user repeatedly: auth info rarely changes, so
+/// repeated `auth_info_request` calls within the cache TTL return the cached response instead of querying Filen.
+/// Cache can be shared between threads; failed responses are never cached.
+pub struct AuthInfoCache {
+    ttl: Duration,
+    clock: Box<dyn Fn() -> Instant + Send + Sync>,
+    entries: Mutex<HashMap<String, (Instant, AuthInfoResponsePayload)>>,
+}
+
+impl fmt::Debug for AuthInfoCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthInfoCache")
+            .field("ttl", &self.ttl)
+            .field("entries", &self.entries)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AuthInfoCache {
+    /// Creates empty cache which keeps responses for the given time-to-live.
+    #[must_use]
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_clock(ttl, Instant::now)
+    }
+
+    /// Creates empty cache which tells time with the given clock, so tests can expire entries without waiting.
+    fn with_clock<C: Fn() -> Instant + Send + Sync + 'static>(ttl: Duration, clock: C) -> Self {
+        Self {
+            ttl,
+            clock: Box::new(clock),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns cached auth info response for the payload email, or calls `AUTH_INFO_PATH` endpoint
+    /// and caches its response if there is no fresh cached one.
+    pub fn auth_info_request(
+        &self,
+        payload: &AuthInfoRequestPayload,
+        filen_settings: &FilenSettings,
+    ) -> Result<AuthInfoResponsePayload> {
+        let key = Self::cache_key(payload.email);
+        match self.get(&key) {
+            Some(cached) => Ok(cached),
+            None => auth_info_request(payload, filen_settings).map(|response| self.put(key, response)),
+        }
+    }
+
+    /// Returns cached auth info response for the payload email, or calls `AUTH_INFO_PATH` endpoint asynchronously
+    /// and caches its response if there is no fresh cached one.
+    #[cfg(feature = "async")]
+    pub async fn auth_info_request_async(
+        &self,
+        payload: &AuthInfoRequestPayload<'_>,
+        filen_settings: &FilenSettings,
+    ) -> Result<AuthInfoResponsePayload> {
+        let key = Self::cache_key(payload.email);
+        match self.get(&key) {
+            Some(cached) => Ok(cached),
+            None => auth_info_request_async(payload, filen_settings)
+                .await
+                .map(|response| self.put(key, response)),
+        }
+    }
+
+    /// Removes all cached responses.
+    pub fn clear(&self) {
+        self.lock_entries().clear();
+    }
+
+    /// Emails are not kept in memory as is, only their hashes.
+    fn cache_key(email: &SecUtf8) -> String {
+        sha512(&email.unsecure().to_owned()).to_hex_string()
+    }
+
+    fn get(&self, key: &str) -> Option<AuthInfoResponsePayload> {
+        let now = (self.clock)();
+        let mut entries = self.lock_entries();
+        match entries.get(key) {
+            Some((cached_at, response)) if now.saturating_duration_since(*cached_at) < self.ttl => {
+                Some(response.clone())
+            }
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn put(&self, key: String, response: AuthInfoResponsePayload) -> AuthInfoResponsePayload {
+        if response.status && response.data.is_some() {
+            let cached_at = (self.clock)();
+            self.lock_entries().insert(key, (cached_at, response.clone()));
+        }
+        response
+    }
+
+    fn lock_entries(&self) -> std::sync::MutexGuard<'_, HashMap<String, (Instant, AuthInfoResponsePayload)>> {
+        // Cache entries stay consistent even if other thread panicked while holding the lock.
+        self.entries.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
 /// Calls `LOGIN_PATH` endpoint. Used to get API key, master keys and private key.
+///
+/// Returns `Error::TwoFactorRequired` if account has two-factor authentication enabled,
+/// but no code was given, so caller can prompt user for the code and try again.
 pub fn login_request(payload: &LoginRequestPayload, filen_settings: &FilenSettings) -> Result<LoginResponsePayload> {
-    queries::query_filen_api(LOGIN_PATH, payload, filen_settings).context(LoginQueryFailedSnafu {
-        auth_version: payload.auth_version,
-    })
+    queries::query_filen_api(LOGIN_PATH, payload, filen_settings)
+        .context(LoginQueryFailedSnafu {
+            auth_version: payload.auth_version,
+        })
+        .and_then(fail_if_two_factor_required)
 }
 
 /// Calls `LOGIN_PATH` endpoint asynchronously. Used to get API key, master keys and private key.
+///
+/// Returns `Error::TwoFactorRequired` if account has two-factor authentication enabled,
+/// but no code was given, so caller can prompt user for the code and try again.
 #[cfg(feature = "async")]
 pub async fn login_request_async(
     payload: &LoginRequestPayload<'_>,
@@ -231,6 +550,18 @@ pub async fn login_request_async(
         .context(LoginQueryFailedSnafu {
             auth_version: payload.auth_version,
         })
+        .and_then(fail_if_two_factor_required)
+}
+
+fn fail_if_two_factor_required(response: LoginResponsePayload) -> Result<LoginResponsePayload> {
+    if !response.status && response.message_code() == Some(FilenMessageCode::Enter2fa) {
+        TwoFactorRequiredSnafu {
+            message: response.message.unwrap_or_default(),
+        }
+        .fail()
+    } else {
+        Ok(response)
+    }
 }
 
 #[cfg(test)]
@@ -240,6 +571,43 @@ mod tests {
     use crate::test_utils::validate_contract_async;
     use crate::test_utils::{self, validate_contract};
     use pretty_assertions::assert_eq;
+    #[cfg(feature = "async")]
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    };
+
+    #[test]
+    fn filen_credentials_should_be_read_from_config_export() {
+        let path = test_utils::project_path_for("tests/resources/config_export.filen");
+
+        let credentials = FilenCredentials::from_config_export(&path).unwrap();
+
+        assert_eq!(credentials.email, "test@test.com");
+        assert_eq!(
+            credentials.api_key.unsecure(),
+            "bYZmrwdVEbHJSqeA1RfnPtKiBcXzUpRdKGRkjw9m1o1eqSGP1s6DM11CDnklpFq6"
+        );
+        assert_eq!(credentials.master_keys.len(), 2);
+        assert_eq!(
+            credentials.last_master_key().unwrap().unsecure(),
+            "b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae"
+        );
+        assert_eq!(credentials.auth_version, Some(2));
+        assert_eq!(
+            credentials.base_folder_uuid,
+            Some(Uuid::parse_str("cf2af9a0-6f4e-485d-862c-0459f4662cf1").unwrap())
+        );
+    }
+
+    #[test]
+    fn filen_credentials_should_not_be_read_from_non_json_file() {
+        let path = test_utils::project_path_for("tests/resources/filen_public_key.txt");
+
+        let result = FilenCredentials::from_config_export(&path);
+
+        assert!(matches!(result, Err(Error::CannotParseConfigExport { .. })));
+    }
 
     #[test]
     fn derived_key_to_sent_password_should_return_valid_mkey_and_password() {
@@ -258,6 +626,137 @@ mod tests {
         assert_eq!(parts.sent_password.unsecure(), expected_password);
     }
 
+    #[test]
+    fn sent_password_only_should_match_full_derivation() {
+        let password = SecUtf8::from("test");
+        let salt = SecUtf8::from("4JtoNm7sbakhPyAfJlpHwakrGB3CGzUq");
+        let full_v1 = FilenPasswordWithMasterKey::from_user_password(&password);
+        let full_v2 = FilenPasswordWithMasterKey::from_user_password_and_auth_info_salt(&password, &salt);
+
+        let sent_v1 = FilenPasswordWithMasterKey::sent_password_only(&password, None, 1).unwrap();
+        let sent_v2 = FilenPasswordWithMasterKey::sent_password_only(&password, Some(&salt), 2).unwrap();
+
+        assert_eq!(sent_v1, full_v1.sent_password);
+        assert_eq!(sent_v2, full_v2.sent_password);
+        assert!(matches!(
+            FilenPasswordWithMasterKey::sent_password_only(&password, Some(&salt), 3),
+            Err(Error::UnsupportedAuthVersion { version: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn sent_password_only_should_require_salt_for_v2() {
+        let password = SecUtf8::from("test");
+
+        let result = FilenPasswordWithMasterKey::sent_password_only(&password, None, 2);
+
+        assert!(matches!(result, Err(Error::BadArgument { .. })));
+    }
+
+    #[test]
+    fn metadata_key_should_decrypt_master_keys_metadata_after_v2_derivation() {
+        let password = SecUtf8::from("test");
+        let salt = SecUtf8::from("4JtoNm7sbakhPyAfJlpHwakrGB3CGzUq");
+        let derived = FilenPasswordWithMasterKey::from_user_password_and_auth_info_salt(&password, &salt);
+        let old_key = SecUtf8::from("ed8d39b6c2d00ece398199a3e83988f1c4942b24");
+        // Filen stores master keys metadata encrypted with the hex string of the derived master key.
+        let master_keys_metadata =
+            crypto::encrypt_master_keys_metadata(&[old_key.clone(), derived.m_key.clone()], &derived.m_key, 2).unwrap();
+        let response_data = LoginResponseData {
+            api_key: SecUtf8::from(""),
+            master_keys_metadata: Some(master_keys_metadata),
+            private_key_metadata: Some(String::new()),
+            extra: serde_json::Map::new(),
+        };
+
+        let metadata_key = derived.metadata_key();
+        let decrypted_m_keys = response_data.decrypt_master_keys_metadata(&metadata_key).unwrap();
+
+        assert_eq!(metadata_key.unsecure().len(), 64);
+        assert!(metadata_key
+            .unsecure()
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase()));
+        assert_eq!(decrypted_m_keys, vec![old_key, metadata_key]);
+    }
+
+    #[test]
+    fn login_request_payload_build_should_derive_password_for_v1() {
+        let email = SecUtf8::from("test@test.com");
+        let user_password = SecUtf8::from("test");
+        let two_factor_key = SecUtf8::from("XXXXXX");
+        let auth_info: AuthInfoResponsePayload =
+            test_utils::deserialize_from_file("tests/resources/responses/auth_info_v1.json");
+        let auth_info_data = auth_info.data.unwrap();
+
+        let password_with_master_key = auth_info_data.filen_password_with_master_key(&user_password).unwrap();
+        let payload =
+            LoginRequestPayload::build(&email, &password_with_master_key, &two_factor_key, &auth_info_data).unwrap();
+
+        let expected = FilenPasswordWithMasterKey::from_user_password(&user_password);
+        assert_eq!(payload.auth_version, 1);
+        assert_eq!(payload.password, &expected.sent_password);
+        assert_eq!(password_with_master_key, expected);
+    }
+
+    #[test]
+    fn login_request_payload_build_should_derive_password_for_v2() {
+        let email = SecUtf8::from("test@test.com");
+        let user_password = SecUtf8::from("test");
+        let two_factor_key = SecUtf8::from("XXXXXX");
+        let auth_info: AuthInfoResponsePayload =
+            test_utils::deserialize_from_file("tests/resources/responses/auth_info_v2.json");
+        let auth_info_data = auth_info.data.unwrap();
+
+        let password_with_master_key = auth_info_data.filen_password_with_master_key(&user_password).unwrap();
+        let payload =
+            LoginRequestPayload::build(&email, &password_with_master_key, &two_factor_key, &auth_info_data).unwrap();
+
+        let expected =
+            FilenPasswordWithMasterKey::from_user_password_and_auth_info_salt(&user_password, &SecUtf8::from("test"));
+        assert_eq!(payload.auth_version, 2);
+        assert_eq!(payload.password, &expected.sent_password);
+        assert_eq!(password_with_master_key, expected);
+    }
+
+    #[test]
+    fn auth_info_response_data_should_derive_password_from_secret_salt() {
+        let user_password = SecUtf8::from("test");
+        let auth_info_data = AuthInfoResponseData {
+            email: SecUtf8::from("test@test.com"),
+            auth_version: 2,
+            salt: Some(SecUtf8::from("Ak8hTMRzQIqCXPZzmwJSGXkP0v8cEe0M")),
+            extra: serde_json::Map::new(),
+        };
+
+        let password_with_master_key = auth_info_data.filen_password_with_master_key(&user_password).unwrap();
+
+        let expected = FilenPasswordWithMasterKey::from_user_password_and_auth_info_salt(
+            &user_password,
+            &SecUtf8::from("Ak8hTMRzQIqCXPZzmwJSGXkP0v8cEe0M"),
+        );
+        assert_eq!(password_with_master_key, expected);
+        assert!(!format!("{:?}", auth_info_data).contains("Ak8hTMRzQIqCXPZzmwJSGXkP0v8cEe0M"));
+    }
+
+    #[test]
+    fn login_request_payload_build_should_fail_for_unknown_auth_version() {
+        let email = SecUtf8::from("test@test.com");
+        let two_factor_key = SecUtf8::from("XXXXXX");
+        let auth_info_data = AuthInfoResponseData {
+            email: email.clone(),
+            auth_version: 3,
+            salt: None,
+            extra: serde_json::Map::new(),
+        };
+
+        let password_with_master_key = FilenPasswordWithMasterKey::from_user_password(&SecUtf8::from("test"));
+
+        let result = LoginRequestPayload::build(&email, &password_with_master_key, &two_factor_key, &auth_info_data);
+
+        assert!(matches!(result, Err(Error::UnsupportedAuthVersion { version: 3, .. })));
+    }
+
     #[test]
     fn login_response_data_should_decrypt_master_keys() {
         let m_key = SecUtf8::from("ed8d39b6c2d00ece398199a3e83988f1c4942b24");
@@ -267,6 +766,7 @@ mod tests {
             api_key: SecUtf8::from(""),
             master_keys_metadata: Some(master_keys_metadata_encrypted),
             private_key_metadata: Some("".to_owned()),
+            extra: serde_json::Map::new(),
         };
 
         let decrypted_m_keys = response_data.decrypt_master_keys_metadata(&m_key).unwrap();
@@ -285,6 +785,7 @@ mod tests {
             api_key: SecUtf8::from(""),
             master_keys_metadata: Some("".to_owned()),
             private_key_metadata: Some(private_key_metadata_encrypted),
+            extra: serde_json::Map::new(),
         };
 
         let decrypted_private_key = response_data.decrypt_private_key(&[m_key]).unwrap();
@@ -292,6 +793,141 @@ mod tests {
         assert_eq!(decrypted_private_key.unsecure().len(), expected_rsa_key_length);
     }
 
+    #[test]
+    fn login_response_data_should_decrypt_all_keys() {
+        let m_key = SecUtf8::from("ed8d39b6c2d00ece398199a3e83988f1c4942b24");
+        let private_key_file_contents = test_utils::read_project_file("tests/resources/filen_private_key.txt");
+        let response_data = LoginResponseData {
+            api_key: SecUtf8::from("bYZmrwdVEbHJSqeA1RfnPtKiBcXzUpRdKGRkjw9m1o1eqSGP1s6DM11CDnklpFq6"),
+            master_keys_metadata: Some(
+                "U2FsdGVkX1/P4QDMaiaanx8kpL7fY+v/f3dSzC9Ajl58gQg5bffqGUbOIzROwGQn8m5NAZa0tRnVya84aJnf1w==".to_owned(),
+            ),
+            private_key_metadata: Some(String::from_utf8_lossy(&private_key_file_contents).to_string()),
+            extra: serde_json::Map::new(),
+        };
+        let password_with_master_key = FilenPasswordWithMasterKey {
+            m_key: m_key.clone(),
+            sent_password: SecUtf8::from(""),
+        };
+
+        let session = response_data.decrypt_all(&password_with_master_key).unwrap();
+
+        assert_eq!(session.api_key, response_data.api_key);
+        assert_eq!(session.master_keys, vec![m_key]);
+        assert_eq!(session.private_key.unwrap().unsecure().len(), 2374);
+    }
+
+    #[test]
+    fn login_response_data_should_decrypt_master_keys_without_private_key() {
+        let m_key = SecUtf8::from("ed8d39b6c2d00ece398199a3e83988f1c4942b24");
+        let response_data = LoginResponseData {
+            api_key: SecUtf8::from("bYZmrwdVEbHJSqeA1RfnPtKiBcXzUpRdKGRkjw9m1o1eqSGP1s6DM11CDnklpFq6"),
+            master_keys_metadata: Some(
+                "U2FsdGVkX1/P4QDMaiaanx8kpL7fY+v/f3dSzC9Ajl58gQg5bffqGUbOIzROwGQn8m5NAZa0tRnVya84aJnf1w==".to_owned(),
+            ),
+            private_key_metadata: Some(String::new()),
+            extra: serde_json::Map::new(),
+        };
+        let password_with_master_key = FilenPasswordWithMasterKey {
+            m_key: m_key.clone(),
+            sent_password: SecUtf8::from(""),
+        };
+
+        let session = response_data.decrypt_all(&password_with_master_key).unwrap();
+
+        assert!(!response_data.is_first_login());
+        assert_eq!(session.master_keys, vec![m_key]);
+        assert_eq!(session.private_key, None);
+    }
+
+    #[test]
+    fn login_response_data_should_be_first_login_without_keys() {
+        let empty_keys_data = LoginResponseData {
+            api_key: SecUtf8::from(""),
+            master_keys_metadata: Some("".to_owned()),
+            private_key_metadata: None,
+            extra: serde_json::Map::new(),
+        };
+        let populated_keys_data = LoginResponseData {
+            master_keys_metadata: Some(
+                "U2FsdGVkX1/P4QDMaiaanx8kpL7fY+v/f3dSzC9Ajl58gQg5bffqGUbOIzROwGQn8m5NAZa0tRnVya84aJnf1w==".to_owned(),
+            ),
+            ..empty_keys_data.clone()
+        };
+
+        assert!(empty_keys_data.is_first_login());
+        assert!(!populated_keys_data.is_first_login());
+    }
+
+    #[test]
+    fn auth_info_cache_should_not_query_filen_again_within_ttl() {
+        let (server, filen_settings) = test_utils::init_server();
+        let email = SecUtf8::from("test@email.com");
+        let request_payload = AuthInfoRequestPayload {
+            email: &email,
+            two_factor_key: &SecUtf8::from("XXXXXX"),
+        };
+        let expected_response: AuthInfoResponsePayload =
+            test_utils::deserialize_from_file("tests/resources/responses/auth_info_v2.json");
+        let mock = test_utils::setup_json_mock(AUTH_INFO_PATH, &request_payload, &expected_response, &server);
+        let cache = AuthInfoCache::new(Duration::from_secs(60));
+
+        let first_response = cache.auth_info_request(&request_payload, &filen_settings).unwrap();
+        let second_response = cache.auth_info_request(&request_payload, &filen_settings).unwrap();
+        cache.clear();
+        let third_response = cache.auth_info_request(&request_payload, &filen_settings).unwrap();
+
+        mock.assert_hits(2);
+        assert_eq!(first_response, expected_response);
+        assert_eq!(second_response, expected_response);
+        assert_eq!(third_response, expected_response);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn auth_info_cache_async_should_query_filen_again_after_ttl() {
+        let (server, filen_settings) = test_utils::init_server();
+        let email = SecUtf8::from("test@email.com");
+        let request_payload = AuthInfoRequestPayload {
+            email: &email,
+            two_factor_key: &SecUtf8::from("XXXXXX"),
+        };
+        let expected_response: AuthInfoResponsePayload =
+            test_utils::deserialize_from_file("tests/resources/responses/auth_info_v2.json");
+        let mock = test_utils::setup_json_mock(AUTH_INFO_PATH, &request_payload, &expected_response, &server);
+        let started = Instant::now();
+        let elapsed_secs = Arc::new(AtomicU64::new(0));
+        let clock_elapsed_secs = Arc::clone(&elapsed_secs);
+        let cache = AuthInfoCache::with_clock(Duration::from_secs(60), move || {
+            started + Duration::from_secs(clock_elapsed_secs.load(Ordering::SeqCst))
+        });
+
+        let first_response = cache
+            .auth_info_request_async(&request_payload, &filen_settings)
+            .await
+            .unwrap();
+        cache
+            .auth_info_request_async(&request_payload, &filen_settings)
+            .await
+            .unwrap();
+        mock.assert_hits(1);
+        elapsed_secs.store(59, Ordering::SeqCst);
+        cache
+            .auth_info_request_async(&request_payload, &filen_settings)
+            .await
+            .unwrap();
+        mock.assert_hits(1);
+        elapsed_secs.store(60, Ordering::SeqCst);
+        let expired_response = cache
+            .auth_info_request_async(&request_payload, &filen_settings)
+            .await
+            .unwrap();
+
+        mock.assert_hits(2);
+        assert_eq!(first_response, expected_response);
+        assert_eq!(expired_response, expected_response);
+    }
+
     #[test]
     fn auth_info_request_should_be_correctly_typed_for_v1() {
         let request_payload = AuthInfoRequestPayload {
@@ -356,6 +992,45 @@ mod tests {
         .await;
     }
 
+    #[test]
+    fn login_request_should_fail_with_two_factor_required_when_code_is_missing() {
+        let request_payload = LoginRequestPayload {
+            email: &SecUtf8::from("test@email.com"),
+            password: &SecUtf8::from("test"),
+            two_factor_key: &SecUtf8::from("XXXXXX"),
+            auth_version: 2,
+        };
+        let (server, filen_settings) = test_utils::init_server();
+        let response: LoginResponsePayload =
+            test_utils::deserialize_from_file("tests/resources/responses/login_2fa_required.json");
+        let mock = test_utils::setup_json_mock(LOGIN_PATH, &request_payload, &response, &server);
+
+        let result = login_request(&request_payload, &filen_settings);
+
+        mock.assert_hits(1);
+        assert!(matches!(result, Err(Error::TwoFactorRequired { .. })));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn login_request_async_should_fail_with_two_factor_required_when_code_is_missing() {
+        let request_payload = LoginRequestPayload {
+            email: &SecUtf8::from("test@email.com"),
+            password: &SecUtf8::from("test"),
+            two_factor_key: &SecUtf8::from("XXXXXX"),
+            auth_version: 2,
+        };
+        let (server, filen_settings) = test_utils::init_server();
+        let response: LoginResponsePayload =
+            test_utils::deserialize_from_file("tests/resources/responses/login_2fa_required.json");
+        let mock = test_utils::setup_json_mock(LOGIN_PATH, &request_payload, &response, &server);
+
+        let result = login_request_async(&request_payload, &filen_settings).await;
+
+        mock.assert_hits(1);
+        assert!(matches!(result, Err(Error::TwoFactorRequired { .. })));
+    }
+
     #[test]
     fn login_request_should_be_correctly_typed_for_v1() {
         let request_payload = LoginRequestPayload {
@@ -0,0 +1,851 @@
+//! Contains helpers to perform the same operation on many files and folders at once.
+//!
+//! Filen does not have batch endpoints for moving or trashing items, so these helpers issue single-item calls
+//! and gather all the results, instead of stopping on the first failure.
+//!
+//! `OperationBatch` generalizes them for any mix of asynchronous operations.
+//!
+//! `wipe_account` is the most destructive of them, so it requires the account email to be typed in as confirmation.
+#[cfg(feature = "async")]
+use crate::v1::{
+    dir_content_request_async, dir_move_request_async, dir_trash_request_async, file_move_request_async,
+    file_trash_request_async, user_dirs_request_async, user_usage_request_async,
+};
+use crate::{
+    v1::{
+        dir_content_request, dir_move_request, dir_trash_request, dirs, file_move_request, file_trash_request, files,
+        usage, user_dirs_request, user_usage_request, ContentKind, DirContentRequestPayload, DirContentResponsePayload,
+        DirMoveRequestPayload, FileMoveRequestPayload, ItemKind, LocationTrashRequestPayload, PlainResponsePayload,
+        UserDirData, UserDirsResponsePayload, UserUsageResponsePayload,
+    },
+    FilenSettings,
+};
+#[cfg(feature = "async")]
+use futures::StreamExt;
+use secstr::SecUtf8;
+use snafu::{Backtrace, ResultExt, Snafu};
+#[cfg(feature = "async")]
+use std::{future::Future, pin::Pin};
+use uuid::Uuid;
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("{}", source))]
+    DirContentQueryFailed { source: dirs::Error },
+
+    #[snafu(display("Filen refused to list content of folder {}: {}", uuid, message))]
+    DirContentRefused {
+        uuid: Uuid,
+        message: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("{}", source))]
+    DirMoveQueryFailed { source: dirs::Error },
+
+    #[snafu(display("{}", source))]
+    DirTrashQueryFailed { source: dirs::Error },
+
+    #[snafu(display("{}", source))]
+    FileMoveQueryFailed { source: files::Error },
+
+    #[snafu(display("{}", source))]
+    FileTrashQueryFailed { source: files::Error },
+
+    #[snafu(display("Filen refused operation on {} {}: {}", kind, uuid, message))]
+    ItemOperationRefused {
+        uuid: Uuid,
+        kind: ItemKind,
+        message: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("{}", source))]
+    UserDirsQueryFailed { source: dirs::Error },
+
+    #[snafu(display("Filen refused to list user folders: {}", message))]
+    UserDirsRefused { message: String, backtrace: Backtrace },
+
+    #[snafu(display("{}", source))]
+    UserUsageQueryFailed { source: usage::Error },
+
+    #[snafu(display("Filen refused to provide account info: {}", message))]
+    UserUsageRefused { message: String, backtrace: Backtrace },
+
+    #[snafu(display("Account wipe was not confirmed: confirmation does not match account email"))]
+    WipeNotConfirmed { backtrace: Backtrace },
+}
+
+/// Outcome of a bulk operation. Items are listed in the same order they were given.
+#[derive(Debug, Default)]
+pub struct BulkResult {
+    /// Items for which operation succeeded.
+    pub succeeded: Vec<(Uuid, ItemKind)>,
+
+    /// Items for which operation failed, with a corresponding failure.
+    pub failed: Vec<((Uuid, ItemKind), Error)>,
+}
+
+impl BulkResult {
+    /// Returns true if operation succeeded for every given item.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    fn from_results<I: IntoIterator<Item = ((Uuid, ItemKind), Result<()>)>>(results: I) -> Self {
+        let mut bulk_result = Self::default();
+        for (item, result) in results {
+            match result {
+                Ok(()) => bulk_result.succeeded.push(item),
+                Err(err) => bulk_result.failed.push((item, err)),
+            }
+        }
+        bulk_result
+    }
+}
+
+/// Boxed asynchronous operation driven by `OperationBatch`.
+#[cfg(feature = "async")]
+pub type BatchOperation<T> = Pin<Box<dyn Future<Output = T> + Send + 'static>>;
+
+/// Queue of arbitrary asynchronous operations, like moves, renames and trashes mixed together,
+/// which are run on Tokio runtime with at most `max_concurrency` of them in flight at the same time.
+///
+/// Operations are spawned as Tokio tasks, so they should own everything they use, like cloned API key and settings.
+#[cfg(feature = "async")]
+pub struct OperationBatch<T> {
+    max_concurrency: usize,
+    operations: Vec<BatchOperation<T>>,
+}
+
+#[cfg(feature = "async")]
+impl<T: Send + 'static> OperationBatch<T> {
+    /// Creates empty batch which will run at most `max_concurrency` operations at the same time.
+    #[must_use]
+    pub fn new(max_concurrency: usize) -> Self {
+        Self {
+            max_concurrency: max_concurrency.max(1),
+            operations: Vec::new(),
+        }
+    }
+
+    /// Enqueues given operation. Operation is not started until `run` is called.
+    pub fn push<F: Future<Output = T> + Send + 'static>(&mut self, operation: F) -> &mut Self {
+        self.operations.push(Box::pin(operation));
+        self
+    }
+
+    /// Amount of enqueued operations.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    /// Returns true if no operations were enqueued.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Runs all enqueued operations and returns their results in the same order operations were pushed.
+    /// New operations are started only when running ones finish, so at most `max_concurrency` are in flight.
+    ///
+    /// # Panics
+    ///
+    /// Resumes panic if any of the operations panicked, or panics if called outside of Tokio runtime.
+    pub async fn run(self) -> Vec<T> {
+        let mut results: Vec<Option<T>> = std::iter::repeat_with(|| None).take(self.operations.len()).collect();
+        let mut pending = self.operations.into_iter().enumerate();
+        let mut running = tokio::task::JoinSet::new();
+        loop {
+            while running.len() < self.max_concurrency {
+                match pending.next() {
+                    Some((index, operation)) => {
+                        running.spawn(async move { (index, operation.await) });
+                    }
+                    None => break,
+                }
+            }
+            match running.join_next().await {
+                Some(Ok((index, result))) => results[index] = Some(result),
+                Some(Err(join_error)) => std::panic::resume_unwind(join_error.into_panic()),
+                None => break,
+            }
+        }
+        results.into_iter().flatten().collect()
+    }
+}
+
+/// Helper which moves given files and folders into the specified parent folder, one by one.
+///
+/// Failure to move one item does not stop the others from being moved, check `BulkResult::failed` for those.
+pub fn move_items(
+    api_key: &SecUtf8,
+    items: &[(Uuid, ItemKind)],
+    target_folder_uuid: Uuid,
+    filen_settings: &FilenSettings,
+) -> BulkResult {
+    BulkResult::from_results(
+        items
+            .iter()
+            .map(|&item| (item, move_item(api_key, item, target_folder_uuid, filen_settings))),
+    )
+}
+
+/// Helper which moves given files and folders into the specified parent folder; asynchronous.
+/// At most `max_concurrency` items will be moved at the same time.
+///
+/// Failure to move one item does not stop the others from being moved, check `BulkResult::failed` for those.
+#[cfg(feature = "async")]
+pub async fn move_items_async(
+    api_key: &SecUtf8,
+    items: &[(Uuid, ItemKind)],
+    target_folder_uuid: Uuid,
+    max_concurrency: usize,
+    filen_settings: &FilenSettings,
+) -> BulkResult {
+    let results = futures::stream::iter(items.iter().copied())
+        .map(|item| async move {
+            let result = move_item_async(api_key, item, target_folder_uuid, filen_settings).await;
+            (item, result)
+        })
+        .buffered(max_concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await;
+    BulkResult::from_results(results)
+}
+
+/// Helper which moves given files and folders to trash, one by one.
+///
+/// Failure to trash one item does not stop the others from being trashed, check `BulkResult::failed` for those.
+pub fn trash_items(api_key: &SecUtf8, items: &[(Uuid, ItemKind)], filen_settings: &FilenSettings) -> BulkResult {
+    BulkResult::from_results(
+        items
+            .iter()
+            .map(|&item| (item, trash_item(api_key, item, filen_settings))),
+    )
+}
+
+/// Helper which moves given files and folders to trash; asynchronous.
+/// At most `max_concurrency` items will be trashed at the same time.
+///
+/// Failure to trash one item does not stop the others from being trashed, check `BulkResult::failed` for those.
+#[cfg(feature = "async")]
+pub async fn trash_items_async(
+    api_key: &SecUtf8,
+    items: &[(Uuid, ItemKind)],
+    max_concurrency: usize,
+    filen_settings: &FilenSettings,
+) -> BulkResult {
+    let results = futures::stream::iter(items.iter().copied())
+        .map(|item| async move { (item, trash_item_async(api_key, item, filen_settings).await) })
+        .buffered(max_concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await;
+    BulkResult::from_results(results)
+}
+
+/// Helper which moves all direct children of the given folder to trash, one by one.
+///
+/// Does not recurse into subfolders: trashing a folder moves its whole subtree to trash along with it,
+/// so only the items listed directly under `folder_uuid` are trashed. The folder itself is left in place.
+/// Failure to trash one item does not stop the others from being trashed, check `BulkResult::failed` for those.
+pub fn trash_folder_contents(
+    api_key: &SecUtf8,
+    folder_uuid: Uuid,
+    filen_settings: &FilenSettings,
+) -> Result<BulkResult> {
+    let payload = DirContentRequestPayload::new(api_key, ContentKind::Folder(folder_uuid));
+    let response = dir_content_request(&payload, filen_settings).context(DirContentQueryFailedSnafu {})?;
+    let children = folder_children(folder_uuid, response)?;
+    Ok(trash_items(api_key, &children, filen_settings))
+}
+
+/// Helper which moves all direct children of the given folder to trash; asynchronous.
+/// At most `max_concurrency` items will be trashed at the same time.
+///
+/// Does not recurse into subfolders: trashing a folder moves its whole subtree to trash along with it,
+/// so only the items listed directly under `folder_uuid` are trashed. The folder itself is left in place.
+/// Failure to trash one item does not stop the others from being trashed, check `BulkResult::failed` for those.
+#[cfg(feature = "async")]
+pub async fn trash_folder_contents_async(
+    api_key: &SecUtf8,
+    folder_uuid: Uuid,
+    max_concurrency: usize,
+    filen_settings: &FilenSettings,
+) -> Result<BulkResult> {
+    let payload = DirContentRequestPayload::new(api_key, ContentKind::Folder(folder_uuid));
+    let response = dir_content_request_async(&payload, filen_settings)
+        .await
+        .context(DirContentQueryFailedSnafu {})?;
+    let children = folder_children(folder_uuid, response)?;
+    Ok(trash_items_async(api_key, &children, max_concurrency, filen_settings).await)
+}
+
+/// Helper which moves everything the user has to trash, one by one.
+///
+/// Fetches account email first and aborts with `Error::WipeNotConfirmed` before touching anything,
+/// unless `confirmation` is exactly the same string as the account email.
+/// Trashes all direct children of the Filen "Default" folder and all other base folders, also known as 'cloud drives',
+/// which takes their whole subtrees along with them. Trash itself is not emptied.
+/// Failure to trash one item does not stop the others from being trashed, check `BulkResult::failed` for those.
+pub fn wipe_account(api_key: &SecUtf8, confirmation: &str, filen_settings: &FilenSettings) -> Result<BulkResult> {
+    let usage_response = user_usage_request(api_key, filen_settings).context(UserUsageQueryFailedSnafu {})?;
+    ensure_wipe_confirmed(confirmation, usage_response)?;
+
+    let dirs_response = user_dirs_request(api_key, filen_settings).context(UserDirsQueryFailedSnafu {})?;
+    let mut items = Vec::new();
+    for folder in base_folders(dirs_response)? {
+        if folder.default {
+            let payload = DirContentRequestPayload::new(api_key, ContentKind::Folder(folder.uuid));
+            let response = dir_content_request(&payload, filen_settings).context(DirContentQueryFailedSnafu {})?;
+            items.extend(folder_children(folder.uuid, response)?);
+        } else {
+            items.push((folder.uuid, ItemKind::Folder));
+        }
+    }
+    Ok(trash_items(api_key, &items, filen_settings))
+}
+
+/// Helper which moves everything the user has to trash; asynchronous.
+/// At most `max_concurrency` items will be trashed at the same time.
+///
+/// Fetches account email first and aborts with `Error::WipeNotConfirmed` before touching anything,
+/// unless `confirmation` is exactly the same string as the account email.
+/// Trashes all direct children of the Filen "Default" folder and all other base folders, also known as 'cloud drives',
+/// which takes their whole subtrees along with them. Trash itself is not emptied.
+/// Failure to trash one item does not stop the others from being trashed, check `BulkResult::failed` for those.
+#[cfg(feature = "async")]
+pub async fn wipe_account_async(
+    api_key: &SecUtf8,
+    confirmation: &str,
+    max_concurrency: usize,
+    filen_settings: &FilenSettings,
+) -> Result<BulkResult> {
+    let usage_response = user_usage_request_async(api_key, filen_settings)
+        .await
+        .context(UserUsageQueryFailedSnafu {})?;
+    ensure_wipe_confirmed(confirmation, usage_response)?;
+
+    let dirs_response = user_dirs_request_async(api_key, filen_settings)
+        .await
+        .context(UserDirsQueryFailedSnafu {})?;
+    let mut items = Vec::new();
+    for folder in base_folders(dirs_response)? {
+        if folder.default {
+            let payload = DirContentRequestPayload::new(api_key, ContentKind::Folder(folder.uuid));
+            let response = dir_content_request_async(&payload, filen_settings)
+                .await
+                .context(DirContentQueryFailedSnafu {})?;
+            items.extend(folder_children(folder.uuid, response)?);
+        } else {
+            items.push((folder.uuid, ItemKind::Folder));
+        }
+    }
+    Ok(trash_items_async(api_key, &items, max_concurrency, filen_settings).await)
+}
+
+fn ensure_wipe_confirmed(confirmation: &str, response: UserUsageResponsePayload) -> Result<()> {
+    match response.data {
+        Some(data) if response.status => {
+            if data.email == confirmation {
+                Ok(())
+            } else {
+                WipeNotConfirmedSnafu {}.fail()
+            }
+        }
+        _ => UserUsageRefusedSnafu {
+            message: response.message.unwrap_or_default(),
+        }
+        .fail(),
+    }
+}
+
+/// Returns user's base folders, also known as 'cloud drives'. Filen "Default" folder is among them,
+/// but cannot be trashed itself.
+fn base_folders(response: UserDirsResponsePayload) -> Result<Vec<UserDirData>> {
+    match response.data {
+        Some(data) if response.status => Ok(data.into_iter().filter(|dir| dir.parent.is_none()).collect()),
+        _ => UserDirsRefusedSnafu {
+            message: response.message.unwrap_or_default(),
+        }
+        .fail(),
+    }
+}
+
+fn folder_children(folder_uuid: Uuid, response: DirContentResponsePayload) -> Result<Vec<(Uuid, ItemKind)>> {
+    match response.data {
+        Some(data) if response.status => Ok(data
+            .uploads
+            .iter()
+            .map(|file| (file.uuid, ItemKind::File))
+            .chain(data.folders.iter().map(|folder| (folder.uuid, ItemKind::Folder)))
+            .collect()),
+        _ => DirContentRefusedSnafu {
+            uuid: folder_uuid,
+            message: response.message.unwrap_or_default(),
+        }
+        .fail(),
+    }
+}
+
+fn move_item(
+    api_key: &SecUtf8,
+    (uuid, kind): (Uuid, ItemKind),
+    target_folder_uuid: Uuid,
+    filen_settings: &FilenSettings,
+) -> Result<()> {
+    let response = match kind {
+        ItemKind::File => {
+            let payload = FileMoveRequestPayload {
+                api_key,
+                folder_uuid: target_folder_uuid,
+                file_uuid: uuid,
+            };
+            file_move_request(&payload, filen_settings).context(FileMoveQueryFailedSnafu {})?
+        }
+        ItemKind::Folder => {
+            let payload = DirMoveRequestPayload {
+                api_key,
+                folder_uuid: target_folder_uuid,
+                uuid,
+            };
+            dir_move_request(&payload, filen_settings).context(DirMoveQueryFailedSnafu {})?
+        }
+    };
+    check_response_status(uuid, kind, response)
+}
+
+#[cfg(feature = "async")]
+async fn move_item_async(
+    api_key: &SecUtf8,
+    (uuid, kind): (Uuid, ItemKind),
+    target_folder_uuid: Uuid,
+    filen_settings: &FilenSettings,
+) -> Result<()> {
+    let response = match kind {
+        ItemKind::File => {
+            let payload = FileMoveRequestPayload {
+                api_key,
+                folder_uuid: target_folder_uuid,
+                file_uuid: uuid,
+            };
+            file_move_request_async(&payload, filen_settings)
+                .await
+                .context(FileMoveQueryFailedSnafu {})?
+        }
+        ItemKind::Folder => {
+            let payload = DirMoveRequestPayload {
+                api_key,
+                folder_uuid: target_folder_uuid,
+                uuid,
+            };
+            dir_move_request_async(&payload, filen_settings)
+                .await
+                .context(DirMoveQueryFailedSnafu {})?
+        }
+    };
+    check_response_status(uuid, kind, response)
+}
+
+fn trash_item(api_key: &SecUtf8, (uuid, kind): (Uuid, ItemKind), filen_settings: &FilenSettings) -> Result<()> {
+    let payload = LocationTrashRequestPayload { api_key, uuid };
+    let response = match kind {
+        ItemKind::File => file_trash_request(&payload, filen_settings).context(FileTrashQueryFailedSnafu {})?,
+        ItemKind::Folder => dir_trash_request(&payload, filen_settings).context(DirTrashQueryFailedSnafu {})?,
+    };
+    check_response_status(uuid, kind, response)
+}
+
+#[cfg(feature = "async")]
+async fn trash_item_async(
+    api_key: &SecUtf8,
+    (uuid, kind): (Uuid, ItemKind),
+    filen_settings: &FilenSettings,
+) -> Result<()> {
+    let payload = LocationTrashRequestPayload { api_key, uuid };
+    let response = match kind {
+        ItemKind::File => file_trash_request_async(&payload, filen_settings)
+            .await
+            .context(FileTrashQueryFailedSnafu {})?,
+        ItemKind::Folder => dir_trash_request_async(&payload, filen_settings)
+            .await
+            .context(DirTrashQueryFailedSnafu {})?,
+    };
+    check_response_status(uuid, kind, response)
+}
+
+fn check_response_status(uuid: Uuid, kind: ItemKind, response: PlainResponsePayload) -> Result<()> {
+    if response.status {
+        Ok(())
+    } else {
+        ItemOperationRefusedSnafu {
+            uuid,
+            kind,
+            message: response.message.unwrap_or_default(),
+        }
+        .fail()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{deserialize_from_file, init_server, setup_json_mock},
+        utils,
+    };
+    use httpmock::{Method::POST, Mock, MockServer};
+    use once_cell::sync::Lazy;
+    use pretty_assertions::assert_eq;
+    use secstr::SecUtf8;
+
+    static API_KEY: Lazy<SecUtf8> =
+        Lazy::new(|| SecUtf8::from("bYZmrwdVEbHJSqeA1RfnPtKiBcXzUpRdKGRkjw9m1o1eqSGP1s6DM11CDnklpFq6"));
+    const FILE_UUID: &str = "b5ec90d4-f3b5-4b5e-8f8d-62a6d7b0a8e9";
+    const FOLDER_UUID: &str = "80f678c0-56ce-4b81-b4ef-f2a9c0c737c4";
+    const PARENT_FOLDER_UUID: &str = "cf2af9a0-6f4e-485d-862c-0459f4662cf1";
+
+    #[test]
+    fn trash_items_should_collect_partial_failures() {
+        let (server, filen_settings) = init_server();
+        let file_uuid = Uuid::parse_str(FILE_UUID).unwrap();
+        let folder_uuid = Uuid::parse_str(FOLDER_UUID).unwrap();
+        let file_response: PlainResponsePayload = deserialize_from_file("tests/resources/responses/file_trash.json");
+        let folder_response: PlainResponsePayload =
+            deserialize_from_file("tests/resources/responses/dir_already_in_trash.json");
+        let file_mock = setup_json_mock(
+            "/v1/file/trash",
+            &LocationTrashRequestPayload {
+                api_key: &API_KEY,
+                uuid: file_uuid,
+            },
+            &file_response,
+            &server,
+        );
+        let folder_mock = setup_json_mock(
+            "/v1/dir/trash",
+            &LocationTrashRequestPayload {
+                api_key: &API_KEY,
+                uuid: folder_uuid,
+            },
+            &folder_response,
+            &server,
+        );
+
+        let result = trash_items(
+            &API_KEY,
+            &[(file_uuid, ItemKind::File), (folder_uuid, ItemKind::Folder)],
+            &filen_settings,
+        );
+
+        file_mock.assert_hits(1);
+        folder_mock.assert_hits(1);
+        assert!(!result.is_success());
+        assert_eq!(result.succeeded, vec![(file_uuid, ItemKind::File)]);
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!(result.failed[0].0, (folder_uuid, ItemKind::Folder));
+        assert!(matches!(result.failed[0].1, Error::ItemOperationRefused { .. }));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn trash_items_async_should_collect_partial_failures() {
+        let (server, filen_settings) = init_server();
+        let file_uuid = Uuid::parse_str(FILE_UUID).unwrap();
+        let folder_uuid = Uuid::parse_str(FOLDER_UUID).unwrap();
+        let file_response: PlainResponsePayload = deserialize_from_file("tests/resources/responses/file_trash.json");
+        let folder_response: PlainResponsePayload =
+            deserialize_from_file("tests/resources/responses/dir_already_in_trash.json");
+        let file_mock = setup_json_mock(
+            "/v1/file/trash",
+            &LocationTrashRequestPayload {
+                api_key: &API_KEY,
+                uuid: file_uuid,
+            },
+            &file_response,
+            &server,
+        );
+        let folder_mock = setup_json_mock(
+            "/v1/dir/trash",
+            &LocationTrashRequestPayload {
+                api_key: &API_KEY,
+                uuid: folder_uuid,
+            },
+            &folder_response,
+            &server,
+        );
+
+        let result = trash_items_async(
+            &API_KEY,
+            &[(folder_uuid, ItemKind::Folder), (file_uuid, ItemKind::File)],
+            2,
+            &filen_settings,
+        )
+        .await;
+
+        file_mock.assert_hits(1);
+        folder_mock.assert_hits(1);
+        assert_eq!(result.succeeded, vec![(file_uuid, ItemKind::File)]);
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!(result.failed[0].0, (folder_uuid, ItemKind::Folder));
+    }
+
+    #[test]
+    fn trash_folder_contents_should_trash_only_direct_children() {
+        let (server, filen_settings) = init_server();
+        let parent_uuid = Uuid::parse_str(PARENT_FOLDER_UUID).unwrap();
+        let (file_uuid, subfolder_uuid, mocks) = setup_two_level_tree_mocks(parent_uuid, &server);
+
+        let result = trash_folder_contents(&API_KEY, parent_uuid, &filen_settings).unwrap();
+
+        assert_two_level_tree_mocks_hits(&mocks);
+        assert!(result.is_success());
+        assert_eq!(
+            result.succeeded,
+            vec![(file_uuid, ItemKind::File), (subfolder_uuid, ItemKind::Folder)]
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn trash_folder_contents_async_should_trash_only_direct_children() {
+        let (server, filen_settings) = init_server();
+        let parent_uuid = Uuid::parse_str(PARENT_FOLDER_UUID).unwrap();
+        let (file_uuid, subfolder_uuid, mocks) = setup_two_level_tree_mocks(parent_uuid, &server);
+
+        let result = trash_folder_contents_async(&API_KEY, parent_uuid, 2, &filen_settings)
+            .await
+            .unwrap();
+
+        assert_two_level_tree_mocks_hits(&mocks);
+        assert!(result.is_success());
+        assert_eq!(
+            result.succeeded,
+            vec![(file_uuid, ItemKind::File), (subfolder_uuid, ItemKind::Folder)]
+        );
+    }
+
+    #[test]
+    fn wipe_account_should_abort_on_wrong_confirmation_without_any_trash_calls() {
+        let (server, filen_settings) = init_server();
+        let (usage_mock, other_mocks) = setup_wipe_account_guard_mocks(&server);
+
+        let result = wipe_account(&API_KEY, "someone@else.com", &filen_settings);
+
+        usage_mock.assert_hits(1);
+        other_mocks.iter().for_each(|mock| mock.assert_hits(0));
+        assert!(matches!(result, Err(Error::WipeNotConfirmed { .. })));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn wipe_account_async_should_abort_on_wrong_confirmation_without_any_trash_calls() {
+        let (server, filen_settings) = init_server();
+        let (usage_mock, other_mocks) = setup_wipe_account_guard_mocks(&server);
+
+        let result = wipe_account_async(&API_KEY, "TEST@TEST.COM", 2, &filen_settings).await;
+
+        usage_mock.assert_hits(1);
+        other_mocks.iter().for_each(|mock| mock.assert_hits(0));
+        assert!(matches!(result, Err(Error::WipeNotConfirmed { .. })));
+    }
+
+    #[test]
+    fn wipe_account_should_trash_default_folder_contents_when_confirmed() {
+        let (server, filen_settings) = init_server();
+        let default_folder_uuid = Uuid::parse_str(PARENT_FOLDER_UUID).unwrap();
+        let usage_response: UserUsageResponsePayload =
+            deserialize_from_file("tests/resources/responses/user_usage.json");
+        let dirs_response: UserDirsResponsePayload =
+            deserialize_from_file("tests/resources/responses/user_dirs_default.json");
+        let usage_mock = setup_json_mock(
+            "/v1/user/usage",
+            &utils::api_key_json(&API_KEY),
+            &usage_response,
+            &server,
+        );
+        let dirs_mock = setup_json_mock("/v1/user/dirs", &utils::api_key_json(&API_KEY), &dirs_response, &server);
+        let (file_uuid, subfolder_uuid, mocks) = setup_two_level_tree_mocks(default_folder_uuid, &server);
+
+        let result = wipe_account(&API_KEY, "test@test.com", &filen_settings).unwrap();
+
+        usage_mock.assert_hits(1);
+        dirs_mock.assert_hits(1);
+        assert_two_level_tree_mocks_hits(&mocks);
+        assert!(result.is_success());
+        assert_eq!(
+            result.succeeded,
+            vec![(file_uuid, ItemKind::File), (subfolder_uuid, ItemKind::Folder)]
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn operation_batch_should_run_mixed_operations_in_submission_order() {
+        let (server, filen_settings) = init_server();
+        let file_uuid = Uuid::parse_str(FILE_UUID).unwrap();
+        let folder_uuid = Uuid::parse_str(FOLDER_UUID).unwrap();
+        let parent_uuid = Uuid::parse_str(PARENT_FOLDER_UUID).unwrap();
+        let move_response: PlainResponsePayload = deserialize_from_file("tests/resources/responses/dir_move.json");
+        let trash_response: PlainResponsePayload = deserialize_from_file("tests/resources/responses/file_trash.json");
+        let refused_response: PlainResponsePayload =
+            deserialize_from_file("tests/resources/responses/dir_already_in_trash.json");
+        let move_mock = setup_json_mock(
+            "/v1/dir/move",
+            &DirMoveRequestPayload {
+                api_key: &API_KEY,
+                folder_uuid: parent_uuid,
+                uuid: folder_uuid,
+            },
+            &move_response,
+            &server,
+        );
+        let file_trash_mock = setup_json_mock(
+            "/v1/file/trash",
+            &LocationTrashRequestPayload {
+                api_key: &API_KEY,
+                uuid: file_uuid,
+            },
+            &trash_response,
+            &server,
+        );
+        let folder_trash_mock = setup_json_mock(
+            "/v1/dir/trash",
+            &LocationTrashRequestPayload {
+                api_key: &API_KEY,
+                uuid: folder_uuid,
+            },
+            &refused_response,
+            &server,
+        );
+        let mut batch = OperationBatch::new(2);
+        let settings = filen_settings.clone();
+        batch.push(
+            async move { move_item_async(&API_KEY, (folder_uuid, ItemKind::Folder), parent_uuid, &settings).await },
+        );
+        let settings = filen_settings.clone();
+        batch.push(async move { trash_item_async(&API_KEY, (file_uuid, ItemKind::File), &settings).await });
+        batch.push(async move { trash_item_async(&API_KEY, (folder_uuid, ItemKind::Folder), &filen_settings).await });
+
+        let results = batch.run().await;
+
+        move_mock.assert_hits(1);
+        file_trash_mock.assert_hits(1);
+        folder_trash_mock.assert_hits(1);
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_ok());
+        assert!(matches!(results[2], Err(Error::ItemOperationRefused { .. })));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn operation_batch_should_not_exceed_max_concurrency() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let mut batch = OperationBatch::new(3);
+        for index in 0..10_u64 {
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+            batch.push(async move {
+                let now_in_flight = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(now_in_flight, Ordering::SeqCst);
+                // Later operations finish sooner, so completion order differs from submission order.
+                tokio::time::sleep(std::time::Duration::from_millis(30 - index * 2)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                index
+            });
+        }
+
+        let results = batch.run().await;
+
+        assert_eq!(results, (0..10).collect::<Vec<_>>());
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 3);
+    }
+
+    /// Sets up account usage mock and catch-all mocks for every endpoint wipe could possibly call after it.
+    fn setup_wipe_account_guard_mocks(server: &MockServer) -> (Mock<'_>, Vec<Mock<'_>>) {
+        let usage_response: UserUsageResponsePayload =
+            deserialize_from_file("tests/resources/responses/user_usage.json");
+        let usage_mock = setup_json_mock(
+            "/v1/user/usage",
+            &utils::api_key_json(&API_KEY),
+            &usage_response,
+            server,
+        );
+        let other_mocks = ["/v1/user/dirs", "/v1/dir/content", "/v1/dir/trash", "/v1/file/trash"]
+            .iter()
+            .map(|path| {
+                server.mock(|when, then| {
+                    when.method(POST).path(*path);
+                    then.status(200).json_body(serde_json::json!({"status": true}));
+                })
+            })
+            .collect();
+        (usage_mock, other_mocks)
+    }
+
+    /// Sets up a parent folder containing a file and a subfolder, which in turn has its own content.
+    /// Returns mocks for parent listing, subfolder listing, file trashing and subfolder trashing.
+    fn setup_two_level_tree_mocks(parent_uuid: Uuid, server: &MockServer) -> (Uuid, Uuid, Vec<Mock<'_>>) {
+        let subfolder_content: DirContentResponsePayload =
+            deserialize_from_file("tests/resources/responses/dir_content.json");
+        let mut parent_content = subfolder_content.clone();
+        let parent_data = parent_content.data.as_mut().unwrap();
+        parent_data.uploads.truncate(1);
+        parent_data.folders.truncate(1);
+        let file_uuid = parent_data.uploads[0].uuid;
+        let subfolder_uuid = parent_data.folders[0].uuid;
+        let file_response: PlainResponsePayload = deserialize_from_file("tests/resources/responses/file_trash.json");
+        let folder_response: PlainResponsePayload = deserialize_from_file("tests/resources/responses/dir_trash.json");
+
+        let mocks = vec![
+            setup_json_mock(
+                "/v1/dir/content",
+                &DirContentRequestPayload::new(&API_KEY, ContentKind::Folder(parent_uuid)),
+                &parent_content,
+                server,
+            ),
+            setup_json_mock(
+                "/v1/dir/content",
+                &DirContentRequestPayload::new(&API_KEY, ContentKind::Folder(subfolder_uuid)),
+                &subfolder_content,
+                server,
+            ),
+            setup_json_mock(
+                "/v1/file/trash",
+                &LocationTrashRequestPayload {
+                    api_key: &API_KEY,
+                    uuid: file_uuid,
+                },
+                &file_response,
+                server,
+            ),
+            setup_json_mock(
+                "/v1/dir/trash",
+                &LocationTrashRequestPayload {
+                    api_key: &API_KEY,
+                    uuid: subfolder_uuid,
+                },
+                &folder_response,
+                server,
+            ),
+        ];
+        (file_uuid, subfolder_uuid, mocks)
+    }
+
+    fn assert_two_level_tree_mocks_hits(mocks: &[Mock]) {
+        mocks[0].assert_hits(1);
+        mocks[1].assert_hits(0);
+        mocks[2].assert_hits(1);
+        mocks[3].assert_hits(1);
+    }
+}
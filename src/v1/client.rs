@@ -1,9 +1,7 @@
 use crate::{
-    crypto, queries, utils,
-    v1::{
-        bool_to_int, response_payload, skip_serializing_none, ItemKind, LocationColor, PlainResponsePayload, Uuid,
-        METADATA_VERSION,
-    },
+    crypto::{self, MetadataVersion},
+    queries, utils,
+    v1::{bool_to_int, response_payload, skip_serializing_none, ItemKind, LocationColor, PlainResponsePayload, Uuid},
     FilenSettings,
 };
 use secstr::SecUtf8;
@@ -105,16 +103,27 @@ pub struct SyncClientMessageRequestPayload<'sync_client_message> {
 utils::display_from_json_with_lifetime!('sync_client_message, SyncClientMessageRequestPayload);
 
 impl<'sync_client_message> SyncClientMessageRequestPayload<'sync_client_message> {
-    #[allow(clippy::missing_panics_doc)]
     #[must_use]
     pub fn from_json(
         api_key: &'sync_client_message SecUtf8,
         json_value: &serde_json::Value,
         last_master_key: &SecUtf8,
     ) -> Self {
-        // Cannot panic, since METADATA_VERSION is supported by definition and json_value.to_string() is valid UTF-8
+        Self::from_json_with_version(api_key, json_value, last_master_key, MetadataVersion::default())
+    }
+
+    /// Encrypts the given JSON value into metadata of the specified version.
+    #[allow(clippy::missing_panics_doc)]
+    #[must_use]
+    pub fn from_json_with_version(
+        api_key: &'sync_client_message SecUtf8,
+        json_value: &serde_json::Value,
+        last_master_key: &SecUtf8,
+        metadata_version: MetadataVersion,
+    ) -> Self {
+        // Cannot panic, since MetadataVersion is supported by definition and json_value.to_string() is valid UTF-8
         let metadata =
-            crypto::encrypt_metadata_str(&json_value.to_string(), last_master_key, METADATA_VERSION).unwrap();
+            crypto::encrypt_metadata_str(&json_value.to_string(), last_master_key, metadata_version.into()).unwrap();
         Self {
             api_key,
             args: metadata,
@@ -125,9 +134,24 @@ impl<'sync_client_message> SyncClientMessageRequestPayload<'sync_client_message>
         api_key: &'sync_client_message SecUtf8,
         data: T,
         last_master_key: &SecUtf8,
+    ) -> Result<Self> {
+        Self::from_data_with_version(api_key, data, last_master_key, MetadataVersion::default())
+    }
+
+    /// Serializes the given data to JSON and encrypts it into metadata of the specified version.
+    pub fn from_data_with_version<T: Serialize>(
+        api_key: &'sync_client_message SecUtf8,
+        data: T,
+        last_master_key: &SecUtf8,
+        metadata_version: MetadataVersion,
     ) -> Result<Self> {
         let json_value = serde_json::to_value(&data).context(CannotSerializeDataToJsonSnafu {})?;
-        Ok(Self::from_json(api_key, &json_value, last_master_key))
+        Ok(Self::from_json_with_version(
+            api_key,
+            &json_value,
+            last_master_key,
+            metadata_version,
+        ))
     }
 }
 
@@ -1,5 +1,6 @@
 use crate::{
-    crypto, queries, utils,
+    crypto::{self, MetadataVersion},
+    queries, utils,
     v1::{
         files, fs, response_payload, Expire, FileProperties, HasFileMetadata, HasLinkKey, HasLocationName, HasUuid,
         ItemKind, Lazy, LocationNameMetadata, ParentOrBase, PasswordState, PlainResponsePayload,
@@ -10,7 +11,7 @@ use secstr::SecUtf8;
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use serde_with::skip_serializing_none;
-use snafu::{ResultExt, Snafu};
+use snafu::{ensure, Backtrace, ResultExt, Snafu};
 use strum::{Display, EnumString};
 use uuid::Uuid;
 
@@ -21,6 +22,11 @@ pub static LINK_EMPTY_PASSWORD_HASH: Lazy<String> = Lazy::new(|| crypto::hash_fn
 pub static SEC_LINK_EMPTY_PASSWORD_VALUE: Lazy<SecUtf8> =
     Lazy::new(|| SecUtf8::from(LINK_EMPTY_PASSWORD_VALUE.as_str()));
 
+/// Returns true if given link password hash is set to something other than the empty password hash.
+pub(crate) fn is_link_password_hash_set(password_hash: Option<&str>) -> bool {
+    password_hash.is_some_and(|hash| !hash.is_empty() && hash != LINK_EMPTY_PASSWORD_HASH.as_str())
+}
+
 const DIR_LINK_ADD_PATH: &str = "/v1/dir/link/add";
 const DIR_LINK_EDIT_PATH: &str = "/v1/dir/link/edit";
 const DIR_LINK_REMOVE_PATH: &str = "/v1/dir/link/remove";
@@ -29,6 +35,9 @@ const DIR_LINK_STATUS_PATH: &str = "/v1/dir/link/status";
 #[allow(clippy::enum_variant_names)]
 #[derive(Snafu, Debug)]
 pub enum Error {
+    #[snafu(display("Caller provided invalid argument: {}", message))]
+    BadArgument { message: String, backtrace: Backtrace },
+
     #[snafu(display("Failed to decrypt link key metadata '{}': {}", metadata, source))]
     DecryptLinkKeyMetadataFailed { metadata: String, source: crypto::Error },
 
@@ -73,6 +82,24 @@ pub enum DownloadBtnStateByte {
     Enable = 1,
 }
 
+impl From<DownloadBtnStateByte> for DownloadBtnState {
+    fn from(state: DownloadBtnStateByte) -> Self {
+        match state {
+            DownloadBtnStateByte::Disable => Self::Disable,
+            DownloadBtnStateByte::Enable => Self::Enable,
+        }
+    }
+}
+
+impl From<DownloadBtnState> for DownloadBtnStateByte {
+    fn from(state: DownloadBtnState) -> Self {
+        match state {
+            DownloadBtnState::Disable => Self::Disable,
+            DownloadBtnState::Enable => Self::Enable,
+        }
+    }
+}
+
 /// Used for requests to `DIR_LINK_ADD_PATH` endpoint.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub struct DirLinkAddRequestPayload<'dir_link_add> {
@@ -126,11 +153,32 @@ impl<'dir_link_add> DirLinkAddRequestPayload<'dir_link_add> {
         link_uuid: Uuid,
         link_key_metadata: &'dir_link_add str,
         master_keys: &[SecUtf8],
+    ) -> Result<Self> {
+        Self::from_file_data_with_version(
+            api_key,
+            file_data,
+            parent,
+            link_uuid,
+            link_key_metadata,
+            master_keys,
+            MetadataVersion::default(),
+        )
+    }
+
+    /// Same as `DirLinkAddRequestPayload::from_file_data`, but encrypts link metadata with the given version.
+    pub fn from_file_data_with_version<T: HasFileMetadata + HasUuid>(
+        api_key: &'dir_link_add SecUtf8,
+        file_data: &T,
+        parent: ParentOrBase,
+        link_uuid: Uuid,
+        link_key_metadata: &'dir_link_add str,
+        master_keys: &[SecUtf8],
+        metadata_version: MetadataVersion,
     ) -> Result<Self> {
         let file_properties = file_data
             .decrypt_file_metadata(master_keys)
             .context(DecryptFileMetadataFailedSnafu {})?;
-        Self::from_file_properties(
+        Self::from_file_properties_with_version(
             api_key,
             *file_data.uuid_ref(),
             &file_properties,
@@ -138,6 +186,7 @@ impl<'dir_link_add> DirLinkAddRequestPayload<'dir_link_add> {
             link_uuid,
             link_key_metadata,
             master_keys,
+            metadata_version,
         )
     }
 
@@ -150,6 +199,31 @@ impl<'dir_link_add> DirLinkAddRequestPayload<'dir_link_add> {
         link_key_metadata: &'dir_link_add str,
         master_keys: &[SecUtf8],
     ) -> Result<Self> {
+        Self::from_file_properties_with_version(
+            api_key,
+            file_uuid,
+            file_properties,
+            parent,
+            link_uuid,
+            link_key_metadata,
+            master_keys,
+            MetadataVersion::default(),
+        )
+    }
+
+    /// Same as `DirLinkAddRequestPayload::from_file_properties`, but encrypts link metadata with the given version.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_file_properties_with_version(
+        api_key: &'dir_link_add SecUtf8,
+        file_uuid: Uuid,
+        file_properties: &FileProperties,
+        parent: ParentOrBase,
+        link_uuid: Uuid,
+        link_key_metadata: &'dir_link_add str,
+        master_keys: &[SecUtf8],
+        metadata_version: MetadataVersion,
+    ) -> Result<Self> {
+        ensure_filen_link_uuid(link_uuid)?;
         let link_key = SecUtf8::from(
             crypto::decrypt_metadata_str_any_key(link_key_metadata, master_keys).context(
                 DecryptLinkKeyMetadataFailedSnafu {
@@ -157,7 +231,7 @@ impl<'dir_link_add> DirLinkAddRequestPayload<'dir_link_add> {
                 },
             )?,
         );
-        let metadata = file_properties.to_metadata_string(&link_key);
+        let metadata = file_properties.to_metadata_string_with_version(&link_key, metadata_version);
         Ok(Self {
             api_key,
             download_btn: DownloadBtnState::Enable,
@@ -180,11 +254,32 @@ impl<'dir_link_add> DirLinkAddRequestPayload<'dir_link_add> {
         link_uuid: Uuid,
         link_key_metadata: &'dir_link_add str,
         master_keys: &[SecUtf8],
+    ) -> Result<Self> {
+        Self::from_folder_data_with_version(
+            api_key,
+            folder_data,
+            parent,
+            link_uuid,
+            link_key_metadata,
+            master_keys,
+            MetadataVersion::default(),
+        )
+    }
+
+    /// Same as `DirLinkAddRequestPayload::from_folder_data`, but encrypts link metadata with the given version.
+    pub fn from_folder_data_with_version<T: HasLocationName + HasUuid>(
+        api_key: &'dir_link_add SecUtf8,
+        folder_data: &T,
+        parent: ParentOrBase,
+        link_uuid: Uuid,
+        link_key_metadata: &'dir_link_add str,
+        master_keys: &[SecUtf8],
+        metadata_version: MetadataVersion,
     ) -> Result<Self> {
         let folder_name = folder_data
             .decrypt_name_metadata(master_keys)
             .context(DecryptLocationNameFailedSnafu {})?;
-        Self::from_folder_name(
+        Self::from_folder_name_with_version(
             api_key,
             *folder_data.uuid_ref(),
             &folder_name,
@@ -192,6 +287,7 @@ impl<'dir_link_add> DirLinkAddRequestPayload<'dir_link_add> {
             link_uuid,
             link_key_metadata,
             master_keys,
+            metadata_version,
         )
     }
 
@@ -204,6 +300,31 @@ impl<'dir_link_add> DirLinkAddRequestPayload<'dir_link_add> {
         link_key_metadata: &'dir_link_add str,
         master_keys: &[SecUtf8],
     ) -> Result<Self> {
+        Self::from_folder_name_with_version(
+            api_key,
+            folder_uuid,
+            folder_name,
+            parent,
+            link_uuid,
+            link_key_metadata,
+            master_keys,
+            MetadataVersion::default(),
+        )
+    }
+
+    /// Same as `DirLinkAddRequestPayload::from_folder_name`, but encrypts link metadata with the given version.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_folder_name_with_version(
+        api_key: &'dir_link_add SecUtf8,
+        folder_uuid: Uuid,
+        folder_name: &str,
+        parent: ParentOrBase,
+        link_uuid: Uuid,
+        link_key_metadata: &'dir_link_add str,
+        master_keys: &[SecUtf8],
+        metadata_version: MetadataVersion,
+    ) -> Result<Self> {
+        ensure_filen_link_uuid(link_uuid)?;
         let link_key = SecUtf8::from(
             crypto::decrypt_metadata_str_any_key(link_key_metadata, master_keys).context(
                 DecryptLinkKeyMetadataFailedSnafu {
@@ -211,7 +332,8 @@ impl<'dir_link_add> DirLinkAddRequestPayload<'dir_link_add> {
                 },
             )?,
         );
-        let metadata = LocationNameMetadata::encrypt_name_to_metadata(folder_name, &link_key);
+        let metadata =
+            LocationNameMetadata::encrypt_name_to_metadata_with_version(folder_name, &link_key, metadata_version);
         Ok(Self {
             api_key,
             download_btn: DownloadBtnState::Enable,
@@ -228,6 +350,17 @@ impl<'dir_link_add> DirLinkAddRequestPayload<'dir_link_add> {
     }
 }
 
+/// Fails with `Error::BadArgument` if given link ID is not a UUID V4, which Filen would reject.
+fn ensure_filen_link_uuid(link_uuid: Uuid) -> Result<()> {
+    ensure!(
+        utils::is_filen_uuid(&link_uuid),
+        BadArgumentSnafu {
+            message: format!("link ID {} is not a UUID V4", link_uuid)
+        }
+    );
+    Ok(())
+}
+
 /// Used for requests to `DIR_LINK_EDIT_PATH` endpoint.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub struct DirLinkEditRequestPayload<'dir_link_edit> {
@@ -282,6 +415,29 @@ impl<'dir_link_edit> DirLinkEditRequestPayload<'dir_link_edit> {
             uuid: item_uuid,
         }
     }
+
+    /// Creates payload which keeps link password previously set with `DirLinkEditRequestPayload::new`,
+    /// so other link properties, like expiration, can be changed without resetting the password.
+    /// `password_hashed` and `salt` should be the values used when the password was set.
+    #[must_use]
+    pub fn with_password_hash(
+        api_key: &'dir_link_edit SecUtf8,
+        download_btn: DownloadBtnState,
+        item_uuid: Uuid,
+        expiration: Expire,
+        password_hashed: String,
+        salt: String,
+    ) -> Self {
+        Self {
+            api_key,
+            download_btn,
+            expiration,
+            password: PasswordState::NotEmpty,
+            password_hashed,
+            salt,
+            uuid: item_uuid,
+        }
+    }
 }
 
 /// Used for requests to `DIR_LINK_REMOVE_PATH` endpoint.
@@ -334,9 +490,21 @@ pub struct DirLinkStatusResponseData {
 
     /// Link password hash in hex string form, or None if no password was set by user or if no link was found.
     pub password: Option<String>,
+
+    /// Response fields unknown to this crate, preserved as is.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 utils::display_from_json!(DirLinkStatusResponseData);
 
+impl DirLinkStatusResponseData {
+    /// Returns true if link requires a password, so it cannot be opened without one.
+    #[must_use]
+    pub fn is_password_protected(&self) -> bool {
+        is_link_password_hash_set(self.password.as_deref())
+    }
+}
+
 impl HasLinkKey for DirLinkStatusResponseData {
     fn link_key_metadata_ref(&self) -> Option<&str> {
         self.key.as_deref()
@@ -435,15 +603,144 @@ pub async fn dir_link_status_request_async(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_utils::validate_contract;
     #[cfg(feature = "async")]
     use crate::test_utils::validate_contract_async;
+    use crate::test_utils::{deserialize_from_file, validate_contract};
     use once_cell::sync::Lazy;
     use secstr::SecUtf8;
 
     static API_KEY: Lazy<SecUtf8> =
         Lazy::new(|| SecUtf8::from("bYZmrwdVEbHJSqeA1RfnPtKiBcXzUpRdKGRkjw9m1o1eqSGP1s6DM11CDnklpFq6"));
 
+    #[test]
+    fn dir_link_status_response_data_should_preserve_unknown_fields() {
+        let response_json = r#"{"exists":false,"linkType":"folder","views":3}"#;
+
+        let response_data: DirLinkStatusResponseData = serde_json::from_str(response_json).unwrap();
+        let serialized = serde_json::to_value(&response_data).unwrap();
+
+        assert_eq!(response_data.extra.get("views"), Some(&serde_json::json!(3)));
+        assert_eq!(
+            serialized,
+            serde_json::from_str::<serde_json::Value>(response_json).unwrap()
+        );
+    }
+
+    #[test]
+    fn dir_link_edit_request_payload_should_keep_password_hash_when_editing_expiration() {
+        let item_uuid = Uuid::parse_str("80f678c0-56ce-4b81-b4ef-f2a9c0c737c4").unwrap();
+        let password_set = DirLinkEditRequestPayload::new(
+            &API_KEY,
+            DownloadBtnState::Enable,
+            item_uuid,
+            Expire::Never,
+            Some(&SecUtf8::from("link password")),
+        );
+
+        let expiration_edited = DirLinkEditRequestPayload::with_password_hash(
+            &API_KEY,
+            DownloadBtnState::Enable,
+            item_uuid,
+            Expire::Days(1),
+            password_set.password_hashed.clone(),
+            password_set.salt.clone(),
+        );
+
+        assert_eq!(expiration_edited.expiration, Expire::Days(1));
+        assert_eq!(expiration_edited.password, PasswordState::NotEmpty);
+        assert_eq!(expiration_edited.password_hashed, password_set.password_hashed);
+        assert_eq!(expiration_edited.salt, password_set.salt);
+    }
+
+    #[test]
+    fn download_btn_state_should_round_trip_through_byte_form() {
+        for state in [DownloadBtnState::Disable, DownloadBtnState::Enable] {
+            assert_eq!(DownloadBtnState::from(DownloadBtnStateByte::from(state)), state);
+        }
+        for state_byte in [DownloadBtnStateByte::Disable, DownloadBtnStateByte::Enable] {
+            assert_eq!(
+                DownloadBtnStateByte::from(DownloadBtnState::from(state_byte)),
+                state_byte
+            );
+        }
+        assert_eq!(
+            DownloadBtnState::from(DownloadBtnStateByte::Enable),
+            DownloadBtnState::Enable
+        );
+        assert_eq!(DownloadBtnStateByte::from(DownloadBtnState::Disable) as u8, 0);
+    }
+
+    #[test]
+    fn dir_link_add_request_payload_should_encrypt_metadata_with_v1_by_default() {
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let link_key = SecUtf8::from("tqNrczqVdTCgFzB1b1gyiQBIYmwDBwa9");
+        let link_key_metadata = crypto::encrypt_metadata_str(link_key.unsecure(), &m_key, 1).unwrap();
+
+        let payload = DirLinkAddRequestPayload::from_folder_name(
+            &API_KEY,
+            Uuid::nil(),
+            "Documents",
+            ParentOrBase::Base,
+            Uuid::new_v4(),
+            &link_key_metadata,
+            &[m_key],
+        )
+        .unwrap();
+
+        assert!(payload.metadata.starts_with("U2FsdGVk"));
+        assert_eq!(
+            LocationNameMetadata::decrypt_name_from_metadata(&payload.metadata, &[link_key]).unwrap(),
+            "Documents"
+        );
+    }
+
+    #[test]
+    fn dir_link_add_request_payload_should_reject_non_v4_link_uuid() {
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let link_key_metadata = crypto::encrypt_metadata_str("tqNrczqVdTCgFzB1b1gyiQBIYmwDBwa9", &m_key, 1).unwrap();
+        let v1_uuid = Uuid::parse_str("c232ab00-9414-11ec-b3c8-9f6bdeced846").unwrap();
+
+        let result = DirLinkAddRequestPayload::from_folder_name(
+            &API_KEY,
+            Uuid::nil(),
+            "Documents",
+            ParentOrBase::Base,
+            v1_uuid,
+            &link_key_metadata,
+            &[m_key],
+        );
+
+        assert!(matches!(result, Err(Error::BadArgument { .. })));
+    }
+
+    #[test]
+    fn dir_link_add_request_payload_should_encrypt_metadata_with_given_version() {
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let link_key = SecUtf8::from("tqNrczqVdTCgFzB1b1gyiQBIYmwDBwa9");
+        let link_key_metadata = crypto::encrypt_metadata_str(link_key.unsecure(), &m_key, 1).unwrap();
+
+        for (metadata_version, prefix) in [(MetadataVersion::V1, "U2FsdGVk"), (MetadataVersion::V2, "002")] {
+            let payload = DirLinkAddRequestPayload::from_folder_name_with_version(
+                &API_KEY,
+                Uuid::nil(),
+                "Documents",
+                ParentOrBase::Base,
+                Uuid::new_v4(),
+                &link_key_metadata,
+                std::slice::from_ref(&m_key),
+                metadata_version,
+            )
+            .unwrap();
+
+            assert!(payload.metadata.starts_with(prefix));
+            assert_eq!(
+                LocationNameMetadata::decrypt_name_from_metadata(&payload.metadata, std::slice::from_ref(&link_key))
+                    .unwrap(),
+                "Documents"
+            );
+        }
+    }
+
     #[test]
     fn dir_link_status_request_should_have_proper_contract_for_no_link() {
         let request_payload = DirLinkStatusRequestPayload {
@@ -490,6 +787,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn dir_link_status_response_should_tell_if_link_is_password_protected() {
+        let open: DirLinkStatusResponsePayload =
+            deserialize_from_file("tests/resources/responses/dir_link_status_no_password.json");
+        let protected: DirLinkStatusResponsePayload =
+            deserialize_from_file("tests/resources/responses/dir_link_status_with_password.json");
+        let mut explicitly_empty = open.data.clone().unwrap();
+        explicitly_empty.password = Some(LINK_EMPTY_PASSWORD_HASH.clone());
+
+        assert!(!open.data.unwrap().is_password_protected());
+        assert!(protected.data.unwrap().is_password_protected());
+        assert!(!explicitly_empty.is_password_protected());
+    }
+
     #[cfg(feature = "async")]
     #[tokio::test]
     async fn dir_link_status_request_async_should_have_proper_contract_for_link_without_password() {
@@ -0,0 +1,425 @@
+//! Contains helpers to make sure a folder path exists in user's account, creating missing folders along the way.
+//!
+//! Folders are looked up with `dir_exists_request` one path segment at a time, and only missing ones are created:
+//! the first segment as a base folder with `dir_create_request`, the rest with `dir_sub_create_request`.
+#[cfg(feature = "async")]
+use crate::v1::{dir_create_request_async, dir_exists_request_async, dir_sub_create_request_async};
+use crate::{
+    v1::{
+        dir_create_request, dir_exists_request, dir_sub_create_request, dirs, DirCreateRequestPayload,
+        DirSubCreateRequestPayload, FilenResponse, LocationExistsRequestPayload, LocationExistsResponsePayload,
+        ParentOrBase, PlainResponsePayload,
+    },
+    SettingsBundle,
+};
+use secstr::SecUtf8;
+use snafu::{ensure, Backtrace, ResultExt, Snafu};
+use uuid::Uuid;
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Delimiter of folder names in paths accepted by `ensure_dir_path`.
+pub const DIR_PATH_DELIMITER: char = '/';
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("Caller provided invalid argument: {}", message))]
+    BadArgument { message: String, backtrace: Backtrace },
+
+    #[snafu(display("{}", source))]
+    DirCreateQueryFailed { source: dirs::Error },
+
+    #[snafu(display("Filen refused to create folder '{}': {}", name, message))]
+    DirCreateRefused {
+        name: String,
+        message: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("{}", source))]
+    DirExistsQueryFailed { source: dirs::Error },
+
+    #[snafu(display("Filen refused to check if folder '{}' exists: {}", name, source))]
+    DirExistsRefused { name: String, source: crate::v1::Error },
+}
+
+/// Helper which makes sure that folder path like "/a/b/c" exists, creating missing folders one by one.
+/// Returns ID of the deepest folder in the path.
+///
+/// Path is split by `DIR_PATH_DELIMITER`; its first folder is a base folder, also known as 'cloud drive'.
+/// Empty segments are ignored, so "a/b", "/a/b/" and "a//b" all denote the same path, while "." and ".." segments
+/// are rejected. Use `ensure_dir_segments` for folder names which contain `DIR_PATH_DELIMITER` themselves.
+pub fn ensure_dir_path(
+    api_key: &SecUtf8,
+    path: &str,
+    last_master_key: &SecUtf8,
+    settings: &SettingsBundle,
+) -> Result<Uuid> {
+    ensure_dir_segments(api_key, &dir_path_segments(path)?, last_master_key, settings)
+}
+
+/// Helper which makes sure that folder path given as a list of folder names exists, creating missing folders
+/// one by one. First name belongs to a base folder, also known as 'cloud drive'. Returns ID of the deepest folder.
+///
+/// If folder is created concurrently by some other client between the check and the creation,
+/// that folder is used instead of failing.
+pub fn ensure_dir_segments<S: AsRef<str>>(
+    api_key: &SecUtf8,
+    names: &[S],
+    last_master_key: &SecUtf8,
+    settings: &SettingsBundle,
+) -> Result<Uuid> {
+    let mut parent = ParentOrBase::Base;
+    for name in names {
+        let name = name.as_ref();
+        let uuid = match find_dir(api_key, parent, name, settings)? {
+            Some(existing_uuid) => existing_uuid,
+            None => {
+                let new_uuid = Uuid::new_v4();
+                let create_response = create_dir(api_key, parent, name, new_uuid, last_master_key, settings)?;
+                if create_response.status {
+                    new_uuid
+                } else {
+                    find_dir(api_key, parent, name, settings)?.ok_or_else(|| create_refused(name, &create_response))?
+                }
+            }
+        };
+        parent = ParentOrBase::Folder(uuid);
+    }
+    deepest_folder_uuid(parent)
+}
+
+/// Helper which asynchronously makes sure that folder path like "/a/b/c" exists, creating missing folders
+/// one by one. Returns ID of the deepest folder in the path.
+///
+/// Path is split by `DIR_PATH_DELIMITER`; its first folder is a base folder, also known as 'cloud drive'.
+/// Empty segments are ignored, so "a/b", "/a/b/" and "a//b" all denote the same path, while "." and ".." segments
+/// are rejected. Use `ensure_dir_segments_async` for folder names which contain `DIR_PATH_DELIMITER` themselves.
+#[cfg(feature = "async")]
+pub async fn ensure_dir_path_async(
+    api_key: &SecUtf8,
+    path: &str,
+    last_master_key: &SecUtf8,
+    settings: &SettingsBundle,
+) -> Result<Uuid> {
+    ensure_dir_segments_async(api_key, &dir_path_segments(path)?, last_master_key, settings).await
+}
+
+/// Helper which asynchronously makes sure that folder path given as a list of folder names exists, creating
+/// missing folders one by one. First name belongs to a base folder, also known as 'cloud drive'.
+/// Returns ID of the deepest folder.
+///
+/// If folder is created concurrently by some other client between the check and the creation,
+/// that folder is used instead of failing.
+#[cfg(feature = "async")]
+pub async fn ensure_dir_segments_async<S: AsRef<str> + Sync>(
+    api_key: &SecUtf8,
+    names: &[S],
+    last_master_key: &SecUtf8,
+    settings: &SettingsBundle,
+) -> Result<Uuid> {
+    let mut parent = ParentOrBase::Base;
+    for name in names {
+        let name = name.as_ref();
+        let uuid = match find_dir_async(api_key, parent, name, settings).await? {
+            Some(existing_uuid) => existing_uuid,
+            None => {
+                let new_uuid = Uuid::new_v4();
+                let create_response =
+                    create_dir_async(api_key, parent, name, new_uuid, last_master_key, settings).await?;
+                if create_response.status {
+                    new_uuid
+                } else {
+                    find_dir_async(api_key, parent, name, settings)
+                        .await?
+                        .ok_or_else(|| create_refused(name, &create_response))?
+                }
+            }
+        };
+        parent = ParentOrBase::Folder(uuid);
+    }
+    deepest_folder_uuid(parent)
+}
+
+fn find_dir(api_key: &SecUtf8, parent: ParentOrBase, name: &str, settings: &SettingsBundle) -> Result<Option<Uuid>> {
+    let exists_payload = LocationExistsRequestPayload::new(api_key, parent, name);
+    let exists_response = settings
+        .retry
+        .call(|| dir_exists_request(&exists_payload, &settings.filen))
+        .context(DirExistsQueryFailedSnafu {})?;
+    existing_dir_uuid(name, &exists_response)
+}
+
+#[cfg(feature = "async")]
+async fn find_dir_async(
+    api_key: &SecUtf8,
+    parent: ParentOrBase,
+    name: &str,
+    settings: &SettingsBundle,
+) -> Result<Option<Uuid>> {
+    let exists_payload = LocationExistsRequestPayload::new(api_key, parent, name);
+    let exists_response = settings
+        .retry
+        .call_async(|| dir_exists_request_async(&exists_payload, &settings.filen))
+        .await
+        .context(DirExistsQueryFailedSnafu {})?;
+    existing_dir_uuid(name, &exists_response)
+}
+
+/// Creates folder with the given ID, so retried creation requests do not create duplicate folders.
+fn create_dir(
+    api_key: &SecUtf8,
+    parent: ParentOrBase,
+    name: &str,
+    uuid: Uuid,
+    last_master_key: &SecUtf8,
+    settings: &SettingsBundle,
+) -> Result<PlainResponsePayload> {
+    match parent {
+        ParentOrBase::Base => {
+            let create_payload = DirCreateRequestPayload::new(api_key, name, Some(uuid), last_master_key)
+                .context(DirCreateQueryFailedSnafu {})?;
+            settings
+                .retry
+                .call(|| dir_create_request(&create_payload, &settings.filen))
+        }
+        ParentOrBase::Folder(parent_uuid) => {
+            let create_payload =
+                DirSubCreateRequestPayload::new(api_key, name, parent_uuid, Some(uuid), last_master_key)
+                    .context(DirCreateQueryFailedSnafu {})?;
+            settings
+                .retry
+                .call(|| dir_sub_create_request(&create_payload, &settings.filen))
+        }
+    }
+    .context(DirCreateQueryFailedSnafu {})
+}
+
+/// Asynchronously creates folder with the given ID, so retried creation requests do not create duplicate folders.
+#[cfg(feature = "async")]
+async fn create_dir_async(
+    api_key: &SecUtf8,
+    parent: ParentOrBase,
+    name: &str,
+    uuid: Uuid,
+    last_master_key: &SecUtf8,
+    settings: &SettingsBundle,
+) -> Result<PlainResponsePayload> {
+    match parent {
+        ParentOrBase::Base => {
+            let create_payload = DirCreateRequestPayload::new(api_key, name, Some(uuid), last_master_key)
+                .context(DirCreateQueryFailedSnafu {})?;
+            settings
+                .retry
+                .call_async(|| dir_create_request_async(&create_payload, &settings.filen))
+                .await
+        }
+        ParentOrBase::Folder(parent_uuid) => {
+            let create_payload =
+                DirSubCreateRequestPayload::new(api_key, name, parent_uuid, Some(uuid), last_master_key)
+                    .context(DirCreateQueryFailedSnafu {})?;
+            settings
+                .retry
+                .call_async(|| dir_sub_create_request_async(&create_payload, &settings.filen))
+                .await
+        }
+    }
+    .context(DirCreateQueryFailedSnafu {})
+}
+
+fn existing_dir_uuid(name: &str, exists_response: &LocationExistsResponsePayload) -> Result<Option<Uuid>> {
+    let exists_data = exists_response
+        .data_ref_or_err()
+        .context(DirExistsRefusedSnafu { name })?;
+    Ok(exists_data.uuid.filter(|_| exists_data.exists))
+}
+
+fn create_refused(name: &str, create_response: &PlainResponsePayload) -> Error {
+    DirCreateRefusedSnafu {
+        name,
+        message: create_response
+            .message
+            .clone()
+            .unwrap_or_else(|| "unknown reason".to_owned()),
+    }
+    .build()
+}
+
+fn dir_path_segments(path: &str) -> Result<Vec<&str>> {
+    let segments = path
+        .split(DIR_PATH_DELIMITER)
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<&str>>();
+    ensure!(
+        segments.iter().all(|segment| *segment != "." && *segment != ".."),
+        BadArgumentSnafu {
+            message: "folder path cannot contain '.' or '..' segments"
+        }
+    );
+    Ok(segments)
+}
+
+/// Returns ID of the last folder in the path, or fails if path had no folders at all.
+fn deepest_folder_uuid(parent: ParentOrBase) -> Result<Uuid> {
+    match parent {
+        ParentOrBase::Folder(uuid) => Ok(uuid),
+        ParentOrBase::Base => BadArgumentSnafu {
+            message: "folder path must contain at least one folder name",
+        }
+        .fail(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{init_server, setup_json_mock};
+    use crate::v1::{LocationExistsResponseData, LocationNameMetadata};
+    use httpmock::{Method::POST, Mock, MockServer};
+    use once_cell::sync::Lazy;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    static API_KEY: Lazy<SecUtf8> =
+        Lazy::new(|| SecUtf8::from("bYZmrwdVEbHJSqeA1RfnPtKiBcXzUpRdKGRkjw9m1o1eqSGP1s6DM11CDnklpFq6"));
+    static M_KEY: Lazy<SecUtf8> = Lazy::new(|| SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae"));
+    const DOCS_UUID: &str = "0d0e1a2b-3c4d-4e5f-8a9b-0c1d2e3f4a5b";
+    const PROJECTS_UUID: &str = "1e2f3a4b-5c6d-4e7f-9a0b-1c2d3e4f5a6b";
+
+    fn setup_exists_mock<'server>(
+        parent: ParentOrBase,
+        name: &str,
+        existing_uuid: Option<&str>,
+        server: &'server MockServer,
+    ) -> Mock<'server> {
+        let response = LocationExistsResponsePayload {
+            status: true,
+            message: None,
+            data: Some(LocationExistsResponseData {
+                exists: existing_uuid.is_some(),
+                uuid: existing_uuid.map(|uuid| Uuid::parse_str(uuid).unwrap()),
+            }),
+        };
+        setup_json_mock(
+            "/v1/dir/exists",
+            &LocationExistsRequestPayload::new(&API_KEY, parent, name),
+            &response,
+            server,
+        )
+    }
+
+    fn setup_create_mocks(server: &MockServer) -> (Mock<'_>, Mock<'_>) {
+        let base_create_mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/dir/create");
+            then.status(200)
+                .json_body(json!({"status": true, "message": "Folder created."}));
+        });
+        let sub_create_mock = server.mock(|when, then| {
+            when.method(POST).path("/v1/dir/sub/create").json_body_partial(
+                json!({
+                    "parent": PROJECTS_UUID,
+                    "nameHashed": LocationNameMetadata::name_hashed("2024"),
+                })
+                .to_string(),
+            );
+            then.status(200)
+                .json_body(json!({"status": true, "message": "Sub folder created."}));
+        });
+        (base_create_mock, sub_create_mock)
+    }
+
+    fn init_server_with_settings() -> (MockServer, SettingsBundle) {
+        let (server, filen_settings) = init_server();
+        let settings = SettingsBundle {
+            filen: filen_settings,
+            ..SettingsBundle::default()
+        };
+        (server, settings)
+    }
+
+    #[test]
+    fn dir_path_segments_should_skip_empty_segments() {
+        assert_eq!(dir_path_segments("/a//b/c/").unwrap(), vec!["a", "b", "c"]);
+        assert_eq!(dir_path_segments("a").unwrap(), vec!["a"]);
+        assert!(matches!(dir_path_segments("a/../b"), Err(Error::BadArgument { .. })));
+    }
+
+    #[test]
+    fn ensure_dir_path_should_reject_empty_path() {
+        let settings = SettingsBundle::default();
+
+        let result = ensure_dir_path(&API_KEY, "//", &M_KEY, &settings);
+
+        assert!(matches!(result, Err(Error::BadArgument { .. })));
+    }
+
+    #[test]
+    fn ensure_dir_path_should_create_only_missing_folders() {
+        let (server, settings) = init_server_with_settings();
+        let docs_mock = setup_exists_mock(ParentOrBase::Base, "Docs", Some(DOCS_UUID), &server);
+        let projects_mock = setup_exists_mock(
+            ParentOrBase::Folder(Uuid::parse_str(DOCS_UUID).unwrap()),
+            "Projects",
+            Some(PROJECTS_UUID),
+            &server,
+        );
+        let year_mock = setup_exists_mock(
+            ParentOrBase::Folder(Uuid::parse_str(PROJECTS_UUID).unwrap()),
+            "2024",
+            None,
+            &server,
+        );
+        let (base_create_mock, sub_create_mock) = setup_create_mocks(&server);
+
+        let result = ensure_dir_path(&API_KEY, "/Docs/Projects/2024", &M_KEY, &settings);
+
+        docs_mock.assert_hits(1);
+        projects_mock.assert_hits(1);
+        year_mock.assert_hits(1);
+        base_create_mock.assert_hits(0);
+        sub_create_mock.assert_hits(1);
+        let created_uuid = result.unwrap();
+        assert_ne!(created_uuid, Uuid::parse_str(PROJECTS_UUID).unwrap());
+        assert_eq!(created_uuid.get_version_num(), 4);
+    }
+
+    #[test]
+    fn ensure_dir_segments_should_keep_names_with_delimiter_intact() {
+        let (server, settings) = init_server_with_settings();
+        let docs_mock = setup_exists_mock(ParentOrBase::Base, "Docs/Old", Some(DOCS_UUID), &server);
+
+        let result = ensure_dir_segments(&API_KEY, &["Docs/Old"], &M_KEY, &settings);
+
+        docs_mock.assert_hits(1);
+        assert_eq!(result.unwrap(), Uuid::parse_str(DOCS_UUID).unwrap());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn ensure_dir_path_async_should_create_only_missing_folders() {
+        let (server, settings) = init_server_with_settings();
+        let docs_mock = setup_exists_mock(ParentOrBase::Base, "Docs", Some(DOCS_UUID), &server);
+        let projects_mock = setup_exists_mock(
+            ParentOrBase::Folder(Uuid::parse_str(DOCS_UUID).unwrap()),
+            "Projects",
+            Some(PROJECTS_UUID),
+            &server,
+        );
+        let year_mock = setup_exists_mock(
+            ParentOrBase::Folder(Uuid::parse_str(PROJECTS_UUID).unwrap()),
+            "2024",
+            None,
+            &server,
+        );
+        let (base_create_mock, sub_create_mock) = setup_create_mocks(&server);
+
+        let result = ensure_dir_path_async(&API_KEY, "Docs/Projects/2024", &M_KEY, &settings).await;
+
+        docs_mock.assert_hits(1);
+        projects_mock.assert_hits(1);
+        year_mock.assert_hits(1);
+        base_create_mock.assert_hits(0);
+        sub_create_mock.assert_hits(1);
+        assert!(result.is_ok());
+    }
+}
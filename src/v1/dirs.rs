@@ -1,18 +1,19 @@
 use crate::{
     queries, utils,
     v1::{
-        bool_from_int, bool_to_int, bool_to_string, optional_bool_from_int, optional_bool_to_int, response_payload,
-        Deserializer, FileStorageInfo, HasFileMetadata, HasFiles, HasFolders, HasLocationName, HasUuid, LocationColor,
-        LocationExistsRequestPayload, LocationExistsResponsePayload, LocationKind, LocationNameMetadata,
-        LocationTrashRequestPayload, PlainResponsePayload, Serializer,
+        bool_from_int, bool_to_int, bool_to_string, files, fs, optional_bool_from_int, optional_bool_to_int,
+        response_payload, Deserializer, FileProperties, FileStorageInfo, HasFileMetadata, HasFiles, HasFolders,
+        HasLocationName, HasUuid, LocationColor, LocationExistsRequestPayload, LocationExistsResponsePayload,
+        LocationKind, LocationNameMetadata, LocationTrashRequestPayload, ParentOrBase, PlainResponsePayload,
+        Serializer,
     },
     FilenSettings,
 };
 use secstr::SecUtf8;
 use serde::{de, Deserialize, Serialize};
 use serde_with::skip_serializing_none;
-use snafu::{Backtrace, ResultExt, Snafu};
-use std::{fmt, str::FromStr};
+use snafu::{ensure, Backtrace, ResultExt, Snafu};
+use std::{fmt, io::Read, marker::PhantomData, str::FromStr};
 use uuid::Uuid;
 
 type Result<T, E = Error> = std::result::Result<T, E>;
@@ -25,6 +26,7 @@ const DIR_CONTENT_PATH: &str = "/v1/dir/content";
 const DIR_CREATE_PATH: &str = "/v1/dir/create";
 const DIR_SUB_CREATE_PATH: &str = "/v1/dir/sub/create";
 const DIR_EXISTS_PATH: &str = "/v1/dir/exists";
+const DIR_METADATA_PATH: &str = "/v1/dir/metadata";
 const DIR_MOVE_PATH: &str = "/v1/dir/move";
 const DIR_RENAME_PATH: &str = "/v1/dir/rename";
 const DIR_RESTORE_PATH: &str = "/v1/dir/restore";
@@ -47,6 +49,24 @@ pub enum Error {
     #[snafu(display("{} query failed: {}", USER_DIRS_PATH, source))]
     UserDirsQueryFailed { source: queries::Error },
 
+    #[cfg(feature = "parallel")]
+    #[snafu(display("Cannot build thread pool for parallel decryption: {}", source))]
+    CannotBuildThreadPool { source: rayon::ThreadPoolBuildError },
+
+    #[snafu(display("Cannot decrypt metadata of file {}: {}", uuid, source))]
+    DecryptFileMetadataFailed {
+        uuid: Uuid,
+        #[snafu(source(from(files::Error, Box::new)))]
+        source: Box<files::Error>,
+    },
+
+    #[snafu(display("Cannot decrypt name of folder {}: {}", uuid, source))]
+    DecryptFolderNameFailed {
+        uuid: Uuid,
+        #[snafu(source(from(fs::Error, Box::new)))]
+        source: Box<fs::Error>,
+    },
+
     #[snafu(display("{} query failed: {}", DIR_CONTENT_PATH, source))]
     DirContentQueryFailed { source: queries::Error },
 
@@ -59,6 +79,9 @@ pub enum Error {
     #[snafu(display("{} query failed: {}", DIR_EXISTS_PATH, source))]
     DirExistsQueryFailed { source: queries::Error },
 
+    #[snafu(display("{} query failed: {}", DIR_METADATA_PATH, source))]
+    DirMetadataQueryFailed { source: queries::Error },
+
     #[snafu(display("{} query failed: {}", DIR_MOVE_PATH, source))]
     DirMoveQueryFailed { source: queries::Error },
 
@@ -493,9 +516,31 @@ pub struct DirContentResponseData {
 
     /// Seems like pagination parameter; currently is always 1.
     pub page: u32,
+
+    /// Response fields unknown to this crate, preserved as is.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 utils::display_from_json!(DirContentResponseData);
 
+impl DirContentResponseData {
+    /// Drops all files, leaving only folders; useful when only folders should be shown, like in a 'move to' picker.
+    /// Folders info and pagination fields are kept as is, so `total_uploads` still reflects files count
+    /// reported by Filen.
+    #[must_use]
+    pub fn folders_only(mut self) -> Self {
+        self.uploads.clear();
+        self
+    }
+
+    /// Drops all folders, leaving only files. Folders info and pagination fields are kept as is.
+    #[must_use]
+    pub fn files_only(mut self) -> Self {
+        self.folders.clear();
+        self
+    }
+}
+
 impl HasFiles<DirContentFile> for DirContentResponseData {
     fn files_ref(&self) -> &[DirContentFile] {
         &self.uploads
@@ -513,6 +558,214 @@ response_payload!(
     DirContentResponsePayload<DirContentResponseData>
 );
 
+/// File or folder from the `DIR_CONTENT_PATH` response, as yielded by `read_dir_content_streaming`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum DirContentEntry {
+    File(DirContentFile),
+    Folder(DirContentFolder),
+}
+
+impl DirContentEntry {
+    /// Decrypts file properties or folder name of this entry using user's master keys.
+    pub fn decrypt(&self, master_keys: &[SecUtf8]) -> Result<DecryptedDirContentEntry> {
+        match self {
+            Self::File(file) => file
+                .decrypt_file_metadata(master_keys)
+                .map(DecryptedDirContentEntry::File)
+                .context(DecryptFileMetadataFailedSnafu { uuid: file.uuid }),
+            Self::Folder(folder) => folder
+                .decrypt_name_metadata(master_keys)
+                .map(DecryptedDirContentEntry::Folder)
+                .context(DecryptFolderNameFailedSnafu { uuid: folder.uuid }),
+        }
+    }
+}
+
+/// Decrypted metadata of a `DirContentEntry`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DecryptedDirContentEntry {
+    /// Decrypted file properties.
+    File(FileProperties),
+    /// Decrypted folder name.
+    Folder(String),
+}
+
+/// Decrypts metadata of the given entries on a dedicated thread pool with the given number of threads,
+/// or with as many threads as there are CPUs if `threads` is 0. Decrypted entries are returned in the same order
+/// as given entries, so results are the same as decrypting entries one by one with `DirContentEntry::decrypt`.
+///
+/// Decrypting thousands of metadata blobs is CPU-bound, so this can be much faster for large listings.
+#[cfg(feature = "parallel")]
+pub fn decrypt_entries_parallel(
+    entries: &[DirContentEntry],
+    master_keys: &[SecUtf8],
+    threads: usize,
+) -> Result<Vec<DecryptedDirContentEntry>> {
+    use rayon::prelude::*;
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .context(CannotBuildThreadPoolSnafu {})?;
+    pool.install(|| entries.par_iter().map(|entry| entry.decrypt(master_keys)).collect())
+}
+
+/// Everything from the streamed `DIR_CONTENT_PATH` response except the files and folders themselves.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct DirContentStreamSummary {
+    /// True when API call was successful; false otherwise.
+    pub status: bool,
+
+    /// Filen reason for success or failure.
+    pub message: Option<String>,
+
+    /// Number of files in the current folder, as reported by Filen.
+    pub total_uploads: Option<u64>,
+
+    /// Number of files passed to the callback.
+    pub files_streamed: u64,
+
+    /// Number of folders passed to the callback.
+    pub folders_streamed: u64,
+}
+
+/// Deserializes `DIR_CONTENT_PATH` response from the given reader, passing every file and folder to the given
+/// callback as soon as it is parsed. Unlike deserializing `DirContentResponsePayload`, entries are never collected,
+/// so peak memory does not depend on the folder size. Folders info and pagination fields are skipped.
+pub fn read_dir_content_streaming<R: Read, F: FnMut(DirContentEntry)>(
+    reader: R,
+    on_entry: F,
+) -> serde_json::Result<DirContentStreamSummary> {
+    let mut deserializer = serde_json::Deserializer::from_reader(std::io::BufReader::new(reader));
+    let summary = de::DeserializeSeed::deserialize(DirContentStreamSeed { on_entry }, &mut deserializer)?;
+    deserializer.end()?;
+    Ok(summary)
+}
+
+struct DirContentStreamSeed<F> {
+    on_entry: F,
+}
+
+impl<'de, F: FnMut(DirContentEntry)> de::DeserializeSeed<'de> for DirContentStreamSeed<F> {
+    type Value = DirContentStreamSummary;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, F: FnMut(DirContentEntry)> de::Visitor<'de> for DirContentStreamSeed<F> {
+    type Value = DirContentStreamSummary;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("Filen response object")
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(mut self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut summary = DirContentStreamSummary::default();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "status" => summary.status = map.next_value()?,
+                "message" => summary.message = map.next_value()?,
+                "data" => map.next_value_seed(DirContentDataSeed {
+                    on_entry: &mut self.on_entry,
+                    summary: &mut summary,
+                })?,
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(summary)
+    }
+}
+
+struct DirContentDataSeed<'stream, F> {
+    on_entry: &'stream mut F,
+    summary: &'stream mut DirContentStreamSummary,
+}
+
+impl<'de, 'stream, F: FnMut(DirContentEntry)> de::DeserializeSeed<'de> for DirContentDataSeed<'stream, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        // Failed responses have no data at all or null data, so anything is accepted here.
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'stream, F: FnMut(DirContentEntry)> de::Visitor<'de> for DirContentDataSeed<'stream, F> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("Filen folder content object or null")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(())
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "uploads" => {
+                    self.summary.files_streamed += map.next_value_seed(EntriesSeed::<DirContentFile, _> {
+                        on_entry: &mut *self.on_entry,
+                        wrap: DirContentEntry::File,
+                        marker: PhantomData,
+                    })?;
+                }
+                "folders" => {
+                    self.summary.folders_streamed += map.next_value_seed(EntriesSeed::<DirContentFolder, _> {
+                        on_entry: &mut *self.on_entry,
+                        wrap: DirContentEntry::Folder,
+                        marker: PhantomData,
+                    })?;
+                }
+                "totalUploads" => self.summary.total_uploads = map.next_value()?,
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Passes every element of JSON array to the callback one by one, returns number of elements.
+struct EntriesSeed<'stream, T, F> {
+    on_entry: &'stream mut F,
+    wrap: fn(T) -> DirContentEntry,
+    marker: PhantomData<T>,
+}
+
+impl<'de, 'stream, T: Deserialize<'de>, F: FnMut(DirContentEntry)> de::DeserializeSeed<'de>
+    for EntriesSeed<'stream, T, F>
+{
+    type Value = u64;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'stream, T: Deserialize<'de>, F: FnMut(DirContentEntry)> de::Visitor<'de> for EntriesSeed<'stream, T, F> {
+    type Value = u64;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("array of Filen folder content entries")
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut count = 0;
+        while let Some(entry) = seq.next_element::<T>()? {
+            (self.on_entry)((self.wrap)(entry));
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
 /// Used for requests to `DIR_CREATE_PATH` endpoint.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub struct DirCreateRequestPayload<'dir_create> {
@@ -543,19 +796,32 @@ impl<'dir_create> DirCreateRequestPayload<'dir_create> {
     /// You should only use this if you are writing your own replacement client.
     #[must_use]
     pub fn payload_for_sync_folder_creation(api_key: &'dir_create SecUtf8, last_master_key: &SecUtf8) -> Self {
-        let mut payload = Self::new(api_key, FILEN_SYNC_FOLDER_NAME, last_master_key);
+        let mut payload = Self::with_uuid(api_key, FILEN_SYNC_FOLDER_NAME, Uuid::new_v4(), last_master_key);
         payload.dir_type = LocationKind::Sync;
         payload
     }
 
     /// Payload to create a new folder with the specified name.
-    #[must_use]
-    pub fn new(api_key: &'dir_create SecUtf8, name: &str, last_master_key: &SecUtf8) -> Self {
+    ///
+    /// If `folder_uuid` is None, random UUID is generated for the new folder. Pass UUID explicitly
+    /// to safely retry folder creation: repeated requests will not create duplicate folders.
+    /// Fails with `Error::BadArgument` if given `folder_uuid` is not a UUID V4, which Filen would reject.
+    pub fn new(
+        api_key: &'dir_create SecUtf8,
+        name: &str,
+        folder_uuid: Option<Uuid>,
+        last_master_key: &SecUtf8,
+    ) -> Result<Self> {
+        let folder_uuid = checked_new_folder_uuid(folder_uuid)?;
+        Ok(Self::with_uuid(api_key, name, folder_uuid, last_master_key))
+    }
+
+    fn with_uuid(api_key: &'dir_create SecUtf8, name: &str, folder_uuid: Uuid, last_master_key: &SecUtf8) -> Self {
         let name_metadata = LocationNameMetadata::encrypt_name_to_metadata(name, last_master_key);
         let name_hashed = LocationNameMetadata::name_hashed(name);
         Self {
             api_key,
-            uuid: Uuid::new_v4(),
+            uuid: folder_uuid,
             name_metadata,
             name_hashed,
             dir_type: LocationKind::Folder,
@@ -588,20 +854,94 @@ utils::display_from_json_with_lifetime!('dir_sub_create, DirSubCreateRequestPayl
 
 impl<'dir_sub_create> DirSubCreateRequestPayload<'dir_sub_create> {
     /// Payload to create a new sub-folder with the specified name.
-    #[must_use]
-    pub fn new(api_key: &'dir_sub_create SecUtf8, name: &str, parent: Uuid, last_master_key: &SecUtf8) -> Self {
+    ///
+    /// If `folder_uuid` is None, random UUID is generated for the new folder. Pass UUID explicitly
+    /// to safely retry folder creation: repeated requests will not create duplicate folders.
+    /// Fails with `Error::BadArgument` if given `folder_uuid` is not a UUID V4, which Filen would reject.
+    pub fn new(
+        api_key: &'dir_sub_create SecUtf8,
+        name: &str,
+        parent: Uuid,
+        folder_uuid: Option<Uuid>,
+        last_master_key: &SecUtf8,
+    ) -> Result<Self> {
+        let folder_uuid = checked_new_folder_uuid(folder_uuid)?;
         let name_metadata = LocationNameMetadata::encrypt_name_to_metadata(name, last_master_key);
         let name_hashed = LocationNameMetadata::name_hashed(name);
-        Self {
+        Ok(Self {
             api_key,
-            uuid: Uuid::new_v4(),
+            uuid: folder_uuid,
             name_metadata,
             name_hashed,
             parent,
+        })
+    }
+}
+
+/// Returns given ID for a new folder if Filen accepts it, or random UUID V4 if no ID is given.
+fn checked_new_folder_uuid(folder_uuid: Option<Uuid>) -> Result<Uuid> {
+    match folder_uuid {
+        Some(folder_uuid) => {
+            ensure!(
+                utils::is_filen_uuid(&folder_uuid),
+                BadArgumentSnafu {
+                    message: format!("folder ID {} is not a UUID V4", folder_uuid)
+                }
+            );
+            Ok(folder_uuid)
         }
+        None => Ok(Uuid::new_v4()),
+    }
+}
+
+/// Used for requests to `DIR_METADATA_PATH` endpoint.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct DirMetadataRequestPayload<'dir_metadata> {
+    /// User-associated Filen API key.
+    #[serde(rename = "apiKey")]
+    pub api_key: &'dir_metadata SecUtf8,
+
+    /// ID of the folder to get metadata for, hyphenated lowercased UUID V4.
+    pub uuid: Uuid,
+}
+utils::display_from_json_with_lifetime!('dir_metadata, DirMetadataRequestPayload);
+
+/// Response data for `DIR_METADATA_PATH` endpoint.
+#[skip_serializing_none]
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct DirMetadataResponseData {
+    /// Folder ID; hyphenated lowercased UUID V4.
+    pub uuid: Uuid,
+
+    /// Metadata containing JSON with folder name: { "name": <name value> }
+    #[serde(rename = "name")]
+    pub name_metadata: String,
+
+    /// Parent folder ID, or "base" for base folders.
+    pub parent: ParentOrBase,
+
+    /// Folder color name; None means default yellow color.
+    pub color: Option<LocationColor>,
+}
+utils::display_from_json!(DirMetadataResponseData);
+
+impl HasLocationName for DirMetadataResponseData {
+    fn name_metadata_ref(&self) -> &str {
+        self.name_metadata.as_ref()
     }
 }
 
+impl HasUuid for DirMetadataResponseData {
+    fn uuid_ref(&self) -> &Uuid {
+        &self.uuid
+    }
+}
+
+response_payload!(
+    /// Response for `DIR_METADATA_PATH` endpoint.
+    DirMetadataResponsePayload<DirMetadataResponseData>
+);
+
 /// Used for requests to `DIR_MOVE_PATH` endpoint.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub struct DirMoveRequestPayload<'dir_move> {
@@ -619,6 +959,8 @@ pub struct DirMoveRequestPayload<'dir_move> {
 utils::display_from_json_with_lifetime!('dir_move, DirMoveRequestPayload);
 
 /// Used for requests to `DIR_RENAME_PATH` endpoint.
+///
+/// Only name fields are sent, so folder favorite flag and color are kept by Filen as is.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub struct DirRenameRequestPayload<'dir_rename> {
     /// User-associated Filen API key.
@@ -724,6 +1066,20 @@ pub fn dir_content_request(
     queries::query_filen_api(DIR_CONTENT_PATH, payload, filen_settings).context(DirContentQueryFailedSnafu {})
 }
 
+/// Calls `DIR_CONTENT_PATH` endpoint and passes every file and folder from the response to the given callback
+/// as soon as it is parsed, without buffering the whole response. Use it instead of `dir_content_request`
+/// for folders with so many items that their listing does not fit into memory comfortably.
+pub fn dir_content_request_streaming<F: FnMut(DirContentEntry)>(
+    payload: &DirContentRequestPayload,
+    filen_settings: &FilenSettings,
+    on_entry: F,
+) -> Result<DirContentStreamSummary> {
+    queries::query_filen_api_with_reader(DIR_CONTENT_PATH, payload, filen_settings, |body| {
+        read_dir_content_streaming(body, on_entry)
+    })
+    .context(DirContentQueryFailedSnafu {})
+}
+
 /// Calls `DIR_CONTENT_PATH` endpoint asynchronously. Used to get a paginated set of user's files and folders in a way
 /// suited for presentation.
 #[cfg(feature = "async")]
@@ -795,6 +1151,29 @@ pub async fn dir_exists_request_async(
         .context(DirExistsQueryFailedSnafu {})
 }
 
+/// Calls `DIR_METADATA_PATH` endpoint.
+/// Gets encrypted name metadata and parent of the folder with the given UUID, without listing its parent folder.
+/// Folder name can be decrypted from the response data with `HasLocationName::decrypt_name_metadata`.
+pub fn dir_metadata_request(
+    payload: &DirMetadataRequestPayload,
+    filen_settings: &FilenSettings,
+) -> Result<DirMetadataResponsePayload> {
+    queries::query_filen_api(DIR_METADATA_PATH, payload, filen_settings).context(DirMetadataQueryFailedSnafu {})
+}
+
+/// Calls `DIR_METADATA_PATH` endpoint asynchronously.
+/// Gets encrypted name metadata and parent of the folder with the given UUID, without listing its parent folder.
+/// Folder name can be decrypted from the response data with `HasLocationName::decrypt_name_metadata`.
+#[cfg(feature = "async")]
+pub async fn dir_metadata_request_async(
+    payload: &DirMetadataRequestPayload<'_>,
+    filen_settings: &FilenSettings,
+) -> Result<DirMetadataResponsePayload> {
+    queries::query_filen_api_async(DIR_METADATA_PATH, payload, filen_settings)
+        .await
+        .context(DirMetadataQueryFailedSnafu {})
+}
+
 /// Calls `DIR_MOVE_PATH` endpoint.
 /// Moves folder with the given uuid to the specified parent folder. It is a good idea to check first if folder
 /// with the same name already exists within the parent folder.
@@ -826,7 +1205,7 @@ pub async fn dir_move_request_async(
 
 /// Calls `DIR_RENAME_PATH` endpoint.
 /// Changes name of the folder with given UUID to the specified name. It is a good idea to check first if folder
-/// with the new name already exists within the parent folder.
+/// with the new name already exists within the parent folder. Folder favorite flag and color persist.
 pub fn dir_rename_request(
     payload: &DirRenameRequestPayload,
     filen_settings: &FilenSettings,
@@ -836,7 +1215,7 @@ pub fn dir_rename_request(
 
 /// Calls `DIR_RENAME_PATH` endpoint asynchronously.
 /// Changes name of the folder with given UUID to the specified name. It is a good idea to check first if folder
-/// with the new name already exists within the parent folder.
+/// with the new name already exists within the parent folder. Folder favorite flag and color persist.
 #[cfg(feature = "async")]
 pub async fn dir_rename_request_async(
     payload: &DirRenameRequestPayload<'_>,
@@ -894,10 +1273,11 @@ mod tests {
     use super::*;
     #[cfg(feature = "async")]
     use crate::test_utils::validate_contract_async;
-    use crate::{test_utils::validate_contract, v1::ParentOrBase};
+    use crate::test_utils::{self, validate_contract};
     use once_cell::sync::Lazy;
-    use pretty_assertions::assert_eq;
+    use pretty_assertions::{assert_eq, assert_ne};
     use secstr::SecUtf8;
+    use serde_json::json;
 
     static API_KEY: Lazy<SecUtf8> =
         Lazy::new(|| SecUtf8::from("bYZmrwdVEbHJSqeA1RfnPtKiBcXzUpRdKGRkjw9m1o1eqSGP1s6DM11CDnklpFq6"));
@@ -928,7 +1308,7 @@ mod tests {
     #[test]
     fn dir_create_request_payload_should_be_created_correctly_from_name() {
         let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
-        let payload = DirCreateRequestPayload::new(&API_KEY, NAME, &m_key);
+        let payload = DirCreateRequestPayload::new(&API_KEY, NAME, None, &m_key).unwrap();
 
         let decrypted_name =
             LocationNameMetadata::decrypt_name_from_metadata(&payload.name_metadata, &[m_key]).unwrap();
@@ -939,6 +1319,38 @@ mod tests {
         assert_eq!(payload.dir_type, LocationKind::Folder);
     }
 
+    #[test]
+    fn dir_create_request_payloads_should_preserve_supplied_uuid() {
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let folder_uuid = Uuid::parse_str("80f678c0-56ce-4b81-b4ef-f2a9c0c737c4").unwrap();
+
+        let payload = DirCreateRequestPayload::new(&API_KEY, NAME, Some(folder_uuid), &m_key).unwrap();
+        let retried_payload = DirCreateRequestPayload::new(&API_KEY, NAME, Some(folder_uuid), &m_key).unwrap();
+        let sub_payload =
+            DirSubCreateRequestPayload::new(&API_KEY, NAME, Uuid::nil(), Some(folder_uuid), &m_key).unwrap();
+
+        assert_eq!(payload.uuid, folder_uuid);
+        assert_eq!(retried_payload.uuid, folder_uuid);
+        assert_eq!(sub_payload.uuid, folder_uuid);
+        assert_ne!(
+            DirCreateRequestPayload::new(&API_KEY, NAME, None, &m_key).unwrap().uuid,
+            folder_uuid
+        );
+    }
+
+    #[test]
+    fn dir_create_request_payloads_should_reject_non_v4_uuid() {
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let v1_uuid = Uuid::parse_str("c232ab00-9414-11ec-b3c8-9f6bdeced846").unwrap();
+
+        let payload_result = DirCreateRequestPayload::new(&API_KEY, NAME, Some(v1_uuid), &m_key);
+        let sub_payload_result =
+            DirSubCreateRequestPayload::new(&API_KEY, NAME, Uuid::nil(), Some(Uuid::nil()), &m_key);
+
+        assert!(matches!(payload_result, Err(Error::BadArgument { .. })));
+        assert!(matches!(sub_payload_result, Err(Error::BadArgument { .. })));
+    }
+
     #[test]
     fn user_dirs_request_should_have_proper_contract() {
         let request_payload = utils::api_key_json(&API_KEY);
@@ -980,6 +1392,178 @@ mod tests {
         );
     }
 
+    #[test]
+    fn dir_content_request_streaming_should_pass_all_entries_to_callback() {
+        let request_payload = DirContentRequestPayload::new(
+            &API_KEY,
+            ContentKind::Folder(Uuid::parse_str("80f678c0-56ce-4b81-b4ef-f2a9c0c737c4").unwrap()),
+        );
+        let (server, filen_settings) = test_utils::init_server();
+        let response: DirContentResponsePayload =
+            test_utils::deserialize_from_file("tests/resources/responses/dir_content.json");
+        let mock = test_utils::setup_json_mock(DIR_CONTENT_PATH, &request_payload, &response, &server);
+        let (mut files, mut folders) = (Vec::new(), Vec::new());
+
+        let summary = dir_content_request_streaming(&request_payload, &filen_settings, |entry| match entry {
+            DirContentEntry::File(file) => files.push(file),
+            DirContentEntry::Folder(folder) => folders.push(folder),
+        })
+        .unwrap();
+
+        mock.assert_hits(1);
+        let data = response.data.unwrap();
+        assert_eq!(files, data.uploads);
+        assert_eq!(folders, data.folders);
+        assert!(summary.status);
+        assert_eq!(summary.total_uploads, Some(data.total_uploads));
+    }
+
+    #[test]
+    fn dir_content_response_data_should_be_filtered_by_item_kind() {
+        let response: DirContentResponsePayload =
+            test_utils::deserialize_from_file("tests/resources/responses/dir_content.json");
+        let data = response.data.unwrap();
+        assert!(!data.uploads.is_empty() && !data.folders.is_empty());
+
+        let folders_only = data.clone().folders_only();
+        let files_only = data.clone().files_only();
+
+        assert!(folders_only.uploads.is_empty());
+        assert_eq!(folders_only.folders, data.folders);
+        assert_eq!(folders_only.total_uploads, data.total_uploads);
+        assert!(files_only.folders.is_empty());
+        assert_eq!(files_only.uploads, data.uploads);
+        assert_eq!(files_only.folders_info, data.folders_info);
+    }
+
+    /// Produces JSON response with the given number of files lazily, piece by piece.
+    struct SyntheticDirContentReader {
+        pieces: Box<dyn Iterator<Item = String>>,
+        current: std::io::Cursor<Vec<u8>>,
+    }
+
+    impl SyntheticDirContentReader {
+        fn new(file_json: String, folder_json: String, files_count: usize) -> Self {
+            let header = r#"{"status":true,"message":"Folder contents fetched.","data":{"uploads":["#.to_owned();
+            let files = (0..files_count).map(move |index| {
+                let separator = if index == 0 { "" } else { "," };
+                format!("{}{}", separator, file_json)
+            });
+            let footer = format!(
+                r#"],"folders":[{}],"foldersInfo":[],"totalUploads":{}}}}}"#,
+                folder_json, files_count
+            );
+            Self {
+                pieces: Box::new(std::iter::once(header).chain(files).chain(std::iter::once(footer))),
+                current: std::io::Cursor::new(Vec::new()),
+            }
+        }
+    }
+
+    impl Read for SyntheticDirContentReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            loop {
+                let read = self.current.read(buf)?;
+                if read > 0 || buf.is_empty() {
+                    return Ok(read);
+                }
+                match self.pieces.next() {
+                    Some(piece) => self.current = std::io::Cursor::new(piece.into_bytes()),
+                    None => return Ok(0),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn read_dir_content_streaming_should_count_entries_of_huge_response() {
+        let response: DirContentResponsePayload =
+            test_utils::deserialize_from_file("tests/resources/responses/dir_content.json");
+        let data = response.data.unwrap();
+        let file_json = serde_json::to_string(&data.uploads[0]).unwrap();
+        let folder_json = serde_json::to_string(&data.folders[0]).unwrap();
+        let reader = SyntheticDirContentReader::new(file_json, folder_json, 100_000);
+        let (mut files, mut folders) = (0_u64, 0_u64);
+
+        let summary = read_dir_content_streaming(reader, |entry| match entry {
+            DirContentEntry::File(_) => files += 1,
+            DirContentEntry::Folder(_) => folders += 1,
+        })
+        .unwrap();
+
+        assert_eq!((files, folders), (100_000, 1));
+        assert_eq!(summary.files_streamed, 100_000);
+        assert_eq!(summary.folders_streamed, 1);
+        assert_eq!(summary.total_uploads, Some(100_000));
+    }
+
+    /// Creates listing entries with metadata encrypted by the given master key.
+    fn encrypted_dir_content_entries(count: usize, m_key: &SecUtf8) -> Vec<DirContentEntry> {
+        let response: DirContentResponsePayload =
+            test_utils::deserialize_from_file("tests/resources/responses/dir_content.json");
+        let data = response.data.unwrap();
+        (0..count)
+            .map(|index| {
+                let name = format!("entry {}", index);
+                if index % 3 == 0 {
+                    let mut folder = data.folders[0].clone();
+                    folder.name_metadata = LocationNameMetadata::encrypt_name_to_metadata(name, m_key);
+                    DirContentEntry::Folder(folder)
+                } else {
+                    let mut file = data.uploads[0].clone();
+                    let properties = FileProperties::from_name_size_modified(
+                        &name,
+                        index as u64,
+                        &std::time::SystemTime::UNIX_EPOCH,
+                    )
+                    .unwrap();
+                    file.metadata = FileProperties::encrypt_file_metadata(&properties, m_key);
+                    DirContentEntry::File(file)
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn dir_content_entry_decrypt_should_decrypt_file_and_folder_metadata() {
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let entries = encrypted_dir_content_entries(2, &m_key);
+        let master_keys = [m_key];
+
+        let folder = entries[0].decrypt(&master_keys).unwrap();
+        let file = entries[1].decrypt(&master_keys).unwrap();
+
+        assert_eq!(folder, DecryptedDirContentEntry::Folder("entry 0".to_owned()));
+        assert!(matches!(file, DecryptedDirContentEntry::File(properties) if properties.name == "entry 1"));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn decrypt_entries_parallel_should_match_sequential_decryption() {
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let master_keys = [m_key.clone()];
+        let entries = encrypted_dir_content_entries(200, &m_key);
+        let sequential = entries
+            .iter()
+            .map(|entry| entry.decrypt(&master_keys))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+
+        let parallel = decrypt_entries_parallel(&entries, &master_keys, 4).unwrap();
+
+        assert_eq!(parallel, sequential);
+    }
+
+    #[test]
+    fn read_dir_content_streaming_should_accept_failed_response_without_data() {
+        let reader = r#"{"status":false,"message":"Folder not found.","data":null}"#.as_bytes();
+
+        let summary = read_dir_content_streaming(reader, |_| panic!("No entries expected")).unwrap();
+
+        assert!(!summary.status);
+        assert_eq!(summary.message.as_deref(), Some("Folder not found."));
+    }
+
     #[cfg(feature = "async")]
     #[tokio::test]
     async fn dir_content_request_async_should_have_proper_contract() {
@@ -1149,6 +1733,54 @@ mod tests {
         .await;
     }
 
+    #[test]
+    fn dir_metadata_request_should_have_proper_contract() {
+        let request_payload = DirMetadataRequestPayload {
+            api_key: &API_KEY,
+            uuid: Uuid::parse_str("80f678c0-56ce-4b81-b4ef-f2a9c0c737c4").unwrap(),
+        };
+        validate_contract(
+            DIR_METADATA_PATH,
+            request_payload,
+            "tests/resources/responses/dir_metadata.json",
+            |request_payload, filen_settings| dir_metadata_request(&request_payload, &filen_settings),
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn dir_metadata_request_async_should_have_proper_contract() {
+        let request_payload = DirMetadataRequestPayload {
+            api_key: &API_KEY,
+            uuid: Uuid::parse_str("80f678c0-56ce-4b81-b4ef-f2a9c0c737c4").unwrap(),
+        };
+        validate_contract_async(
+            DIR_METADATA_PATH,
+            request_payload,
+            "tests/resources/responses/dir_metadata.json",
+            |request_payload, filen_settings| async move {
+                dir_metadata_request_async(&request_payload, &filen_settings).await
+            },
+        )
+        .await;
+    }
+
+    #[test]
+    fn dir_metadata_response_data_should_decrypt_folder_name() {
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let response: DirMetadataResponsePayload =
+            test_utils::deserialize_from_file("tests/resources/responses/dir_metadata.json");
+        let data = response.data.unwrap();
+
+        let decrypted_name = data.decrypt_name_metadata(&[m_key]).unwrap();
+
+        assert_eq!(decrypted_name, NAME);
+        assert_eq!(
+            data.parent,
+            ParentOrBase::from_str("c1d7fc8a-1d2b-4c4b-9e6f-0ec86b39bc44").unwrap()
+        );
+    }
+
     #[test]
     fn dir_move_request_should_have_proper_contract() {
         let request_payload = DirMoveRequestPayload {
@@ -1196,6 +1828,30 @@ mod tests {
         );
     }
 
+    #[test]
+    fn dir_rename_request_should_send_only_name_fields() {
+        let (server, filen_settings) = test_utils::init_server();
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let uuid = Uuid::parse_str("80f678c0-56ce-4b81-b4ef-f2a9c0c737c4").unwrap();
+        let request_payload = DirRenameRequestPayload::new(&API_KEY, uuid, NAME, &m_key);
+        let mock = server.mock(|when, then| {
+            when.method(httpmock::Method::POST)
+                .path(DIR_RENAME_PATH)
+                .json_body(json!({
+                    "apiKey": API_KEY.unsecure(),
+                    "uuid": uuid,
+                    "name": request_payload.name_metadata,
+                    "nameHashed": NAME_HASHED,
+                }));
+            then.status(200).json_body(json!({"status": true}));
+        });
+
+        let response = dir_rename_request(&request_payload, &filen_settings).unwrap();
+
+        mock.assert_hits(1);
+        assert!(response.status);
+    }
+
     #[cfg(feature = "async")]
     #[tokio::test]
     async fn dir_rename_request_async_should_have_proper_contract() {
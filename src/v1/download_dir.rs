@@ -310,6 +310,10 @@ pub struct DownloadDirResponseData {
     pub folders: Vec<FolderData>,
 
     pub files: Vec<FileData>,
+
+    /// Response fields unknown to this crate, preserved as is.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 utils::display_from_json!(DownloadDirResponseData);
 
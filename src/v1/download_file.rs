@@ -1,12 +1,18 @@
 use crate::{
     crypto, queries, utils,
-    v1::{FileData, HasFileLocation},
+    v1::{upload_file::FILE_CHUNK_SIZE, FileData, HasFileLocation, Region},
     FilenSettings, SettingsBundle,
 };
 use secstr::SecUtf8;
 use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
-use std::{convert::TryInto, fmt, io::Write};
+use std::{
+    collections::BTreeSet,
+    convert::TryInto,
+    fmt, fs,
+    io::{self, Seek, SeekFrom, Write},
+    path::Path,
+};
 use uuid::Uuid;
 
 type Result<T, E = Error> = std::result::Result<T, E>;
@@ -20,7 +26,8 @@ pub enum Error {
     #[snafu(display("Cannot download file chunk '{}': {}", chunk_location, source))]
     CannotDownloadFileChunk {
         chunk_location: FileChunkLocation,
-        source: queries::Error,
+        #[snafu(source(from(queries::Error, Box::new)))]
+        source: Box<queries::Error>,
     },
 
     #[snafu(display(
@@ -38,15 +45,48 @@ pub enum Error {
     #[snafu(display("Writer could not be flushed: {}", source))]
     CannotFlushWriter { source: std::io::Error },
 
+    #[snafu(display("Writer could not seek to file chunk '{}': {}", chunk_location, source))]
+    CannotSeekWriter {
+        chunk_location: FileChunkLocation,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Download journal '{}' could not be updated: {}", journal_path, source))]
+    CannotUpdateDownloadJournal {
+        journal_path: String,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Cannot check whether file chunk '{}' exists: {}", chunk_location, source))]
+    CannotProbeFileChunk {
+        chunk_location: FileChunkLocation,
+        #[snafu(source(from(queries::Error, Box::new)))]
+        source: Box<queries::Error>,
+    },
+
     #[snafu(display("Cannot decrypt file chunk {} ({} bytes): {}", chunk_location, length, source))]
     CannotDecryptFileChunk {
         length: usize,
         chunk_location: FileChunkLocation,
-        source: crypto::Error,
+        #[snafu(source(from(crypto::Error, Box::new)))]
+        source: Box<crypto::Error>,
+    },
+
+    #[snafu(display("File chunk {} failed verification ({}): {}", chunk_index, chunk_location, source))]
+    ChunkVerificationFailed {
+        chunk_index: u32,
+        chunk_location: FileChunkLocation,
+        #[snafu(source(from(crypto::Error, Box::new)))]
+        source: Box<crypto::Error>,
     },
 
     #[snafu(display("File key is not 32 bytes long: {}", source))]
     InvalidFileKeySize { source: std::array::TryFromSliceError },
+
+    /// Download server sent less bytes than announced, which usually means a lossy connection.
+    /// Downloading this chunk again should help.
+    #[snafu(display("File chunk {} was truncated: got {} of {} bytes", index, got, expected))]
+    TruncatedChunk { index: u32, got: usize, expected: usize },
 }
 
 /// Represents file's address on Filen servers, assuming all this file's chunks use the same region and bucket.
@@ -68,6 +108,12 @@ impl FileLocation {
         }
     }
 
+    /// Typed storage region where file is stored.
+    #[must_use]
+    pub fn storage_region(&self) -> Region {
+        Region::new(&self.region)
+    }
+
     #[must_use]
     pub fn get_file_chunk_location(&self, chunk_index: u32) -> FileChunkLocation {
         FileChunkLocation::new(&self.region, &self.bucket, self.file_uuid, chunk_index)
@@ -120,9 +166,8 @@ impl fmt::Display for FileChunkLocation {
 /// Download server endpoint is <filen download server>/<region>/<bucket>/<file uuid>/<chunk index>
 pub fn download_file_chunk(file_chunk_location: &FileChunkLocation, filen_settings: &FilenSettings) -> Result<Vec<u8>> {
     let api_endpoint = utils::filen_file_location_to_api_endpoint(file_chunk_location);
-    queries::download_from_filen(&api_endpoint, filen_settings).context(CannotDownloadFileChunkSnafu {
-        chunk_location: file_chunk_location.clone(),
-    })
+    queries::download_from_filen(&api_endpoint, filen_settings)
+        .map_err(|err| chunk_download_error(err, file_chunk_location))
 }
 
 /// Asynchronously gets encrypted file chunk bytes from Filen download server defined by a region and a bucket.
@@ -135,11 +180,25 @@ pub async fn download_file_chunk_async(
     filen_settings: &FilenSettings,
 ) -> Result<Vec<u8>> {
     let api_endpoint = utils::filen_file_location_to_api_endpoint(file_chunk_location);
+    let _permit = filen_settings.acquire_chunk_permit_async().await;
     queries::download_from_filen_async(&api_endpoint, filen_settings)
         .await
-        .context(CannotDownloadFileChunkSnafu {
+        .map_err(|err| chunk_download_error(err, file_chunk_location))
+}
+
+/// Tells truncated file chunks apart from other download failures, so caller can retry just the truncated chunk.
+fn chunk_download_error(source: queries::Error, file_chunk_location: &FileChunkLocation) -> Error {
+    match source {
+        queries::Error::TruncatedResponseBody { got, expected } => Error::TruncatedChunk {
+            index: file_chunk_location.chunk_index,
+            got,
+            expected,
+        },
+        source => Error::CannotDownloadFileChunk {
             chunk_location: file_chunk_location.clone(),
-        })
+            source: Box::new(source),
+        },
+    }
 }
 
 /// Synchronously downloads and decrypts the file defined by given `DownloadedFileData` from Filen download server.
@@ -231,9 +290,307 @@ pub fn download_and_decrypt_file<W: Write>(
     Ok(written_chunk_lengths.iter().sum::<u64>())
 }
 
+/// Synchronously probes Filen download servers for the chunks of the specified file and returns the number
+/// of chunks actually stored there. Useful to recover a partially corrupted upload, when chunk count in file
+/// metadata disagrees with the server and file download fails because of it.
+///
+/// Probing starts from the last chunk according to `file_location.chunks` and walks down until an existing chunk
+/// is found, or up while chunks beyond the recorded count keep existing. Returned count can be used in place of
+/// `file_location.chunks` to proceed with file download.
+pub fn probe_file_chunk_count(file_location: &FileLocation, settings: &SettingsBundle) -> Result<u32> {
+    let chunk_exists = |chunk_index: u32| {
+        let file_chunk_location = file_location.get_file_chunk_location(chunk_index);
+        let api_endpoint = utils::filen_file_location_to_api_endpoint(&file_chunk_location);
+        settings
+            .retry
+            .call(|| queries::file_chunk_exists_on_filen(&api_endpoint, &settings.filen))
+            .context(CannotProbeFileChunkSnafu {
+                chunk_location: file_chunk_location,
+            })
+    };
+    let mut chunk_count = file_location.chunks;
+    if chunk_count > 0 && chunk_exists(chunk_count - 1)? {
+        while chunk_exists(chunk_count)? {
+            chunk_count += 1;
+        }
+    } else {
+        chunk_count = chunk_count.saturating_sub(1);
+        while chunk_count > 0 && !chunk_exists(chunk_count - 1)? {
+            chunk_count -= 1;
+        }
+    }
+    Ok(chunk_count)
+}
+
+/// Asynchronously probes Filen download servers for the chunks of the specified file and returns the number
+/// of chunks actually stored there. Useful to recover a partially corrupted upload, when chunk count in file
+/// metadata disagrees with the server and file download fails because of it.
+///
+/// Probing starts from the last chunk according to `file_location.chunks` and walks down until an existing chunk
+/// is found, or up while chunks beyond the recorded count keep existing. Returned count can be used in place of
+/// `file_location.chunks` to proceed with file download.
+#[cfg(feature = "async")]
+pub async fn probe_file_chunk_count_async(file_location: &FileLocation, settings: &SettingsBundle) -> Result<u32> {
+    let mut chunk_count = file_location.chunks;
+    if chunk_count > 0 && file_chunk_exists_async(file_location, chunk_count - 1, settings).await? {
+        while file_chunk_exists_async(file_location, chunk_count, settings).await? {
+            chunk_count += 1;
+        }
+    } else {
+        chunk_count = chunk_count.saturating_sub(1);
+        while chunk_count > 0 && !file_chunk_exists_async(file_location, chunk_count - 1, settings).await? {
+            chunk_count -= 1;
+        }
+    }
+    Ok(chunk_count)
+}
+
+#[cfg(feature = "async")]
+async fn file_chunk_exists_async(
+    file_location: &FileLocation,
+    chunk_index: u32,
+    settings: &SettingsBundle,
+) -> Result<bool> {
+    let file_chunk_location = file_location.get_file_chunk_location(chunk_index);
+    let api_endpoint = utils::filen_file_location_to_api_endpoint(&file_chunk_location);
+    settings
+        .retry
+        .call_async(|| queries::file_chunk_exists_on_filen_async(&api_endpoint, &settings.filen))
+        .await
+        .context(CannotProbeFileChunkSnafu {
+            chunk_location: file_chunk_location,
+        })
+}
+
+/// Synchronously downloads every chunk of the specified file and verifies its AES-GCM authentication tag,
+/// without writing decrypted bytes anywhere. Useful to check whether stored file is intact.
+/// Only version 2 files are AES-GCM encrypted, so only they can be verified.
+///
+/// Chunks are verified one by one; on the first chunk which fails verification,
+/// `Error::ChunkVerificationFailed` with its index is returned.
+pub fn verify_file(file_location: &FileLocation, file_key: &SecUtf8, settings: &SettingsBundle) -> Result<()> {
+    (0..file_location.chunks).try_for_each(|chunk_index| {
+        let file_chunk_location = file_location.get_file_chunk_location(chunk_index);
+        let encrypted_bytes = settings
+            .retry
+            .call(|| download_file_chunk(&file_chunk_location, &settings.filen))?;
+        verify_downloaded_chunk(&encrypted_bytes, file_key, file_chunk_location)
+    })
+}
+
+/// Asynchronously downloads every chunk of the specified file and verifies its AES-GCM authentication tag,
+/// without writing decrypted bytes anywhere. Useful to check whether stored file is intact.
+/// Only version 2 files are AES-GCM encrypted, so only they can be verified.
+///
+/// Chunks are verified one by one; on the first chunk which fails verification,
+/// `Error::ChunkVerificationFailed` with its index is returned.
+#[cfg(feature = "async")]
+pub async fn verify_file_async(
+    file_location: &FileLocation,
+    file_key: &SecUtf8,
+    settings: &SettingsBundle,
+) -> Result<()> {
+    for chunk_index in 0..file_location.chunks {
+        let file_chunk_location = file_location.get_file_chunk_location(chunk_index);
+        let encrypted_bytes = settings
+            .retry
+            .call_async(|| download_file_chunk_async(&file_chunk_location, &settings.filen))
+            .await?;
+        verify_downloaded_chunk(&encrypted_bytes, file_key, file_chunk_location)?;
+    }
+    Ok(())
+}
+
+fn verify_downloaded_chunk(
+    encrypted_bytes: &[u8],
+    file_key: &SecUtf8,
+    file_chunk_location: FileChunkLocation,
+) -> Result<()> {
+    crypto::verify_file_chunk(encrypted_bytes, file_key).context(ChunkVerificationFailedSnafu {
+        chunk_index: file_chunk_location.chunk_index,
+        chunk_location: file_chunk_location,
+    })
+}
+
+/// Synchronously downloads and decrypts the specified file, keeping track of downloaded chunks in a journal file
+/// at the given path. If download gets interrupted, calling this again with the same journal and writer
+/// skips chunks which were already written. Journal is removed once the whole file is downloaded.
+///
+/// Chunks are written at their offsets in the file, so writer should be seekable, like a `File`.
+/// Returns total size of encrypted chunks downloaded during this call.
+pub fn download_with_journal<W: Write + Seek>(
+    file_location: &FileLocation,
+    version: u32,
+    file_key: &SecUtf8,
+    writer: &mut W,
+    journal_path: &Path,
+    settings: &SettingsBundle,
+) -> Result<u64> {
+    let mut journal = DownloadJournal::open(journal_path, file_location.file_uuid)?;
+    let mut downloaded_length: u64 = 0;
+    for chunk_index in 0..file_location.chunks {
+        if journal.is_completed(chunk_index) {
+            continue;
+        }
+
+        let file_chunk_location = file_location.get_file_chunk_location(chunk_index);
+        let encrypted_bytes = settings
+            .retry
+            .call(|| download_file_chunk(&file_chunk_location, &settings.filen))?;
+        let decrypted_bytes = decrypt_file_chunk(&encrypted_bytes, &file_chunk_location, version, file_key)?;
+        write_chunk_at_offset(&decrypted_bytes, &file_chunk_location, writer)?;
+        journal.mark_completed(chunk_index)?;
+        downloaded_length += encrypted_bytes.len() as u64;
+    }
+
+    journal.remove()?;
+    Ok(downloaded_length)
+}
+
+/// Asynchronously downloads and decrypts the specified file, keeping track of downloaded chunks in a journal file
+/// at the given path. If download gets interrupted, calling this again with the same journal and writer
+/// skips chunks which were already written. Journal is removed once the whole file is downloaded.
+///
+/// Chunks are downloaded one by one and written at their offsets in the file, so writer should be seekable,
+/// like a `File`. Returns total size of encrypted chunks downloaded during this call.
+#[cfg(feature = "async")]
+pub async fn download_with_journal_async<W: Write + Seek + Send>(
+    file_location: &FileLocation,
+    version: u32,
+    file_key: &SecUtf8,
+    writer: &mut W,
+    journal_path: &Path,
+    settings: &SettingsBundle,
+) -> Result<u64> {
+    let mut journal = DownloadJournal::open(journal_path, file_location.file_uuid)?;
+    let mut downloaded_length: u64 = 0;
+    for chunk_index in 0..file_location.chunks {
+        if journal.is_completed(chunk_index) {
+            continue;
+        }
+
+        let file_chunk_location = file_location.get_file_chunk_location(chunk_index);
+        let encrypted_bytes = settings
+            .retry
+            .call_async(|| download_file_chunk_async(&file_chunk_location, &settings.filen))
+            .await?;
+        let decrypted_bytes = decrypt_file_chunk(&encrypted_bytes, &file_chunk_location, version, file_key)?;
+        write_chunk_at_offset(&decrypted_bytes, &file_chunk_location, writer)?;
+        journal.mark_completed(chunk_index)?;
+        downloaded_length += encrypted_bytes.len() as u64;
+    }
+
+    journal.remove()?;
+    Ok(downloaded_length)
+}
+
+fn decrypt_file_chunk(
+    encrypted_bytes: &[u8],
+    file_chunk_location: &FileChunkLocation,
+    version: u32,
+    file_key: &SecUtf8,
+) -> Result<Vec<u8>> {
+    let file_key_bytes: &[u8; 32] = file_key
+        .unsecure()
+        .as_bytes()
+        .try_into()
+        .context(InvalidFileKeySizeSnafu {})?;
+    crypto::decrypt_file_chunk(encrypted_bytes, file_key_bytes, version).context(CannotDecryptFileChunkSnafu {
+        length: encrypted_bytes.len(),
+        chunk_location: file_chunk_location.clone(),
+    })
+}
+
+/// Writes decrypted chunk at its offset in the file and flushes it, so it can be marked as completed.
+fn write_chunk_at_offset<W: Write + Seek>(
+    decrypted_bytes: &[u8],
+    file_chunk_location: &FileChunkLocation,
+    writer: &mut W,
+) -> Result<()> {
+    let offset = u64::from(file_chunk_location.chunk_index) * u64::from(FILE_CHUNK_SIZE);
+    writer.seek(SeekFrom::Start(offset)).context(CannotSeekWriterSnafu {
+        chunk_location: file_chunk_location.clone(),
+    })?;
+    writer.write_all(decrypted_bytes).context(CannotWriteFileChunkSnafu {
+        length: decrypted_bytes.len(),
+        chunk_location: file_chunk_location.clone(),
+    })?;
+    writer.flush().context(CannotFlushWriterSnafu {})
+}
+
+/// On-disk list of file chunks which were already downloaded and written. First line is file UUID,
+/// every next line is a completed chunk index.
+struct DownloadJournal<'path> {
+    path: &'path Path,
+    completed_chunks: BTreeSet<u32>,
+    file: fs::File,
+}
+
+impl<'path> DownloadJournal<'path> {
+    /// Reads journal at the given path, if it exists and belongs to the given file, and prepares it for updates.
+    fn open(path: &'path Path, file_uuid: Uuid) -> Result<Self> {
+        Self::open_io(path, file_uuid).context(CannotUpdateDownloadJournalSnafu {
+            journal_path: path.display().to_string(),
+        })
+    }
+
+    fn open_io(path: &'path Path, file_uuid: Uuid) -> io::Result<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+        let mut lines = contents.lines();
+        let journal_file_uuid = lines.next().and_then(|line| Uuid::parse_str(line.trim()).ok());
+        let completed_chunks = if journal_file_uuid == Some(file_uuid) {
+            // Last line might be written partially, but it will just fail to parse.
+            lines.filter_map(|line| line.trim().parse::<u32>().ok()).collect()
+        } else {
+            BTreeSet::new()
+        };
+
+        // Journal is rewritten to drop partially written lines and leftovers from other files.
+        let mut file = fs::File::create(path)?;
+        writeln!(file, "{}", file_uuid.as_hyphenated())?;
+        for chunk_index in &completed_chunks {
+            writeln!(file, "{}", chunk_index)?;
+        }
+        file.sync_data()?;
+        Ok(Self {
+            path,
+            completed_chunks,
+            file,
+        })
+    }
+
+    fn is_completed(&self, chunk_index: u32) -> bool {
+        self.completed_chunks.contains(&chunk_index)
+    }
+
+    fn mark_completed(&mut self, chunk_index: u32) -> Result<()> {
+        writeln!(self.file, "{}", chunk_index)
+            .and_then(|_| self.file.sync_data())
+            .context(CannotUpdateDownloadJournalSnafu {
+                journal_path: self.path.display().to_string(),
+            })?;
+        self.completed_chunks.insert(chunk_index);
+        Ok(())
+    }
+
+    fn remove(self) -> Result<()> {
+        drop(self.file);
+        fs::remove_file(self.path).context(CannotUpdateDownloadJournalSnafu {
+            journal_path: self.path.display().to_string(),
+        })
+    }
+}
+
 /// Asynchronously downloads the specified file from Filen download server defined by a region and a bucket.
 /// Returns total size of downloaded encrypted file chunks.
 /// All file chunks are downloaded and decrypted concurrently first, and then written to the provided writer.
+///
+/// If `FilenSettings::max_buffered_download_bytes` is set, chunks are instead downloaded concurrently
+/// only as long as they fit into that budget, and are written as soon as all preceding chunks are written.
 #[cfg(feature = "async")]
 pub async fn download_and_decrypt_file_async<W: Write + Send>(
     file_location: &FileLocation,
@@ -242,6 +599,18 @@ pub async fn download_and_decrypt_file_async<W: Write + Send>(
     writer: &mut std::io::BufWriter<W>,
     settings: &SettingsBundle,
 ) -> Result<u64> {
+    if let Some(budget_bytes) = settings.filen.max_buffered_download_bytes {
+        return download_and_decrypt_file_within_budget_async(
+            file_location,
+            version,
+            file_key,
+            writer,
+            settings,
+            budget_bytes,
+        )
+        .await;
+    }
+
     let download_and_decrypt_action = |batch_index: u32, batch_indices: Vec<u32>| async move {
         let batch_or_err = download_batch_async(file_location, &batch_indices, settings).await;
         match batch_or_err {
@@ -273,6 +642,165 @@ pub async fn download_and_decrypt_file_async<W: Write + Send>(
     Ok(written_batch_lengths.iter().sum::<u64>())
 }
 
+#[cfg(feature = "async")]
+async fn download_and_decrypt_file_within_budget_async<W: Write + Send>(
+    file_location: &FileLocation,
+    version: u32,
+    file_key: &SecUtf8,
+    writer: &mut std::io::BufWriter<W>,
+    settings: &SettingsBundle,
+    budget_bytes: usize,
+) -> Result<u64> {
+    let file_key_bytes: &[u8; 32] = file_key
+        .unsecure()
+        .as_bytes()
+        .try_into()
+        .context(InvalidFileKeySizeSnafu {})?;
+    let download = |chunk_index: u32| async move {
+        let file_chunk_location = file_location.get_file_chunk_location(chunk_index);
+        settings
+            .retry
+            .call_async(|| download_file_chunk_async(&file_chunk_location, &settings.filen))
+            .await
+    };
+    let decrypt = |chunk_index: u32, encrypted_bytes: &[u8]| {
+        crypto::decrypt_file_chunk(encrypted_bytes, file_key_bytes, version).context(CannotDecryptFileChunkSnafu {
+            length: encrypted_bytes.len(),
+            chunk_location: file_location.get_file_chunk_location(chunk_index),
+        })
+    };
+    let mut encrypted_total: u64 = 0;
+    process_chunks_in_order_within_budget(
+        file_location.chunks,
+        budget_bytes,
+        download,
+        decrypt,
+        |chunk_index, decrypted_bytes: Vec<u8>, encrypted_length| {
+            encrypted_total += encrypted_length as u64;
+            writer.write_all(&decrypted_bytes).context(CannotWriteFileChunkSnafu {
+                length: decrypted_bytes.len(),
+                chunk_location: file_location.get_file_chunk_location(chunk_index),
+            })
+        },
+    )
+    .await?;
+
+    writer.flush().context(CannotFlushWriterSnafu {})?;
+    Ok(encrypted_total)
+}
+
+/// Downloads chunks concurrently, decrypts them and passes decrypted chunks to `consume` in chunk index order,
+/// charging real sizes of chunk buffers against `budget_bytes`.
+///
+/// Budget for every chunk is reserved in chunk index order before its download starts. Chunk holds it for
+/// encrypted and decrypted bytes while being decrypted, and then only for decrypted bytes until it is consumed,
+/// so a stalled early chunk cannot make later completed chunks pile up beyond the budget.
+///
+/// Chunk sizes are not known before download, so the first chunk reserves the whole budget, and every next one
+/// reserves as much as the largest chunk seen so far took. Filen splits files into equal chunks except for
+/// the last smaller one, so that is exact in practice; a chunk larger than its reservation takes the difference
+/// only if it is free right away, since waiting for it could deadlock with later chunks waiting to be consumed.
+/// Budget is capped at `u32::MAX` bytes, and a chunk larger than the budget is processed alone.
+#[cfg(feature = "async")]
+async fn process_chunks_in_order_within_budget<E, D, F, Fut, G, C>(
+    chunk_count: u32,
+    budget_bytes: usize,
+    download: F,
+    decrypt: G,
+    mut consume: C,
+) -> Result<()>
+where
+    E: AsRef<[u8]>,
+    D: AsRef<[u8]>,
+    F: Fn(u32) -> Fut,
+    Fut: std::future::Future<Output = Result<E>>,
+    G: Fn(u32, &[u8]) -> Result<D>,
+    C: FnMut(u32, D, usize) -> Result<()>,
+{
+    use futures::{StreamExt, TryStreamExt};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let budget_bytes = budget_bytes.clamp(1, u32::MAX as usize);
+    let budget = tokio::sync::Semaphore::new(budget_bytes);
+    let largest_chunk_bytes = AtomicUsize::new(budget_bytes);
+    let (budget, largest_chunk_bytes, download, decrypt) = (&budget, &largest_chunk_bytes, &download, &decrypt);
+    let chunks = futures::stream::iter(0..chunk_count)
+        .then(|chunk_index| async move {
+            let reserved_bytes = largest_chunk_bytes.load(Ordering::SeqCst);
+            (chunk_index, BudgetCharge::reserve(budget, reserved_bytes).await)
+        })
+        .map(|(chunk_index, mut charge)| async move {
+            let encrypted_bytes = download(chunk_index).await?;
+            let encrypted_length = encrypted_bytes.as_ref().len();
+            let decrypted_bytes = decrypt(chunk_index, encrypted_bytes.as_ref())?;
+            let chunk_bytes = encrypted_length + decrypted_bytes.as_ref().len();
+            charge.try_grow_to(chunk_bytes);
+            if chunk_index == 0 {
+                largest_chunk_bytes.store(chunk_bytes.clamp(1, budget_bytes), Ordering::SeqCst);
+            } else {
+                largest_chunk_bytes.fetch_max(chunk_bytes.min(budget_bytes), Ordering::SeqCst);
+            }
+            drop(encrypted_bytes);
+            charge.shrink_to(decrypted_bytes.as_ref().len());
+            Ok((chunk_index, decrypted_bytes, encrypted_length, charge))
+        })
+        .buffered(chunk_count.max(1) as usize);
+    futures::pin_mut!(chunks);
+    while let Some((chunk_index, decrypted_bytes, encrypted_length, charge)) = chunks.try_next().await? {
+        consume(chunk_index, decrypted_bytes, encrypted_length)?;
+        drop(charge);
+    }
+    Ok(())
+}
+
+/// Bytes of download budget held by a single chunk, returned to the budget when dropped.
+#[cfg(feature = "async")]
+struct BudgetCharge<'budget> {
+    budget: &'budget tokio::sync::Semaphore,
+    bytes: usize,
+}
+
+#[cfg(feature = "async")]
+impl<'budget> BudgetCharge<'budget> {
+    /// Waits until the given amount of bytes is free, which should not exceed total budget.
+    async fn reserve(budget: &'budget tokio::sync::Semaphore, bytes: usize) -> BudgetCharge<'budget> {
+        #[allow(clippy::expect_used)]
+        budget
+            .acquire_many(bytes as u32)
+            .await
+            .expect("download budget semaphore should never be closed")
+            .forget();
+        Self { budget, bytes }
+    }
+
+    /// Takes more bytes up to the given amount, if they are free right now.
+    fn try_grow_to(&mut self, bytes: usize) {
+        let missing_bytes = bytes.saturating_sub(self.bytes);
+        let missing_permits = u32::try_from(missing_bytes).unwrap_or(u32::MAX);
+        if missing_bytes > 0 {
+            if let Ok(permit) = self.budget.try_acquire_many(missing_permits) {
+                permit.forget();
+                self.bytes += missing_permits as usize;
+            }
+        }
+    }
+
+    /// Returns bytes above the given amount to the budget.
+    fn shrink_to(&mut self, bytes: usize) {
+        if bytes < self.bytes {
+            self.budget.add_permits(self.bytes - bytes);
+            self.bytes = bytes;
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl Drop for BudgetCharge<'_> {
+    fn drop(&mut self) {
+        self.budget.add_permits(self.bytes);
+    }
+}
+
 /// Writes batch of file chunks to the given writer and returns total size of passed encrypted batch.
 /// If one write in the batch fails, entire batch fails.
 #[cfg(feature = "async")]
@@ -363,3 +891,411 @@ fn batch_chunks(file_chunk_count: u32, batch_size: usize) -> Vec<Vec<u32>> {
     let chunk_indicies: Vec<u32> = (0..file_chunk_count).collect();
     chunk_indicies.chunks(batch_size).map(|slice| slice.to_vec()).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::init_server;
+    use httpmock::Method::{GET, HEAD};
+    #[cfg(feature = "async")]
+    use std::time::{Duration, Instant};
+    use std::{
+        io::{BufRead, BufReader},
+        net::TcpListener,
+        thread,
+    };
+    use url::Url;
+
+    /// Starts a server which answers a single request with a chunk body cut short of its `Content-Length`.
+    fn start_truncating_server(announced_length: usize, sent_length: usize) -> FilenSettings {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server_url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", announced_length);
+            stream.write_all(head.as_bytes()).unwrap();
+            stream.write_all(&vec![b'x'; sent_length]).unwrap();
+        });
+        FilenSettings {
+            download_servers: vec![server_url],
+            ..FilenSettings::default()
+        }
+    }
+
+    #[test]
+    fn download_file_chunk_should_fail_with_truncated_chunk_for_short_body() {
+        let filen_settings = start_truncating_server(1024, 100);
+        let file_chunk_location = FileChunkLocation::new("de-1", "filen-1", Uuid::nil(), 3);
+
+        let result = download_file_chunk(&file_chunk_location, &filen_settings);
+
+        assert!(matches!(
+            result,
+            Err(Error::TruncatedChunk {
+                index: 3,
+                got: 100,
+                expected: 1024
+            })
+        ));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn download_file_chunk_async_should_fail_with_truncated_chunk_for_short_body() {
+        let filen_settings = start_truncating_server(1024, 100);
+        let file_chunk_location = FileChunkLocation::new("de-1", "filen-1", Uuid::nil(), 3);
+
+        let result = download_file_chunk_async(&file_chunk_location, &filen_settings).await;
+
+        assert!(matches!(
+            result,
+            Err(Error::TruncatedChunk {
+                index: 3,
+                got: 100,
+                expected: 1024
+            })
+        ));
+    }
+
+    const FILE_KEY: &[u8; 32] = b"sh1YRHfx22Ij40tQBbt6BgpBlqkzch8Y";
+
+    /// Chunk buffer which keeps track of total size of live buffers and its maximum.
+    #[cfg(feature = "async")]
+    struct TrackedBuffer {
+        bytes: Vec<u8>,
+        live_bytes: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    #[cfg(feature = "async")]
+    impl TrackedBuffer {
+        fn new(
+            length: usize,
+            live_bytes: &std::sync::Arc<std::sync::atomic::AtomicUsize>,
+            max_live_bytes: &std::sync::Arc<std::sync::atomic::AtomicUsize>,
+        ) -> Self {
+            use std::sync::atomic::Ordering;
+            let now_live = live_bytes.fetch_add(length, Ordering::SeqCst) + length;
+            max_live_bytes.fetch_max(now_live, Ordering::SeqCst);
+            Self {
+                bytes: vec![0; length],
+                live_bytes: live_bytes.clone(),
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    impl AsRef<[u8]> for TrackedBuffer {
+        fn as_ref(&self) -> &[u8] {
+            &self.bytes
+        }
+    }
+
+    #[cfg(feature = "async")]
+    impl Drop for TrackedBuffer {
+        fn drop(&mut self) {
+            self.live_bytes
+                .fetch_sub(self.bytes.len(), std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn process_chunks_in_order_within_budget_should_not_buffer_more_than_budget() {
+        use std::sync::{atomic::AtomicUsize, atomic::Ordering, Arc};
+
+        // Chunks are much larger than FILE_CHUNK_SIZE, and decrypted ones are only a bit smaller than encrypted.
+        const ENCRYPTED_BYTES: usize = 16 * 1024 * 1024 + 28;
+        const DECRYPTED_BYTES: usize = 16 * 1024 * 1024;
+        let budget_bytes = 3 * (ENCRYPTED_BYTES + DECRYPTED_BYTES) + DECRYPTED_BYTES / 2;
+        let live_bytes = Arc::new(AtomicUsize::new(0));
+        let max_live_bytes = Arc::new(AtomicUsize::new(0));
+        let mut consumed_indices = Vec::new();
+
+        let download = |chunk_index: u32| {
+            let (live_bytes, max_live_bytes) = (live_bytes.clone(), max_live_bytes.clone());
+            async move {
+                // Third chunk stalls, so later chunks complete before it.
+                let delay = if chunk_index == 2 { 100 } else { 1 };
+                tokio::time::sleep(Duration::from_millis(delay)).await;
+                Ok(TrackedBuffer::new(ENCRYPTED_BYTES, &live_bytes, &max_live_bytes))
+            }
+        };
+        let decrypt = |_, encrypted: &[u8]| {
+            assert_eq!(encrypted.len(), ENCRYPTED_BYTES);
+            Ok(TrackedBuffer::new(DECRYPTED_BYTES, &live_bytes, &max_live_bytes))
+        };
+        process_chunks_in_order_within_budget(
+            12,
+            budget_bytes,
+            download,
+            decrypt,
+            |chunk_index, decrypted: TrackedBuffer, encrypted_length| {
+                assert_eq!(decrypted.as_ref().len(), DECRYPTED_BYTES);
+                assert_eq!(encrypted_length, ENCRYPTED_BYTES);
+                consumed_indices.push(chunk_index);
+                Ok(())
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(consumed_indices, (0..12).collect::<Vec<u32>>());
+        assert_eq!(live_bytes.load(Ordering::SeqCst), 0);
+        let max_live_bytes = max_live_bytes.load(Ordering::SeqCst);
+        assert!(max_live_bytes <= budget_bytes, "{} > {}", max_live_bytes, budget_bytes);
+        // While the third chunk stalls, two more chunks are downloaded and decrypted behind it.
+        assert!(max_live_bytes >= ENCRYPTED_BYTES + 3 * DECRYPTED_BYTES);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn download_and_decrypt_file_async_should_write_chunks_in_order_within_budget() {
+        let (server, mut filen_settings) = init_server();
+        filen_settings.max_buffered_download_bytes = Some(2 * FILE_CHUNK_SIZE as usize);
+        let settings = SettingsBundle {
+            filen: filen_settings,
+            ..SettingsBundle::default()
+        };
+        let (file_location, journal_path, chunk_mocks) = setup_journaled_download(&server);
+        fs::remove_file(&journal_path).unwrap();
+        let file_key = SecUtf8::from(String::from_utf8_lossy(FILE_KEY).to_string());
+        let mut writer = io::BufWriter::new(Vec::new());
+
+        let result = download_and_decrypt_file_async(&file_location, 2, &file_key, &mut writer, &settings).await;
+
+        assert!(result.unwrap() > 0);
+        chunk_mocks.iter().for_each(|mock| mock.assert_hits(1));
+        assert_eq!(writer.into_inner().unwrap(), b"chunk 0chunk 1chunk 2");
+    }
+
+    /// Mocks three file chunks and a journal which says first two of them were already downloaded.
+    fn setup_journaled_download(
+        server: &httpmock::MockServer,
+    ) -> (FileLocation, std::path::PathBuf, Vec<httpmock::Mock<'_>>) {
+        let file_location = FileLocation::new("de-1", "filen-1", Uuid::new_v4(), 3);
+        let chunk_mocks = (0..file_location.chunks)
+            .map(|chunk_index| {
+                let encrypted_chunk =
+                    crypto::encrypt_file_chunk(format!("chunk {}", chunk_index).as_bytes(), FILE_KEY, 2)
+                        .unwrap()
+                        .chars()
+                        .map(|c| c as u8)
+                        .collect::<Vec<u8>>();
+                let chunk_path = format!("/de-1/filen-1/{}/{}", file_location.file_uuid, chunk_index);
+                server.mock(|when, then| {
+                    when.method(GET).path(chunk_path);
+                    then.status(200).body(encrypted_chunk);
+                })
+            })
+            .collect::<Vec<httpmock::Mock>>();
+        let journal_path =
+            std::env::temp_dir().join(format!("rust_filen_{}.journal", utils::random_alphanumeric_string(8)));
+        fs::write(&journal_path, format!("{}\n0\n1\n", file_location.file_uuid)).unwrap();
+        (file_location, journal_path, chunk_mocks)
+    }
+
+    /// Mocks three version 2 file chunks, with the middle one corrupted.
+    fn setup_file_with_corrupt_chunk(server: &httpmock::MockServer) -> (FileLocation, Vec<httpmock::Mock<'_>>) {
+        let file_location = FileLocation::new("de-1", "filen-1", Uuid::new_v4(), 3);
+        let chunk_mocks = (0..file_location.chunks)
+            .map(|chunk_index| {
+                let mut encrypted_chunk =
+                    crypto::encrypt_file_chunk(format!("chunk {}", chunk_index).as_bytes(), FILE_KEY, 2)
+                        .unwrap()
+                        .chars()
+                        .map(|c| c as u8)
+                        .collect::<Vec<u8>>();
+                if chunk_index == 1 {
+                    let last = encrypted_chunk.len() - 1;
+                    encrypted_chunk[last] ^= 0x01;
+                }
+                let chunk_path = format!("/de-1/filen-1/{}/{}", file_location.file_uuid, chunk_index);
+                server.mock(|when, then| {
+                    when.method(GET).path(chunk_path);
+                    then.status(200).body(encrypted_chunk);
+                })
+            })
+            .collect::<Vec<httpmock::Mock>>();
+        (file_location, chunk_mocks)
+    }
+
+    /// Mocks file which metadata says has 3 chunks, while the server has only the first 2 of them.
+    fn setup_file_with_missing_last_chunk(server: &httpmock::MockServer) -> (FileLocation, Vec<httpmock::Mock<'_>>) {
+        let file_location = FileLocation::new("de-1", "filen-1", Uuid::nil(), 3);
+        let chunk_mocks = (0..3)
+            .map(|chunk_index| {
+                server.mock(|when, then| {
+                    when.method(HEAD)
+                        .path(format!("/de-1/filen-1/{}/{}", Uuid::nil(), chunk_index));
+                    then.status(if chunk_index < 2 { 200 } else { 404 });
+                })
+            })
+            .collect();
+        (file_location, chunk_mocks)
+    }
+
+    #[test]
+    fn probe_file_chunk_count_should_report_chunks_actually_stored_on_server() {
+        let (server, filen_settings) = init_server();
+        let settings = SettingsBundle {
+            filen: filen_settings,
+            ..SettingsBundle::default()
+        };
+        let (file_location, chunk_mocks) = setup_file_with_missing_last_chunk(&server);
+
+        let result = probe_file_chunk_count(&file_location, &settings);
+
+        assert_eq!(result.unwrap(), 2);
+        chunk_mocks[0].assert_hits(0);
+        chunk_mocks[1].assert_hits(1);
+        chunk_mocks[2].assert_hits(1);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn probe_file_chunk_count_async_should_report_chunks_actually_stored_on_server() {
+        let (server, filen_settings) = init_server();
+        let settings = SettingsBundle {
+            filen: filen_settings,
+            ..SettingsBundle::default()
+        };
+        let (file_location, chunk_mocks) = setup_file_with_missing_last_chunk(&server);
+
+        let result = probe_file_chunk_count_async(&file_location, &settings).await;
+
+        assert_eq!(result.unwrap(), 2);
+        chunk_mocks[0].assert_hits(0);
+        chunk_mocks[1].assert_hits(1);
+        chunk_mocks[2].assert_hits(1);
+    }
+
+    #[test]
+    fn verify_file_should_return_index_of_first_corrupt_chunk() {
+        let (server, filen_settings) = init_server();
+        let settings = SettingsBundle {
+            filen: filen_settings,
+            ..SettingsBundle::default()
+        };
+        let (file_location, chunk_mocks) = setup_file_with_corrupt_chunk(&server);
+        let file_key = SecUtf8::from(String::from_utf8_lossy(FILE_KEY).to_string());
+
+        let result = verify_file(&file_location, &file_key, &settings);
+
+        assert!(matches!(
+            result,
+            Err(Error::ChunkVerificationFailed { chunk_index: 1, .. })
+        ));
+        chunk_mocks[0].assert_hits(1);
+        chunk_mocks[1].assert_hits(1);
+        chunk_mocks[2].assert_hits(0);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn verify_file_async_should_return_index_of_first_corrupt_chunk() {
+        let (server, filen_settings) = init_server();
+        let settings = SettingsBundle {
+            filen: filen_settings,
+            ..SettingsBundle::default()
+        };
+        let (file_location, chunk_mocks) = setup_file_with_corrupt_chunk(&server);
+        let file_key = SecUtf8::from(String::from_utf8_lossy(FILE_KEY).to_string());
+
+        let result = verify_file_async(&file_location, &file_key, &settings).await;
+
+        assert!(matches!(
+            result,
+            Err(Error::ChunkVerificationFailed { chunk_index: 1, .. })
+        ));
+        chunk_mocks[0].assert_hits(1);
+        chunk_mocks[1].assert_hits(1);
+        chunk_mocks[2].assert_hits(0);
+    }
+
+    fn assert_only_last_chunk_written(written: &[u8], chunk_mocks: &[httpmock::Mock], journal_path: &Path) {
+        chunk_mocks[0].assert_hits(0);
+        chunk_mocks[1].assert_hits(0);
+        chunk_mocks[2].assert_hits(1);
+        let last_chunk_offset = 2 * FILE_CHUNK_SIZE as usize;
+        assert_eq!(&written[last_chunk_offset..], b"chunk 2");
+        assert!(!journal_path.exists());
+    }
+
+    #[test]
+    fn download_with_journal_should_skip_completed_chunks() {
+        let (server, filen_settings) = init_server();
+        let settings = SettingsBundle {
+            filen: filen_settings,
+            ..SettingsBundle::default()
+        };
+        let (file_location, journal_path, chunk_mocks) = setup_journaled_download(&server);
+        let file_key = SecUtf8::from(String::from_utf8_lossy(FILE_KEY).to_string());
+        let mut writer = io::Cursor::new(Vec::new());
+
+        let result = download_with_journal(&file_location, 2, &file_key, &mut writer, &journal_path, &settings);
+
+        assert!(result.unwrap() > 0);
+        assert_only_last_chunk_written(writer.get_ref(), &chunk_mocks, &journal_path);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn download_with_journal_async_should_skip_completed_chunks() {
+        let (server, filen_settings) = init_server();
+        let settings = SettingsBundle {
+            filen: filen_settings,
+            ..SettingsBundle::default()
+        };
+        let (file_location, journal_path, chunk_mocks) = setup_journaled_download(&server);
+        let file_key = SecUtf8::from(String::from_utf8_lossy(FILE_KEY).to_string());
+        let mut writer = io::Cursor::new(Vec::new());
+
+        let result =
+            download_with_journal_async(&file_location, 2, &file_key, &mut writer, &journal_path, &settings).await;
+
+        assert!(result.unwrap() > 0);
+        assert_only_last_chunk_written(writer.get_ref(), &chunk_mocks, &journal_path);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn download_file_chunk_async_should_respect_max_concurrent_chunks_across_transfers() {
+        let (server, mut filen_settings) = init_server();
+        filen_settings.max_concurrent_chunks = Some(1);
+        let chunk_delay = Duration::from_millis(150);
+        let mock = server.mock(|when, then| {
+            when.method(GET).path_contains("/de-1/filen-1/");
+            then.status(200).body("chunk").delay(chunk_delay);
+        });
+        let transfer = |file_uuid: Uuid, filen_settings: FilenSettings| async move {
+            let file_location = FileLocation::new("de-1", "filen-1", file_uuid, 2);
+            let chunk_downloads = (0..file_location.chunks).map(|chunk_index| {
+                let file_chunk_location = file_location.get_file_chunk_location(chunk_index);
+                let filen_settings = &filen_settings;
+                async move { download_file_chunk_async(&file_chunk_location, filen_settings).await }
+            });
+            futures::future::try_join_all(chunk_downloads).await
+        };
+
+        let started = Instant::now();
+        let (first_result, second_result) = futures::join!(
+            transfer(Uuid::new_v4(), filen_settings.clone()),
+            transfer(Uuid::new_v4(), filen_settings.clone())
+        );
+        let elapsed = started.elapsed();
+
+        assert!(first_result.is_ok());
+        assert!(second_result.is_ok());
+        mock.assert_hits(4);
+        assert!(
+            elapsed >= chunk_delay * 4,
+            "chunks were downloaded concurrently in {:?}",
+            elapsed
+        );
+    }
+}
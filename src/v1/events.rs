@@ -830,6 +830,50 @@ pub enum UserEvent {
     Unknown(PlainUserEvent),
 }
 
+impl UserEvent {
+    /// Time when the event has occured, as Unix timestamp in seconds.
+    #[must_use]
+    pub const fn timestamp(&self) -> u64 {
+        match self {
+            Self::BaseFolderCreated(event) => event.timestamp,
+            Self::CodeRedeemed(event) => event.timestamp,
+            Self::DeleteAll(event) => event.timestamp,
+            Self::DeleteUnfinished(event) => event.timestamp,
+            Self::DeleteVersioned(event) => event.timestamp,
+            Self::Disabled2FA(event) => event.timestamp,
+            Self::EmailChangeAttempt(event) => event.timestamp,
+            Self::EmailChanged(event) => event.timestamp,
+            Self::Enabled2FA(event) => event.timestamp,
+            Self::FileLinkEdited(event) => event.timestamp,
+            Self::FileMoved(event) => event.timestamp,
+            Self::FileRenamed(event) => event.timestamp,
+            Self::FileRestored(event) => event.timestamp,
+            Self::FileRm(event) => event.timestamp,
+            Self::FileShared(event) => event.timestamp,
+            Self::FileTrash(event) => event.timestamp,
+            Self::FileUploaded(event) => event.timestamp,
+            Self::FileVersioned(event) => event.timestamp,
+            Self::FolderColorChanged(event) => event.timestamp,
+            Self::FolderLinkEdited(event) => event.timestamp,
+            Self::FolderMoved(event) => event.timestamp,
+            Self::FolderRenamed(event) => event.timestamp,
+            Self::FolderRestored(event) => event.timestamp,
+            Self::FolderShared(event) => event.timestamp,
+            Self::FolderTrash(event) => event.timestamp,
+            Self::ItemFavorite(event) => event.timestamp,
+            Self::Login(event) => event.timestamp,
+            Self::PasswordChanged(event) => event.timestamp,
+            Self::RemovedSharedInItems(event) => event.timestamp,
+            Self::RemovedSharedOutItems(event) => event.timestamp,
+            Self::SubFolderCreated(event) => event.timestamp,
+            Self::RequestAccountDeletion(event) => event.timestamp,
+            Self::TrashEmptied(event) => event.timestamp,
+            Self::VersionedFileRestored(event) => event.timestamp,
+            Self::Unknown(event) => event.timestamp,
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub(crate) struct UserEventDeserializeHelper {
     pub id: u64,
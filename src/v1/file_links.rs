@@ -1,30 +1,57 @@
+#![allow(clippy::redundant_pub_crate)]
+#[cfg(feature = "async")]
+use crate::v1::download_and_decrypt_file_async;
 use crate::{
     queries, utils,
     v1::{
-        crypto, response_payload, DownloadBtnState, DownloadBtnStateByte, Expire, PasswordState, PlainResponsePayload,
-        SEC_LINK_EMPTY_PASSWORD_VALUE,
+        crypto, dir_links::is_link_password_hash_set, download_and_decrypt_file,
+        download_dir::gen_download_and_decrypt_file, download_file, response_payload, DownloadBtnState,
+        DownloadBtnStateByte, Expire, FileStorageInfo, FilenResponse, HasFileLocation, HasUuid, PasswordState,
+        PlainResponsePayload, LINK_EMPTY_PASSWORD_HASH, SEC_LINK_EMPTY_PASSWORD_VALUE,
     },
-    FilenSettings,
+    FilenSettings, SettingsBundle,
 };
 use secstr::SecUtf8;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
-use snafu::{ResultExt, Snafu};
+use snafu::{Backtrace, ResultExt, Snafu};
+use std::{io::Write, str::FromStr};
 use strum::{Display, EnumString};
+use url::Url;
 use uuid::Uuid;
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
 const LINK_EDIT_PATH: &str = "/v1/link/edit";
+const LINK_INFO_PATH: &str = "/v1/link/info";
 const LINK_STATUS_PATH: &str = "/v1/link/status";
 
 #[derive(Snafu, Debug)]
 pub enum Error {
+    #[snafu(display("'{}' is not a valid public link URL: {}", url, message))]
+    BadPublicLinkUrl {
+        url: String,
+        message: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("{}", source))]
+    DownloadLinkedFileFailed { source: download_file::Error },
+
     #[snafu(display("{} query failed: {}", LINK_EDIT_PATH, source))]
     LinkEditQueryFailed { source: queries::Error },
 
+    #[snafu(display("{} query failed: {}", LINK_INFO_PATH, source))]
+    LinkInfoQueryFailed { source: queries::Error },
+
+    #[snafu(display("Filen refused to give link info: {}", source))]
+    LinkInfoRefused { source: crate::v1::Error },
+
     #[snafu(display("{} query failed: {}", LINK_STATUS_PATH, source))]
     LinkStatusQueryFailed { source: queries::Error },
+
+    #[snafu(display("Link {} is protected by password, but no password was given", link_uuid))]
+    PasswordRequired { link_uuid: Uuid, backtrace: Backtrace },
 }
 
 /// Determines public link state.
@@ -175,14 +202,149 @@ pub struct LinkStatusResponseData {
 
     /// Link password hash in hex string form, or None if no password was set by user or if link is disabled.
     pub password: Option<String>,
+
+    /// Response fields unknown to this crate, preserved as is.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 utils::display_from_json!(LinkStatusResponseData);
 
+impl LinkStatusResponseData {
+    /// Returns true if link requires a password, so it cannot be opened without one.
+    #[must_use]
+    pub fn is_password_protected(&self) -> bool {
+        is_link_password_hash_set(self.password.as_deref())
+    }
+}
+
 response_payload!(
     /// Response for `LINK_STATUS_PATH` endpoint.
     LinkStatusResponsePayload<LinkStatusResponseData>
 );
 
+/// Public file link, as in <https://drive.filen.io/d/link-uuid#file-key>.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PublicFileLink {
+    /// Link ID; hyphenated lowercased UUID V4.
+    pub link_uuid: Uuid,
+
+    /// Key used to decrypt linked file chunks and metadata.
+    pub file_key: SecUtf8,
+}
+
+impl FromStr for PublicFileLink {
+    type Err = Error;
+
+    /// Parses public file link URL, like <https://drive.filen.io/d/link-uuid#file-key>.
+    fn from_str(url: &str) -> Result<Self> {
+        let bad_url = |message: &str| {
+            BadPublicLinkUrlSnafu {
+                url,
+                message: message.to_owned(),
+            }
+            .fail()
+        };
+        let parsed_url = match Url::parse(url) {
+            Ok(parsed_url) => parsed_url,
+            Err(_) => return bad_url("URL cannot be parsed"),
+        };
+        let link_uuid = match parsed_url.path_segments().map(Iterator::collect::<Vec<_>>).as_deref() {
+            Some(["d", link_id]) => match Uuid::parse_str(link_id) {
+                Ok(link_uuid) => link_uuid,
+                Err(_) => return bad_url("link ID is not a valid UUID"),
+            },
+            _ => return bad_url("URL path should be '/d/<link ID>'"),
+        };
+        match parsed_url.fragment() {
+            Some(file_key) if !file_key.is_empty() => Ok(Self {
+                link_uuid,
+                file_key: SecUtf8::from(file_key),
+            }),
+            _ => bad_url("URL has no file key after '#'"),
+        }
+    }
+}
+
+/// Used for requests to `LINK_INFO_PATH` endpoint.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct LinkInfoRequestPayload<'link_info> {
+    /// Link ID; hyphenated lowercased UUID V4.
+    pub uuid: Uuid,
+
+    /// Hashed link password, or `LINK_EMPTY_PASSWORD_HASH` if link has no password.
+    pub password: &'link_info str,
+}
+utils::display_from_json_with_lifetime!('link_info, LinkInfoRequestPayload);
+
+/// Response data for `LINK_INFO_PATH` endpoint.
+#[skip_serializing_none]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct LinkInfoResponseData {
+    /// Linked file ID; hyphenated lowercased UUID V4.
+    pub uuid: Uuid,
+
+    /// File name metadata, encrypted with the file key from the link URL.
+    #[serde(rename = "name")]
+    pub name_metadata: String,
+
+    /// File size metadata, encrypted with the file key from the link URL.
+    #[serde(rename = "size")]
+    pub size_metadata: String,
+
+    /// File mime type metadata, encrypted with the file key from the link URL.
+    #[serde(rename = "mime")]
+    pub mime_metadata: String,
+
+    /// Filen file storage info.
+    #[serde(flatten)]
+    pub storage: FileStorageInfo,
+
+    /// Determines how file bytes should be encrypted/decrypted.
+    pub version: u32,
+
+    /// Whether link page shows a download button.
+    #[serde(rename = "downloadBtn")]
+    pub download_btn: DownloadBtnStateByte,
+
+    /// Link password hash in hex string form, or None if no password was set by user.
+    pub password: Option<String>,
+
+    /// File creation time, as Unix timestamp in seconds.
+    pub timestamp: u64,
+
+    /// Response fields unknown to this crate, preserved as is.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+utils::display_from_json!(LinkInfoResponseData);
+
+impl HasFileLocation for LinkInfoResponseData {
+    fn file_storage_ref(&self) -> &FileStorageInfo {
+        &self.storage
+    }
+}
+
+impl HasUuid for LinkInfoResponseData {
+    fn uuid_ref(&self) -> &Uuid {
+        &self.uuid
+    }
+}
+
+impl LinkInfoResponseData {
+    /// Returns true if link requires a password, so it cannot be opened without one.
+    #[must_use]
+    pub fn is_password_protected(&self) -> bool {
+        is_link_password_hash_set(self.password.as_deref())
+    }
+
+    gen_download_and_decrypt_file!();
+}
+
+response_payload!(
+    /// Response for `LINK_INFO_PATH` endpoint.
+    LinkInfoResponsePayload<LinkInfoResponseData>
+);
+
 /// Calls `LINK_EDIT_PATH` endpoint. Used to edit given file link.
 pub fn link_edit_request(
     payload: &LinkEditRequestPayload,
@@ -202,6 +364,26 @@ pub async fn link_edit_request_async(
         .context(LinkEditQueryFailedSnafu {})
 }
 
+/// Calls `LINK_INFO_PATH` endpoint. Used to get info about publicly linked file. Does not require API key.
+pub fn link_info_request(
+    payload: &LinkInfoRequestPayload,
+    filen_settings: &FilenSettings,
+) -> Result<LinkInfoResponsePayload> {
+    queries::query_filen_api(LINK_INFO_PATH, payload, filen_settings).context(LinkInfoQueryFailedSnafu {})
+}
+
+/// Calls `LINK_INFO_PATH` endpoint asynchronously. Used to get info about publicly linked file.
+/// Does not require API key.
+#[cfg(feature = "async")]
+pub async fn link_info_request_async(
+    payload: &LinkInfoRequestPayload<'_>,
+    filen_settings: &FilenSettings,
+) -> Result<LinkInfoResponsePayload> {
+    queries::query_filen_api_async(LINK_INFO_PATH, payload, filen_settings)
+        .await
+        .context(LinkInfoQueryFailedSnafu {})
+}
+
 /// Calls `LINK_STATUS_PATH` endpoint. Used to check file link status.
 pub fn link_status_request(
     payload: &LinkStatusRequestPayload,
@@ -221,13 +403,119 @@ pub async fn link_status_request_async(
         .context(LinkStatusQueryFailedSnafu {})
 }
 
+/// Helper which downloads and decrypts a file from a public link URL without password,
+/// like <https://drive.filen.io/d/link-uuid#file-key>, writing file contents into the given writer.
+/// Returns total size of downloaded encrypted file chunks.
+///
+/// Fails with `Error::PasswordRequired` if link is protected by password,
+/// use `download_public_link_with_password` for such links.
+pub fn download_public_link<W: Write>(
+    url: &str,
+    writer: &mut std::io::BufWriter<W>,
+    settings: &SettingsBundle,
+) -> Result<u64> {
+    download_public_link_with_password(url, None, writer, settings)
+}
+
+/// Helper which downloads and decrypts a file from a public link URL,
+/// like <https://drive.filen.io/d/link-uuid#file-key>, writing file contents into the given writer.
+/// Returns total size of downloaded encrypted file chunks.
+///
+/// Fails with `Error::PasswordRequired` without downloading anything if link is protected by password,
+/// but no password was given.
+pub fn download_public_link_with_password<W: Write>(
+    url: &str,
+    password: Option<&SecUtf8>,
+    writer: &mut std::io::BufWriter<W>,
+    settings: &SettingsBundle,
+) -> Result<u64> {
+    let public_link = url.parse::<PublicFileLink>()?;
+    let password_hash = link_info_password_hash(password);
+    let link_info_payload = LinkInfoRequestPayload {
+        uuid: public_link.link_uuid,
+        password: &password_hash,
+    };
+    let link_info_response = settings
+        .retry
+        .call(|| link_info_request(&link_info_payload, &settings.filen))?;
+    let link_info = link_info_response.data_ref_or_err().context(LinkInfoRefusedSnafu {})?;
+    ensure_password_given(public_link.link_uuid, password, link_info)?;
+    link_info
+        .download_and_decrypt_file(&public_link.file_key, writer, settings)
+        .context(DownloadLinkedFileFailedSnafu {})
+}
+
+/// Helper which asynchronously downloads and decrypts a file from a public link URL without password,
+/// like <https://drive.filen.io/d/link-uuid#file-key>, writing file contents into the given writer.
+/// Returns total size of downloaded encrypted file chunks.
+///
+/// Fails with `Error::PasswordRequired` if link is protected by password,
+/// use `download_public_link_with_password_async` for such links.
+#[cfg(feature = "async")]
+pub async fn download_public_link_async<W: Write + Send>(
+    url: &str,
+    writer: &mut std::io::BufWriter<W>,
+    settings: &SettingsBundle,
+) -> Result<u64> {
+    download_public_link_with_password_async(url, None, writer, settings).await
+}
+
+/// Helper which asynchronously downloads and decrypts a file from a public link URL,
+/// like <https://drive.filen.io/d/link-uuid#file-key>, writing file contents into the given writer.
+/// Returns total size of downloaded encrypted file chunks.
+///
+/// Fails with `Error::PasswordRequired` without downloading anything if link is protected by password,
+/// but no password was given.
+#[cfg(feature = "async")]
+pub async fn download_public_link_with_password_async<W: Write + Send>(
+    url: &str,
+    password: Option<&SecUtf8>,
+    writer: &mut std::io::BufWriter<W>,
+    settings: &SettingsBundle,
+) -> Result<u64> {
+    let public_link = url.parse::<PublicFileLink>()?;
+    let password_hash = link_info_password_hash(password);
+    let link_info_payload = LinkInfoRequestPayload {
+        uuid: public_link.link_uuid,
+        password: &password_hash,
+    };
+    let link_info_response = settings
+        .retry
+        .call_async(|| link_info_request_async(&link_info_payload, &settings.filen))
+        .await?;
+    let link_info = link_info_response.data_ref_or_err().context(LinkInfoRefusedSnafu {})?;
+    ensure_password_given(public_link.link_uuid, password, link_info)?;
+    link_info
+        .download_and_decrypt_file_async(&public_link.file_key, writer, settings)
+        .await
+        .context(DownloadLinkedFileFailedSnafu {})
+}
+
+/// Hashes link password the way `LINK_INFO_PATH` endpoint expects it; no password is hashed as an empty one.
+fn link_info_password_hash(password: Option<&SecUtf8>) -> String {
+    password.map_or_else(
+        || LINK_EMPTY_PASSWORD_HASH.clone(),
+        |password| crypto::hash_fn(password.unsecure()),
+    )
+}
+
+fn ensure_password_given(link_uuid: Uuid, password: Option<&SecUtf8>, link_info: &LinkInfoResponseData) -> Result<()> {
+    if password.is_none() && link_info.is_password_protected() {
+        PasswordRequiredSnafu { link_uuid }.fail()
+    } else {
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_utils::validate_contract;
     #[cfg(feature = "async")]
     use crate::test_utils::validate_contract_async;
+    use crate::test_utils::{deserialize_from_file, init_server, setup_json_mock, validate_contract};
+    use httpmock::Method::GET;
     use once_cell::sync::Lazy;
+    use pretty_assertions::assert_eq;
     use secstr::SecUtf8;
 
     static API_KEY: Lazy<SecUtf8> =
@@ -328,4 +616,157 @@ mod tests {
         )
         .await;
     }
+
+    const FILE_KEY: &str = "sh1YRHfx22Ij40tQBbt6BgpBlqkzch8Y";
+    const LINK_UUID: &str = "ea6faf67-904e-483f-bc9b-4274c893b4f5";
+
+    #[test]
+    fn public_file_link_should_be_parsed_from_url() {
+        let url = format!("https://drive.filen.io/d/{}#{}", LINK_UUID, FILE_KEY);
+
+        let public_link = url.parse::<PublicFileLink>().unwrap();
+
+        assert_eq!(public_link.link_uuid, Uuid::parse_str(LINK_UUID).unwrap());
+        assert_eq!(public_link.file_key.unsecure(), FILE_KEY);
+    }
+
+    #[test]
+    fn public_file_link_should_not_be_parsed_from_bad_urls() {
+        let bad_urls = [
+            "drive.filen.io/d/ea6faf67-904e-483f-bc9b-4274c893b4f5#key".to_owned(),
+            format!("https://drive.filen.io/f/{}#{}", LINK_UUID, FILE_KEY),
+            format!("https://drive.filen.io/d/not-uuid#{}", FILE_KEY),
+            format!("https://drive.filen.io/d/{}", LINK_UUID),
+            format!("https://drive.filen.io/d/{}#", LINK_UUID),
+        ];
+
+        for bad_url in &bad_urls {
+            assert!(matches!(
+                bad_url.parse::<PublicFileLink>(),
+                Err(Error::BadPublicLinkUrl { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn link_info_request_should_have_proper_contract() {
+        let request_payload = LinkInfoRequestPayload {
+            uuid: Uuid::parse_str(LINK_UUID).unwrap(),
+            password: &LINK_EMPTY_PASSWORD_HASH,
+        };
+        validate_contract(
+            LINK_INFO_PATH,
+            request_payload,
+            "tests/resources/responses/link_info.json",
+            |request_payload, filen_settings| link_info_request(&request_payload, &filen_settings),
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn link_info_request_async_should_have_proper_contract() {
+        let request_payload = LinkInfoRequestPayload {
+            uuid: Uuid::parse_str(LINK_UUID).unwrap(),
+            password: &LINK_EMPTY_PASSWORD_HASH,
+        };
+        validate_contract_async(
+            LINK_INFO_PATH,
+            request_payload,
+            "tests/resources/responses/link_info.json",
+            |request_payload, filen_settings| async move {
+                link_info_request_async(&request_payload, &filen_settings).await
+            },
+        )
+        .await;
+    }
+
+    #[test]
+    fn download_public_link_should_download_linked_file() {
+        let (server, filen_settings) = init_server();
+        let link_info_response: LinkInfoResponsePayload =
+            deserialize_from_file("tests/resources/responses/link_info.json");
+        let link_info_mock = setup_json_mock(
+            LINK_INFO_PATH,
+            &LinkInfoRequestPayload {
+                uuid: Uuid::parse_str(LINK_UUID).unwrap(),
+                password: &LINK_EMPTY_PASSWORD_HASH,
+            },
+            &link_info_response,
+            &server,
+        );
+        let encrypted_chunk =
+            crypto::encrypt_file_chunk(b"Linked file contents", b"sh1YRHfx22Ij40tQBbt6BgpBlqkzch8Y", 2)
+                .unwrap()
+                .chars()
+                .map(|c| c as u8)
+                .collect::<Vec<u8>>();
+        let chunk_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/de-1/filen-1/b5ec90d2-957c-4481-b211-08a68accd1b2/0");
+            then.status(200).body(encrypted_chunk);
+        });
+        let settings = SettingsBundle {
+            filen: filen_settings,
+            ..SettingsBundle::default()
+        };
+        let mut writer = std::io::BufWriter::new(Vec::new());
+
+        let download_result = download_public_link(
+            &format!("https://drive.filen.io/d/{}#{}", LINK_UUID, FILE_KEY),
+            &mut writer,
+            &settings,
+        );
+
+        link_info_mock.assert_hits(1);
+        chunk_mock.assert_hits(1);
+        assert!(download_result.is_ok());
+        assert_eq!(writer.into_inner().unwrap(), b"Linked file contents");
+    }
+
+    #[test]
+    fn download_public_link_should_require_password_for_protected_link() {
+        let (server, filen_settings) = init_server();
+        let mut link_info_response: LinkInfoResponsePayload =
+            deserialize_from_file("tests/resources/responses/link_info.json");
+        link_info_response.data.as_mut().unwrap().password = Some(crypto::hash_fn("secret"));
+        let link_info_mock = setup_json_mock(
+            LINK_INFO_PATH,
+            &LinkInfoRequestPayload {
+                uuid: Uuid::parse_str(LINK_UUID).unwrap(),
+                password: &LINK_EMPTY_PASSWORD_HASH,
+            },
+            &link_info_response,
+            &server,
+        );
+        let chunk_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/de-1/filen-1/b5ec90d2-957c-4481-b211-08a68accd1b2/0");
+            then.status(200);
+        });
+        let settings = SettingsBundle {
+            filen: filen_settings,
+            ..SettingsBundle::default()
+        };
+        let mut writer = std::io::BufWriter::new(Vec::new());
+
+        let download_result = download_public_link(
+            &format!("https://drive.filen.io/d/{}#{}", LINK_UUID, FILE_KEY),
+            &mut writer,
+            &settings,
+        );
+
+        link_info_mock.assert_hits(1);
+        chunk_mock.assert_hits(0);
+        assert!(matches!(download_result, Err(Error::PasswordRequired { .. })));
+    }
+
+    #[test]
+    fn link_info_response_should_tell_if_link_is_password_protected() {
+        let open: LinkInfoResponsePayload = deserialize_from_file("tests/resources/responses/link_info.json");
+        let mut protected = open.data.clone().unwrap();
+        protected.password = Some(crypto::hash_fn("secret"));
+
+        assert!(!open.data.unwrap().is_password_protected());
+        assert!(protected.is_password_protected());
+    }
 }
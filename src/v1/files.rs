@@ -5,10 +5,12 @@ use std::{
 };
 
 use crate::{
-    crypto, queries, utils,
+    crypto::{self, MetadataVersion},
+    queries,
+    utils::{self, Base64Padding},
     v1::{
-        response_payload, DirContentFile, LocationExistsRequestPayload, LocationExistsResponsePayload,
-        LocationNameMetadata, LocationTrashRequestPayload, PlainResponsePayload, METADATA_VERSION,
+        response_payload, DirContentFile, FileKey, LocationExistsRequestPayload, LocationExistsResponsePayload,
+        LocationNameMetadata, LocationTrashRequestPayload, PlainResponsePayload,
     },
     FilenSettings,
 };
@@ -126,11 +128,12 @@ impl FileProperties {
     /// * `size` - File size in bytes.
     /// * `last_modified` - Last modification time as reported by file system.
     /// * `file_key` - Optional file key to use. If none, random alphanumeric string (32 chars) will be used.
+    ///   Pass existing file key when re-uploading a known file, so its chunks are encrypted with the same key.
     pub fn from_name_size_modified_key(
         name: &str,
         size: u64,
         last_modified: &SystemTime,
-        file_key: Option<SecUtf8>,
+        file_key: Option<FileKey>,
     ) -> Result<Self> {
         ensure!(
             size > 0,
@@ -149,7 +152,7 @@ impl FileProperties {
             name: name.to_owned(),
             size,
             mime: mime.to_owned(),
-            key: file_key.unwrap_or_else(|| SecUtf8::from(utils::random_alphanumeric_string(32))),
+            key: file_key.unwrap_or_else(FileKey::generate).into(),
             last_modified: last_modified_secs,
         })
     }
@@ -170,9 +173,35 @@ impl FileProperties {
     /// Fills file properties from local file properties, with a way to change file name.
     /// File key will be randomly generated.
     pub fn from_name_and_local_path(filen_filename: &str, local_file_path: &Path) -> Result<Self> {
+        Self::from_name_local_path_key(filen_filename, local_file_path, None)
+    }
+
+    /// Fills file properties from local file properties, with a way to change file name and to use
+    /// an existing file key. If file key is None, it will be randomly generated.
+    pub fn from_name_local_path_key(
+        filen_filename: &str,
+        local_file_path: &Path,
+        file_key: Option<FileKey>,
+    ) -> Result<Self> {
         let fs_metadata = fs::metadata(local_file_path).context(FileSystemMetadataSnafu {})?;
         let last_modified_time = fs_metadata.modified().unwrap_or_else(|_| SystemTime::now());
-        Self::from_name_size_modified(filen_filename, fs_metadata.len(), &last_modified_time)
+        Self::from_name_size_modified_key(filen_filename, fs_metadata.len(), &last_modified_time, file_key)
+    }
+
+    /// Fills file properties from local file properties like `from_local_path` does, and encrypts them
+    /// to a metadata string of the specified version, ready to be sent along with uploaded file.
+    ///
+    /// File size and 'last modified' timestamp are read from the file system; if file system cannot provide
+    /// modification time, current time is used instead. File key will be randomly generated, so returned
+    /// properties should be used to encrypt file chunks.
+    pub fn metadata_from_local_path(
+        local_file_path: &Path,
+        last_master_key: &SecUtf8,
+        metadata_version: MetadataVersion,
+    ) -> Result<(Self, String)> {
+        let file_properties = Self::from_local_path(local_file_path)?;
+        let metadata = file_properties.to_metadata_string_with_version(last_master_key, metadata_version);
+        Ok((file_properties, metadata))
     }
 
     /// Decrypts file properties from metadata string.
@@ -189,18 +218,28 @@ impl FileProperties {
     }
 
     /// Encrypts file properties to a metadata string.
-    #[allow(clippy::missing_panics_doc)]
     #[must_use]
     pub fn encrypt_file_metadata(file_properties: &Self, last_master_key: &SecUtf8) -> String {
+        Self::encrypt_file_metadata_with_version(file_properties, last_master_key, MetadataVersion::default())
+    }
+
+    /// Encrypts file properties to a metadata string of the specified version.
+    #[allow(clippy::missing_panics_doc)]
+    #[must_use]
+    pub fn encrypt_file_metadata_with_version(
+        file_properties: &Self,
+        last_master_key: &SecUtf8,
+        metadata_version: MetadataVersion,
+    ) -> String {
         let metadata_json = json!(file_properties).to_string();
-        // Cannot panic due to the way encrypt_metadata_str is implemented.
-        crypto::encrypt_metadata_str(&metadata_json, last_master_key, METADATA_VERSION).unwrap()
+        // Cannot panic, since MetadataVersion is supported by definition.
+        crypto::encrypt_metadata_str(&metadata_json, last_master_key, metadata_version.into()).unwrap()
     }
 
     /// Decrypts file properties from a metadata string using RSA private key.
     /// Assumes given metadata string is base64-encoded.
     pub fn decrypt_file_metadata_rsa(metadata: &str, rsa_private_key_bytes: &SecVec<u8>) -> Result<Self> {
-        let decoded = base64::decode(metadata).context(CannotDecodeBase64MetadataSnafu {
+        let decoded = utils::base64_decode(metadata).context(CannotDecodeBase64MetadataSnafu {
             metadata: metadata.to_owned(),
         })?;
         let decrypted = crypto::decrypt_rsa(&decoded, rsa_private_key_bytes.unsecure()).context(
@@ -222,7 +261,7 @@ impl FileProperties {
                 metadata: metadata_json,
             },
         )?;
-        Ok(base64::encode(&encrypted))
+        Ok(utils::base64_encode(&encrypted, Base64Padding::Padded))
     }
 
     #[must_use]
@@ -230,29 +269,53 @@ impl FileProperties {
         Self::encrypt_file_metadata(self, last_master_key)
     }
 
+    #[must_use]
+    pub fn to_metadata_string_with_version(
+        &self,
+        last_master_key: &SecUtf8,
+        metadata_version: MetadataVersion,
+    ) -> String {
+        Self::encrypt_file_metadata_with_version(self, last_master_key, metadata_version)
+    }
+
     pub fn to_metadata_rsa_string(&self, rsa_public_key_bytes: &[u8]) -> Result<String> {
         Self::encrypt_file_metadata_rsa(self, rsa_public_key_bytes)
     }
 
-    #[allow(clippy::missing_panics_doc)]
     #[must_use]
     pub fn name_encrypted(&self) -> String {
-        // Cannot panic due to the way encrypt_metadata_str is implemented.
-        crypto::encrypt_metadata_str(&self.name, &self.key, METADATA_VERSION).unwrap()
+        self.name_encrypted_with_version(MetadataVersion::default())
     }
 
-    #[allow(clippy::missing_panics_doc)]
     #[must_use]
     pub fn size_encrypted(&self) -> String {
-        // Cannot panic due to the way encrypt_metadata_str is implemented.
-        crypto::encrypt_metadata_str(&self.size.to_string(), &self.key, METADATA_VERSION).unwrap()
+        self.size_encrypted_with_version(MetadataVersion::default())
     }
 
-    #[allow(clippy::missing_panics_doc)]
     #[must_use]
     pub fn mime_encrypted(&self) -> String {
-        // Cannot panic due to the way encrypt_metadata_str is implemented.
-        crypto::encrypt_metadata_str(&self.mime, &self.key, METADATA_VERSION).unwrap()
+        self.mime_encrypted_with_version(MetadataVersion::default())
+    }
+
+    #[allow(clippy::missing_panics_doc)]
+    #[must_use]
+    pub fn name_encrypted_with_version(&self, metadata_version: MetadataVersion) -> String {
+        // Cannot panic, since MetadataVersion is supported by definition.
+        crypto::encrypt_metadata_str(&self.name, &self.key, metadata_version.into()).unwrap()
+    }
+
+    #[allow(clippy::missing_panics_doc)]
+    #[must_use]
+    pub fn size_encrypted_with_version(&self, metadata_version: MetadataVersion) -> String {
+        // Cannot panic, since MetadataVersion is supported by definition.
+        crypto::encrypt_metadata_str(&self.size.to_string(), &self.key, metadata_version.into()).unwrap()
+    }
+
+    #[allow(clippy::missing_panics_doc)]
+    #[must_use]
+    pub fn mime_encrypted_with_version(&self, metadata_version: MetadataVersion) -> String {
+        // Cannot panic, since MetadataVersion is supported by definition.
+        crypto::encrypt_metadata_str(&self.mime, &self.key, metadata_version.into()).unwrap()
     }
 }
 
@@ -290,6 +353,8 @@ pub struct FileMoveRequestPayload<'file_move> {
 utils::display_from_json_with_lifetime!('file_move, FileMoveRequestPayload);
 
 /// Used for requests to `FILE_RENAME_PATH` endpoint.
+///
+/// Only name fields and file metadata are sent, so file favorite flag is kept by Filen as is.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub struct FileRenameRequestPayload<'file_rename> {
     /// User-associated Filen API key.
@@ -439,7 +504,7 @@ pub async fn file_move_request_async(
 
 /// Calls `FILE_RENAME_PATH` endpoint.
 /// Changes name of the file with given UUID to the specified name. It is a good idea to check first if file
-/// with the new name already exists within the parent folder.
+/// with the new name already exists within the parent folder. File favorite flag persists.
 pub fn file_rename_request(
     payload: &FileRenameRequestPayload,
     filen_settings: &FilenSettings,
@@ -449,7 +514,7 @@ pub fn file_rename_request(
 
 /// Calls `FILE_RENAME_PATH` endpoint asynchronously.
 /// Changes name of the file with given UUID to the specified name. It is a good idea to check first if file
-/// with the new name already exists within the parent folder.
+/// with the new name already exists within the parent folder. File favorite flag persists.
 #[cfg(feature = "async")]
 pub async fn file_rename_request_async(
     payload: &FileRenameRequestPayload<'_>,
@@ -557,7 +622,10 @@ mod tests {
     use super::*;
     #[cfg(feature = "async")]
     use crate::test_utils::validate_contract_async;
-    use crate::{test_utils::validate_contract, v1::ParentOrBase};
+    use crate::{
+        test_utils::{self, validate_contract},
+        v1::ParentOrBase,
+    };
     use once_cell::sync::Lazy;
     use secstr::SecUtf8;
     use std::str::FromStr;
@@ -568,6 +636,57 @@ mod tests {
     // const NAME_METADATA: &str = "U2FsdGVkX19d09wR+Ti+qMO7o8habxXkS501US7uv96+zbHHZwDDPbnq1di1z0/S";
     const NAME_HASHED: &str = "19d24c63b1170a0b1b40520a636a25235735f39f";
 
+    #[test]
+    fn metadata_from_local_path_should_use_file_size_and_modification_time() {
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let file_path = std::env::temp_dir().join(format!("rust_filen_{}.txt", utils::random_alphanumeric_string(8)));
+        let modified = UNIX_EPOCH + std::time::Duration::from_secs(1_640_995_200);
+        let file = std::fs::File::create(&file_path).unwrap();
+        std::io::Write::write_all(&mut &file, b"backup me").unwrap();
+        file.set_modified(modified).unwrap();
+        drop(file);
+
+        let result = FileProperties::metadata_from_local_path(&file_path, &m_key, MetadataVersion::V1);
+        std::fs::remove_file(&file_path).unwrap();
+
+        let (file_properties, metadata) = result.unwrap();
+        assert_eq!(file_properties.name, file_path.file_name().unwrap().to_str().unwrap());
+        assert_eq!(file_properties.size, 9);
+        assert_eq!(file_properties.mime, "text/plain");
+        assert_eq!(file_properties.last_modified, 1_640_995_200);
+        assert!(metadata.starts_with("U2FsdGVk"));
+        assert_eq!(
+            FileProperties::decrypt_file_metadata(&metadata, &[m_key]).unwrap(),
+            file_properties
+        );
+    }
+
+    #[test]
+    fn file_rename_request_should_send_only_name_fields_and_metadata() {
+        let (server, filen_settings) = test_utils::init_server();
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let uuid = Uuid::parse_str("80f678c0-56ce-4b81-b4ef-f2a9c0c737c4").unwrap();
+        let file_properties = FileProperties::from_name_size_modified("test_folder", 1, &UNIX_EPOCH).unwrap();
+        let request_payload = FileRenameRequestPayload::new(&API_KEY, uuid, "test_folder", &file_properties, &m_key);
+        let mock = server.mock(|when, then| {
+            when.method(httpmock::Method::POST)
+                .path(FILE_RENAME_PATH)
+                .json_body(json!({
+                    "apiKey": API_KEY.unsecure(),
+                    "uuid": uuid,
+                    "name": request_payload.name_metadata,
+                    "nameHashed": NAME_HASHED,
+                    "metaData": request_payload.metadata,
+                }));
+            then.status(200).json_body(json!({"status": true}));
+        });
+
+        let response = file_rename_request(&request_payload, &filen_settings).unwrap();
+
+        mock.assert_hits(1);
+        assert!(response.status);
+    }
+
     #[test]
     fn file_exists_request_should_be_correctly_typed() {
         let request_payload = LocationExistsRequestPayload {
@@ -625,4 +744,68 @@ mod tests {
         )
         .await;
     }
+
+    #[test]
+    fn file_trash_request_should_be_correctly_typed() {
+        let request_payload = LocationTrashRequestPayload {
+            api_key: &API_KEY,
+            uuid: Uuid::parse_str("b5ec90d4-f3b5-4b5e-8f8d-62a6d7b0a8e9").unwrap(),
+        };
+        validate_contract(
+            FILE_TRASH_PATH,
+            request_payload,
+            "tests/resources/responses/file_trash.json",
+            |request_payload, filen_settings| file_trash_request(&request_payload, &filen_settings),
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn file_trash_request_async_should_be_correctly_typed() {
+        let request_payload = LocationTrashRequestPayload {
+            api_key: &API_KEY,
+            uuid: Uuid::parse_str("b5ec90d4-f3b5-4b5e-8f8d-62a6d7b0a8e9").unwrap(),
+        };
+        validate_contract_async(
+            FILE_TRASH_PATH,
+            request_payload,
+            "tests/resources/responses/file_trash.json",
+            |request_payload, filen_settings| async move {
+                file_trash_request_async(&request_payload, &filen_settings).await
+            },
+        )
+        .await;
+    }
+
+    #[test]
+    fn file_restore_request_should_be_correctly_typed() {
+        let request_payload = FileRestoreRequestPayload {
+            api_key: &API_KEY,
+            uuid: Uuid::parse_str("b5ec90d4-f3b5-4b5e-8f8d-62a6d7b0a8e9").unwrap(),
+        };
+        validate_contract(
+            FILE_RESTORE_PATH,
+            request_payload,
+            "tests/resources/responses/file_restore.json",
+            |request_payload, filen_settings| file_restore_request(&request_payload, &filen_settings),
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn file_restore_request_async_should_be_correctly_typed() {
+        let request_payload = FileRestoreRequestPayload {
+            api_key: &API_KEY,
+            uuid: Uuid::parse_str("b5ec90d4-f3b5-4b5e-8f8d-62a6d7b0a8e9").unwrap(),
+        };
+        validate_contract_async(
+            FILE_RESTORE_PATH,
+            request_payload,
+            "tests/resources/responses/file_restore.json",
+            |request_payload, filen_settings| async move {
+                file_restore_request_async(&request_payload, &filen_settings).await
+            },
+        )
+        .await;
+    }
 }
@@ -1,18 +1,22 @@
 //! Contains structures common for Filen file&folder API.
 use crate::{
-    crypto, utils,
+    crypto::{self, MetadataVersion},
+    utils::{self, Base64Padding},
     v1::{files, fs, optional_uuid_from_empty_string, response_payload, FileLocation, FileProperties},
 };
 use secstr::{SecUtf8, SecVec};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::json;
 use snafu::{Backtrace, ResultExt, Snafu};
-use std::{fmt, num::ParseIntError, str::FromStr};
+use std::{convert::TryFrom, fmt, num::ParseIntError, str::FromStr};
 use strum::{Display, EnumString};
 use uuid::Uuid;
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
+/// Length of randomly generated file and link keys.
+pub const FILEN_KEY_LENGTH: usize = 32;
+
 #[derive(Snafu, Debug)]
 pub enum Error {
     #[snafu(display("Caller provided invalid argument: {}", message))]
@@ -36,6 +40,9 @@ pub enum Error {
     ))]
     CannotParseParentOrNoneFromString { string_length: usize, backtrace: Backtrace },
 
+    #[snafu(display("{}", source))]
+    DecryptFileMetadataFailed { source: files::Error },
+
     #[snafu(display("Failed to decrypt link key '{}': {}", metadata, source))]
     DecryptLinkKeyFailed { metadata: String, source: crypto::Error },
 
@@ -53,6 +60,9 @@ pub enum Error {
 
     #[snafu(display("Expire duration value '{}' is not a number: {}", value, source))]
     DurationValueIsNotNum { value: String, source: ParseIntError },
+
+    #[snafu(display("Key should have {} characters, but it has {}", FILEN_KEY_LENGTH, length))]
+    KeyLengthIsInvalid { length: usize, backtrace: Backtrace },
 }
 
 /// Public link or file chunk expiration time.
@@ -124,6 +134,73 @@ impl Serialize for Expire {
     }
 }
 
+/// Generates a newtype wrapper for one of 32-char random keys, so different kinds of keys cannot be mixed up.
+macro_rules! filen_key {
+    (
+        $(#[$meta:meta])*
+        $key_type:ident
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+        #[serde(try_from = "SecUtf8", into = "SecUtf8")]
+        pub struct $key_type(SecUtf8);
+
+        impl $key_type {
+            /// Wraps the given key, checking that it has exactly `FILEN_KEY_LENGTH` characters.
+            pub fn new(key: SecUtf8) -> Result<Self> {
+                let length = key.unsecure().chars().count();
+                if length == FILEN_KEY_LENGTH {
+                    Ok(Self(key))
+                } else {
+                    KeyLengthIsInvalidSnafu { length }.fail()
+                }
+            }
+
+            /// Generates a new random alphanumeric key.
+            #[must_use]
+            pub fn generate() -> Self {
+                Self(SecUtf8::from(utils::random_alphanumeric_string(FILEN_KEY_LENGTH)))
+            }
+
+            /// Returns reference to the wrapped key.
+            #[must_use]
+            pub const fn as_sec_utf8(&self) -> &SecUtf8 {
+                &self.0
+            }
+        }
+
+        impl AsRef<SecUtf8> for $key_type {
+            fn as_ref(&self) -> &SecUtf8 {
+                &self.0
+            }
+        }
+
+        impl From<$key_type> for SecUtf8 {
+            fn from(key: $key_type) -> Self {
+                key.0
+            }
+        }
+
+        impl TryFrom<SecUtf8> for $key_type {
+            type Error = Error;
+
+            fn try_from(key: SecUtf8) -> Result<Self> {
+                Self::new(key)
+            }
+        }
+    };
+}
+
+filen_key!(
+    /// Key used to encrypt file chunks and file metadata, stored inside file metadata.
+    FileKey
+);
+
+filen_key!(
+    /// Key used to encrypt metadata of linked items instead of user's master keys.
+    LinkKey
+);
+
 /// Identifies whether an item is a file or folder.
 #[derive(Clone, Copy, Debug, Deserialize, Display, EnumString, Eq, Hash, PartialEq, Serialize, PartialOrd, Ord)]
 #[serde(rename_all = "lowercase")]
@@ -135,6 +212,70 @@ pub enum ItemKind {
     Folder,
 }
 
+/// Filen storage region where file data is physically stored, such as "de-1".
+///
+/// Regions unknown to this crate are kept as is in `Region::Other`.
+#[derive(Clone, Debug, EnumString, Eq, Hash, PartialEq)]
+pub enum Region {
+    /// Filen data center in Germany.
+    #[strum(serialize = "de-1")]
+    De1,
+    #[strum(default)]
+    Other(String),
+}
+
+impl Region {
+    /// Parses region identifier as Filen API uses it, like "de-1". Unknown regions become `Region::Other`.
+    #[must_use]
+    pub fn new(region: &str) -> Self {
+        Self::from_str(region).unwrap_or_else(|_| Self::Other(region.to_owned()))
+    }
+
+    /// Returns true if region is known to store data within European Union, false if it is known to store data
+    /// outside of it, and `None` for regions unknown to this crate.
+    #[must_use]
+    pub const fn is_in_eu(&self) -> Option<bool> {
+        match self {
+            Self::De1 => Some(true),
+            Self::Other(_) => None,
+        }
+    }
+
+    /// Region identifier as Filen API uses it, like "de-1".
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::De1 => "de-1",
+            Self::Other(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Region {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Ok(Self::from_str(&s).unwrap_or(Self::Other(s)))
+    }
+}
+
+impl Serialize for Region {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
 /// Determines where file is stored by Filen.
 #[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
 pub struct FileStorageInfo {
@@ -148,6 +289,14 @@ pub struct FileStorageInfo {
     pub chunks: u32,
 }
 
+impl FileStorageInfo {
+    /// Typed storage region where file is stored.
+    #[must_use]
+    pub fn storage_region(&self) -> Region {
+        Region::new(&self.region)
+    }
+}
+
 impl fmt::Display for FileStorageInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}/{} [{} chunks]", self.region, self.bucket, self.chunks)
@@ -217,11 +366,21 @@ pub struct LocationNameMetadata {
 
 impl LocationNameMetadata {
     /// Puts the given name into Filen-expected JSON { name: "some name" } and encrypts it into metadata.
-    #[allow(clippy::missing_panics_doc)]
     pub fn encrypt_name_to_metadata<S: Into<String>>(name: S, key: &SecUtf8) -> String {
+        Self::encrypt_name_to_metadata_with_version(name, key, MetadataVersion::default())
+    }
+
+    /// Puts the given name into Filen-expected JSON { name: "some name" } and encrypts it into metadata
+    /// of the specified version.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn encrypt_name_to_metadata_with_version<S: Into<String>>(
+        name: S,
+        key: &SecUtf8,
+        metadata_version: MetadataVersion,
+    ) -> String {
         let name_json = json!(Self { name: name.into() }).to_string();
-        // Cannot panic due to the way encrypt_metadata_str is implemented.
-        crypto::encrypt_metadata_str(&name_json, key, super::METADATA_VERSION).unwrap()
+        // Cannot panic, since MetadataVersion is supported by definition.
+        crypto::encrypt_metadata_str(&name_json, key, metadata_version.into()).unwrap()
     }
 
     /// Decrypt name metadata into actual name.
@@ -246,7 +405,7 @@ impl LocationNameMetadata {
             return Ok("Default".to_owned());
         }
 
-        let decoded = base64::decode(name_metadata).context(CannotDecodeBase64MetadataSnafu {
+        let decoded = utils::base64_decode(name_metadata).context(CannotDecodeBase64MetadataSnafu {
             metadata: name_metadata.to_owned(),
         })?;
         let decrypted_folder_properties_json = crypto::decrypt_rsa(&decoded, rsa_private_key_bytes.unsecure())
@@ -264,13 +423,14 @@ impl LocationNameMetadata {
         rsa_public_key_bytes: &[u8],
     ) -> Result<String, crypto::Error> {
         let name_json = json!(Self { name: name.into() }).to_string();
-        crypto::encrypt_rsa(name_json.as_bytes(), rsa_public_key_bytes).map(base64::encode)
+        crypto::encrypt_rsa(name_json.as_bytes(), rsa_public_key_bytes)
+            .map(|encrypted| utils::base64_encode(encrypted, Base64Padding::Padded))
     }
 
-    /// Returns hashed given location name.
+    /// Returns hashed given location name. Same as `crypto::hash_name`.
     #[must_use]
     pub fn name_hashed(name: &str) -> String {
-        crypto::hash_fn(&name.to_lowercase())
+        crypto::hash_name(name)
     }
 
     pub(crate) fn extract_name_from_folder_properties_json(folder_properties_json_bytes: &[u8]) -> Result<String> {
@@ -280,6 +440,39 @@ impl LocationNameMetadata {
     }
 }
 
+/// Decrypted metadata of a file or a folder.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ItemMetadata {
+    /// File metadata: name, size, mime, key and last modification time.
+    File(FileProperties),
+    /// Folder metadata, which only contains a name.
+    Folder(LocationNameMetadata),
+}
+
+impl ItemMetadata {
+    /// Decrypts file or folder metadata, depending on the given item kind.
+    /// Useful when listing a mix of files and folders.
+    pub fn decrypt_item_metadata(metadata: &str, kind: ItemKind, master_keys: &[SecUtf8]) -> Result<Self> {
+        match kind {
+            ItemKind::File => FileProperties::decrypt_file_metadata(metadata, master_keys)
+                .map(ItemMetadata::File)
+                .context(DecryptFileMetadataFailedSnafu {}),
+            ItemKind::Folder => LocationNameMetadata::decrypt_name_from_metadata(metadata, master_keys)
+                .map(|name| ItemMetadata::Folder(LocationNameMetadata { name })),
+        }
+    }
+
+    /// Returns decrypted item name.
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            ItemMetadata::File(file_properties) => &file_properties.name,
+            ItemMetadata::Folder(name_metadata) => &name_metadata.name,
+        }
+    }
+}
+
 /// Implemented to add decryption of a metadata containing Filen's file properties JSON.
 pub trait HasFileMetadata {
     /// Gets a reference to file metadata, if present.
@@ -645,6 +838,71 @@ mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
 
+    #[test]
+    fn region_should_be_deserialized_from_known_and_unknown_strings() {
+        let known: Region = serde_json::from_str(r#""de-1""#).unwrap();
+        let unknown: Region = serde_json::from_str(r#""us-3""#).unwrap();
+
+        assert_eq!(known, Region::De1);
+        assert_eq!(known.is_in_eu(), Some(true));
+        assert_eq!(unknown, Region::Other("us-3".to_owned()));
+        assert_eq!(unknown.is_in_eu(), None);
+        assert_eq!(serde_json::to_string(&known).unwrap(), r#""de-1""#);
+        assert_eq!(serde_json::to_string(&unknown).unwrap(), r#""us-3""#);
+        assert_eq!(unknown.to_string(), "us-3");
+    }
+
+    #[test]
+    fn file_storage_info_should_provide_typed_region() {
+        let storage: FileStorageInfo =
+            serde_json::from_str(r#"{"bucket": "filen-1", "region": "de-1", "chunks": 1}"#).unwrap();
+
+        assert_eq!(storage.storage_region(), Region::De1);
+    }
+
+    #[test]
+    fn decrypt_item_metadata_should_decrypt_file_metadata() {
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let file_properties = FileProperties {
+            name: "perform.js".to_owned(),
+            size: 156,
+            mime: "application/javascript".to_owned(),
+            key: SecUtf8::from("tqNrczqVdTCgFzB1b1gyiQBIYmwDBwa9"),
+            last_modified: 499_162_500,
+        };
+        let metadata = FileProperties::encrypt_file_metadata(&file_properties, &m_key);
+
+        let decrypted = ItemMetadata::decrypt_item_metadata(&metadata, ItemKind::File, &[m_key]).unwrap();
+
+        assert_eq!(decrypted, ItemMetadata::File(file_properties));
+        assert_eq!(decrypted.name(), "perform.js");
+    }
+
+    #[test]
+    fn decrypt_item_metadata_should_decrypt_folder_metadata() {
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let metadata = LocationNameMetadata::encrypt_name_to_metadata("Documents", &m_key);
+
+        let decrypted = ItemMetadata::decrypt_item_metadata(&metadata, ItemKind::Folder, &[m_key]).unwrap();
+
+        assert_eq!(
+            decrypted,
+            ItemMetadata::Folder(LocationNameMetadata {
+                name: "Documents".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn decrypt_item_metadata_should_fail_for_wrong_kind() {
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let metadata = LocationNameMetadata::encrypt_name_to_metadata("Documents", &m_key);
+
+        let decrypted_result = ItemMetadata::decrypt_item_metadata(&metadata, ItemKind::File, &[m_key]);
+
+        assert!(matches!(decrypted_result, Err(Error::DecryptFileMetadataFailed { .. })));
+    }
+
     #[test]
     fn location_should_be_deserialized_from_empty_string_uuid() {
         let json = r#"{"exists":false, "uuid":""}"#;
@@ -702,4 +960,35 @@ mod tests {
 
         assert_eq!(result.unwrap(), expected);
     }
+
+    #[test]
+    fn file_key_should_accept_32_chars() {
+        let key = SecUtf8::from("sh1YRHfx22Ij40tQBbt6BgpBlqkzch8Y");
+
+        let file_key = FileKey::new(key.clone()).unwrap();
+
+        assert_eq!(file_key.as_sec_utf8(), &key);
+    }
+
+    #[test]
+    fn file_key_should_reject_invalid_length() {
+        let result = FileKey::new(SecUtf8::from("sh1YRHfx22Ij40tQBbt6BgpBlqkzch8"));
+
+        assert!(matches!(result, Err(Error::KeyLengthIsInvalid { length: 31, .. })));
+    }
+
+    #[test]
+    fn link_key_should_be_deserialized_only_with_valid_length() {
+        let valid = serde_json::from_str::<LinkKey>(r#""sh1YRHfx22Ij40tQBbt6BgpBlqkzch8Y""#);
+        let invalid = serde_json::from_str::<LinkKey>(r#""too short""#);
+
+        assert!(valid.is_ok());
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn generated_keys_should_have_valid_length() {
+        assert_eq!(FileKey::generate().as_sec_utf8().unsecure().len(), FILEN_KEY_LENGTH);
+        assert_eq!(LinkKey::generate().as_sec_utf8().unsecure().len(), FILEN_KEY_LENGTH);
+    }
 }
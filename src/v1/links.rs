@@ -1,12 +1,13 @@
 #[cfg(feature = "async")]
 use crate::v1::{dir_link_add_request_async, download_dir_request_async, link_edit_request_async};
 use crate::{
+    crypto::MetadataVersion,
     queries, secstr, utils, uuid, v1,
     v1::{
         crypto, dir_link_add_request, dir_links, download_dir, download_dir_request, file_links, link_edit_request,
         response_payload, Backtrace, DirLinkAddRequestPayload, DownloadBtnState, DownloadDirRequestPayload, Expire,
         FileProperties, FilenResponse, HasFileMetadata, HasLinkKey, HasLocationName, HasUuid, LinkEditRequestPayload,
-        LocationNameMetadata, ParentOrBase, PlainResponsePayload, METADATA_VERSION,
+        LinkKey, LocationNameMetadata, ParentOrBase, PlainResponsePayload,
     },
     FilenSettings, SettingsBundle,
 };
@@ -139,6 +140,10 @@ pub struct LinkDirItemStatusResponseData {
     /// Found links. Empty if given item is not linked.
     #[serde(default)]
     pub links: Vec<LinkIdWithKey>,
+
+    /// Response fields unknown to this crate, preserved as is.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 utils::display_from_json!(LinkDirItemStatusResponseData);
 
@@ -181,13 +186,23 @@ impl HasLinkKey for LinkIdWithKey {
 
 impl LinkIdWithKey {
     /// Generates a new link uuid and a link key metadata.
-    #[allow(clippy::missing_panics_doc)]
     #[must_use]
     pub fn generate(last_master_key: &SecUtf8) -> Self {
+        Self::generate_with_version(last_master_key, MetadataVersion::default())
+    }
+
+    /// Generates a new link uuid and a link key metadata of the specified version.
+    #[allow(clippy::missing_panics_doc)]
+    #[must_use]
+    pub fn generate_with_version(last_master_key: &SecUtf8, metadata_version: MetadataVersion) -> Self {
         let (link_uuid, link_key_plain) = Self::generate_unencrypted();
-        // Cannot panic due to the way encrypt_metadata_str is implemented.
-        let link_key_metadata =
-            crypto::encrypt_metadata_str(link_key_plain.unsecure(), last_master_key, METADATA_VERSION).unwrap();
+        // Cannot panic, since MetadataVersion is supported by definition.
+        let link_key_metadata = crypto::encrypt_metadata_str(
+            link_key_plain.as_sec_utf8().unsecure(),
+            last_master_key,
+            metadata_version.into(),
+        )
+        .unwrap();
         Self {
             link_key_metadata,
             link_uuid,
@@ -196,8 +211,8 @@ impl LinkIdWithKey {
 
     /// Generates a new link uuid and a link key.
     #[must_use]
-    pub fn generate_unencrypted() -> (Uuid, SecUtf8) {
-        (Uuid::new_v4(), SecUtf8::from(utils::random_alphanumeric_string(32)))
+    pub fn generate_unencrypted() -> (Uuid, LinkKey) {
+        (Uuid::new_v4(), LinkKey::generate())
     }
 }
 
@@ -211,6 +226,10 @@ pub struct LinkDirStatusResponseData {
     /// Found links. Empty if given folder is not linked.
     #[serde(default)]
     pub links: Vec<LinkIdWithKey>,
+
+    /// Response fields unknown to this crate, preserved as is.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 utils::display_from_json!(LinkDirStatusResponseData);
 
@@ -399,9 +418,16 @@ pub fn add_file_to_link<'add_file_to_link, T: HasFileMetadata + HasUuid>(
     master_keys: &[SecUtf8],
     filen_settings: &FilenSettings,
 ) -> Result<String> {
-    let dir_link_add_payload =
-        DirLinkAddRequestPayload::from_file_data(api_key, file_data, parent, link_uuid, link_key_metadata, master_keys)
-            .context(DirLinkAddRequestPayloadCreationFailedSnafu {})?;
+    let dir_link_add_payload = DirLinkAddRequestPayload::from_file_data_with_version(
+        api_key,
+        file_data,
+        parent,
+        link_uuid,
+        link_key_metadata,
+        master_keys,
+        filen_settings.metadata_version,
+    )
+    .context(DirLinkAddRequestPayloadCreationFailedSnafu {})?;
     let dir_link_add_response =
         dir_link_add_request(&dir_link_add_payload, filen_settings).context(DirLinkAddQueryFailedSnafu {})?;
     let message = dir_link_add_response.message_ref().unwrap_or_default().to_owned();
@@ -423,9 +449,16 @@ pub async fn add_file_to_link_async<'add_file_to_link, T: HasFileMetadata + HasU
     master_keys: &[SecUtf8],
     filen_settings: &FilenSettings,
 ) -> Result<String> {
-    let dir_link_add_payload =
-        DirLinkAddRequestPayload::from_file_data(api_key, file_data, parent, link_uuid, link_key_metadata, master_keys)
-            .context(DirLinkAddRequestPayloadCreationFailedSnafu {})?;
+    let dir_link_add_payload = DirLinkAddRequestPayload::from_file_data_with_version(
+        api_key,
+        file_data,
+        parent,
+        link_uuid,
+        link_key_metadata,
+        master_keys,
+        filen_settings.metadata_version,
+    )
+    .context(DirLinkAddRequestPayloadCreationFailedSnafu {})?;
     let dir_link_add_response = dir_link_add_request_async(&dir_link_add_payload, filen_settings)
         .await
         .context(DirLinkAddQueryFailedSnafu {})?;
@@ -447,13 +480,14 @@ pub fn add_folder_to_link<'add_folder_to_link, T: HasLocationName + HasUuid>(
     master_keys: &[SecUtf8],
     filen_settings: &FilenSettings,
 ) -> Result<String> {
-    let dir_link_add_payload = DirLinkAddRequestPayload::from_folder_data(
+    let dir_link_add_payload = DirLinkAddRequestPayload::from_folder_data_with_version(
         api_key,
         folder_data,
         parent,
         link_uuid,
         link_key_metadata,
         master_keys,
+        filen_settings.metadata_version,
     )
     .context(DirLinkAddRequestPayloadCreationFailedSnafu {})?;
     let dir_link_add_response =
@@ -477,13 +511,14 @@ pub async fn add_folder_to_link_async<'add_folder_to_link, T: HasLocationName +
     master_keys: &[SecUtf8],
     filen_settings: &FilenSettings,
 ) -> Result<String> {
-    let dir_link_add_payload = DirLinkAddRequestPayload::from_folder_data(
+    let dir_link_add_payload = DirLinkAddRequestPayload::from_folder_data_with_version(
         api_key,
         folder_data,
         parent,
         link_uuid,
         link_key_metadata,
         master_keys,
+        filen_settings.metadata_version,
     )
     .context(DirLinkAddRequestPayloadCreationFailedSnafu {})?;
     let dir_link_add_response = dir_link_add_request_async(&dir_link_add_payload, filen_settings)
@@ -530,7 +565,7 @@ pub fn link_folder_recursively(
     // TODO: add_(file|folder)_to_link will decrypt link_key_metadata inside,
     // and it is possible to generate unencrypted metadata here with LinkIdWithKey::generate_unencrypted()
     // So implement overloads for add_(file|folder)_to_link for an unencrypted link key?
-    let link_id_with_key = LinkIdWithKey::generate(last_master_key);
+    let link_id_with_key = LinkIdWithKey::generate_with_version(last_master_key, settings.filen.metadata_version);
     let link_metadata = &link_id_with_key.link_key_metadata;
 
     // Share this folder and all sub-folders:
@@ -612,7 +647,7 @@ pub async fn link_folder_recursively_async(
         .data_ref_or_err()
         .context(CannotGetUserFolderContentsSnafu {})?;
 
-    let link_id_with_key = LinkIdWithKey::generate(last_master_key);
+    let link_id_with_key = LinkIdWithKey::generate_with_version(last_master_key, settings.filen.metadata_version);
     let link_uuid = link_id_with_key.link_uuid;
     let link_metadata = &link_id_with_key.link_key_metadata;
 
@@ -735,4 +770,18 @@ mod tests {
         )
         .await;
     }
+
+    #[test]
+    fn link_dir_status_response_data_should_preserve_unknown_fields() {
+        let response_json = r#"{"link":true,"links":[],"expiration":3600}"#;
+
+        let response_data: LinkDirStatusResponseData = serde_json::from_str(response_json).unwrap();
+        let serialized = serde_json::to_value(&response_data).unwrap();
+
+        assert_eq!(response_data.extra.get("expiration"), Some(&serde_json::json!(3600)));
+        assert_eq!(
+            serialized,
+            serde_json::from_str::<serde_json::Value>(response_json).unwrap()
+        );
+    }
 }
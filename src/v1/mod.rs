@@ -1,28 +1,32 @@
 pub use {
-    auth::Error as AuthError, client::Error as ClientError, crypto::Error as CryptoError,
-    dir_links::Error as DirLinksError, dirs::Error as DirsError, download_dir::Error as DownloadDirError,
-    download_file::Error as DownloadFileError, events::Error as EventsError, file_links::Error as FileLinksError,
-    files::Error as FilesError, fs::Error as FsError, links::Error as LinksError, share::Error as ShareError,
-    sync_dir::Error as SyncDirError, upload_file::Error as UploadFileError, usage::Error as UsageError,
-    user::Error as UserError, user_keys::Error as UserKeysError, versions::Error as VersionsError,
+    auth::Error as AuthError, bulk::Error as BulkError, client::Error as ClientError, crypto::Error as CryptoError,
+    dir_links::Error as DirLinksError, dir_path::Error as DirPathError, dirs::Error as DirsError,
+    download_dir::Error as DownloadDirError, download_file::Error as DownloadFileError, events::Error as EventsError,
+    file_links::Error as FileLinksError, files::Error as FilesError, fs::Error as FsError, links::Error as LinksError,
+    share::Error as ShareError, sync_changes::Error as SyncChangesError, sync_dir::Error as SyncDirError,
+    upload_file::Error as UploadFileError, usage::Error as UsageError, user::Error as UserError,
+    user_keys::Error as UserKeysError, versions::Error as VersionsError, walk::Error as WalkError,
 };
 
 pub use {
-    auth::*, client::*, dir_links::*, dirs::*, download_dir::*, download_file::*, events::*, file_links::*, files::*,
-    fs::*, links::*, share::*, sync_dir::*, upload_file::*, usage::*, user::*, user_keys::*, versions::*,
+    auth::*, bulk::*, client::*, dir_links::*, dir_path::*, dirs::*, download_dir::*, download_file::*, events::*,
+    file_links::*, files::*, fs::*, links::*, share::*, sync_changes::*, sync_dir::*, upload_file::*, usage::*,
+    user::*, user_keys::*, versions::*, walk::*,
 };
 
 use crate::{crypto, utils};
 use once_cell::sync::Lazy;
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use serde_with::skip_serializing_none;
-use snafu::{Backtrace, Snafu};
+use snafu::{Backtrace, ResultExt, Snafu};
 use strum::{Display, EnumString};
 use uuid::Uuid;
 
 mod auth;
+mod bulk;
 mod client;
 mod dir_links;
+mod dir_path;
 mod dirs;
 mod download_dir;
 mod download_file;
@@ -32,24 +36,34 @@ mod files;
 mod fs;
 mod links;
 mod share;
+mod sync_changes;
 mod sync_dir;
 mod upload_file;
 mod usage;
 mod user;
 mod user_keys;
 mod versions;
+mod walk;
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
-const METADATA_VERSION: u32 = 1;
-
 #[derive(Snafu, Debug)]
 pub enum Error {
+    /// Note that `code` field was added after this variant was introduced, so patterns listing all fields
+    /// of this variant without `..` have to be updated.
     #[snafu(display("Filen response had status: false, reason: {}", message))]
-    FilenResponseIndicatesFailure { message: String, backtrace: Backtrace },
+    FilenResponseIndicatesFailure {
+        message: String,
+        /// Machine-readable code parsed from the response message, if it is a known one.
+        code: Option<FilenMessageCode>,
+        backtrace: Backtrace,
+    },
 
     #[snafu(display("Filen response does not contain 'data'"))]
     FilenResponseHasNoData { backtrace: Backtrace },
+
+    #[snafu(display("Filen response 'data' cannot be deserialized into the requested type: {}", source))]
+    CannotDeserializeResponseData { source: serde_json::Error },
 }
 
 /// Common trait for all Filen API responses.
@@ -63,6 +77,11 @@ pub trait FilenResponse<T> {
     /// Reference to the data associated with response.
     fn data_ref(&self) -> Option<&T>;
 
+    /// Known code of the Filen response message, if message is recognized.
+    fn message_code(&self) -> Option<FilenMessageCode> {
+        self.message_ref().and_then(|message| message.parse().ok())
+    }
+
     /// Returns extracted Filen response data or failure if response status is false or data is empty.
     fn data_ref_or_err(&self) -> Result<&T> {
         if self.status_ref() {
@@ -72,13 +91,59 @@ pub trait FilenResponse<T> {
             }
         } else {
             FilenResponseIndicatesFailureSnafu {
-                message: format!("{:?}", self.message_ref()),
+                message: self.message_ref().unwrap_or("unknown reason"),
+                code: self.message_code(),
             }
             .fail()
         }
     }
 }
 
+/// Known values of Filen response message which can be matched on instead of comparing strings.
+///
+/// Filen returns either a machine-readable code, like "sharing_stopped", or a human-readable sentence,
+/// like "Folder does not exist.", in the response `message` field; both forms are parsed into the same code.
+/// Displayed value is always the machine-readable code, so it can be used as a stable localization key.
+#[derive(Clone, Copy, Debug, Display, EnumString, Eq, Hash, PartialEq)]
+#[strum(ascii_case_insensitive)]
+pub enum FilenMessageCode {
+    #[strum(to_string = "account_not_activated")]
+    AccountNotActivated,
+    #[strum(
+        to_string = "already_in_trash",
+        serialize = "This file is already in the trash folder."
+    )]
+    #[strum(serialize = "This folder is already in the trash folder.")]
+    AlreadyInTrash,
+    #[strum(to_string = "email_or_password_wrong")]
+    EmailOrPasswordWrong,
+    #[strum(
+        to_string = "enter_2fa",
+        serialize = "Please enter your Two Factor Authentication code."
+    )]
+    Enter2fa,
+    #[strum(to_string = "file_not_found")]
+    FileNotFound,
+    #[strum(to_string = "folder_link_not_found", serialize = "Folder link not found.")]
+    FolderLinkNotFound,
+    #[strum(to_string = "folder_not_found", serialize = "Folder does not exist.")]
+    FolderNotFound,
+    #[strum(to_string = "internal_error")]
+    InternalError,
+    #[strum(to_string = "invalid_api_key")]
+    InvalidApiKey,
+    #[strum(to_string = "invalid_auth_version", serialize = "Invalid authentication version.")]
+    InvalidAuthVersion,
+    #[strum(to_string = "invalid_params")]
+    InvalidParams,
+    #[strum(to_string = "invalid_password")]
+    InvalidPassword,
+    #[strum(to_string = "sharing_stopped")]
+    SharingStopped,
+    #[strum(to_string = "wrong_2fa")]
+    Wrong2fa,
+}
+
 /// Contains just the response status and corresponding message.
 #[skip_serializing_none]
 #[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
@@ -238,3 +303,113 @@ macro_rules! response_payload {
     }
 }
 pub(crate) use response_payload;
+
+response_payload!(
+    /// Response with arbitrary JSON data. Can be used for endpoints which are not fully modelled by this crate,
+    /// or which return data in varying shapes.
+    RawResponsePayload<serde_json::Value>
+);
+
+impl RawResponsePayload {
+    /// Deserializes response data into the given type.
+    /// Fails if response status is false, if response has no data or if data does not match the given type.
+    pub fn deserialize_data<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        let data = self.data_ref_or_err()?;
+        T::deserialize(data).context(CannotDeserializeResponseDataSnafu {})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct UserDefinedData {
+        uploads: u64,
+        email: String,
+    }
+
+    #[test]
+    fn filen_message_code_should_parse_known_messages() {
+        let parsed = [
+            "sharing_stopped",
+            "INVALID_PASSWORD",
+            "This folder is already in the trash folder.",
+            "This file is already in the trash folder.",
+            "Folder does not exist.",
+        ]
+        .iter()
+        .map(|message| message.parse::<FilenMessageCode>().unwrap())
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            parsed,
+            vec![
+                FilenMessageCode::SharingStopped,
+                FilenMessageCode::InvalidPassword,
+                FilenMessageCode::AlreadyInTrash,
+                FilenMessageCode::AlreadyInTrash,
+                FilenMessageCode::FolderNotFound,
+            ]
+        );
+        assert_eq!(FilenMessageCode::FolderNotFound.to_string(), "folder_not_found");
+        assert!("Folder created.".parse::<FilenMessageCode>().is_err());
+    }
+
+    #[test]
+    fn data_ref_or_err_should_attach_message_code_to_failure() {
+        let response: PlainResponsePayload =
+            crate::test_utils::deserialize_from_file("tests/resources/responses/dir_already_in_trash.json");
+
+        let result = response.data_ref_or_err();
+
+        assert_eq!(response.message_code(), Some(FilenMessageCode::AlreadyInTrash));
+        assert!(matches!(
+            result,
+            Err(Error::FilenResponseIndicatesFailure {
+                code: Some(FilenMessageCode::AlreadyInTrash),
+                ..
+            })
+        ));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            format!(
+                "Filen response had status: false, reason: {}",
+                response.message.unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn raw_response_payload_should_deserialize_data_on_demand() {
+        let response: RawResponsePayload =
+            crate::test_utils::deserialize_from_file("tests/resources/responses/user_usage.json");
+
+        let data = response.deserialize_data::<UserDefinedData>().unwrap();
+
+        assert_eq!(
+            data,
+            UserDefinedData {
+                uploads: 70412,
+                email: "test@test.com".to_owned()
+            }
+        );
+        assert!(matches!(
+            response.deserialize_data::<Vec<u64>>(),
+            Err(Error::CannotDeserializeResponseData { .. })
+        ));
+    }
+
+    #[test]
+    fn raw_response_payload_should_handle_absent_data() {
+        let response: RawResponsePayload =
+            serde_json::from_str(r#"{"status":true,"message":"Folder moved."}"#).unwrap();
+
+        assert_eq!(response.data, None);
+        assert!(matches!(
+            response.deserialize_data::<UserDefinedData>(),
+            Err(Error::FilenResponseHasNoData { .. })
+        ));
+    }
+}
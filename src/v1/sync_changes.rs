@@ -0,0 +1,264 @@
+//! Contains helpers to get incremental changes of user's files and folders since the given time.
+//!
+//! Filen v1 API has no dedicated delta endpoint, so changes are collected from the latest page of user events,
+//! which is what official clients show as account activity.
+#[cfg(feature = "async")]
+use crate::v1::user_events_request_async;
+use crate::{
+    v1::{
+        events, user_events_request, UserEvent, UserEventFilter, UserEventsRequestPayload, UserEventsResponsePayload,
+    },
+    FilenSettings,
+};
+use secstr::SecUtf8;
+use snafu::{Backtrace, ResultExt, Snafu};
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Event ID used to request the latest page of user events.
+const LATEST_EVENTS_ID: u64 = 0;
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("{}", source))]
+    UserEventsQueryFailed { source: events::Error },
+
+    #[snafu(display("Filen refused to list user events: {}", message))]
+    UserEventsRefused { message: String, backtrace: Backtrace },
+}
+
+/// Changes of user's files and folders which happened after some point in time, newest first.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SyncChanges {
+    /// Events about created files and folders, including ones restored from trash.
+    pub created: Vec<UserEvent>,
+
+    /// Events about renamed, moved, recolored or (un)favorited files and folders,
+    /// files which got a new version and files restored to their previous version.
+    pub updated: Vec<UserEvent>,
+
+    /// Events about files and folders moved to trash or deleted permanently.
+    pub deleted: Vec<UserEvent>,
+
+    /// False if changes might be missing, because fetched events did not reach back to the requested time,
+    /// because all user files were deleted, or because some events are unknown to this crate
+    /// and could have changed anything. In this case a full re-listing is needed to get in sync.
+    pub complete: bool,
+}
+
+impl SyncChanges {
+    /// Sorts given events, newest first, into created, updated and deleted items, skipping events
+    /// which happened at `since` or earlier. Account events which do not change files or folders are skipped
+    /// as well, while unknown events mark changes as incomplete.
+    #[must_use]
+    pub fn from_events(events: &[UserEvent], since: u64) -> Self {
+        let mut changes = Self {
+            complete: events.is_empty() || events.iter().any(|event| event.timestamp() <= since),
+            ..Self::default()
+        };
+        for event in events.iter().filter(|event| event.timestamp() > since) {
+            #[allow(clippy::wildcard_enum_match_arm)]
+            match event {
+                UserEvent::BaseFolderCreated(_)
+                | UserEvent::FileRestored(_)
+                | UserEvent::FileUploaded(_)
+                | UserEvent::FolderRestored(_)
+                | UserEvent::SubFolderCreated(_) => changes.created.push(event.clone()),
+                UserEvent::FileMoved(_)
+                | UserEvent::FileRenamed(_)
+                | UserEvent::FileVersioned(_)
+                | UserEvent::FolderColorChanged(_)
+                | UserEvent::FolderMoved(_)
+                | UserEvent::FolderRenamed(_)
+                | UserEvent::ItemFavorite(_)
+                | UserEvent::VersionedFileRestored(_) => changes.updated.push(event.clone()),
+                UserEvent::FileRm(_) | UserEvent::FileTrash(_) | UserEvent::FolderTrash(_) => {
+                    changes.deleted.push(event.clone());
+                }
+                UserEvent::DeleteAll(_) | UserEvent::Unknown(_) => changes.complete = false,
+                _ => {}
+            }
+        }
+        changes
+    }
+}
+
+/// Fetches the latest user events and returns changes of user's files and folders made after `since`,
+/// a Unix timestamp in seconds.
+///
+/// Only the latest page of events is examined, so check `SyncChanges::complete` before trusting the result.
+pub fn sync_changes_request(api_key: &SecUtf8, since: u64, filen_settings: &FilenSettings) -> Result<SyncChanges> {
+    let payload = latest_events_payload(api_key);
+    let response = user_events_request(&payload, filen_settings).context(UserEventsQueryFailedSnafu {})?;
+    sync_changes_from_response(response, since)
+}
+
+/// Asynchronously fetches the latest user events and returns changes of user's files and folders made after `since`,
+/// a Unix timestamp in seconds.
+///
+/// Only the latest page of events is examined, so check `SyncChanges::complete` before trusting the result.
+#[cfg(feature = "async")]
+pub async fn sync_changes_request_async(
+    api_key: &SecUtf8,
+    since: u64,
+    filen_settings: &FilenSettings,
+) -> Result<SyncChanges> {
+    let payload = latest_events_payload(api_key);
+    let response = user_events_request_async(&payload, filen_settings)
+        .await
+        .context(UserEventsQueryFailedSnafu {})?;
+    sync_changes_from_response(response, since)
+}
+
+const fn latest_events_payload(api_key: &SecUtf8) -> UserEventsRequestPayload<'_> {
+    UserEventsRequestPayload {
+        api_key,
+        id: LATEST_EVENTS_ID,
+        filter: UserEventFilter::All,
+    }
+}
+
+fn sync_changes_from_response(response: UserEventsResponsePayload, since: u64) -> Result<SyncChanges> {
+    match response.data {
+        Some(data) if response.status => Ok(SyncChanges::from_events(&data.events, since)),
+        _ => UserEventsRefusedSnafu {
+            message: response.message.unwrap_or_default(),
+        }
+        .fail(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{deserialize_from_file, init_server, setup_json_mock};
+    use once_cell::sync::Lazy;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    static API_KEY: Lazy<SecUtf8> =
+        Lazy::new(|| SecUtf8::from("bYZmrwdVEbHJSqeA1RfnPtKiBcXzUpRdKGRkjw9m1o1eqSGP1s6DM11CDnklpFq6"));
+    const SINCE: u64 = 1_636_740_000;
+    const EVENTS_RESPONSE_PATH: &str = "tests/resources/responses/user_events_sync_changes.json";
+
+    #[test]
+    fn sync_changes_request_should_sort_events_since_given_time() {
+        let (server, filen_settings) = init_server();
+        let response: UserEventsResponsePayload = deserialize_from_file(EVENTS_RESPONSE_PATH);
+        let mock = setup_json_mock("/v1/user/events", &latest_events_payload(&API_KEY), &response, &server);
+
+        let changes = sync_changes_request(&API_KEY, SINCE, &filen_settings).unwrap();
+
+        mock.assert_hits(1);
+        assert_sample_changes(&changes);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn sync_changes_request_async_should_sort_events_since_given_time() {
+        let (server, filen_settings) = init_server();
+        let response: UserEventsResponsePayload = deserialize_from_file(EVENTS_RESPONSE_PATH);
+        let mock = setup_json_mock("/v1/user/events", &latest_events_payload(&API_KEY), &response, &server);
+
+        let changes = sync_changes_request_async(&API_KEY, SINCE, &filen_settings)
+            .await
+            .unwrap();
+
+        mock.assert_hits(1);
+        assert_sample_changes(&changes);
+    }
+
+    #[test]
+    fn sync_changes_should_be_incomplete_when_events_do_not_reach_given_time() {
+        let response: UserEventsResponsePayload = deserialize_from_file(EVENTS_RESPONSE_PATH);
+
+        let changes = SyncChanges::from_events(&response.data.unwrap().events, 0);
+
+        assert!(!changes.complete);
+        assert_eq!(changes.created.len(), 3);
+    }
+
+    #[test]
+    fn sync_changes_should_treat_versioned_file_as_updated() {
+        let event = user_event(
+            "fileVersioned",
+            json!({
+                "uuid": "b5ec90d2-957c-4481-b211-08a68accd1b2",
+                "metadata": "U2FsdGVkX19wZ0qnk+d52Yz+zJDPF6qQL2Dyu0efKmZ52V782d/xUz8eXaBDDhGefelk3Fcysutm2h+lFzsBOrmZJM1KPVkaxfUaj04HMiQbXe1+J2zpeTvWqfaeye8LBi4KU167A5dSyxk0Ct5H13RZwc6tXEq2GN2+HiqvTcdHs1S59XEJHPW17NmrYBoT",
+                "ip": "127.0.0.1",
+                "userAgent": "Mozilla/5.0"
+            }),
+        );
+
+        let changes = SyncChanges::from_events(&with_sample_events(event.clone()), SINCE);
+
+        assert!(changes.complete);
+        assert_eq!(changes.updated.first(), Some(&event));
+        assert_eq!(changes.updated.len(), 3);
+    }
+
+    #[test]
+    fn sync_changes_should_be_incomplete_when_events_are_unknown() {
+        let event = user_event(
+            "someFutureEvent",
+            json!({"ip": "127.0.0.1", "userAgent": "Mozilla/5.0"}),
+        );
+
+        let changes = SyncChanges::from_events(&with_sample_events(event), SINCE);
+
+        assert!(!changes.complete);
+        assert_eq!(changes.created.len(), 3);
+    }
+
+    #[test]
+    fn sync_changes_request_should_fail_when_filen_refuses() {
+        let (server, filen_settings) = init_server();
+        let response = json!({"status": false, "message": "Invalid API key."});
+        let mock = setup_json_mock("/v1/user/events", &latest_events_payload(&API_KEY), &response, &server);
+
+        let result = sync_changes_request(&API_KEY, SINCE, &filen_settings);
+
+        mock.assert_hits(1);
+        assert!(matches!(result, Err(Error::UserEventsRefused { .. })));
+    }
+
+    fn user_event(kind: &str, info: serde_json::Value) -> UserEvent {
+        serde_json::from_value(json!({
+            "id": 11_056_028,
+            "uuid": "062ec149-1a44-4a64-8aed-b85be6e0b584",
+            "type": kind,
+            "info": info,
+            "timestamp": 1_636_760_000
+        }))
+        .unwrap()
+    }
+
+    /// Puts the given event in front of the sample events, which reach back to `SINCE`.
+    fn with_sample_events(newest_event: UserEvent) -> Vec<UserEvent> {
+        let response: UserEventsResponsePayload = deserialize_from_file(EVENTS_RESPONSE_PATH);
+        std::iter::once(newest_event)
+            .chain(response.data.unwrap().events)
+            .collect()
+    }
+
+    fn assert_sample_changes(changes: &SyncChanges) {
+        let kinds = |events: &[UserEvent]| {
+            events
+                .iter()
+                .map(|event| {
+                    serde_json::to_value(event).unwrap()["type"]
+                        .as_str()
+                        .unwrap()
+                        .to_owned()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert!(changes.complete);
+        assert_eq!(
+            kinds(&changes.created),
+            vec!["folderRestored", "subFolderCreated", "fileUploaded"]
+        );
+        assert_eq!(kinds(&changes.updated), vec!["itemFavorite", "fileRenamed"]);
+        assert_eq!(kinds(&changes.deleted), vec!["folderTrash"]);
+    }
+}
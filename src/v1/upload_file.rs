@@ -1,10 +1,10 @@
 use crate::{
-    crypto,
+    crypto::{self, MetadataVersion},
     file_chunk_pos::{FileChunkPosition, FileChunkPositions},
     queries, utils,
     v1::{
-        bool_from_int, bool_to_int, response_payload, Expire, FileChunkLocation, FileProperties, LocationNameMetadata,
-        PlainResponsePayload,
+        bool_from_int, bool_to_int, response_payload, user, Expire, FileChunkLocation, FileProperties, FilenResponse,
+        LocationNameMetadata, PlainResponsePayload,
     },
     FilenSettings, SettingsBundle,
 };
@@ -14,14 +14,13 @@ use snafu::{ensure, Backtrace, ResultExt, Snafu};
 use std::{
     cmp::{Eq, PartialEq},
     convert::TryInto,
-    io::{BufReader, Read, Seek, SeekFrom},
+    io::{BufReader, Read, Seek},
 };
-use url::Url;
 use uuid::Uuid;
 
 type Result<T, E = Error> = std::result::Result<T, E>;
 
-const FILE_CHUNK_SIZE: u32 = 1024 * 1024; // Hardcoded mostly because Filen has hardcoded chunk size as well
+pub(crate) const FILE_CHUNK_SIZE: u32 = 1024 * 1024; // Hardcoded mostly because Filen has hardcoded chunk size as well
 const UPLOAD_PATH: &str = "/v1/upload";
 const UPLOAD_DONE_PATH: &str = "/v1/upload/done";
 const UPLOAD_STOP_PATH: &str = "/v1/upload/stop";
@@ -60,6 +59,17 @@ pub enum Error {
     #[snafu(display("Filen did not accept uploaded dummy chunk: {}", message))]
     DummyChunkNotAccepted { message: String, backtrace: Backtrace },
 
+    #[snafu(display(
+        "File of size {} bytes does not fit into remaining storage of {} bytes",
+        file_size,
+        remaining_storage
+    ))]
+    InsufficientStorage {
+        file_size: u64,
+        remaining_storage: u64,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("File key be an alphanumeric string of 32 chars"))]
     FileKeyShouldHave32Chars { source: std::array::TryFromSliceError },
 
@@ -81,6 +91,12 @@ pub enum Error {
 
     #[snafu(display("{} query failed: {}", USER_UNFINISHED_DELETE_PATH, source))]
     UserUnfinishedDeleteQueryFailed { source: queries::Error },
+
+    #[snafu(display("User info query failed: {}", source))]
+    UserInfoQueryFailed { source: user::Error },
+
+    #[snafu(display("Filen refused to give user info: {}", source))]
+    UserInfoRefused { source: crate::v1::Error },
 }
 
 /// Response data for `UPLOAD_PATH` endpoint.
@@ -191,14 +207,34 @@ impl FileUploadProperties {
         version: u32,
         parent_folder_uuid: Uuid,
         last_master_key: &SecUtf8,
+    ) -> Self {
+        Self::from_file_properties_with_version(
+            file_properties,
+            version,
+            parent_folder_uuid,
+            last_master_key,
+            MetadataVersion::default(),
+        )
+    }
+
+    /// Assigns file upload properties like `from_file_properties` does, encrypting all metadata
+    /// with the specified metadata version.
+    #[must_use]
+    pub fn from_file_properties_with_version(
+        file_properties: &FileProperties,
+        version: u32,
+        parent_folder_uuid: Uuid,
+        last_master_key: &SecUtf8,
+        metadata_version: MetadataVersion,
     ) -> Self {
         let rm = utils::random_alphanumeric_string(32);
         let upload_key = utils::random_alphanumeric_string(32);
 
-        let file_metadata_encrypted = file_properties.to_metadata_string(last_master_key);
-        let name_metadata_encrypted = file_properties.name_encrypted();
-        let size_metadata_encrypted = file_properties.size_encrypted();
-        let mime_metadata_encrypted = file_properties.mime_encrypted();
+        let file_metadata_encrypted =
+            file_properties.to_metadata_string_with_version(last_master_key, metadata_version);
+        let name_metadata_encrypted = file_properties.name_encrypted_with_version(metadata_version);
+        let size_metadata_encrypted = file_properties.size_encrypted_with_version(metadata_version);
+        let mime_metadata_encrypted = file_properties.mime_encrypted_with_version(metadata_version);
         let name_hashed = LocationNameMetadata::name_hashed(&file_properties.name);
 
         let file_chunks = calculate_chunk_count(FILE_CHUNK_SIZE, file_properties.size);
@@ -220,41 +256,78 @@ impl FileUploadProperties {
     }
 
     /// Produces percent-encoded string of query parameters for Filen upload endpoint, using this properties.
-    #[allow(clippy::missing_panics_doc)]
     #[must_use]
     pub fn to_query_params(&self, chunk_index: u32, api_key: &SecUtf8) -> String {
-        // Cannot panic, all query parts are valid.
-        let query_builder = Url::parse_with_params(
-            "https://localhost?",
-            &[
-                ("apiKey", api_key.unsecure()),
-                ("uuid", &self.uuid.as_hyphenated().to_string()),
-                ("name", &self.name_metadata),
-                ("nameHashed", &self.name_hashed),
-                ("size", &self.size_metadata),
-                ("chunks", &self.chunks.to_string()),
-                ("mime", &self.mime_metadata),
-                ("index", &chunk_index.to_string()),
-                ("rm", &self.rm),
-                ("expire", &self.expire.to_string()),
-                ("uploadKey", &self.upload_key),
-                ("metaData", &self.file_metadata),
-                ("parent", &self.parent_uuid.as_hyphenated().to_string()),
-                ("version", &self.version.to_string()),
-            ],
-        )
-        .unwrap();
-        query_builder.query().unwrap().to_owned()
+        UploadChunkParams::new(api_key, self, chunk_index).to_query_string()
     }
 
     /// Produces API endpoint for file upload using this properties.
     #[must_use]
     pub fn to_api_endpoint(&self, chunk_index: u32, api_key: &SecUtf8) -> String {
-        format!("{}?{}", UPLOAD_PATH, self.to_query_params(chunk_index, api_key))
+        UploadChunkParams::new(api_key, self, chunk_index).to_api_endpoint()
     }
 }
 utils::display_from_json!(FileUploadProperties);
 
+/// Parameters of a single file chunk upload to `UPLOAD_PATH` endpoint.
+/// Filen expects them in the query string, while request body contains only encrypted chunk bytes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UploadChunkParams<'upload_chunk> {
+    /// User-associated Filen API key.
+    pub api_key: &'upload_chunk SecUtf8,
+
+    /// Properties of the file being uploaded.
+    pub upload_properties: &'upload_chunk FileUploadProperties,
+
+    /// Zero-based index of the uploaded chunk.
+    pub chunk_index: u32,
+}
+
+impl<'upload_chunk> UploadChunkParams<'upload_chunk> {
+    #[must_use]
+    pub const fn new(
+        api_key: &'upload_chunk SecUtf8,
+        upload_properties: &'upload_chunk FileUploadProperties,
+        chunk_index: u32,
+    ) -> Self {
+        Self {
+            api_key,
+            upload_properties,
+            chunk_index,
+        }
+    }
+
+    /// Produces percent-encoded query string with parameters in the order Filen clients send them.
+    #[must_use]
+    pub fn to_query_string(&self) -> String {
+        let properties = self.upload_properties;
+        url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(&[
+                ("apiKey", self.api_key.unsecure()),
+                ("uuid", &properties.uuid.as_hyphenated().to_string()),
+                ("name", &properties.name_metadata),
+                ("nameHashed", &properties.name_hashed),
+                ("size", &properties.size_metadata),
+                ("chunks", &properties.chunks.to_string()),
+                ("mime", &properties.mime_metadata),
+                ("index", &self.chunk_index.to_string()),
+                ("rm", &properties.rm),
+                ("expire", &properties.expire.to_string()),
+                ("uploadKey", &properties.upload_key),
+                ("metaData", &properties.file_metadata),
+                ("parent", &properties.parent_uuid.as_hyphenated().to_string()),
+                ("version", &properties.version.to_string()),
+            ])
+            .finish()
+    }
+
+    /// Produces relative API endpoint with query string, ready to be passed to `queries::upload_to_filen`.
+    #[must_use]
+    pub fn to_api_endpoint(&self) -> String {
+        format!("{}?{}", UPLOAD_PATH, self.to_query_string())
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct FileUploadInfo {
     pub properties: FileUploadProperties,
@@ -365,7 +438,7 @@ pub fn encrypt_and_upload_chunk(
             file_version: upload_properties.version,
         })?;
     let chunk_size = chunk_encrypted.len();
-    let api_endpoint = upload_properties.to_api_endpoint(chunk_index, api_key);
+    let api_endpoint = UploadChunkParams::new(api_key, upload_properties, chunk_index).to_api_endpoint();
     queries::upload_to_filen::<UploadFileChunkResponsePayload>(
         &api_endpoint,
         chunk_encrypted.as_bytes(),
@@ -402,7 +475,8 @@ pub async fn encrypt_and_upload_chunk_async(
         })?;
 
     let chunk_size = chunk_encrypted.len();
-    let api_endpoint = upload_properties.to_api_endpoint(chunk_index, api_key);
+    let api_endpoint = UploadChunkParams::new(api_key, upload_properties, chunk_index).to_api_endpoint();
+    let _permit = filen_settings.acquire_chunk_permit_async().await;
     queries::upload_to_filen_async::<UploadFileChunkResponsePayload>(
         &api_endpoint,
         chunk_encrypted.as_bytes(),
@@ -443,10 +517,56 @@ pub async fn user_unfinished_delete_request_async(
     .context(UserUnfinishedDeleteQueryFailedSnafu {})
 }
 
+/// Checks if file of the given size fits into user's remaining storage, so large uploads can fail fast
+/// instead of wasting bandwidth and leaving orphaned chunks behind. Returns remaining storage in bytes,
+/// or `Error::InsufficientStorage` if file does not fit.
+pub fn ensure_upload_fits_storage(api_key: &SecUtf8, file_size: u64, settings: &SettingsBundle) -> Result<u64> {
+    let user_info_response = settings
+        .retry
+        .call(|| user::user_info_request(api_key, &settings.filen))
+        .context(UserInfoQueryFailedSnafu {})?;
+    let user_info = user_info_response.data_ref_or_err().context(UserInfoRefusedSnafu {})?;
+    ensure_file_fits(file_size, user_info.remaining_storage())
+}
+
+/// Asynchronously checks if file of the given size fits into user's remaining storage, so large uploads can fail
+/// fast instead of wasting bandwidth and leaving orphaned chunks behind. Returns remaining storage in bytes,
+/// or `Error::InsufficientStorage` if file does not fit.
+#[cfg(feature = "async")]
+pub async fn ensure_upload_fits_storage_async(
+    api_key: &SecUtf8,
+    file_size: u64,
+    settings: &SettingsBundle,
+) -> Result<u64> {
+    let user_info_response = settings
+        .retry
+        .call_async(|| user::user_info_request_async(api_key, &settings.filen))
+        .await
+        .context(UserInfoQueryFailedSnafu {})?;
+    let user_info = user_info_response.data_ref_or_err().context(UserInfoRefusedSnafu {})?;
+    ensure_file_fits(file_size, user_info.remaining_storage())
+}
+
+fn ensure_file_fits(file_size: u64, remaining_storage: u64) -> Result<u64> {
+    ensure!(
+        file_size <= remaining_storage,
+        InsufficientStorageSnafu {
+            file_size,
+            remaining_storage
+        }
+    );
+    Ok(remaining_storage)
+}
+
 /// Uploads file to Filen by reading file chunks from given reader,
 /// encrypting them and uploading each chunk with additional dummy chunk at the end.
 ///
 /// 'version' determines how file bytes should be encrypted/decrypted, for now Filen uses version = 1 everywhere.
+/// File chunks are encrypted with `file_properties.key`, so to reuse an existing file key, create file properties
+/// with `FileProperties::from_name_size_modified_key` or `FileProperties::from_name_local_path_key`.
+///
+/// Reader is rewound to the start before reading; use `encrypt_and_upload_from_reader` for readers
+/// which cannot seek.
 pub fn encrypt_and_upload_file<R: Read + Seek>(
     api_key: &SecUtf8,
     parent_uuid: Uuid,
@@ -456,8 +576,42 @@ pub fn encrypt_and_upload_file<R: Read + Seek>(
     reader: &mut BufReader<R>,
     settings: &SettingsBundle,
 ) -> Result<FileUploadInfo> {
-    let upload_properties =
-        FileUploadProperties::from_file_properties(file_properties, version, parent_uuid, last_master_key);
+    reader.rewind().context(SeekReadSnafu {})?;
+    encrypt_and_upload_from_reader(
+        api_key,
+        parent_uuid,
+        file_properties,
+        version,
+        last_master_key,
+        reader,
+        settings,
+    )
+}
+
+/// Uploads file to Filen by reading exactly `file_properties.size` bytes from the given reader,
+/// encrypting them and uploading each chunk with additional dummy chunk at the end.
+///
+/// Reader does not need to seek, so file contents can be streamed straight from another source,
+/// like a body of an HTTP response, without storing them in a temporary file first.
+/// File chunks are read sequentially, one chunk is held in memory at a time.
+///
+/// 'version' determines how file bytes should be encrypted/decrypted, for now Filen uses version = 1 everywhere.
+pub fn encrypt_and_upload_from_reader<R: Read>(
+    api_key: &SecUtf8,
+    parent_uuid: Uuid,
+    file_properties: &FileProperties,
+    version: u32,
+    last_master_key: &SecUtf8,
+    reader: &mut R,
+    settings: &SettingsBundle,
+) -> Result<FileUploadInfo> {
+    let upload_properties = FileUploadProperties::from_file_properties_with_version(
+        file_properties,
+        version,
+        parent_uuid,
+        last_master_key,
+        settings.filen.metadata_version,
+    );
     let chunk_upload_responses = upload_chunks(
         api_key,
         FILE_CHUNK_SIZE,
@@ -510,10 +664,15 @@ pub fn encrypt_and_upload_file<R: Read + Seek>(
 /// encrypting them and uploading each chunk with additional dummy chunk at the end.
 ///
 /// 'version' determines how file bytes should be encrypted/decrypted, for now Filen uses version = 1 everywhere.
+/// File chunks are encrypted with `file_properties.key`, so to reuse an existing file key, create file properties
+/// with `FileProperties::from_name_size_modified_key` or `FileProperties::from_name_local_path_key`.
 ///
 /// Note that file upload is explicitly retriable and always requires `RetrySettings` as an argument.
 /// You can pass `crate::NO_RETRIES` if you really want to fail the entire file upload  even if a single chunk
 /// upload request fails temporarily, otherwise `crate::STANDARD_RETRIES` is a better fit.
+///
+/// Reader is rewound to the start before reading; use `encrypt_and_upload_from_reader_async` for readers
+/// which cannot seek.
 #[cfg(feature = "async")]
 pub async fn encrypt_and_upload_file_async<R: Read + Seek + Send>(
     api_key: &SecUtf8,
@@ -524,8 +683,43 @@ pub async fn encrypt_and_upload_file_async<R: Read + Seek + Send>(
     reader: &mut BufReader<R>,
     settings: &SettingsBundle,
 ) -> Result<FileUploadInfo> {
-    let upload_properties =
-        FileUploadProperties::from_file_properties(file_properties, version, parent_uuid, last_master_key);
+    reader.rewind().context(SeekReadSnafu {})?;
+    encrypt_and_upload_from_reader_async(
+        api_key,
+        parent_uuid,
+        file_properties,
+        version,
+        last_master_key,
+        reader,
+        settings,
+    )
+    .await
+}
+
+/// Asynchronously uploads file to Filen by reading exactly `file_properties.size` bytes from the given reader,
+/// encrypting them and uploading each chunk with additional dummy chunk at the end.
+///
+/// Reader does not need to seek, so file contents can be streamed straight from another source,
+/// like a body of an HTTP response, without storing them in a temporary file first.
+///
+/// 'version' determines how file bytes should be encrypted/decrypted, for now Filen uses version = 1 everywhere.
+#[cfg(feature = "async")]
+pub async fn encrypt_and_upload_from_reader_async<R: Read + Send>(
+    api_key: &SecUtf8,
+    parent_uuid: Uuid,
+    file_properties: &FileProperties,
+    version: u32,
+    last_master_key: &SecUtf8,
+    reader: &mut R,
+    settings: &SettingsBundle,
+) -> Result<FileUploadInfo> {
+    let upload_properties = FileUploadProperties::from_file_properties_with_version(
+        file_properties,
+        version,
+        parent_uuid,
+        last_master_key,
+        settings.filen.metadata_version,
+    );
     let chunk_upload_responses = upload_chunks_async(
         api_key,
         FILE_CHUNK_SIZE,
@@ -602,12 +796,12 @@ where
 /// Uploads all real file chunks to Filen; do not forget to upload dummy chunk after real chunks are uploaded.
 /// Returned file chunk upload responses are in order: first upload response corresponds to the
 /// first file chunk uploaded, and so on.
-fn upload_chunks<R: Read + Seek>(
+fn upload_chunks<R: Read>(
     api_key: &SecUtf8,
     file_chunk_size: u32,
     file_size: u64,
     upload_properties: &FileUploadProperties,
-    reader: &mut BufReader<R>,
+    reader: &mut R,
     settings: &SettingsBundle,
 ) -> Result<Vec<UploadFileChunkResponsePayload>> {
     let chunk_processor = |chunk_pos: FileChunkPosition, chunk: Vec<u8>| {
@@ -624,12 +818,12 @@ fn upload_chunks<R: Read + Seek>(
 /// Returned file chunk upload responses are in order: first upload response corresponds to the
 /// first file chunk uploaded, and so on.
 #[cfg(feature = "async")]
-async fn upload_chunks_async<R: Read + Seek + Send>(
+async fn upload_chunks_async<R: Read + Send>(
     api_key: &SecUtf8,
     file_chunk_size: u32,
     file_size: u64,
     upload_properties: &FileUploadProperties,
-    reader: &mut BufReader<R>,
+    reader: &mut R,
     settings: &SettingsBundle,
 ) -> Result<Vec<UploadFileChunkResponsePayload>> {
     let chunk_processor = |chunk_pos: FileChunkPosition, chunk: Vec<u8>| async move {
@@ -641,29 +835,29 @@ async fn upload_chunks_async<R: Read + Seek + Send>(
             .await
     };
     // You might notice that file chunks are still read sequentially.
-    // I assume that trying to read multiple chunks of the file in parallel is not fast
-    // because it forces continuos seeks during IO.
+    // Readers are not required to seek, and for those which can, reading multiple chunks of the file
+    // in parallel is not fast anyway, because it forces continuos seeks during IO.
     let future_chunk_responses: Result<Vec<_>> =
         read_into_chunks_and_process(file_chunk_size, file_size, reader, chunk_processor).collect();
     futures::future::try_join_all(future_chunk_responses?).await
 }
 
+/// Reads file chunks one after another from the current reader position, so reader does not need to seek.
 fn read_into_chunks_and_process<'reader, R, ProcType, ProcResult>(
     file_chunk_size: u32,
     file_size: u64,
-    reader: &'reader mut BufReader<R>,
+    reader: &'reader mut R,
     chunk_processor: ProcType,
 ) -> impl Iterator<Item = Result<ProcResult>> + 'reader
 where
-    R: Read + Seek,
+    R: Read,
     ProcType: 'reader + Fn(FileChunkPosition, Vec<u8>) -> ProcResult,
 {
     let file_chunk_positions = FileChunkPositions::new(file_chunk_size, file_size);
     file_chunk_positions.map(move |chunk_pos| {
         let mut chunk_buf = vec![0_u8; chunk_pos.chunk_size as usize];
         reader
-            .seek(SeekFrom::Start(chunk_pos.start_position))
-            .and_then(|_| reader.read_exact(&mut chunk_buf))
+            .read_exact(&mut chunk_buf)
             .context(SeekReadSnafu {})
             .map(|_| chunk_processor(chunk_pos, chunk_buf))
     })
@@ -724,9 +918,345 @@ const fn calculate_chunk_count(chunk_size: u32, file_size: u64) -> u32 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{test_utils, v1::FileKey};
+    use httpmock::{Method::POST, Mock, MockServer};
     use pretty_assertions::assert_eq;
     use std::time::SystemTime;
 
+    #[test]
+    fn file_upload_properties_should_encrypt_all_metadata_with_given_version() {
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let file_properties = FileProperties::from_name_size_modified("test.txt", 14, &SystemTime::now()).unwrap();
+
+        let v1_properties = FileUploadProperties::from_file_properties(&file_properties, 1, Uuid::nil(), &m_key);
+        let v2_properties = FileUploadProperties::from_file_properties_with_version(
+            &file_properties,
+            1,
+            Uuid::nil(),
+            &m_key,
+            MetadataVersion::V2,
+        );
+
+        for metadata in [
+            &v1_properties.file_metadata,
+            &v1_properties.name_metadata,
+            &v1_properties.size_metadata,
+            &v1_properties.mime_metadata,
+        ] {
+            assert!(metadata.starts_with("U2FsdGVk"), "{}", metadata);
+        }
+        for metadata in [
+            &v2_properties.file_metadata,
+            &v2_properties.name_metadata,
+            &v2_properties.size_metadata,
+            &v2_properties.mime_metadata,
+        ] {
+            assert!(metadata.starts_with("002"), "{}", metadata);
+        }
+        assert_eq!(
+            FileProperties::decrypt_file_metadata(&v2_properties.file_metadata, &[m_key]).unwrap(),
+            file_properties
+        );
+    }
+
+    #[test]
+    fn encrypt_and_upload_chunk_should_encrypt_chunk_with_supplied_file_key() {
+        let (server, filen_settings) = test_utils::init_server();
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let file_key = FileKey::new(SecUtf8::from("sh1YRHfx22Ij40tQBbt6BgpBlqkzch8Y")).unwrap();
+        let chunk = b"This is Jimmy.";
+        let expected_chunk = crypto::encrypt_file_chunk(chunk, b"sh1YRHfx22Ij40tQBbt6BgpBlqkzch8Y", 1).unwrap();
+        let file_properties =
+            FileProperties::from_name_size_modified_key("test.txt", 14, &SystemTime::now(), Some(file_key.clone()))
+                .unwrap();
+        let properties = FileUploadProperties::from_file_properties(&file_properties, 1, Uuid::nil(), &m_key);
+        let expected_response: UploadFileChunkResponsePayload =
+            test_utils::deserialize_from_file("tests/resources/responses/upload.json");
+        let mock = server.mock(|when, then| {
+            when.method(POST).path(UPLOAD_PATH).body(expected_chunk);
+            then.status(200).json_body_obj(&expected_response);
+        });
+
+        let response =
+            encrypt_and_upload_chunk(&SecUtf8::from("some api key"), 0, chunk, &properties, &filen_settings).unwrap();
+
+        mock.assert_hits(1);
+        assert_eq!(response, expected_response);
+        assert_eq!(&properties.file_key, file_key.as_sec_utf8());
+    }
+
+    #[test]
+    fn ensure_upload_fits_storage_should_fail_when_file_exceeds_remaining_storage() {
+        let (server, filen_settings) = test_utils::init_server();
+        let api_key = SecUtf8::from("some api key");
+        let user_info_response: user::UserInfoResponsePayload =
+            test_utils::deserialize_from_file("tests/resources/responses/user_info.json");
+        let user_info_mock = test_utils::setup_json_mock(
+            "/v1/user/info",
+            &utils::api_key_json(&api_key),
+            &user_info_response,
+            &server,
+        );
+        let settings = SettingsBundle {
+            filen: filen_settings,
+            ..SettingsBundle::default()
+        };
+
+        let fitting_result = ensure_upload_fits_storage(&api_key, 10_735_260_224, &settings);
+        let exceeding_result = ensure_upload_fits_storage(&api_key, 10_735_260_225, &settings);
+
+        user_info_mock.assert_hits(2);
+        assert_eq!(fitting_result.unwrap(), 10_735_260_224);
+        assert!(matches!(
+            exceeding_result,
+            Err(Error::InsufficientStorage {
+                file_size: 10_735_260_225,
+                remaining_storage: 10_735_260_224,
+                ..
+            })
+        ));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn ensure_upload_fits_storage_async_should_fail_when_file_exceeds_remaining_storage() {
+        let (server, filen_settings) = test_utils::init_server();
+        let api_key = SecUtf8::from("some api key");
+        let user_info_response: user::UserInfoResponsePayload =
+            test_utils::deserialize_from_file("tests/resources/responses/user_info.json");
+        let user_info_mock = test_utils::setup_json_mock(
+            "/v1/user/info",
+            &utils::api_key_json(&api_key),
+            &user_info_response,
+            &server,
+        );
+        let settings = SettingsBundle {
+            filen: filen_settings,
+            ..SettingsBundle::default()
+        };
+
+        let result = ensure_upload_fits_storage_async(&api_key, 20 * 1024 * 1024 * 1024, &settings).await;
+
+        user_info_mock.assert_hits(1);
+        assert!(matches!(result, Err(Error::InsufficientStorage { .. })));
+    }
+
+    #[test]
+    fn upload_file_chunk_response_should_have_proper_contract() {
+        let response_json: serde_json::Value =
+            test_utils::deserialize_from_file("tests/resources/responses/upload.json");
+
+        let response: UploadFileChunkResponsePayload = serde_json::from_value(response_json.clone()).unwrap();
+
+        assert_eq!(
+            response.data,
+            Some(UploadFileChunkResponseData {
+                bucket: "filen-1".to_owned(),
+                region: "de-1".to_owned(),
+                expire_set: true,
+                expire_timestamp: 4_784_907_962,
+                delete_timestamp: 4_784_907_962,
+            })
+        );
+        assert_eq!(serde_json::to_value(&response).unwrap(), response_json);
+    }
+
+    #[test]
+    fn file_upload_info_should_collect_chunk_locations_from_chunk_responses() {
+        let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
+        let file_properties =
+            FileProperties::from_name_size_modified("test.txt", u64::from(FILE_CHUNK_SIZE) + 1, &SystemTime::now())
+                .unwrap();
+        let properties = FileUploadProperties::from_file_properties(&file_properties, 1, Uuid::nil(), &m_key);
+        let chunk_response: UploadFileChunkResponsePayload =
+            test_utils::deserialize_from_file("tests/resources/responses/upload.json");
+        let mut missing_data_response = chunk_response.clone();
+        missing_data_response.data = None;
+
+        let upload_info = FileUploadInfo::new(properties.clone(), vec![chunk_response.clone(), chunk_response.clone()]);
+        let incomplete_upload_info =
+            FileUploadInfo::new(properties.clone(), vec![chunk_response, missing_data_response]);
+
+        assert_eq!(
+            upload_info.get_file_chunk_locations().unwrap(),
+            (0..2)
+                .map(|chunk_index| FileChunkLocation {
+                    region: "de-1".to_owned(),
+                    bucket: "filen-1".to_owned(),
+                    file_uuid: properties.uuid,
+                    chunk_index,
+                })
+                .collect::<Vec<_>>()
+        );
+        assert!(matches!(
+            incomplete_upload_info.get_file_chunk_locations(),
+            Err(Error::ChunkUploadResponseMissingData { .. })
+        ));
+    }
+
+    const FILE_KEY: &str = "sh1YRHfx22Ij40tQBbt6BgpBlqkzch8Y";
+
+    /// Sets up mocks for a file of 2 chunks with the given contents, returns them in the order of upload.
+    fn setup_two_chunk_upload_mocks<'server>(contents: &[u8], server: &'server MockServer) -> Vec<Mock<'server>> {
+        let file_key = FILE_KEY.as_bytes().try_into().unwrap();
+        let (first_chunk, second_chunk) = contents.split_at(FILE_CHUNK_SIZE as usize);
+        let chunk_response: UploadFileChunkResponsePayload =
+            test_utils::deserialize_from_file("tests/resources/responses/upload.json");
+        let mut mocks = [first_chunk, second_chunk, &[]]
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| {
+                let expected_chunk = crypto::encrypt_file_chunk(chunk, file_key, 1).unwrap();
+                server.mock(|when, then| {
+                    when.method(POST)
+                        .path(UPLOAD_PATH)
+                        .query_param("index", &index.to_string())
+                        .body(expected_chunk);
+                    then.status(200).json_body_obj(&chunk_response);
+                })
+            })
+            .collect::<Vec<_>>();
+        mocks.push(server.mock(|when, then| {
+            when.method(POST).path(UPLOAD_DONE_PATH);
+            then.status(200)
+                .json_body(serde_json::json!({"status": true, "message": "Upload done."}));
+        }));
+        mocks
+    }
+
+    fn two_chunk_file() -> (Vec<u8>, FileProperties) {
+        let contents = (0..FILE_CHUNK_SIZE + 10).map(|i| i as u8).collect::<Vec<u8>>();
+        let file_key = FileKey::new(SecUtf8::from(FILE_KEY)).unwrap();
+        let file_properties = FileProperties::from_name_size_modified_key(
+            "migrated.bin",
+            contents.len() as u64,
+            &SystemTime::now(),
+            Some(file_key),
+        )
+        .unwrap();
+        (contents, file_properties)
+    }
+
+    #[test]
+    fn encrypt_and_upload_from_reader_should_upload_from_reader_which_cannot_seek() {
+        let (server, filen_settings) = test_utils::init_server();
+        let (contents, file_properties) = two_chunk_file();
+        let mocks = setup_two_chunk_upload_mocks(&contents, &server);
+        let settings = SettingsBundle {
+            filen: filen_settings,
+            ..SettingsBundle::default()
+        };
+        // Byte slice reader does not implement Seek, just like a body of an HTTP response.
+        let mut reader: &[u8] = &contents;
+
+        let upload_info = encrypt_and_upload_from_reader(
+            &SecUtf8::from("some api key"),
+            Uuid::nil(),
+            &file_properties,
+            1,
+            &SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae"),
+            &mut reader,
+            &settings,
+        )
+        .unwrap();
+
+        for mock in mocks {
+            mock.assert_hits(1);
+        }
+        assert_eq!(upload_info.chunk_responses.len(), 2);
+        assert!(reader.is_empty());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn encrypt_and_upload_from_reader_async_should_upload_from_reader_which_cannot_seek() {
+        let (server, filen_settings) = test_utils::init_server();
+        let (contents, file_properties) = two_chunk_file();
+        let mocks = setup_two_chunk_upload_mocks(&contents, &server);
+        let settings = SettingsBundle {
+            filen: filen_settings,
+            ..SettingsBundle::default()
+        };
+        let mut reader: &[u8] = &contents;
+
+        let upload_info = encrypt_and_upload_from_reader_async(
+            &SecUtf8::from("some api key"),
+            Uuid::nil(),
+            &file_properties,
+            1,
+            &SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae"),
+            &mut reader,
+            &settings,
+        )
+        .await
+        .unwrap();
+
+        for mock in mocks {
+            mock.assert_hits(1);
+        }
+        assert_eq!(upload_info.chunk_responses.len(), 2);
+    }
+
+    #[test]
+    fn encrypt_and_upload_file_should_rewind_reader_before_upload() {
+        let (server, filen_settings) = test_utils::init_server();
+        let (contents, file_properties) = two_chunk_file();
+        let mocks = setup_two_chunk_upload_mocks(&contents, &server);
+        let settings = SettingsBundle {
+            filen: filen_settings,
+            ..SettingsBundle::default()
+        };
+        let mut reader = BufReader::new(std::io::Cursor::new(contents));
+        reader.read_exact(&mut [0_u8; 100]).unwrap();
+
+        let upload_result = encrypt_and_upload_file(
+            &SecUtf8::from("some api key"),
+            Uuid::nil(),
+            &file_properties,
+            1,
+            &SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae"),
+            &mut reader,
+            &settings,
+        );
+
+        for mock in mocks {
+            mock.assert_hits(1);
+        }
+        assert!(upload_result.is_ok());
+    }
+
+    #[test]
+    fn upload_chunk_params_should_produce_known_good_api_endpoint() {
+        let properties = FileUploadProperties {
+            uuid: Uuid::parse_str("b5ec90d4-f3b5-4b5e-8f8d-62a6d7b0a8e9").unwrap(),
+            name_metadata: "U2FsdGVkX1+name/metadata=".to_owned(),
+            name_hashed: "809a953250a3917a9993645d1ba146348a198fc2".to_owned(),
+            size_metadata: "U2FsdGVkX1+size".to_owned(),
+            chunks: 2,
+            mime_metadata: "U2FsdGVkX1+mime".to_owned(),
+            file_metadata: "U2FsdGVkX1+file".to_owned(),
+            file_key: SecUtf8::from("sh1YRHfx22Ij40tQBbt6BgpBlqkzch8Y"),
+            rm: "rmrmrmrmrmrmrmrmrmrmrmrmrmrmrmrm".to_owned(),
+            upload_key: "ukukukukukukukukukukukukukukukuk".to_owned(),
+            expire: Expire::Never,
+            parent_uuid: Uuid::parse_str("80f678c0-56ce-4b81-b4ef-f2a9c0c737c4").unwrap(),
+            version: 1,
+        };
+        let api_key = SecUtf8::from("some api key");
+
+        let api_endpoint = UploadChunkParams::new(&api_key, &properties, 1).to_api_endpoint();
+
+        assert_eq!(
+            api_endpoint,
+            "/v1/upload?apiKey=some+api+key&uuid=b5ec90d4-f3b5-4b5e-8f8d-62a6d7b0a8e9\
+             &name=U2FsdGVkX1%2Bname%2Fmetadata%3D&nameHashed=809a953250a3917a9993645d1ba146348a198fc2\
+             &size=U2FsdGVkX1%2Bsize&chunks=2&mime=U2FsdGVkX1%2Bmime&index=1&rm=rmrmrmrmrmrmrmrmrmrmrmrmrmrmrmrm\
+             &expire=never&uploadKey=ukukukukukukukukukukukukukukukuk&metaData=U2FsdGVkX1%2Bfile\
+             &parent=80f678c0-56ce-4b81-b4ef-f2a9c0c737c4&version=1"
+        );
+        assert_eq!(properties.to_api_endpoint(1, &api_key), api_endpoint);
+    }
+
     #[test]
     fn uploaded_file_properties_should_produce_query_string_with_expected_parts() {
         let m_key = SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae");
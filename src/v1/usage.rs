@@ -103,12 +103,18 @@ pub async fn user_sync_get_data_request_async(
 }
 
 /// Calls `USER_USAGE_PATH` endpoint. Used to fetch user general usage stats.
+///
+/// This is the single call returning an overview of the account state: used and available storage,
+/// files and folders count, 2FA and premium status.
 pub fn user_usage_request(api_key: &SecUtf8, filen_settings: &FilenSettings) -> Result<UserUsageResponsePayload> {
     queries::query_filen_api(USER_USAGE_PATH, &utils::api_key_json(api_key), filen_settings)
         .context(UserUsageQueryFailedSnafu {})
 }
 
 /// Calls `USER_USAGE_PATH` endpoint asynchronously. Used to fetch user general usage stats.
+///
+/// This is the single call returning an overview of the account state: used and available storage,
+/// files and folders count, 2FA and premium status.
 #[cfg(feature = "async")]
 pub async fn user_usage_request_async(
     api_key: &SecUtf8,
@@ -118,3 +124,37 @@ pub async fn user_usage_request_async(
         .await
         .context(UserUsageQueryFailedSnafu {})
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::validate_contract;
+    #[cfg(feature = "async")]
+    use crate::test_utils::validate_contract_async;
+    use once_cell::sync::Lazy;
+
+    static API_KEY: Lazy<SecUtf8> =
+        Lazy::new(|| SecUtf8::from("bYZmrwdVEbHJSqeA1RfnPtKiBcXzUpRdKGRkjw9m1o1eqSGP1s6DM11CDnklpFq6"));
+
+    #[test]
+    fn user_usage_request_should_have_proper_contract() {
+        validate_contract(
+            USER_USAGE_PATH,
+            &utils::api_key_json(&API_KEY),
+            "tests/resources/responses/user_usage.json",
+            |_, filen_settings| user_usage_request(&API_KEY, &filen_settings),
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn user_usage_request_async_should_have_proper_contract() {
+        validate_contract_async(
+            USER_USAGE_PATH,
+            &utils::api_key_json(&API_KEY),
+            "tests/resources/responses/user_usage.json",
+            |_, filen_settings| async move { user_usage_request_async(&API_KEY, &filen_settings).await },
+        )
+        .await;
+    }
+}
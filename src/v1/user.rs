@@ -1,6 +1,7 @@
 use crate::{
+    crypto::{self, MetadataVersion},
     queries, utils,
-    v1::{bool_from_int, bool_to_int, response_payload, FilenResponse, Uuid},
+    v1::{bool_from_int, bool_to_int, response_payload, FilenPasswordWithMasterKey, FilenResponse, Uuid},
     FilenSettings,
 };
 use secstr::SecUtf8;
@@ -16,6 +17,12 @@ type Result<T, E = Error> = std::result::Result<T, E>;
 const USER_GET_ACCOUNT_PATH: &str = "/v1/user/get/account";
 const USER_GET_SETTINGS_PATH: &str = "/v1/user/get/settings";
 const USER_INFO_PATH: &str = "/v1/user/info";
+const USER_SETTINGS_PASSWORD_CHANGE_PATH: &str = "/v1/user/settings/password/change";
+const USER_SETTINGS_2FA_DISABLE_PATH: &str = "/v1/user/settings/2fa/disable";
+const USER_SETTINGS_2FA_ENABLE_PATH: &str = "/v1/user/settings/2fa/enable";
+
+/// Length of the salt generated for a new password, same as the one Filen generates on registration.
+const FILEN_SALT_LENGTH: usize = 256;
 
 #[derive(Snafu, Debug)]
 pub enum Error {
@@ -27,6 +34,15 @@ pub enum Error {
 
     #[snafu(display("{} query failed: {}", USER_INFO_PATH, source))]
     UserInfoQueryFailed { source: queries::Error },
+
+    #[snafu(display("{} query failed: {}", USER_SETTINGS_PASSWORD_CHANGE_PATH, source))]
+    UserSettingsPasswordChangeQueryFailed { source: queries::Error },
+
+    #[snafu(display("{} query failed: {}", USER_SETTINGS_2FA_DISABLE_PATH, source))]
+    UserSettings2faDisableQueryFailed { source: queries::Error },
+
+    #[snafu(display("{} query failed: {}", USER_SETTINGS_2FA_ENABLE_PATH, source))]
+    UserSettings2faEnableQueryFailed { source: queries::Error },
 }
 
 #[allow(clippy::doc_markdown)]
@@ -271,6 +287,10 @@ pub struct UserGetSettingsResponseData {
     /// Storage bytes used by uploaded unversioned files.
     #[serde(rename = "storageUsed")]
     pub storage_used: u64,
+
+    /// Response fields unknown to this crate, preserved as is.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 utils::display_from_json!(UserGetSettingsResponseData);
 
@@ -307,14 +327,188 @@ pub struct UserInfoResponseData {
     #[serde(rename = "avatarURL")]
     #[serde_as(as = "DisplayFromStr")]
     pub avatar_url: Url,
+
+    /// True if user has verified account email; false if client should prompt user to verify it.
+    /// Responses without this field are considered to be verified.
+    #[serde(
+        rename = "emailVerified",
+        default = "email_verified_by_default",
+        deserialize_with = "bool_from_int",
+        serialize_with = "bool_to_int"
+    )]
+    pub email_verified: bool,
+
+    /// Response fields unknown to this crate, preserved as is.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 utils::display_from_json!(UserInfoResponseData);
 
+impl UserInfoResponseData {
+    /// Returns amount of storage in bytes which is still available to the user.
+    #[must_use]
+    pub const fn remaining_storage(&self) -> u64 {
+        self.max_storage.saturating_sub(self.storage_used)
+    }
+}
+
+const fn email_verified_by_default() -> bool {
+    true
+}
+
 response_payload!(
     /// Response for `USER_INFO_PATH` endpoint.
     UserInfoResponsePayload<UserInfoResponseData>
 );
 
+/// Used for requests to `USER_SETTINGS_PASSWORD_CHANGE_PATH` endpoint.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct UserSettingsPasswordChangeRequestPayload<'user_settings_password_change> {
+    /// User-associated Filen API key.
+    #[serde(rename = "apiKey")]
+    pub api_key: &'user_settings_password_change SecUtf8,
+
+    /// Filen-processed new password. Note that this is not a plain text password, but its hash.
+    pub password: SecUtf8,
+
+    /// Same as `password`.
+    #[serde(rename = "passwordRepeat")]
+    pub password_repeat: SecUtf8,
+
+    /// Filen-processed current password, as used for login. Serves as a proof that user knows current password.
+    #[serde(rename = "currentPassword")]
+    pub current_password: &'user_settings_password_change SecUtf8,
+
+    /// Auth version used to derive new password; always 2, since version 1 is deprecated.
+    #[serde(rename = "authVersion")]
+    pub auth_version: u32,
+
+    /// Salt used to derive new password, 256 alphanumeric characters.
+    pub salt: String,
+
+    /// User's master keys with the new master key appended, encrypted by the new master key.
+    #[serde(rename = "masterKeys")]
+    pub master_keys_metadata: String,
+
+    /// User's master keys with the new master key appended, as used for `master_keys_metadata`.
+    /// Replace your master keys with these after a successful password change.
+    #[serde(skip)]
+    pub new_master_keys: Vec<SecUtf8>,
+}
+utils::display_from_json_with_lifetime!('user_settings_password_change, UserSettingsPasswordChangeRequestPayload);
+
+impl<'user_settings_password_change> UserSettingsPasswordChangeRequestPayload<'user_settings_password_change> {
+    /// Derives new Filen password and master key from the given plain text password and a freshly generated salt,
+    /// then re-encrypts user's master keys with the new master key appended.
+    ///
+    /// `current_password` is a Filen-processed current password, see `FilenPasswordWithMasterKey::sent_password`.
+    #[must_use]
+    pub fn new(
+        api_key: &'user_settings_password_change SecUtf8,
+        current_password: &'user_settings_password_change SecUtf8,
+        new_user_password: &SecUtf8,
+        master_keys: &[SecUtf8],
+    ) -> Self {
+        Self::new_with_version(
+            api_key,
+            current_password,
+            new_user_password,
+            master_keys,
+            MetadataVersion::default(),
+        )
+    }
+
+    /// Same as `new`, but re-encrypts user's master keys into metadata of the specified version.
+    #[allow(clippy::missing_panics_doc)]
+    #[must_use]
+    pub fn new_with_version(
+        api_key: &'user_settings_password_change SecUtf8,
+        current_password: &'user_settings_password_change SecUtf8,
+        new_user_password: &SecUtf8,
+        master_keys: &[SecUtf8],
+        metadata_version: MetadataVersion,
+    ) -> Self {
+        let salt = utils::random_alphanumeric_string(FILEN_SALT_LENGTH);
+        let new_password_with_master_key =
+            FilenPasswordWithMasterKey::from_user_password_and_auth_info_salt(new_user_password, &SecUtf8::from(&salt));
+        let mut new_master_keys = master_keys.to_vec();
+        new_master_keys.push(new_password_with_master_key.m_key.clone());
+        // Cannot panic, since MetadataVersion is supported by definition.
+        let master_keys_metadata = crypto::encrypt_master_keys_metadata(
+            &new_master_keys,
+            &new_password_with_master_key.m_key,
+            metadata_version.into(),
+        )
+        .unwrap();
+        Self {
+            api_key,
+            password: new_password_with_master_key.sent_password.clone(),
+            password_repeat: new_password_with_master_key.sent_password,
+            current_password,
+            auth_version: 2,
+            salt,
+            master_keys_metadata,
+            new_master_keys,
+        }
+    }
+}
+
+/// Response data for `USER_SETTINGS_PASSWORD_CHANGE_PATH` endpoint.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UserSettingsPasswordChangeResponseData {
+    /// New Filen API key; previous API key is no longer valid after password change.
+    #[serde(rename = "newAPIKey")]
+    pub new_api_key: SecUtf8,
+}
+utils::display_from_json!(UserSettingsPasswordChangeResponseData);
+
+response_payload!(
+    /// Response for `USER_SETTINGS_PASSWORD_CHANGE_PATH` endpoint.
+    UserSettingsPasswordChangeResponsePayload<UserSettingsPasswordChangeResponseData>
+);
+
+/// Used for requests to `USER_SETTINGS_2FA_ENABLE_PATH` and `USER_SETTINGS_2FA_DISABLE_PATH` endpoints.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct UserSettings2faRequestPayload<'user_settings_2fa> {
+    /// User-associated Filen API key.
+    #[serde(rename = "apiKey")]
+    pub api_key: &'user_settings_2fa SecUtf8,
+
+    /// Current 6-digit code generated by an authenticator app from the 2FA secret,
+    /// which can be found in `UserGetSettingsResponseData::two_factor_key`.
+    pub code: &'user_settings_2fa SecUtf8,
+}
+utils::display_from_json_with_lifetime!('user_settings_2fa, UserSettings2faRequestPayload);
+
+/// Response data for `USER_SETTINGS_2FA_ENABLE_PATH` endpoint.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UserSettings2faEnableResponseData {
+    /// Recovery key which can be used to login when authenticator app is not available.
+    /// It should be shown to user, since Filen will not return it again.
+    #[serde(rename = "recoveryKeys")]
+    pub recovery_keys: SecUtf8,
+}
+utils::display_from_json!(UserSettings2faEnableResponseData);
+
+response_payload!(
+    /// Response for `USER_SETTINGS_2FA_ENABLE_PATH` endpoint.
+    UserSettings2faEnableResponsePayload<UserSettings2faEnableResponseData>
+);
+
+/// Response data for `USER_SETTINGS_2FA_DISABLE_PATH` endpoint.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UserSettings2faDisableResponseData {
+    /// New 2FA secret, which should be used if user decides to enable 2FA again.
+    #[serde(rename = "newKey")]
+    pub new_key: SecUtf8,
+}
+utils::display_from_json!(UserSettings2faDisableResponseData);
+
+response_payload!(
+    /// Response for `USER_SETTINGS_2FA_DISABLE_PATH` endpoint.
+    UserSettings2faDisableResponsePayload<UserSettings2faDisableResponseData>
+);
+
 /// Calls `USER_GET_ACCOUNT_PATH` endpoint.
 /// Used to get various account-associated data, such as plans, invoices, referrals.
 pub fn user_get_account_request(
@@ -375,14 +569,85 @@ pub async fn user_info_request_async(
         .context(UserInfoQueryFailedSnafu {})
 }
 
+/// Calls `USER_SETTINGS_PASSWORD_CHANGE_PATH` endpoint. Used to change user's password.
+///
+/// On success, API key changes and new master key is added to user's master keys.
+pub fn user_settings_password_change_request(
+    payload: &UserSettingsPasswordChangeRequestPayload,
+    filen_settings: &FilenSettings,
+) -> Result<UserSettingsPasswordChangeResponsePayload> {
+    queries::query_filen_api(USER_SETTINGS_PASSWORD_CHANGE_PATH, payload, filen_settings)
+        .context(UserSettingsPasswordChangeQueryFailedSnafu {})
+}
+
+/// Calls `USER_SETTINGS_PASSWORD_CHANGE_PATH` endpoint asynchronously. Used to change user's password.
+///
+/// On success, API key changes and new master key is added to user's master keys.
+#[cfg(feature = "async")]
+pub async fn user_settings_password_change_request_async(
+    payload: &UserSettingsPasswordChangeRequestPayload<'_>,
+    filen_settings: &FilenSettings,
+) -> Result<UserSettingsPasswordChangeResponsePayload> {
+    queries::query_filen_api_async(USER_SETTINGS_PASSWORD_CHANGE_PATH, payload, filen_settings)
+        .await
+        .context(UserSettingsPasswordChangeQueryFailedSnafu {})
+}
+
+/// Calls `USER_SETTINGS_2FA_ENABLE_PATH` endpoint. Used to enable two-factor authentication for user's account.
+///
+/// On success, returns recovery keys which should be shown to user.
+pub fn enable_2fa_request(
+    payload: &UserSettings2faRequestPayload,
+    filen_settings: &FilenSettings,
+) -> Result<UserSettings2faEnableResponsePayload> {
+    queries::query_filen_api(USER_SETTINGS_2FA_ENABLE_PATH, payload, filen_settings)
+        .context(UserSettings2faEnableQueryFailedSnafu {})
+}
+
+/// Calls `USER_SETTINGS_2FA_ENABLE_PATH` endpoint asynchronously.
+/// Used to enable two-factor authentication for user's account.
+///
+/// On success, returns recovery keys which should be shown to user.
+#[cfg(feature = "async")]
+pub async fn enable_2fa_request_async(
+    payload: &UserSettings2faRequestPayload<'_>,
+    filen_settings: &FilenSettings,
+) -> Result<UserSettings2faEnableResponsePayload> {
+    queries::query_filen_api_async(USER_SETTINGS_2FA_ENABLE_PATH, payload, filen_settings)
+        .await
+        .context(UserSettings2faEnableQueryFailedSnafu {})
+}
+
+/// Calls `USER_SETTINGS_2FA_DISABLE_PATH` endpoint. Used to disable two-factor authentication for user's account.
+pub fn disable_2fa_request(
+    payload: &UserSettings2faRequestPayload,
+    filen_settings: &FilenSettings,
+) -> Result<UserSettings2faDisableResponsePayload> {
+    queries::query_filen_api(USER_SETTINGS_2FA_DISABLE_PATH, payload, filen_settings)
+        .context(UserSettings2faDisableQueryFailedSnafu {})
+}
+
+/// Calls `USER_SETTINGS_2FA_DISABLE_PATH` endpoint asynchronously.
+/// Used to disable two-factor authentication for user's account.
+#[cfg(feature = "async")]
+pub async fn disable_2fa_request_async(
+    payload: &UserSettings2faRequestPayload<'_>,
+    filen_settings: &FilenSettings,
+) -> Result<UserSettings2faDisableResponsePayload> {
+    queries::query_filen_api_async(USER_SETTINGS_2FA_DISABLE_PATH, payload, filen_settings)
+        .await
+        .context(UserSettings2faDisableQueryFailedSnafu {})
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_utils::validate_contract;
     #[cfg(feature = "async")]
     use crate::test_utils::validate_contract_async;
+    use crate::test_utils::{deserialize_from_file, validate_contract};
     use once_cell::sync::Lazy;
     use secstr::SecUtf8;
+    use serde_json::json;
 
     static API_KEY: Lazy<SecUtf8> =
         Lazy::new(|| SecUtf8::from("bYZmrwdVEbHJSqeA1RfnPtKiBcXzUpRdKGRkjw9m1o1eqSGP1s6DM11CDnklpFq6"));
@@ -441,6 +706,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn user_info_request_should_have_proper_contract_for_unverified_email() {
+        validate_contract(
+            USER_INFO_PATH,
+            &utils::api_key_json(&API_KEY),
+            "tests/resources/responses/user_info_unverified.json",
+            |_, filen_settings| user_info_request(&API_KEY, &filen_settings),
+        );
+    }
+
+    #[test]
+    fn user_info_response_should_calculate_remaining_storage() {
+        let response: UserInfoResponsePayload = deserialize_from_file("tests/resources/responses/user_info.json");
+        let mut over_quota = response.data.clone().unwrap();
+        over_quota.storage_used = over_quota.max_storage + 1;
+
+        assert_eq!(response.data.unwrap().remaining_storage(), 10_735_260_224);
+        assert_eq!(over_quota.remaining_storage(), 0);
+    }
+
+    #[test]
+    fn user_info_response_should_report_email_verification() {
+        let verified: UserInfoResponsePayload = deserialize_from_file("tests/resources/responses/user_info.json");
+        let unverified: UserInfoResponsePayload =
+            deserialize_from_file("tests/resources/responses/user_info_unverified.json");
+        let missing: UserInfoResponseData = serde_json::from_value(json!({
+            "email": "test@test.com",
+            "maxStorage": 10_737_418_240_u64,
+            "storageUsed": 0,
+            "isPremium": 0,
+            "avatarURL": "https://down.filen.io/avatar.jpg"
+        }))
+        .unwrap();
+
+        assert!(verified.data.unwrap().email_verified);
+        assert!(!unverified.data.unwrap().email_verified);
+        assert!(missing.email_verified);
+    }
+
     #[cfg(feature = "async")]
     #[tokio::test]
     async fn user_info_request_async_should_have_proper_contract() {
@@ -452,4 +756,119 @@ mod tests {
         )
         .await;
     }
+
+    #[test]
+    fn user_settings_password_change_request_should_have_proper_contract() {
+        let m_key = SecUtf8::from("ed8d39b6c2d00ece398199a3e83988f1c4942b24");
+        let current_password = SecUtf8::from("current");
+        let request_payload = UserSettingsPasswordChangeRequestPayload::new(
+            &API_KEY,
+            &current_password,
+            &SecUtf8::from("new password"),
+            std::slice::from_ref(&m_key),
+        );
+        let new_m_key = request_payload.new_master_keys.last().unwrap().clone();
+        let expected_m_key = FilenPasswordWithMasterKey::from_user_password_and_auth_info_salt(
+            &SecUtf8::from("new password"),
+            &SecUtf8::from(request_payload.salt.clone()),
+        )
+        .m_key;
+
+        let decrypted_master_keys =
+            crypto::decrypt_master_keys_metadata(&request_payload.master_keys_metadata, &new_m_key).unwrap();
+
+        assert_eq!(new_m_key, expected_m_key);
+        assert_eq!(decrypted_master_keys, vec![m_key, new_m_key]);
+        validate_contract(
+            USER_SETTINGS_PASSWORD_CHANGE_PATH,
+            request_payload,
+            "tests/resources/responses/user_settings_password_change.json",
+            |request_payload, filen_settings| user_settings_password_change_request(&request_payload, &filen_settings),
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn user_settings_password_change_request_async_should_have_proper_contract() {
+        let current_password = SecUtf8::from("current");
+        let request_payload = UserSettingsPasswordChangeRequestPayload::new(
+            &API_KEY,
+            &current_password,
+            &SecUtf8::from("new password"),
+            &[SecUtf8::from("ed8d39b6c2d00ece398199a3e83988f1c4942b24")],
+        );
+        validate_contract_async(
+            USER_SETTINGS_PASSWORD_CHANGE_PATH,
+            request_payload,
+            "tests/resources/responses/user_settings_password_change.json",
+            |request_payload, filen_settings| async move {
+                user_settings_password_change_request_async(&request_payload, &filen_settings).await
+            },
+        )
+        .await;
+    }
+
+    #[test]
+    fn enable_2fa_request_should_have_proper_contract() {
+        let request_payload = UserSettings2faRequestPayload {
+            api_key: &API_KEY,
+            code: &SecUtf8::from("123456"),
+        };
+        validate_contract(
+            USER_SETTINGS_2FA_ENABLE_PATH,
+            request_payload,
+            "tests/resources/responses/user_settings_2fa_enable.json",
+            |request_payload, filen_settings| enable_2fa_request(&request_payload, &filen_settings),
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn enable_2fa_request_async_should_have_proper_contract() {
+        let request_payload = UserSettings2faRequestPayload {
+            api_key: &API_KEY,
+            code: &SecUtf8::from("123456"),
+        };
+        validate_contract_async(
+            USER_SETTINGS_2FA_ENABLE_PATH,
+            request_payload,
+            "tests/resources/responses/user_settings_2fa_enable.json",
+            |request_payload, filen_settings| async move {
+                enable_2fa_request_async(&request_payload, &filen_settings).await
+            },
+        )
+        .await;
+    }
+
+    #[test]
+    fn disable_2fa_request_should_have_proper_contract() {
+        let request_payload = UserSettings2faRequestPayload {
+            api_key: &API_KEY,
+            code: &SecUtf8::from("123456"),
+        };
+        validate_contract(
+            USER_SETTINGS_2FA_DISABLE_PATH,
+            request_payload,
+            "tests/resources/responses/user_settings_2fa_disable.json",
+            |request_payload, filen_settings| disable_2fa_request(&request_payload, &filen_settings),
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn disable_2fa_request_async_should_have_proper_contract() {
+        let request_payload = UserSettings2faRequestPayload {
+            api_key: &API_KEY,
+            code: &SecUtf8::from("123456"),
+        };
+        validate_contract_async(
+            USER_SETTINGS_2FA_DISABLE_PATH,
+            request_payload,
+            "tests/resources/responses/user_settings_2fa_disable.json",
+            |request_payload, filen_settings| async move {
+                disable_2fa_request_async(&request_payload, &filen_settings).await
+            },
+        )
+        .await;
+    }
 }
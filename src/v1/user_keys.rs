@@ -1,6 +1,8 @@
 use crate::{
-    crypto, queries, utils,
-    v1::{response_payload, PlainResponsePayload, METADATA_VERSION},
+    crypto::{self, MetadataVersion},
+    queries,
+    utils::{self, Base64Padding},
+    v1::{response_payload, PlainResponsePayload},
     FilenSettings,
 };
 use secstr::{SecUtf8, SecVec};
@@ -29,6 +31,11 @@ pub enum Error {
     #[snafu(display("Failed to decrypt private key: {}", source))]
     DecryptPrivateKeyFailed { source: crypto::Error },
 
+    #[snafu(display(
+        "User has no RSA private key; key pair should be initialized with `user_key_pair_update_request` first"
+    ))]
+    NoPrivateKey { backtrace: Backtrace },
+
     #[snafu(display("Failed to encrypt master keys: {}", source))]
     EncryptMasterKeysFailed { source: crypto::Error },
 
@@ -73,15 +80,15 @@ pub trait HasPrivateKey {
     fn private_key_metadata_ref(&self) -> Option<&str>;
 
     /// Decrypts `private_key_metadata_ref` into RSA key bytes, using the specified user's last master key.
+    ///
+    /// Accounts created before key pair setup have empty private key; for them `Error::NoPrivateKey` is returned,
+    /// and key pair should be initialized with `user_key_pair_update_request`.
     fn decrypt_private_key(&self, master_keys: &[SecUtf8]) -> Result<SecVec<u8>> {
         match self.private_key_metadata_ref() {
-            Some(metadata) => {
+            Some(metadata) if !metadata.is_empty() => {
                 crypto::decrypt_private_key_metadata(metadata, master_keys).context(DecryptPrivateKeyFailedSnafu {})
             }
-            None => BadArgumentSnafu {
-                message: "private key metadata is absent, cannot decrypt None",
-            }
-            .fail(),
+            _ => NoPrivateKeySnafu {}.fail(),
         }
     }
 }
@@ -94,7 +101,7 @@ pub trait HasPublicKey {
     /// Conveniently decodes base64-encoded public key into bytes.
     fn decode_public_key(&self) -> Result<Vec<u8>> {
         match self.public_key_ref() {
-            Some(key) => base64::decode(key).context(DecodePublicKeyFailedSnafu {}),
+            Some(key) => utils::base64_decode(key).context(DecodePublicKeyFailedSnafu {}),
             None => BadArgumentSnafu {
                 message: "public key is absent, cannot decode None",
             }
@@ -168,16 +175,34 @@ impl<'user_key_pair_update> UserKeyPairUpdateRequestPayload<'user_key_pair_updat
         private_key_bytes: &SecVec<u8>,
         public_key_bytes: &[u8],
         last_master_key: &SecUtf8,
+    ) -> Result<Self> {
+        Self::new_with_version(
+            api_key,
+            private_key_bytes,
+            public_key_bytes,
+            last_master_key,
+            MetadataVersion::default(),
+        )
+    }
+
+    /// Creates `UserKeyPairUpdateRequestPayload` like `new` does, encrypting private key into metadata
+    /// of the specified version.
+    pub fn new_with_version(
+        api_key: &'user_key_pair_update SecUtf8,
+        private_key_bytes: &SecVec<u8>,
+        public_key_bytes: &[u8],
+        last_master_key: &SecUtf8,
+        metadata_version: MetadataVersion,
     ) -> Result<Self> {
         let private_key = crypto::encrypt_metadata_str(
-            &base64::encode(private_key_bytes.unsecure()),
+            &utils::base64_encode(private_key_bytes.unsecure(), Base64Padding::Padded),
             last_master_key,
-            METADATA_VERSION,
+            metadata_version.into(),
         )
         .map(SecUtf8::from)
         .context(EncryptPrivateKeyFailedSnafu {})?;
 
-        let public_key = base64::encode(public_key_bytes);
+        let public_key = utils::base64_encode(public_key_bytes, Base64Padding::Padded);
         Ok(Self {
             api_key,
             private_key,
@@ -204,6 +229,16 @@ impl<'master_keys_fetch> MasterKeysFetchRequestPayload<'master_keys_fetch> {
     /// Creates `MasterKeysFetchRequestPayload` from user's API key and user's master keys.
     /// Assumes user's last master key is the last element of given master keys slice.
     pub fn new(api_key: &'master_keys_fetch SecUtf8, raw_master_keys: &[SecUtf8]) -> Result<Self> {
+        Self::new_with_version(api_key, raw_master_keys, MetadataVersion::default())
+    }
+
+    /// Creates `MasterKeysFetchRequestPayload` like `new` does, encrypting master keys into metadata
+    /// of the specified version.
+    pub fn new_with_version(
+        api_key: &'master_keys_fetch SecUtf8,
+        raw_master_keys: &[SecUtf8],
+        metadata_version: MetadataVersion,
+    ) -> Result<Self> {
         let empty_key = SecUtf8::from("");
         let last_master_key = raw_master_keys.last().unwrap_or(&empty_key);
 
@@ -215,7 +250,7 @@ impl<'master_keys_fetch> MasterKeysFetchRequestPayload<'master_keys_fetch> {
         );
 
         let master_keys_metadata =
-            crypto::encrypt_master_keys_metadata(raw_master_keys, last_master_key, super::METADATA_VERSION)
+            crypto::encrypt_master_keys_metadata(raw_master_keys, last_master_key, metadata_version.into())
                 .context(EncryptMasterKeysFailedSnafu {})?;
 
         Ok(Self {
@@ -343,6 +378,9 @@ pub async fn user_master_keys_request_async(
 }
 
 /// Calls `USER_PUBLIC_KEY_GET_PATH` endpoint. Used to get any user's RSA public key.
+///
+/// Sharing an item requires receiver's public key: decode it with `HasPublicKey::decode_public_key`
+/// and pass the bytes to `ShareRequestPayload` constructors to encrypt item metadata for the receiver.
 pub fn user_public_key_get_request(
     payload: &UserPublicKeyGetRequestPayload,
     filen_settings: &FilenSettings,
@@ -352,6 +390,9 @@ pub fn user_public_key_get_request(
 }
 
 /// Calls `USER_PUBLIC_KEY_GET_PATH` endpoint asynchronously. Used to get any user's RSA public key.
+///
+/// Sharing an item requires receiver's public key: decode it with `HasPublicKey::decode_public_key`
+/// and pass the bytes to `ShareRequestPayload` constructors to encrypt item metadata for the receiver.
 #[cfg(feature = "async")]
 pub async fn user_public_key_get_request_async(
     payload: &UserPublicKeyGetRequestPayload<'_>,
@@ -367,7 +408,7 @@ mod tests {
     use super::*;
     #[cfg(feature = "async")]
     use crate::test_utils::validate_contract_async;
-    use crate::test_utils::{read_project_file, validate_contract};
+    use crate::test_utils::{deserialize_from_file, read_project_file, validate_contract};
     use once_cell::sync::Lazy;
     use pretty_assertions::assert_eq;
 
@@ -406,6 +447,19 @@ mod tests {
         assert_eq!(decrypted_private_key.unsecure(), expected.unsecure());
     }
 
+    #[test]
+    fn decrypt_private_key_should_fail_with_no_private_key_for_empty_key() {
+        let m_key = SecUtf8::from("ed8d39b6c2d00ece398199a3e83988f1c4942b24");
+        let user_key_pair = UserKeyPairInfoResponseData {
+            public_key: Some(String::new()),
+            private_key_metadata: Some(String::new()),
+        };
+
+        let result = user_key_pair.decrypt_private_key(&[m_key]);
+
+        assert!(matches!(result, Err(Error::NoPrivateKey { .. })));
+    }
+
     #[test]
     fn user_key_pair_info_request_should_be_correctly_typed() {
         let request_payload = utils::api_key_json(&API_KEY);
@@ -475,6 +529,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn user_public_key_get_response_should_contain_key_usable_for_sharing() {
+        let response: UserPublicKeyGetResponsePayload =
+            deserialize_from_file("tests/resources/responses/user_public_key_get.json");
+        let public_key_bytes = response.data.unwrap().decode_public_key().unwrap();
+
+        let encrypted = crypto::encrypt_rsa(b"{\"name\":\"shared\"}", &public_key_bytes);
+
+        assert!(encrypted.is_ok());
+    }
+
     #[cfg(feature = "async")]
     #[tokio::test]
     async fn user_public_key_get_request_async_should_be_correctly_typed() {
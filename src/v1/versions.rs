@@ -1,6 +1,12 @@
+#![allow(clippy::redundant_pub_crate)]
+#[cfg(feature = "async")]
+use crate::v1::download_and_decrypt_file_async;
 use crate::{
     queries, utils,
-    v1::{bool_from_int, bool_to_int, response_payload, FileStorageInfo},
+    v1::{
+        bool_from_int, bool_to_int, download_and_decrypt_file, download_dir::gen_download_and_decrypt_file,
+        response_payload, FileStorageInfo, HasFileLocation, HasFileMetadata, HasUuid,
+    },
     FilenSettings,
 };
 use secstr::SecUtf8;
@@ -106,6 +112,29 @@ pub struct FileVersion {
     /// use [crypto::encrypt_file_data] and [crypto::decrypt_file_data] for the task.
     pub version: u32,
 }
+utils::display_from_json!(FileVersion);
+
+impl HasFileMetadata for FileVersion {
+    fn file_metadata_ref(&self) -> &str {
+        &self.metadata
+    }
+}
+
+impl HasFileLocation for FileVersion {
+    fn file_storage_ref(&self) -> &FileStorageInfo {
+        &self.storage
+    }
+}
+
+impl HasUuid for FileVersion {
+    fn uuid_ref(&self) -> &Uuid {
+        &self.uuid
+    }
+}
+
+impl FileVersion {
+    gen_download_and_decrypt_file!();
+}
 
 /// Used for requests to `FILE_VERSIONS_PATH` endpoint.
 #[derive(Clone, Debug, Eq, PartialEq, Serialize)]
@@ -134,7 +163,8 @@ response_payload!(
     FileVersionsResponsePayload<FileVersionsResponseData>
 );
 
-/// Calls `FILE_ARCHIVE_RESTORE_PATH` endpoint. Used to get versions of the given file.
+/// Calls `FILE_ARCHIVE_RESTORE_PATH` endpoint. Used to restore an archived file version,
+/// making it the current one. Archived versions can be found with `file_versions_request`.
 pub fn file_archive_restore_request(
     payload: &FileArchiveRestoreRequestPayload,
     filen_settings: &FilenSettings,
@@ -143,7 +173,8 @@ pub fn file_archive_restore_request(
         .context(FileArchiveRestoreQueryFailedSnafu {})
 }
 
-/// Calls `FILE_ARCHIVE_RESTORE_PATH` endpoint asynchronously. Used to get versions of the given file.
+/// Calls `FILE_ARCHIVE_RESTORE_PATH` endpoint asynchronously. Used to restore an archived file version,
+/// making it the current one. Archived versions can be found with `file_versions_request_async`.
 #[cfg(feature = "async")]
 pub async fn file_archive_restore_request_async(
     payload: &FileArchiveRestoreRequestPayload<'_>,
@@ -176,10 +207,16 @@ pub async fn file_versions_request_async(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_utils::validate_contract;
     #[cfg(feature = "async")]
     use crate::test_utils::validate_contract_async;
+    use crate::{
+        crypto,
+        test_utils::{init_server, validate_contract},
+        SettingsBundle, NO_RETRIES,
+    };
+    use httpmock::Method::GET;
     use once_cell::sync::Lazy;
+    use pretty_assertions::assert_eq;
 
     static API_KEY: Lazy<SecUtf8> =
         Lazy::new(|| SecUtf8::from("bYZmrwdVEbHJSqeA1RfnPtKiBcXzUpRdKGRkjw9m1o1eqSGP1s6DM11CDnklpFq6"));
@@ -249,4 +286,52 @@ mod tests {
         )
         .await;
     }
+
+    #[test]
+    fn file_version_should_download_and_decrypt_its_chunks() {
+        let (server, filen_settings) = init_server();
+        let file_key = SecUtf8::from("sh1YRHfx22Ij40tQBbt6BgpBlqkzch8Y");
+        let file_version = FileVersion {
+            uuid: Uuid::parse_str("b5ec90d2-957c-4481-b211-08a68accd1b2").unwrap(),
+            metadata: String::new(),
+            storage: FileStorageInfo {
+                bucket: "filen-1".to_owned(),
+                region: "de-1".to_owned(),
+                chunks: 2,
+            },
+            rm: String::new(),
+            timestamp: 1_632_080_400,
+            version: 2,
+        };
+        let chunk_mocks = ["Yesterday's ", "copy"]
+            .iter()
+            .enumerate()
+            .map(|(chunk_index, chunk)| {
+                let encrypted_chunk =
+                    crypto::encrypt_file_chunk(chunk.as_bytes(), b"sh1YRHfx22Ij40tQBbt6BgpBlqkzch8Y", 2)
+                        .unwrap()
+                        .chars()
+                        .map(|c| c as u8)
+                        .collect::<Vec<u8>>();
+                server.mock(|when, then| {
+                    when.method(GET).path(format!(
+                        "/de-1/filen-1/b5ec90d2-957c-4481-b211-08a68accd1b2/{}",
+                        chunk_index
+                    ));
+                    then.status(200).body(encrypted_chunk);
+                })
+            })
+            .collect::<Vec<_>>();
+        let settings = SettingsBundle {
+            filen: filen_settings,
+            retry: NO_RETRIES.clone(),
+        };
+        let mut writer = std::io::BufWriter::new(Vec::new());
+
+        let download_result = file_version.download_and_decrypt_file(&file_key, &mut writer, &settings);
+
+        chunk_mocks.iter().for_each(|mock| mock.assert_hits(1));
+        assert!(download_result.is_ok());
+        assert_eq!(writer.into_inner().unwrap(), b"Yesterday's copy");
+    }
 }
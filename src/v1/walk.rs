@@ -0,0 +1,356 @@
+//! Contains helpers to enumerate every file in user's account along with its decrypted path.
+//!
+//! Each base folder, also known as 'cloud drive', is fetched whole with a single `download_dir_request`,
+//! and file paths are reconstructed by joining decrypted names of the folders above them.
+#[cfg(feature = "async")]
+use crate::v1::{download_dir_request_async, user_dirs_request_async};
+use crate::{
+    v1::{
+        dirs, download_dir, download_dir_request, files, fs, user_dirs_request, DownloadDirRequestPayload,
+        DownloadDirResponsePayload, FileProperties, FolderData, HasFileMetadata, HasLocationName, ParentOrBase,
+        UserDirsResponsePayload,
+    },
+    FilenSettings,
+};
+#[cfg(feature = "async")]
+use futures::{Stream, StreamExt};
+use secstr::SecUtf8;
+use snafu::{ensure, Backtrace, OptionExt, ResultExt, Snafu};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Snafu, Debug)]
+pub enum Error {
+    #[snafu(display("Failed to decrypt metadata of file {}: {}", uuid, source))]
+    DecryptFileMetadataFailed {
+        uuid: Uuid,
+        #[snafu(source(from(files::Error, Box::new)))]
+        source: Box<files::Error>,
+    },
+
+    #[snafu(display("Failed to decrypt name of folder {}: {}", uuid, source))]
+    DecryptFolderNameFailed {
+        uuid: Uuid,
+        #[snafu(source(from(fs::Error, Box::new)))]
+        source: Box<fs::Error>,
+    },
+
+    #[snafu(display("{}", source))]
+    DownloadDirQueryFailed {
+        #[snafu(source(from(download_dir::Error, Box::new)))]
+        source: Box<download_dir::Error>,
+    },
+
+    #[snafu(display("Filen refused to fetch folder tree of {}: {}", uuid, message))]
+    DownloadDirRefused {
+        uuid: Uuid,
+        message: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Folder {} is its own ancestor, folder tree has a cycle", uuid))]
+    FolderCycleDetected { uuid: Uuid, backtrace: Backtrace },
+
+    #[snafu(display("Folder {} is referenced as a parent, but it is not in the folder tree", uuid))]
+    OrphanedLocation { uuid: Uuid, backtrace: Backtrace },
+
+    #[snafu(display("{}", source))]
+    UserDirsQueryFailed {
+        #[snafu(source(from(dirs::Error, Box::new)))]
+        source: Box<dirs::Error>,
+    },
+
+    #[snafu(display("Filen refused to list user folders: {}", message))]
+    UserDirsRefused { message: String, backtrace: Backtrace },
+}
+
+/// File found while walking user's account.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AccountFile {
+    /// Decrypted file path, starting with base folder name and delimited by '/', like "Default/docs/file.txt".
+    pub path: String,
+
+    /// File ID; hyphenated lowercased UUID V4.
+    pub uuid: Uuid,
+
+    /// Decrypted file metadata.
+    pub properties: FileProperties,
+}
+
+/// Helper which enumerates every file in user's account with its decrypted path, one base folder at a time.
+///
+/// Base folders are listed right away, but each of them is fetched only when iteration reaches it.
+/// Files which cannot be placed in the folder tree, because their folders are missing or form a cycle,
+/// are yielded as errors instead of failing the whole walk, as are base folders which failed to be fetched.
+pub fn walk_account<'walk>(
+    api_key: &'walk SecUtf8,
+    master_keys: &'walk [SecUtf8],
+    filen_settings: &'walk FilenSettings,
+) -> Result<impl Iterator<Item = Result<AccountFile>> + 'walk> {
+    let dirs_response = user_dirs_request(api_key, filen_settings).context(UserDirsQueryFailedSnafu {})?;
+    let base_folder_uuids = base_folder_uuids(dirs_response)?;
+    Ok(base_folder_uuids.into_iter().flat_map(move |base_folder_uuid| {
+        let payload = DownloadDirRequestPayload {
+            api_key,
+            uuid: base_folder_uuid,
+        };
+        let response = download_dir_request(&payload, filen_settings).context(DownloadDirQueryFailedSnafu {});
+        tree_files(base_folder_uuid, response, master_keys)
+    }))
+}
+
+/// Helper which enumerates every file in user's account with its decrypted path, one base folder at a time;
+/// asynchronous.
+///
+/// Base folders are listed right away, but each of them is fetched only when stream reaches it.
+/// Files which cannot be placed in the folder tree, because their folders are missing or form a cycle,
+/// are yielded as errors instead of failing the whole walk, as are base folders which failed to be fetched.
+#[cfg(feature = "async")]
+pub async fn walk_account_async<'walk>(
+    api_key: &'walk SecUtf8,
+    master_keys: &'walk [SecUtf8],
+    filen_settings: &'walk FilenSettings,
+) -> Result<impl Stream<Item = Result<AccountFile>> + 'walk> {
+    let dirs_response = user_dirs_request_async(api_key, filen_settings)
+        .await
+        .context(UserDirsQueryFailedSnafu {})?;
+    let base_folder_uuids = base_folder_uuids(dirs_response)?;
+    Ok(futures::stream::iter(base_folder_uuids)
+        .then(move |base_folder_uuid| async move {
+            let payload = DownloadDirRequestPayload {
+                api_key,
+                uuid: base_folder_uuid,
+            };
+            let response = download_dir_request_async(&payload, filen_settings)
+                .await
+                .context(DownloadDirQueryFailedSnafu {});
+            futures::stream::iter(tree_files(base_folder_uuid, response, master_keys))
+        })
+        .flatten())
+}
+
+fn base_folder_uuids(response: UserDirsResponsePayload) -> Result<Vec<Uuid>> {
+    match response.data {
+        Some(data) if response.status => Ok(data
+            .iter()
+            .filter(|dir| dir.parent.is_none())
+            .map(|dir| dir.uuid)
+            .collect()),
+        _ => UserDirsRefusedSnafu {
+            message: response.message.unwrap_or_default(),
+        }
+        .fail(),
+    }
+}
+
+/// Decrypts all files of the given base folder tree and reconstructs their paths.
+fn tree_files(
+    base_folder_uuid: Uuid,
+    response: Result<DownloadDirResponsePayload>,
+    master_keys: &[SecUtf8],
+) -> Vec<Result<AccountFile>> {
+    let data = match response {
+        Ok(DownloadDirResponsePayload {
+            status: true,
+            data: Some(data),
+            ..
+        }) => data,
+        Ok(response) => {
+            return vec![DownloadDirRefusedSnafu {
+                uuid: base_folder_uuid,
+                message: response.message.unwrap_or_default(),
+            }
+            .fail()]
+        }
+        Err(err) => return vec![Err(err)],
+    };
+    let mut folder_paths = FolderPaths::new(base_folder_uuid, &data.folders);
+    data.files
+        .iter()
+        .map(|file| {
+            let folder_path = folder_paths.path_of(file.parent, master_keys)?;
+            let properties = file
+                .decrypt_file_metadata(master_keys)
+                .context(DecryptFileMetadataFailedSnafu { uuid: file.uuid })?;
+            Ok(AccountFile {
+                path: format!("{}/{}", folder_path, properties.name),
+                uuid: file.uuid,
+                properties,
+            })
+        })
+        .collect()
+}
+
+/// Decrypted paths of the folders from a single base folder tree, computed on demand and remembered.
+struct FolderPaths<'tree> {
+    base_folder_uuid: Uuid,
+    folders: HashMap<Uuid, &'tree FolderData>,
+    paths: HashMap<Uuid, String>,
+}
+
+impl<'tree> FolderPaths<'tree> {
+    fn new(base_folder_uuid: Uuid, folders: &'tree [FolderData]) -> Self {
+        Self {
+            base_folder_uuid,
+            folders: folders.iter().map(|folder| (folder.uuid, folder)).collect(),
+            paths: HashMap::new(),
+        }
+    }
+
+    /// Returns path of the given folder, decrypting names of its ancestors which were not seen yet.
+    fn path_of(&mut self, folder_uuid: Uuid, master_keys: &[SecUtf8]) -> Result<String> {
+        let mut unresolved = Vec::new();
+        let mut visited = HashSet::new();
+        let mut current_uuid = folder_uuid;
+        let mut path = loop {
+            if let Some(known_path) = self.paths.get(&current_uuid) {
+                break Some(known_path.clone());
+            }
+            ensure!(
+                visited.insert(current_uuid),
+                FolderCycleDetectedSnafu { uuid: current_uuid }
+            );
+            let folder = *self
+                .folders
+                .get(&current_uuid)
+                .context(OrphanedLocationSnafu { uuid: current_uuid })?;
+            unresolved.push(folder);
+            match folder.parent {
+                ParentOrBase::Folder(parent_uuid) if folder.uuid != self.base_folder_uuid => current_uuid = parent_uuid,
+                _ => break None,
+            }
+        };
+        for folder in unresolved.into_iter().rev() {
+            let name = folder
+                .decrypt_name_metadata(master_keys)
+                .context(DecryptFolderNameFailedSnafu { uuid: folder.uuid })?;
+            let folder_path = path.map_or_else(|| name.clone(), |parent_path| format!("{}/{}", parent_path, name));
+            self.paths.insert(folder.uuid, folder_path.clone());
+            path = Some(folder_path);
+        }
+        Ok(path.unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_utils::{deserialize_from_file, init_server, setup_json_mock},
+        utils,
+        v1::LocationNameMetadata,
+    };
+    use httpmock::{Mock, MockServer};
+    use once_cell::sync::Lazy;
+    use pretty_assertions::assert_eq;
+
+    static API_KEY: Lazy<SecUtf8> =
+        Lazy::new(|| SecUtf8::from("bYZmrwdVEbHJSqeA1RfnPtKiBcXzUpRdKGRkjw9m1o1eqSGP1s6DM11CDnklpFq6"));
+    static M_KEY: Lazy<SecUtf8> = Lazy::new(|| SecUtf8::from("b49cadfb92e1d7d54e9dd9d33ba9feb2af1f10ae"));
+    const BASE_FOLDER_UUID: &str = "cf2af9a0-6f4e-485d-862c-0459f4662cf1";
+
+    #[test]
+    fn walk_account_should_reconstruct_file_paths() {
+        let (server, filen_settings) = init_server();
+        let mocks = setup_small_tree_mocks(&server);
+        let master_keys = [M_KEY.clone()];
+
+        let results = walk_account(&API_KEY, &master_keys, &filen_settings)
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        mocks.iter().for_each(|mock| mock.assert_hits(1));
+        assert_small_tree_results(&results);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn walk_account_async_should_reconstruct_file_paths() {
+        let (server, filen_settings) = init_server();
+        let mocks = setup_small_tree_mocks(&server);
+        let master_keys = [M_KEY.clone()];
+
+        let results = walk_account_async(&API_KEY, &master_keys, &filen_settings)
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+
+        mocks.iter().for_each(|mock| mock.assert_hits(1));
+        assert_small_tree_results(&results);
+    }
+
+    fn assert_small_tree_results(results: &[Result<AccountFile>]) {
+        assert_eq!(results.len(), 4);
+        let paths = results
+            .iter()
+            .filter_map(|result| result.as_ref().ok())
+            .map(|file| file.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["Default/root.txt", "Default/docs/notes/deep.txt"]);
+        assert!(matches!(results[2], Err(Error::OrphanedLocation { .. })));
+        assert!(matches!(results[3], Err(Error::FolderCycleDetected { .. })));
+    }
+
+    /// Sets up "Default" base folder with "docs/notes" subfolders, two files placed in the tree,
+    /// one file in a folder missing from the tree and one file in a pair of folders parented to each other.
+    fn setup_small_tree_mocks(server: &MockServer) -> Vec<Mock<'_>> {
+        let base_uuid = Uuid::parse_str(BASE_FOLDER_UUID).unwrap();
+        let docs_uuid = Uuid::parse_str("353fa773-64e7-4989-8b5b-b829b2c7dcc7").unwrap();
+        let notes_uuid = Uuid::parse_str("51845ac9-47ce-4820-aedb-876f591aef84").unwrap();
+        let cycle_uuids = [
+            Uuid::parse_str("7c0e1a48-8b8b-4f9c-9f4e-0c5b8b0d7c10").unwrap(),
+            Uuid::parse_str("d1f0f7e2-3c55-4c1e-8a4d-3f9a3b1f2e21").unwrap(),
+        ];
+        let missing_uuid = Uuid::parse_str("00000000-0000-4000-8000-000000000001").unwrap();
+        let folder = |uuid: Uuid, name: &str, parent: ParentOrBase| FolderData {
+            uuid,
+            name_metadata: LocationNameMetadata::encrypt_name_to_metadata(name, &M_KEY),
+            parent,
+        };
+        let mut response: DownloadDirResponsePayload =
+            deserialize_from_file("tests/resources/responses/download_dir.json");
+        let data = response.data.as_mut().unwrap();
+        data.folders = vec![
+            folder(base_uuid, "Default", ParentOrBase::Base),
+            folder(docs_uuid, "docs", ParentOrBase::Folder(base_uuid)),
+            folder(notes_uuid, "notes", ParentOrBase::Folder(docs_uuid)),
+            folder(cycle_uuids[0], "loop", ParentOrBase::Folder(cycle_uuids[1])),
+            folder(cycle_uuids[1], "pool", ParentOrBase::Folder(cycle_uuids[0])),
+        ];
+        let file_template = data.files[0].clone();
+        data.files = [
+            ("root.txt", base_uuid),
+            ("deep.txt", notes_uuid),
+            ("orphan.txt", missing_uuid),
+            ("cycled.txt", cycle_uuids[0]),
+        ]
+        .iter()
+        .map(|(name, parent)| {
+            let mut file = file_template.clone();
+            let properties =
+                FileProperties::from_name_size_modified(name, 1, &std::time::SystemTime::UNIX_EPOCH).unwrap();
+            file.uuid = Uuid::new_v4();
+            file.parent = *parent;
+            file.metadata = FileProperties::encrypt_file_metadata(&properties, &M_KEY);
+            file
+        })
+        .collect();
+        let dirs_response: UserDirsResponsePayload =
+            deserialize_from_file("tests/resources/responses/user_dirs_default.json");
+        vec![
+            setup_json_mock("/v1/user/dirs", &utils::api_key_json(&API_KEY), &dirs_response, server),
+            setup_json_mock(
+                "/v1/download/dir",
+                &DownloadDirRequestPayload {
+                    api_key: &API_KEY,
+                    uuid: base_uuid,
+                },
+                &response,
+                server,
+            ),
+        ]
+    }
+}